@@ -0,0 +1,282 @@
+//! Minimal parser of the C header written by cbindgen.
+//!
+//! It only understands what cbindgen emits for this crate: struct typedefs
+//! (opaque or with fields), function pointer typedefs, `#define` constants and
+//! function declarations, each optionally preceded by a doc comment. Anything
+//! else is skipped.
+
+/// Parameter of a function or field of a struct.
+#[derive(Debug, Clone)]
+pub struct Decl {
+    pub ty: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Function {
+    pub name: String,
+    pub ret: String,
+    pub params: Vec<Decl>,
+    pub doc: String,
+    /// Codes listed under "## Specific Error Codes" in the doc comment.
+    pub error_codes: Vec<(i32, String)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Struct {
+    pub name: String,
+    pub doc: String,
+    /// Empty for opaque structs.
+    pub fields: Vec<Decl>,
+    pub opaque: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Callback {
+    pub name: String,
+    pub ret: String,
+    pub params: Vec<Decl>,
+    pub doc: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Constant {
+    pub name: String,
+    pub value: String,
+    pub doc: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Api {
+    pub functions: Vec<Function>,
+    pub structs: Vec<Struct>,
+    pub callbacks: Vec<Callback>,
+    pub constants: Vec<Constant>,
+}
+
+/// True for lines of comments inside a declaration (e.g. field docs).
+fn is_comment_line(line: &str) -> bool {
+    line.starts_with("/*") || line.starts_with('*') || line.starts_with("//")
+}
+
+pub fn parse(header: &str) -> Api {
+    let mut api = Api::default();
+    let mut doc: Vec<String> = Vec::new();
+    let mut in_doc = false;
+    let mut stmt = String::new();
+    let mut depth = 0i32;
+    for raw in header.lines() {
+        let line = raw.trim();
+        if !stmt.is_empty() {
+            if is_comment_line(line) {
+                continue;
+            }
+            depth += line.matches('{').count() as i32 - line.matches('}').count() as i32;
+            stmt.push(' ');
+            stmt.push_str(line);
+            if depth == 0 && line.ends_with(';') {
+                add_item(&mut api, &stmt, &doc.join("\n"));
+                stmt.clear();
+                doc.clear();
+            }
+            continue;
+        }
+        if in_doc {
+            let (content, end) = match line.find("*/") {
+                Some(i) => (&line[..i], true),
+                None => (line, false),
+            };
+            let content = content.strip_prefix('*').unwrap_or(content);
+            let content = content.strip_prefix(' ').unwrap_or(content);
+            if !(end && content.trim().is_empty()) {
+                doc.push(content.trim_end().to_string());
+            }
+            in_doc = !end;
+        } else if let Some(rest) = line.strip_prefix("/**") {
+            doc.clear();
+            match rest.find("*/") {
+                Some(i) => doc.push(rest[..i].trim().to_string()),
+                None => {
+                    if !rest.trim().is_empty() {
+                        doc.push(rest.trim().to_string());
+                    }
+                    in_doc = true;
+                }
+            }
+        } else if let Some(rest) = line.strip_prefix("///") {
+            doc.push(rest.strip_prefix(' ').unwrap_or(rest).to_string());
+        } else if line.is_empty() || line.starts_with("//") || line.starts_with("/*") {
+            continue;
+        } else if let Some(rest) = line.strip_prefix("#define ") {
+            let mut parts = rest.trim().splitn(2, char::is_whitespace);
+            let name = parts.next().unwrap_or_default().to_string();
+            let value = parts.next().unwrap_or_default().trim().to_string();
+            api.constants.push(Constant {
+                name,
+                value,
+                doc: doc.join("\n"),
+            });
+            doc.clear();
+        } else if line.starts_with('#') {
+            doc.clear();
+        } else {
+            depth = line.matches('{').count() as i32 - line.matches('}').count() as i32;
+            stmt.push_str(line);
+            if depth == 0 && line.ends_with(';') {
+                add_item(&mut api, &stmt, &doc.join("\n"));
+                stmt.clear();
+                doc.clear();
+            }
+        }
+    }
+    api
+}
+
+/// Collapse all runs of whitespace into single spaces.
+fn normalize(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Split `type name` (e.g. `const struct cavc_vertex *vertexes` or
+/// `double m[6]`) into its type and name.
+fn parse_decl(decl: &str) -> Decl {
+    let decl = normalize(decl);
+    let (decl, array) = match decl.find('[') {
+        Some(i) => (decl[..i].trim_end().to_string(), decl[i..].to_string()),
+        None => (decl, String::new()),
+    };
+    let split = decl
+        .rfind(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .map_or(0, |i| i + 1);
+    Decl {
+        ty: format!("{}{}", decl[..split].trim_end(), array),
+        name: decl[split..].to_string(),
+    }
+}
+
+/// Parameters of the parenthesized list `params` (without the parentheses).
+fn parse_params(params: &str) -> Vec<Decl> {
+    let params = params.trim();
+    if params.is_empty() || params == "void" {
+        return Vec::new();
+    }
+    let mut result = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in params.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                result.push(parse_decl(&params[start..i]));
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    result.push(parse_decl(&params[start..]));
+    result
+}
+
+/// Contents of the parenthesized list starting at `open` in `s`.
+fn parens_at(s: &str, open: usize) -> &str {
+    let mut depth = 0;
+    for (i, c) in s[open..].char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return &s[open + 1..open + i];
+                }
+            }
+            _ => {}
+        }
+    }
+    &s[open + 1..]
+}
+
+/// Error codes listed under "## Specific Error Codes" as `* N = text` items,
+/// text continued on indented lines.
+fn parse_error_codes(doc: &str) -> Vec<(i32, String)> {
+    let mut codes: Vec<(i32, String)> = Vec::new();
+    let mut in_section = false;
+    for line in doc.lines() {
+        if line.starts_with('#') {
+            in_section = line.trim() == "## Specific Error Codes";
+            continue;
+        }
+        if !in_section {
+            continue;
+        }
+        if let Some(item) = line.strip_prefix("* ") {
+            if let Some((code, text)) = item.split_once('=') {
+                if let Ok(code) = code.trim().parse() {
+                    codes.push((code, text.trim().to_string()));
+                    continue;
+                }
+            }
+        }
+        match codes.last_mut() {
+            Some((_, text)) if line.starts_with(' ') => {
+                text.push(' ');
+                text.push_str(line.trim());
+            }
+            _ => {}
+        }
+    }
+    codes
+}
+
+fn add_item(api: &mut Api, stmt: &str, doc: &str) {
+    let stmt = normalize(stmt);
+    let stmt = stmt.trim_end_matches(';').trim();
+    let doc = doc.to_string();
+    if let Some(rest) = stmt.strip_prefix("typedef struct ") {
+        match (rest.find('{'), rest.rfind('}')) {
+            (Some(open), Some(close)) => {
+                let fields = rest[open + 1..close]
+                    .split(';')
+                    .filter(|f| !f.trim().is_empty())
+                    .map(parse_decl)
+                    .collect();
+                api.structs.push(Struct {
+                    name: rest[close + 1..].trim().to_string(),
+                    doc,
+                    fields,
+                    opaque: false,
+                });
+            }
+            _ => {
+                let name = rest.split_whitespace().last().unwrap_or_default();
+                api.structs.push(Struct {
+                    name: name.to_string(),
+                    doc,
+                    fields: Vec::new(),
+                    opaque: true,
+                });
+            }
+        }
+    } else if let Some(rest) = stmt.strip_prefix("typedef ") {
+        // function pointers: `typedef ret (*name)(params)`
+        if let Some(star) = rest.find("(*") {
+            let name_end = star + rest[star..].find(')').unwrap_or(0);
+            let params_start = name_end + 1 + rest[name_end + 1..].find('(').unwrap_or(0);
+            api.callbacks.push(Callback {
+                name: rest[star + 2..name_end].trim().to_string(),
+                ret: rest[..star].trim().to_string(),
+                params: parse_params(parens_at(rest, params_start)),
+                doc,
+            });
+        }
+    } else if let Some(open) = stmt.find('(') {
+        let head = parse_decl(&stmt[..open]);
+        api.functions.push(Function {
+            name: head.name,
+            ret: head.ty,
+            params: parse_params(parens_at(stmt, open)),
+            error_codes: parse_error_codes(&doc),
+            doc,
+        });
+    }
+}
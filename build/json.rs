@@ -0,0 +1,110 @@
+//! JSON description of the API parsed from the header.
+use crate::header::{Api, Decl};
+
+/// JSON string literal of `s`, also a valid Python string literal.
+pub fn quote(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn decls(decls: &[Decl]) -> String {
+    let items: Vec<String> = decls
+        .iter()
+        .map(|d| {
+            format!(
+                "{{\"type\": {}, \"name\": {}}}",
+                quote(&d.ty),
+                quote(&d.name)
+            )
+        })
+        .collect();
+    format!("[{}]", items.join(", "))
+}
+
+fn array(items: Vec<String>) -> String {
+    if items.is_empty() {
+        return "[]".to_string();
+    }
+    format!("[\n    {}\n  ]", items.join(",\n    "))
+}
+
+/// Every function (with its documented error codes), struct, callback type
+/// and constant of the API.
+pub fn api_to_json(api: &Api) -> String {
+    let functions = api
+        .functions
+        .iter()
+        .map(|f| {
+            let codes: Vec<String> = f
+                .error_codes
+                .iter()
+                .map(|(code, text)| format!("{}: {}", quote(&code.to_string()), quote(text)))
+                .collect();
+            format!(
+                "{{\"name\": {}, \"return\": {}, \"params\": {}, \"error_codes\": {{{}}}, \"doc\": {}}}",
+                quote(&f.name),
+                quote(&f.ret),
+                decls(&f.params),
+                codes.join(", "),
+                quote(&f.doc)
+            )
+        })
+        .collect();
+    let structs = api
+        .structs
+        .iter()
+        .map(|s| {
+            format!(
+                "{{\"name\": {}, \"opaque\": {}, \"fields\": {}, \"doc\": {}}}",
+                quote(&s.name),
+                s.opaque,
+                decls(&s.fields),
+                quote(&s.doc)
+            )
+        })
+        .collect();
+    let callbacks = api
+        .callbacks
+        .iter()
+        .map(|c| {
+            format!(
+                "{{\"name\": {}, \"return\": {}, \"params\": {}, \"doc\": {}}}",
+                quote(&c.name),
+                quote(&c.ret),
+                decls(&c.params),
+                quote(&c.doc)
+            )
+        })
+        .collect();
+    let constants = api
+        .constants
+        .iter()
+        .map(|c| {
+            format!(
+                "{{\"name\": {}, \"value\": {}, \"doc\": {}}}",
+                quote(&c.name),
+                quote(&c.value),
+                quote(&c.doc)
+            )
+        })
+        .collect();
+    format!(
+        "{{\n  \"common_error_codes\": {{\"0\": \"success\", \"-1\": \"a panic was caught\", \"-2\": \"the operation was cancelled\"}},\n  \"functions\": {},\n  \"structs\": {},\n  \"callbacks\": {},\n  \"constants\": {}\n}}\n",
+        array(functions),
+        array(structs),
+        array(callbacks),
+        array(constants)
+    )
+}
@@ -0,0 +1,64 @@
+use cbindgen;
+use std::path::Path;
+use cargo_metadata::{MetadataCommand, CargoOpt};
+
+mod header;
+mod json;
+mod python;
+
+fn main() {
+    let metadata = MetadataCommand::new()
+        .manifest_path("./Cargo.toml")
+        .features(CargoOpt::AllFeatures)
+        .exec()
+        .unwrap();
+    let cavc_ffi_package =
+        metadata.packages.iter().find(|&p| p.name == "cavalier_contours_ffi")
+        .unwrap();
+    let cavc_ffi_path = cavc_ffi_package.manifest_path.parent().unwrap();
+    // Exposed through cavc_upstream_version
+    let cavc_version = &metadata.packages.iter().find(|&p| p.name == "cavalier_contours")
+        .unwrap()
+        .version;
+    println!("cargo:rustc-env=CAVC_UPSTREAM_VERSION_MAJOR={}", cavc_version.major);
+    println!("cargo:rustc-env=CAVC_UPSTREAM_VERSION_MINOR={}", cavc_version.minor);
+    println!("cargo:rustc-env=CAVC_UPSTREAM_VERSION_PATCH={}", cavc_version.patch);
+
+
+    let bindings = cbindgen::Builder::new()
+        .with_no_includes()
+        .with_language(cbindgen::Language::C)
+        .with_crate(cavc_ffi_path)
+        .generate()
+        .unwrap();
+    // Our own extensions refer to the upstream types, so they have to be
+    // declared after them in the same header.
+    let ext_bindings = cbindgen::Builder::new()
+        .with_no_includes()
+        .with_language(cbindgen::Language::C)
+        .with_crate(env!("CARGO_MANIFEST_DIR"))
+        .generate()
+        .unwrap();
+
+    let mut header = Vec::new();
+    bindings.write(&mut header);
+    ext_bindings.write(&mut header);
+    std::fs::write(Path::new("target").join("header.h"), &header).unwrap();
+
+    // Declarations for the Python side and a description of the whole API
+    // (including the error codes documented on every function) so they never
+    // get out of sync with the header.
+    let header_text = String::from_utf8(header).unwrap();
+    let api = header::parse(&header_text);
+    std::fs::write(Path::new("target").join("cavc_api.json"), json::api_to_json(&api)).unwrap();
+    std::fs::write(Path::new("target").join("cavc_cdef.py"), python::api_to_python(&api, &header_text)).unwrap();
+
+    // Exported function names, exposed through cavc_has_feature so functions
+    // added to a module can be probed on their own.
+    let mut functions: Vec<_> = api.functions.iter().map(|f| f.name.as_str()).collect();
+    functions.sort();
+    functions.dedup();
+    let functions: Vec<_> = functions.iter().map(|name| format!("{:?}", name)).collect();
+    let out_dir = std::env::var("OUT_DIR").unwrap();
+    std::fs::write(Path::new(&out_dir).join("functions.rs"), format!("&[{}]", functions.join(", "))).unwrap();
+}
@@ -0,0 +1,60 @@
+//! Python module with the cffi declarations and error codes of the API.
+use crate::header::Api;
+use crate::json::quote;
+
+/// Header without the preprocessor lines cffi does not understand, only
+/// integer `#define` constants are kept.
+fn cdef(header: &str) -> String {
+    let mut out = String::new();
+    for line in header.lines() {
+        let trimmed = line.trim();
+        if let Some(rest) = trimmed.strip_prefix("#define ") {
+            let value = rest.split_whitespace().nth(1).unwrap_or_default();
+            if value.parse::<i64>().is_err() {
+                continue;
+            }
+        } else if trimmed.starts_with('#') {
+            continue;
+        }
+        out.push_str(line);
+        out.push('\n');
+    }
+    out
+}
+
+/// Python source defining `CDEF` (the declarations to pass to `ffi.cdef`),
+/// `FUNCTIONS` (return type and parameter types by function name),
+/// `ERROR_CODES` (specific error codes by function name) and
+/// `COMMON_ERROR_CODES`.
+pub fn api_to_python(api: &Api, header: &str) -> String {
+    let mut out = String::new();
+    out.push_str("# Generated by build/main.rs from header.h, do not edit.\n\n");
+    out.push_str(&format!("CDEF = {}\n\n", quote(&cdef(header))));
+    out.push_str("FUNCTIONS = {\n");
+    for f in &api.functions {
+        let params: Vec<String> = f.params.iter().map(|p| quote(&p.ty)).collect();
+        out.push_str(&format!(
+            "    {}: ({}, [{}]),\n",
+            quote(&f.name),
+            quote(&f.ret),
+            params.join(", ")
+        ));
+    }
+    out.push_str("}\n\n");
+    out.push_str("ERROR_CODES = {\n");
+    for f in api.functions.iter().filter(|f| !f.error_codes.is_empty()) {
+        let codes: Vec<String> = f
+            .error_codes
+            .iter()
+            .map(|(code, text)| format!("{}: {}", code, quote(text)))
+            .collect();
+        out.push_str(&format!(
+            "    {}: {{{}}},\n",
+            quote(&f.name),
+            codes.join(", ")
+        ));
+    }
+    out.push_str("}\n\n");
+    out.push_str("COMMON_ERROR_CODES = {0: \"success\", -1: \"a panic was caught\", -2: \"the operation was cancelled\"}\n");
+    out
+}
@@ -1 +1,12 @@
-from .polyline import Vertex, Polyline
+try:
+    from .polyline import Vertex, Polyline
+except ModuleNotFoundError:
+    # wheels of the native extension module do not ship the cffi bindings
+    pass
+
+try:
+    from . import _native as native
+except ModuleNotFoundError:
+    # the native extension module is only built with the `python` feature,
+    # see src/python.rs
+    native = None
@@ -82,7 +82,7 @@ class Polyline(MutableSequence[Vertex]):
             lib.cavc_pline_add(self.native, v.x, v.y, v.bulge)
 
     def __del__(self) -> None:
-        lib.cavc_pline_f(self.native)
+        lib.cavc_pline_release(self.native)
 
     def __str__(self) -> str:
         return f"Polyline({', '.join([str(x) for x in self])})"
@@ -218,7 +218,10 @@ class Polyline(MutableSequence[Vertex]):
         other, along a straight line, or forming a concentric arc with sweep
         angle less than or equal to PI.
         """
-        lib.cavc_pline_remove_redundant(self.native, eps)
+        result = ffi.new("cavc_pline**")
+        lib.cavc_pline_remove_redundant(self.native, eps, result)
+        lib.cavc_pline_release(self.native)
+        self.native = result[0]
 
     def clear(self) -> None:
         """
@@ -282,7 +285,7 @@ class Polyline(MutableSequence[Vertex]):
             polyline = Polyline.__new__(Polyline)
             polyline.native = p_native[0]
             result.append(polyline)
-        lib.cavc_plinelist_f(list_handle)
+        lib.cavc_plinelist_release(list_handle)
         return result
 
 
@@ -0,0 +1,356 @@
+//! Compact binary serialization of polylines.
+//!
+//! All values are little endian. Data starts with a header of the magic bytes
+//! `CAVC`, the format version (u16, currently 1) and the kind of the content
+//! (u16, 1 = polyline, 2 = polyline list). A polyline is written as a flags
+//! byte (bit 0 set if closed), its vertex count (u32) and its vertexes as
+//! `x, y, bulge` f64 triples. A polyline list is written as its polyline count
+//! (u32) followed by its polylines. Values are stored bit exact, so a round
+//! trip is lossless (including NaN and infinite values).
+use cavalier_contours::polyline::{PlineCreation, PlineSource, PlineSourceMut, Polyline};
+use cavalier_contours_ffi::{cavc_pline, cavc_plinelist};
+
+use crate::buffer::cavc_buffer;
+use crate::error::set_last_error;
+use crate::util::{into_raw, pline_into_raw, plinelist_into_raw};
+
+const MAGIC: &[u8; 4] = b"CAVC";
+const VERSION: u16 = 1;
+const KIND_PLINE: u16 = 1;
+const KIND_PLINELIST: u16 = 2;
+
+fn write_header(out: &mut Vec<u8>, kind: u16) {
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&VERSION.to_le_bytes());
+    out.extend_from_slice(&kind.to_le_bytes());
+}
+
+fn write_pline(out: &mut Vec<u8>, pline: &Polyline<f64>) {
+    out.push(pline.is_closed() as u8);
+    out.extend_from_slice(&(pline.vertex_count() as u32).to_le_bytes());
+    for v in pline.iter_vertexes() {
+        for value in [v.x, v.y, v.bulge] {
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+}
+
+/// Error returned when reading binary data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BinaryError {
+    Malformed(&'static str),
+    UnsupportedVersion,
+}
+
+/// Reader over the bytes of serialized data.
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], BinaryError> {
+        if self.bytes.len() < len {
+            return Err(BinaryError::Malformed("unexpected end of data"));
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8, BinaryError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, BinaryError> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, BinaryError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn f64(&mut self) -> Result<f64, BinaryError> {
+        Ok(f64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    /// Read the header and check that the content is of `kind`.
+    fn header(&mut self, kind: u16) -> Result<(), BinaryError> {
+        if self.take(4)? != MAGIC {
+            return Err(BinaryError::Malformed("not serialized polyline data"));
+        }
+        if self.u16()? > VERSION {
+            return Err(BinaryError::UnsupportedVersion);
+        }
+        match self.u16()? {
+            k if k == kind => Ok(()),
+            KIND_PLINE => Err(BinaryError::Malformed("data holds a polyline")),
+            KIND_PLINELIST => Err(BinaryError::Malformed("data holds a polyline list")),
+            _ => Err(BinaryError::Malformed("unknown content kind")),
+        }
+    }
+
+    fn pline(&mut self) -> Result<Polyline<f64>, BinaryError> {
+        let flags = self.u8()?;
+        if flags & !1 != 0 {
+            return Err(BinaryError::Malformed("unknown polyline flags"));
+        }
+        let count = self.u32()? as usize;
+        // the count is checked against the remaining data before allocating
+        if self.bytes.len() / 24 < count {
+            return Err(BinaryError::Malformed("unexpected end of data"));
+        }
+        let mut pline = Polyline::with_capacity(count, flags & 1 != 0);
+        for _ in 0..count {
+            let (x, y, bulge) = (self.f64()?, self.f64()?, self.f64()?);
+            pline.add(x, y, bulge);
+        }
+        Ok(pline)
+    }
+
+    fn finish(&self) -> Result<(), BinaryError> {
+        if self.bytes.is_empty() {
+            Ok(())
+        } else {
+            Err(BinaryError::Malformed("trailing bytes after the data"))
+        }
+    }
+}
+
+/// Read serialized data of `kind` from the `len` bytes of `buffer` with
+/// `read`, recording the reason of a failure.
+///
+/// Returns the error code of the failure: 2 for malformed data and 3 for an
+/// unsupported version.
+unsafe fn read_bytes<T>(
+    buffer: *const u8,
+    len: usize,
+    kind: u16,
+    read: impl FnOnce(&mut Reader) -> Result<T, BinaryError>,
+) -> Result<T, i32> {
+    let bytes: &[u8] = if len == 0 {
+        &[]
+    } else {
+        std::slice::from_raw_parts(buffer, len)
+    };
+    let mut reader = Reader { bytes };
+    let result = reader
+        .header(kind)
+        .and_then(|_| read(&mut reader))
+        .and_then(|value| reader.finish().map(|_| value));
+    result.map_err(|e| match e {
+        BinaryError::Malformed(message) => {
+            set_last_error(message);
+            2
+        }
+        BinaryError::UnsupportedVersion => {
+            set_last_error("data was written by a newer version");
+            3
+        }
+    })
+}
+
+/// Serialize the polyline into the compact binary format (see the module
+/// documentation for the layout).
+///
+/// A polyline with `n` vertexes takes `13 + 24 * n` bytes. `result` is only
+/// written to if the function returns 0 (success) and must be freed with
+/// [crate::buffer::cavc_buffer_f].
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `result` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_to_bytes(
+    pline: *const cavc_pline,
+    result: *mut *mut cavc_buffer,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || result.is_null() {
+            return 1;
+        }
+        let pline = &(*pline).0;
+        let mut bytes = Vec::with_capacity(13 + 24 * pline.vertex_count());
+        write_header(&mut bytes, KIND_PLINE);
+        write_pline(&mut bytes, pline);
+        *result = into_raw(cavc_buffer::from(bytes));
+        0
+    })
+}
+
+/// Create a polyline from the `len` bytes in `buffer` written by
+/// [cavc_pline_to_bytes].
+///
+/// `result` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `result` is null or `buffer` is null while `len` is not 0.
+/// * 2 = the bytes are not a serialized polyline (including truncated data
+///   and data holding a polyline list).
+/// * 3 = the data was written with a newer version of the format.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_from_bytes(
+    buffer: *const u8,
+    len: u32,
+    result: *mut *mut cavc_pline,
+) -> i32 {
+    ffi_catch_unwind!({
+        if result.is_null() || (buffer.is_null() && len != 0) {
+            return 1;
+        }
+        match read_bytes(buffer, len as usize, KIND_PLINE, |reader| reader.pline()) {
+            Ok(pline) => {
+                *result = pline_into_raw(pline);
+                0
+            }
+            Err(code) => code,
+        }
+    })
+}
+
+/// Serialize the polylines of the list into the compact binary format (see
+/// the module documentation for the layout).
+///
+/// `result` is only written to if the function returns 0 (success) and must be
+/// freed with [crate::buffer::cavc_buffer_f].
+///
+/// ## Specific Error Codes
+/// * 1 = `plinelist` or `result` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_plinelist_to_bytes(
+    plinelist: *const cavc_plinelist,
+    result: *mut *mut cavc_buffer,
+) -> i32 {
+    ffi_catch_unwind!({
+        if plinelist.is_null() || result.is_null() {
+            return 1;
+        }
+        let plines = &(*plinelist).0;
+        let mut bytes = Vec::new();
+        write_header(&mut bytes, KIND_PLINELIST);
+        bytes.extend_from_slice(&(plines.len() as u32).to_le_bytes());
+        for &pline in plines.iter() {
+            write_pline(&mut bytes, &(*pline).0);
+        }
+        *result = into_raw(cavc_buffer::from(bytes));
+        0
+    })
+}
+
+/// Create a polyline list from the `len` bytes in `buffer` written by
+/// [cavc_plinelist_to_bytes].
+///
+/// `result` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `result` is null or `buffer` is null while `len` is not 0.
+/// * 2 = the bytes are not a serialized polyline list (including truncated
+///   data and data holding a single polyline).
+/// * 3 = the data was written with a newer version of the format.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_plinelist_from_bytes(
+    buffer: *const u8,
+    len: u32,
+    result: *mut *mut cavc_plinelist,
+) -> i32 {
+    ffi_catch_unwind!({
+        if result.is_null() || (buffer.is_null() && len != 0) {
+            return 1;
+        }
+        let read = |reader: &mut Reader| {
+            let count = reader.u32()?;
+            (0..count)
+                .map(|_| reader.pline())
+                .collect::<Result<Vec<_>, _>>()
+        };
+        match read_bytes(buffer, len as usize, KIND_PLINELIST, read) {
+            Ok(plines) => {
+                *result = plinelist_into_raw(plines);
+                0
+            }
+            Err(code) => code,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_bytes(pline: &Polyline<f64>) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        write_header(&mut bytes, KIND_PLINE);
+        write_pline(&mut bytes, pline);
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Polyline<f64>, i32> {
+        unsafe { read_bytes(bytes.as_ptr(), bytes.len(), KIND_PLINE, |r| r.pline()) }
+    }
+
+    #[test]
+    fn round_trips_bit_exact() {
+        let mut pline = Polyline::with_capacity(3, true);
+        pline.add(0.1, -0.0, 0.5);
+        pline.add(f64::MAX, f64::MIN_POSITIVE, -1.0);
+        pline.add(f64::NAN, f64::INFINITY, f64::NEG_INFINITY);
+        let bytes = to_bytes(&pline);
+        assert_eq!(bytes.len(), 13 + 24 * 3);
+        let read = from_bytes(&bytes).unwrap();
+        assert!(read.is_closed());
+        assert_eq!(read.vertex_count(), 3);
+        for (a, b) in read.iter_vertexes().zip(pline.iter_vertexes()) {
+            for (x, y) in [(a.x, b.x), (a.y, b.y), (a.bulge, b.bulge)] {
+                assert_eq!(x.to_bits(), y.to_bits());
+            }
+        }
+    }
+
+    #[test]
+    fn round_trips_empty_polylines() {
+        let bytes = to_bytes(&Polyline::new());
+        assert_eq!(bytes.len(), 13);
+        let read = from_bytes(&bytes).unwrap();
+        assert!(!read.is_closed());
+        assert_eq!(read.vertex_count(), 0);
+    }
+
+    #[test]
+    fn rejects_truncated_and_trailing_data() {
+        let mut pline = Polyline::new();
+        pline.add(1.0, 2.0, 0.0);
+        pline.add(3.0, 4.0, 0.0);
+        let bytes = to_bytes(&pline);
+        for len in 0..bytes.len() {
+            assert_eq!(from_bytes(&bytes[..len]).err(), Some(2), "{}", len);
+        }
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert_eq!(from_bytes(&trailing).err(), Some(2));
+    }
+
+    #[test]
+    fn rejects_bad_headers() {
+        let bytes = to_bytes(&Polyline::new());
+        let mut magic = bytes.clone();
+        magic[0] = b'X';
+        assert_eq!(from_bytes(&magic).err(), Some(2));
+        let mut version = bytes.clone();
+        version[4] = 2;
+        assert_eq!(from_bytes(&version).err(), Some(3));
+        let mut kind = bytes.clone();
+        kind[6] = KIND_PLINELIST as u8;
+        assert_eq!(from_bytes(&kind).err(), Some(2));
+        let mut flags = bytes;
+        flags[8] = 2;
+        assert_eq!(from_bytes(&flags).err(), Some(2));
+    }
+
+    #[test]
+    fn rejects_huge_counts_without_allocating() {
+        let mut bytes = Vec::new();
+        write_header(&mut bytes, KIND_PLINE);
+        bytes.push(0);
+        bytes.extend_from_slice(&u32::MAX.to_le_bytes());
+        bytes.extend_from_slice(&[0; 24]);
+        assert_eq!(from_bytes(&bytes).err(), Some(2));
+    }
+}
@@ -0,0 +1,1543 @@
+//! Boolean operation variants and post processing.
+use std::os::raw::c_void;
+
+use cavalier_contours::polyline::{
+    BooleanOp, BooleanResultInfo, BooleanResultPline, PlineBooleanOptions, PlineOrientation,
+    PlineSource, PlineSourceMut, Polyline,
+};
+use cavalier_contours_ffi::{cavc_aabbindex, cavc_pline, cavc_plinelist};
+
+use crate::cache::evict;
+use crate::hierarchy::parent_indexes;
+use crate::intersect::find_all_intersects;
+use crate::options::cavc_options;
+use crate::progress::{cavc_progress_fn, Cancelled, Progress, CAVC_CANCELLED};
+use crate::userdata::{get_tag, set_tag, tag_plinelist};
+use crate::util::{free_raw, into_raw, plinelist_into_raw, valid_eps};
+
+/// Convert the FFI boolean operation code into a [BooleanOp].
+pub(crate) fn boolean_op_from_i32(operation: i32) -> Option<BooleanOp> {
+    match operation {
+        0 => Some(BooleanOp::Or),
+        1 => Some(BooleanOp::And),
+        2 => Some(BooleanOp::Not),
+        3 => Some(BooleanOp::Xor),
+        _ => None,
+    }
+}
+
+/// Filled region bounded by a counter clockwise `outer` polyline with
+/// clockwise `holes`.
+#[derive(Debug, Clone)]
+pub(crate) struct Region {
+    pub outer: Polyline<f64>,
+    pub holes: Vec<Polyline<f64>>,
+}
+
+/// Copy of `pline` with counter clockwise orientation.
+pub(crate) fn to_ccw(pline: &Polyline<f64>) -> Polyline<f64> {
+    let mut pline = pline.clone();
+    if pline.orientation() == PlineOrientation::Clockwise {
+        pline.invert_direction_mut();
+    }
+    pline
+}
+
+/// Boolean operation between two closed polylines with default options apart
+/// from `pos_equal_eps`.
+pub(crate) fn boolean_eps(
+    pline1: &Polyline<f64>,
+    pline2: &Polyline<f64>,
+    op: BooleanOp,
+    pos_equal_eps: f64,
+) -> (Vec<Polyline<f64>>, Vec<Polyline<f64>>, BooleanResultInfo) {
+    let mut options = PlineBooleanOptions::new();
+    options.pos_equal_eps = pos_equal_eps;
+    let r = pline1.boolean_opt(pline2, op, &options);
+    let take = |plines: Vec<BooleanResultPline<Polyline<f64>>>| {
+        plines.into_iter().map(|p| p.pline).collect::<Vec<_>>()
+    };
+    (take(r.pos_plines), take(r.neg_plines), r.result_info)
+}
+
+/// Tag for the results of a boolean operation between `pline1` and `pline2`,
+/// the tag of `pline1` or of `pline2` if `pline1` has none.
+fn boolean_tag(pline1: *const cavc_pline, pline2: *const cavc_pline) -> u64 {
+    match get_tag(pline1) {
+        0 => get_tag(pline2),
+        tag => tag,
+    }
+}
+
+/// Extents of `pline` as `[min_x, min_y, max_x, max_y]`.
+fn bounds(pline: &Polyline<f64>) -> Option<[f64; 4]> {
+    pline
+        .extents()
+        .map(|e| [e.min_x, e.min_y, e.max_x, e.max_y])
+}
+
+/// Whether the extents `a` and `b` overlap (or are within `eps`), boolean
+/// operations are only needed between polylines with overlapping extents.
+fn bounds_overlap(a: Option<[f64; 4]>, b: Option<[f64; 4]>, eps: f64) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => {
+            a[0] <= b[2] + eps && b[0] <= a[2] + eps && a[1] <= b[3] + eps && b[1] <= a[3] + eps
+        }
+        _ => false,
+    }
+}
+
+/// Whether the closed polyline `inner` lies strictly inside the closed
+/// polyline `outer`.
+fn is_inside(outer: &Polyline<f64>, inner: &Polyline<f64>, pos_equal_eps: f64) -> bool {
+    if !bounds_overlap(bounds(outer), bounds(inner), pos_equal_eps) {
+        return false;
+    }
+    let (_, _, info) = boolean_eps(outer, inner, BooleanOp::Not, pos_equal_eps);
+    info == BooleanResultInfo::Pline2InsidePline1
+}
+
+/// Whether the filled area of `region` overlaps the closed counter clockwise
+/// polyline `pline`.
+fn overlaps_region(region: &Region, pline: &Polyline<f64>, pos_equal_eps: f64) -> bool {
+    if !bounds_overlap(bounds(&region.outer), bounds(pline), pos_equal_eps) {
+        return false;
+    }
+    let (_, _, info) = boolean_eps(&region.outer, pline, BooleanOp::Or, pos_equal_eps);
+    match info {
+        BooleanResultInfo::Disjoint => false,
+        BooleanResultInfo::Pline2InsidePline1 => !region
+            .holes
+            .iter()
+            .any(|h| is_inside(&to_ccw(h), pline, pos_equal_eps)),
+        _ => true,
+    }
+}
+
+/// Remove the filled area of `region` from the counter clockwise `pieces`.
+fn subtract_region(
+    pieces: Vec<Polyline<f64>>,
+    region: &Region,
+    pos_equal_eps: f64,
+) -> Vec<Polyline<f64>> {
+    let mut result = Vec::new();
+    let region_bounds = bounds(&region.outer);
+    for piece in &pieces {
+        if !bounds_overlap(bounds(piece), region_bounds, pos_equal_eps) {
+            // the holes of the region lie inside its outer boundary
+            result.push(piece.clone());
+            continue;
+        }
+        result.extend(boolean_eps(piece, &region.outer, BooleanOp::Not, pos_equal_eps).0);
+        // the holes of the region are not covered by it
+        for hole in &region.holes {
+            result.extend(boolean_eps(piece, &to_ccw(hole), BooleanOp::And, pos_equal_eps).0);
+        }
+    }
+    result
+}
+
+/// Add `region` to the union of disjoint `regions`.
+///
+/// Regions overlapping the outer boundary of `region` are merged into a single
+/// region. Holes of the merged regions and holes enclosed by the merge are
+/// trimmed by everything that was merged. Regions lying in a hole of another
+/// region (islands) stay separate regions.
+pub(crate) fn union_into(regions: &mut Vec<Region>, region: Region, pos_equal_eps: f64) {
+    let mut merged = region.outer.clone();
+    // holes are tagged with the index of the merged region they belong to (if
+    // any) since they must not be trimmed by it
+    let mut hole_candidates: Vec<_> = region.holes.iter().map(|h| (Some(0), to_ccw(h))).collect();
+    let mut merged_regions = vec![region];
+    let mut merged_bounds = bounds(&merged);
+    let mut region_bounds: Vec<_> = regions.iter().map(|r| bounds(&r.outer)).collect();
+    let mut i = 0;
+    while i < regions.len() {
+        if !bounds_overlap(merged_bounds, region_bounds[i], pos_equal_eps) {
+            i += 1;
+            continue;
+        }
+        let (pos, neg, info) =
+            boolean_eps(&merged, &regions[i].outer, BooleanOp::Or, pos_equal_eps);
+        if info == BooleanResultInfo::Disjoint || pos.len() != 1 {
+            i += 1;
+            continue;
+        }
+        let other = regions.swap_remove(i);
+        region_bounds.swap_remove(i);
+        merged = pos.into_iter().next().unwrap();
+        merged_bounds = bounds(&merged);
+        let owner = merged_regions.len();
+        hole_candidates.extend(neg.iter().map(|h| (None, to_ccw(h))));
+        hole_candidates.extend(other.holes.iter().map(|h| (Some(owner), to_ccw(h))));
+        merged_regions.push(other);
+        // regions skipped earlier may overlap the grown outline
+        i = 0;
+    }
+
+    let in_hole = |j: usize| {
+        hole_candidates.iter().any(|(owner, h)| {
+            *owner != Some(j) && is_inside(h, &merged_regions[j].outer, pos_equal_eps)
+        })
+    };
+    if in_hole(0) {
+        // the added region lies in a hole of a merged region, only the islands
+        // in that hole can overlap it
+        let mut islands = Vec::new();
+        let mut rest = merged_regions.drain(..);
+        let region = rest.next().unwrap();
+        for other in rest {
+            if overlaps_region(&other, &region.outer, pos_equal_eps) {
+                // never the case for the region owning the hole
+                islands.push(other);
+            } else {
+                regions.push(other);
+            }
+        }
+        union_into(&mut islands, region, pos_equal_eps);
+        regions.extend(islands);
+        return;
+    }
+    // the existing regions are disjoint, so a region lying in a hole of
+    // another merged region only joins the union if the added region
+    // overlaps it
+    let is_island: Vec<bool> = (0..merged_regions.len())
+        .map(|j| {
+            j != 0
+                && in_hole(j)
+                && !overlaps_region(&merged_regions[0], &merged_regions[j].outer, pos_equal_eps)
+        })
+        .collect();
+
+    let mut holes = Vec::new();
+    for (owner, candidate) in hole_candidates {
+        if matches!(owner, Some(o) if is_island[o]) {
+            continue;
+        }
+        let mut pieces = vec![candidate];
+        for (j, filler) in merged_regions.iter().enumerate() {
+            if Some(j) != owner && !is_island[j] {
+                pieces = subtract_region(pieces, filler, pos_equal_eps);
+            }
+        }
+        holes.extend(pieces.into_iter().map(|mut h| {
+            h.invert_direction_mut();
+            h
+        }));
+    }
+    for (other, island) in merged_regions.into_iter().zip(is_island) {
+        if island {
+            regions.push(other);
+        }
+    }
+    regions.push(Region {
+        outer: merged,
+        holes,
+    });
+}
+
+/// Units of work of uniting `count` polylines with [union_slice], the number
+/// of polylines taking part in every merge.
+fn union_work(count: usize) -> usize {
+    if count <= 1 {
+        0
+    } else {
+        count + union_work(count / 2) + union_work(count - count / 2)
+    }
+}
+
+/// Union of the regions of `plines` by uniting the unions of both halves.
+///
+/// `step` is called with the units of work done (see [union_work]) after
+/// every region added, an error aborts the union.
+fn union_slice(
+    plines: &[Polyline<f64>],
+    pos_equal_eps: f64,
+    step: &mut dyn FnMut(usize) -> Result<(), Cancelled>,
+) -> Result<Vec<Region>, Cancelled> {
+    if plines.len() <= 1 {
+        return Ok(plines
+            .iter()
+            .map(|p| Region {
+                outer: to_ccw(p),
+                holes: Vec::new(),
+            })
+            .collect());
+    }
+    let (first, second) = plines.split_at(plines.len() / 2);
+    let mut regions = union_slice(first, pos_equal_eps, step)?;
+    for region in union_slice(second, pos_equal_eps, step)? {
+        union_into(&mut regions, region, pos_equal_eps);
+        step(0)?;
+    }
+    step(plines.len())?;
+    Ok(regions)
+}
+
+/// Union of closed polylines (of any orientation) as disjoint regions.
+///
+/// The polylines are united divide and conquer style, keeping the outlines
+/// merged at each step of similar complexity.
+pub(crate) fn union_all(
+    plines: impl IntoIterator<Item = Polyline<f64>>,
+    pos_equal_eps: f64,
+) -> Vec<Region> {
+    let plines: Vec<_> = plines.into_iter().collect();
+    union_slice(&plines, pos_equal_eps, &mut |_| Ok(())).unwrap_or_default()
+}
+
+/// Closed polylines of the list with an area, cloned.
+unsafe fn area_plines(plinelist: *const cavc_plinelist) -> Vec<Polyline<f64>> {
+    (*plinelist)
+        .0
+        .iter()
+        .map(|&p| &(*p).0)
+        .filter(|p| p.is_closed() && p.vertex_count() > 1 && p.area() != 0.0)
+        .cloned()
+        .collect()
+}
+
+/// Outer boundaries of `regions` each followed by its holes.
+pub(crate) fn region_plines(regions: Vec<Region>) -> Vec<Polyline<f64>> {
+    let mut plines = Vec::new();
+    for region in regions {
+        plines.push(region.outer);
+        plines.extend(region.holes);
+    }
+    plines
+}
+
+/// Union of every closed polyline of the list.
+///
+/// Closed polylines of either orientation are treated as filled areas, open
+/// polylines and polylines without area are ignored. `result` receives the
+/// disjoint regions of the union, each as its outer boundary (counter
+/// clockwise) followed by its holes (clockwise). Regions lying in a hole of
+/// another region are separate regions.
+///
+/// The polylines are united by recursively uniting the unions of both halves
+/// of the list, which keeps the merged outlines small compared to adding the
+/// polylines one by one.
+///
+/// `result` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `plinelist` or `result` is null.
+/// * 2 = `pos_equal_eps` is negative, NaN or infinite.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_plinelist_union_all(
+    plinelist: *const cavc_plinelist,
+    pos_equal_eps: f64,
+    result: *mut *mut cavc_plinelist,
+) -> i32 {
+    ffi_catch_unwind!({
+        if plinelist.is_null() || result.is_null() {
+            return 1;
+        }
+        if !valid_eps(pos_equal_eps) {
+            return 2;
+        }
+        let regions = union_all(area_plines(plinelist), pos_equal_eps);
+        *result = plinelist_into_raw(region_plines(regions));
+        0
+    })
+}
+
+/// Same as [cavc_plinelist_union_all] but uses the `pos_equal_eps` of the
+/// shared `options`, which may be null in which case defaults are used.
+///
+/// `result` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `plinelist` or `result` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_plinelist_union_all_opt(
+    plinelist: *const cavc_plinelist,
+    options: *const cavc_options,
+    result: *mut *mut cavc_plinelist,
+) -> i32 {
+    ffi_catch_unwind!({
+        let pos_equal_eps = cavc_options::or_default(options).pos_equal_eps;
+        cavc_plinelist_union_all(plinelist, pos_equal_eps, result)
+    })
+}
+
+/// Same as [cavc_plinelist_union_all] but reports its progress to `progress`
+/// and can be cancelled by it.
+///
+/// `progress` is invoked with `user_data` on the calling thread between the
+/// steps of the union (at most every few milliseconds) with the fraction of
+/// the work done, weighted by the number of polylines taking part in every
+/// merge, and with 1 once the union is done. Once it returns non-zero the
+/// union stops and the function returns [CAVC_CANCELLED], a single step is
+/// not interrupted. If `progress` is null this behaves exactly like
+/// [cavc_plinelist_union_all].
+///
+/// `result` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `plinelist` or `result` is null.
+/// * 2 = `pos_equal_eps` is negative, NaN or infinite.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_plinelist_union_all_cancellable(
+    plinelist: *const cavc_plinelist,
+    pos_equal_eps: f64,
+    progress: cavc_progress_fn,
+    user_data: *mut c_void,
+    result: *mut *mut cavc_plinelist,
+) -> i32 {
+    ffi_catch_unwind!({
+        if plinelist.is_null() || result.is_null() {
+            return 1;
+        }
+        if !valid_eps(pos_equal_eps) {
+            return 2;
+        }
+        let mut progress = Progress::new(progress, user_data);
+        if progress.report(0.0).is_err() {
+            return CAVC_CANCELLED;
+        }
+        let plines = area_plines(plinelist);
+        let total = union_work(plines.len()).max(1) as f64;
+        let mut done = 0;
+        let regions = union_slice(&plines, pos_equal_eps, &mut |work| {
+            done += work;
+            progress.report(done as f64 / total)
+        });
+        match regions {
+            Ok(regions) => {
+                progress.finish();
+                *result = plinelist_into_raw(region_plines(regions));
+                0
+            }
+            Err(Cancelled) => CAVC_CANCELLED,
+        }
+    })
+}
+
+/// Same as [cavc_plinelist_union_all_cancellable] but uses the
+/// `pos_equal_eps` of the shared `options`, which may be null in which case
+/// defaults are used.
+///
+/// `result` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `plinelist` or `result` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_plinelist_union_all_cancellable_opt(
+    plinelist: *const cavc_plinelist,
+    options: *const cavc_options,
+    progress: cavc_progress_fn,
+    user_data: *mut c_void,
+    result: *mut *mut cavc_plinelist,
+) -> i32 {
+    ffi_catch_unwind!({
+        let pos_equal_eps = cavc_options::or_default(options).pos_equal_eps;
+        cavc_plinelist_union_all_cancellable(plinelist, pos_equal_eps, progress, user_data, result)
+    })
+}
+
+/// Same as `cavc_pline_boolean` but reports its progress to `progress` and
+/// can be cancelled by it.
+///
+/// `operation` is 0 = union, 1 = intersection, 2 = difference (pline1 -
+/// pline2), 3 = symmetric difference. The operation runs on the calling
+/// thread, `progress` is invoked with `user_data` before the operation with 0
+/// and with 1 once it is done. The boolean operation of two polylines is a
+/// single step of `cavalier_contours` and is not interrupted, if `progress`
+/// returns non-zero before it the function returns [CAVC_CANCELLED] without
+/// computing anything. Use [cavc_plinelist_union_all_cancellable] or
+/// [cavc_plinelist_boolean] on lists for operations made of many steps. If
+/// `progress` is null this behaves exactly like `cavc_pline_boolean`.
+///
+/// `pos_plinelist` and `neg_plinelist` are only written to if the function
+/// returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline1`, `pline2`, `pos_plinelist` or `neg_plinelist` is null.
+/// * 2 = `operation` is not a valid operation.
+/// * 3 = `pos_equal_eps` is negative, NaN or infinite.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_boolean_cancellable(
+    pline1: *const cavc_pline,
+    pline2: *const cavc_pline,
+    operation: i32,
+    pos_equal_eps: f64,
+    progress: cavc_progress_fn,
+    user_data: *mut c_void,
+    pos_plinelist: *mut *mut cavc_plinelist,
+    neg_plinelist: *mut *mut cavc_plinelist,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline1.is_null()
+            || pline2.is_null()
+            || pos_plinelist.is_null()
+            || neg_plinelist.is_null()
+        {
+            return 1;
+        }
+        if !valid_eps(pos_equal_eps) {
+            return 3;
+        }
+        let operation = match boolean_op_from_i32(operation) {
+            Some(op) => op,
+            None => return 2,
+        };
+        let mut progress = Progress::new(progress, user_data);
+        if progress.report(0.0).is_err() {
+            return CAVC_CANCELLED;
+        }
+        let tag = boolean_tag(pline1, pline2);
+        let (pos, neg, _) = boolean_eps(&(*pline1).0, &(*pline2).0, operation, pos_equal_eps);
+        progress.finish();
+        *pos_plinelist = plinelist_into_raw(pos);
+        *neg_plinelist = plinelist_into_raw(neg);
+        tag_plinelist(*pos_plinelist, tag);
+        tag_plinelist(*neg_plinelist, tag);
+        0
+    })
+}
+
+/// Same as [cavc_pline_boolean_cancellable] but uses the `pos_equal_eps` of
+/// the shared `options`, which may be null in which case defaults are used.
+/// With a null `progress` this is `cavc_pline_boolean` taking the shared
+/// options.
+///
+/// `pos_plinelist` and `neg_plinelist` are only written to if the function
+/// returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline1`, `pline2`, `pos_plinelist` or `neg_plinelist` is null.
+/// * 2 = `operation` is not a valid operation.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_boolean_cancellable_opt(
+    pline1: *const cavc_pline,
+    pline2: *const cavc_pline,
+    operation: i32,
+    options: *const cavc_options,
+    progress: cavc_progress_fn,
+    user_data: *mut c_void,
+    pos_plinelist: *mut *mut cavc_plinelist,
+    neg_plinelist: *mut *mut cavc_plinelist,
+) -> i32 {
+    ffi_catch_unwind!({
+        let pos_equal_eps = cavc_options::or_default(options).pos_equal_eps;
+        cavc_pline_boolean_cancellable(
+            pline1,
+            pline2,
+            operation,
+            pos_equal_eps,
+            progress,
+            user_data,
+            pos_plinelist,
+            neg_plinelist,
+        )
+    })
+}
+
+/// Remove redundant vertexes from every polyline of the list in place.
+unsafe fn remove_redundant_all(plinelist: *mut cavc_plinelist, pos_equal_eps: f64) {
+    for &pline in (*plinelist).0.iter() {
+        if let Some(cleaned) = (*pline).0.remove_redundant(pos_equal_eps) {
+            evict(pline);
+            (*pline).0 = cleaned;
+        }
+    }
+}
+
+/// Clean up the result of `cavc_pline_boolean` in place.
+///
+/// Redundant vertexes (repeated positions, collinear lines and concentric arcs
+/// which can be merged) are removed from every polyline of `pos_plinelist`
+/// and `neg_plinelist`. The polylines keep their positions in the lists so
+/// any association between them stays valid. Either list may be null.
+///
+/// ## Specific Error Codes
+/// * 1 = both `pos_plinelist` and `neg_plinelist` are null.
+/// * 2 = `pos_equal_eps` is negative, NaN or infinite.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_boolean_result_cleanup(
+    pos_plinelist: *mut cavc_plinelist,
+    neg_plinelist: *mut cavc_plinelist,
+    pos_equal_eps: f64,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pos_plinelist.is_null() && neg_plinelist.is_null() {
+            return 1;
+        }
+        if !valid_eps(pos_equal_eps) {
+            return 2;
+        }
+        for list in [pos_plinelist, neg_plinelist] {
+            if !list.is_null() {
+                remove_redundant_all(list, pos_equal_eps);
+            }
+        }
+        0
+    })
+}
+
+/// Options for [cavc_pline_boolean_opt].
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct cavc_pline_boolean_opt_o {
+    /// Fuzzy comparison epsilon used for determining if two positions are
+    /// equal.
+    pub pos_equal_eps: f64,
+    /// Result polylines with an absolute area less than this are dropped,
+    /// 0 keeps everything.
+    pub collapsed_area_eps: f64,
+    /// Orientation of the result polylines: 0 = as produced by the operation,
+    /// 1 = positive polylines counter clockwise and negative polylines
+    /// clockwise, 2 = positive polylines clockwise and negative polylines
+    /// counter clockwise.
+    pub orientation: i32,
+}
+
+impl Default for cavc_pline_boolean_opt_o {
+    fn default() -> Self {
+        Self {
+            pos_equal_eps: PlineBooleanOptions::<f64>::new().pos_equal_eps,
+            collapsed_area_eps: 0.0,
+            orientation: 0,
+        }
+    }
+}
+
+/// Write the default [cavc_pline_boolean_opt_o] values to `options`.
+///
+/// ## Specific Error Codes
+/// * 1 = `options` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_boolean_opt_o_init(
+    options: *mut cavc_pline_boolean_opt_o,
+) -> i32 {
+    ffi_catch_unwind!({
+        if options.is_null() {
+            return 1;
+        }
+        *options = cavc_pline_boolean_opt_o::default();
+        0
+    })
+}
+
+/// Copy the `pos_equal_eps` of the shared `options` into `boolean_options`, so
+/// [cavc_pline_boolean_opt] uses the same tolerance as the other `_opt`
+/// functions. The other fields of `boolean_options` are left unchanged.
+///
+/// ## Specific Error Codes
+/// * 1 = `boolean_options` or `options` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_boolean_opt_o_set_options(
+    boolean_options: *mut cavc_pline_boolean_opt_o,
+    options: *const cavc_options,
+) -> i32 {
+    ffi_catch_unwind!({
+        if boolean_options.is_null() || options.is_null() {
+            return 1;
+        }
+        (*boolean_options).pos_equal_eps = (*options).pos_equal_eps;
+        0
+    })
+}
+
+/// Boolean result polyline along with the input each of its slices came
+/// from.
+struct OriginTrackedPline {
+    pline: cavc_pline,
+    /// 0 = pline1, 1 = pline2, in path order of `pline`.
+    slice_origins: Vec<u8>,
+}
+
+/// Opaque type holding the result of [cavc_pline_boolean_opt].
+pub struct cavc_boolean_result {
+    pos_plines: Vec<OriginTrackedPline>,
+    neg_plines: Vec<OriginTrackedPline>,
+}
+
+impl cavc_boolean_result {
+    fn plines(&self, is_neg: u8) -> &[OriginTrackedPline] {
+        if is_neg != 0 {
+            &self.neg_plines
+        } else {
+            &self.pos_plines
+        }
+    }
+}
+
+/// Convert upstream results applying the area filter and orientation option.
+fn track_origins(
+    results: Vec<BooleanResultPline<Polyline<f64>>>,
+    options: &cavc_pline_boolean_opt_o,
+    want_ccw: Option<bool>,
+) -> Vec<OriginTrackedPline> {
+    results
+        .into_iter()
+        .filter(|r| r.pline.area().abs() >= options.collapsed_area_eps)
+        .map(|r| {
+            let mut pline = r.pline;
+            let mut slice_origins: Vec<u8> = r
+                .subslices
+                .iter()
+                .map(|s| if s.source_is_pline1 { 0 } else { 1 })
+                .collect();
+            if let Some(want_ccw) = want_ccw {
+                let is_ccw = pline.orientation() == PlineOrientation::CounterClockwise;
+                if is_ccw != want_ccw {
+                    pline.invert_direction_mut();
+                    slice_origins.reverse();
+                }
+            }
+            OriginTrackedPline {
+                pline: cavc_pline(pline),
+                slice_origins,
+            }
+        })
+        .collect()
+}
+
+/// Perform a boolean operation between two closed polylines with extended
+/// options, tracking which input every slice of the result came from.
+///
+/// `operation` is 0 = union, 1 = intersection, 2 = difference (pline1 -
+/// pline2), 3 = symmetric difference, the same as for `cavc_pline_boolean`.
+/// `options` may be null in which case defaults are used (see
+/// [cavc_pline_boolean_opt_o_init]).
+///
+/// `result` is only written to if the function returns 0 (success) and must be
+/// freed with [cavc_boolean_result_f].
+///
+/// ## Specific Error Codes
+/// * 1 = `pline1`, `pline2` or `result` is null.
+/// * 2 = `operation` is not a valid operation.
+/// * 3 = `options` has an invalid `orientation`.
+/// * 4 = `pos_equal_eps` or `collapsed_area_eps` of `options` is negative, NaN
+///   or infinite.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_boolean_opt(
+    pline1: *const cavc_pline,
+    pline2: *const cavc_pline,
+    operation: i32,
+    options: *const cavc_pline_boolean_opt_o,
+    result: *mut *mut cavc_boolean_result,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline1.is_null() || pline2.is_null() || result.is_null() {
+            return 1;
+        }
+        let operation = match boolean_op_from_i32(operation) {
+            Some(op) => op,
+            None => return 2,
+        };
+        let options = options.as_ref().copied().unwrap_or_default();
+        let (pos_ccw, neg_ccw) = match options.orientation {
+            0 => (None, None),
+            1 => (Some(true), Some(false)),
+            2 => (Some(false), Some(true)),
+            _ => return 3,
+        };
+        if !valid_eps(options.pos_equal_eps) || !valid_eps(options.collapsed_area_eps) {
+            return 4;
+        }
+
+        let mut internal_options = PlineBooleanOptions::new();
+        internal_options.pos_equal_eps = options.pos_equal_eps;
+        let boolean_result = (*pline1)
+            .0
+            .boolean_opt(&(*pline2).0, operation, &internal_options);
+        *result = into_raw(cavc_boolean_result {
+            pos_plines: track_origins(boolean_result.pos_plines, &options, pos_ccw),
+            neg_plines: track_origins(boolean_result.neg_plines, &options, neg_ccw),
+        });
+        let tags = [get_tag(pline1), get_tag(pline2)];
+        for p in (**result).pos_plines.iter().chain(&(**result).neg_plines) {
+            // the input contributing most slices dominates
+            let from_pline2 = p.slice_origins.iter().filter(|&&o| o == 1).count();
+            let from_pline1 = p.slice_origins.len() - from_pline2;
+            let (dominant, other) = if from_pline1 >= from_pline2 {
+                (tags[0], tags[1])
+            } else {
+                (tags[1], tags[0])
+            };
+            set_tag(&p.pline, if dominant != 0 { dominant } else { other });
+        }
+        0
+    })
+}
+
+/// Free an existing [cavc_boolean_result] object. Nothing happens if `result`
+/// is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_boolean_result_f(result: *mut cavc_boolean_result) {
+    if !result.is_null() {
+        for p in (*result).pos_plines.iter().chain(&(*result).neg_plines) {
+            set_tag(&p.pline, 0);
+        }
+        free_raw(result);
+    }
+}
+
+/// Get the number of positive (`is_neg` = 0) or negative (`is_neg` != 0)
+/// polylines of the result.
+///
+/// ## Specific Error Codes
+/// * 1 = `result` or `count` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_boolean_result_get_count(
+    result: *const cavc_boolean_result,
+    is_neg: u8,
+    count: *mut u32,
+) -> i32 {
+    ffi_catch_unwind!({
+        if result.is_null() || count.is_null() {
+            return 1;
+        }
+        *count = (*result).plines(is_neg).len() as u32;
+        0
+    })
+}
+
+/// Get a positive (`is_neg` = 0) or negative (`is_neg` != 0) polyline of the
+/// result.
+///
+/// The polyline is owned by `result` and stays valid until the result is
+/// freed, use `cavc_pline_clone` to keep it around longer.
+///
+/// ## Specific Error Codes
+/// * 1 = `result` or `pline` is null.
+/// * 2 = `index` is out of bounds.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_boolean_result_get_pline(
+    result: *const cavc_boolean_result,
+    is_neg: u8,
+    index: u32,
+    pline: *mut *const cavc_pline,
+) -> i32 {
+    ffi_catch_unwind!({
+        if result.is_null() || pline.is_null() {
+            return 1;
+        }
+        match (*result).plines(is_neg).get(index as usize) {
+            Some(p) => {
+                *pline = &p.pline;
+                0
+            }
+            None => 2,
+        }
+    })
+}
+
+/// Get the number of slices a result polyline was stitched together from.
+///
+/// ## Specific Error Codes
+/// * 1 = `result` or `count` is null.
+/// * 2 = `index` is out of bounds.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_boolean_result_get_slice_count(
+    result: *const cavc_boolean_result,
+    is_neg: u8,
+    index: u32,
+    count: *mut u32,
+) -> i32 {
+    ffi_catch_unwind!({
+        if result.is_null() || count.is_null() {
+            return 1;
+        }
+        match (*result).plines(is_neg).get(index as usize) {
+            Some(p) => {
+                *count = p.slice_origins.len() as u32;
+                0
+            }
+            None => 2,
+        }
+    })
+}
+
+/// Write the origin of every slice of a result polyline to `origins`, 0 if the
+/// slice came from pline1 and 1 if it came from pline2. Slices are in path
+/// order of the result polyline.
+///
+/// `origins` must have room for as many values as
+/// [cavc_boolean_result_get_slice_count] reports.
+///
+/// ## Specific Error Codes
+/// * 1 = `result` or `origins` is null.
+/// * 2 = `index` is out of bounds.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_boolean_result_get_slice_origins(
+    result: *const cavc_boolean_result,
+    is_neg: u8,
+    index: u32,
+    origins: *mut u8,
+) -> i32 {
+    ffi_catch_unwind!({
+        if result.is_null() || origins.is_null() {
+            return 1;
+        }
+        match (*result).plines(is_neg).get(index as usize) {
+            Some(p) => {
+                std::ptr::copy_nonoverlapping(
+                    p.slice_origins.as_ptr(),
+                    origins,
+                    p.slice_origins.len(),
+                );
+                0
+            }
+            None => 2,
+        }
+    })
+}
+
+/// Perform a boolean operation between two closed polylines with the full set
+/// of upstream boolean options and pruning of collapsed results.
+///
+/// `operation` is the same as for `cavc_pline_boolean`. `pline1_aabbindex`
+/// may be a spatial index of `pline1` in its current state (e.g. from
+/// `cavc_pline_create_approx_aabb_index`) to avoid rebuilding it when
+/// combining one polyline with many others, or null in which case it is built
+/// internally. Result polylines with an absolute area less than
+/// `collapsed_area_eps` (hairline slivers left by nearly coincident edges)
+/// are dropped, 0 keeps everything.
+///
+/// `pos_plinelist` and `neg_plinelist` are only written to if the function
+/// returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline1`, `pline2`, `pos_plinelist` or `neg_plinelist` is null.
+/// * 2 = `operation` is not a valid operation.
+/// * 3 = `collapsed_area_eps` is negative or NaN.
+/// * 4 = `pos_equal_eps` is negative, NaN or infinite.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_boolean_with_options(
+    pline1: *const cavc_pline,
+    pline2: *const cavc_pline,
+    operation: i32,
+    pline1_aabbindex: *const cavc_aabbindex,
+    pos_equal_eps: f64,
+    collapsed_area_eps: f64,
+    pos_plinelist: *mut *mut cavc_plinelist,
+    neg_plinelist: *mut *mut cavc_plinelist,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline1.is_null()
+            || pline2.is_null()
+            || pos_plinelist.is_null()
+            || neg_plinelist.is_null()
+        {
+            return 1;
+        }
+        if !valid_eps(pos_equal_eps) {
+            return 4;
+        }
+        let operation = match boolean_op_from_i32(operation) {
+            Some(op) => op,
+            None => return 2,
+        };
+        if collapsed_area_eps.is_nan() || collapsed_area_eps < 0.0 {
+            return 3;
+        }
+        let mut options = PlineBooleanOptions::new();
+        options.pos_equal_eps = pos_equal_eps;
+        options.pline1_aabb_index = pline1_aabbindex.as_ref().map(|i| &i.0);
+        let result = (*pline1).0.boolean_opt(&(*pline2).0, operation, &options);
+        let keep = |plines: Vec<BooleanResultPline<Polyline<f64>>>| {
+            plines
+                .into_iter()
+                .map(|r| r.pline)
+                .filter(|p| p.area().abs() >= collapsed_area_eps)
+                .collect()
+        };
+        *pos_plinelist = plinelist_into_raw(keep(result.pos_plines));
+        *neg_plinelist = plinelist_into_raw(keep(result.neg_plines));
+        let tag = boolean_tag(pline1, pline2);
+        tag_plinelist(*pos_plinelist, tag);
+        tag_plinelist(*neg_plinelist, tag);
+        0
+    })
+}
+
+/// Same as [cavc_pline_boolean_with_options] but uses the `pos_equal_eps` of
+/// the shared `options`, which may be null in which case defaults are used.
+///
+/// `pos_plinelist` and `neg_plinelist` are only written to if the function
+/// returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline1`, `pline2`, `pos_plinelist` or `neg_plinelist` is null.
+/// * 2 = `operation` is not a valid operation.
+/// * 3 = `collapsed_area_eps` is negative or NaN.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_boolean_with_options_opt(
+    pline1: *const cavc_pline,
+    pline2: *const cavc_pline,
+    operation: i32,
+    pline1_aabbindex: *const cavc_aabbindex,
+    options: *const cavc_options,
+    collapsed_area_eps: f64,
+    pos_plinelist: *mut *mut cavc_plinelist,
+    neg_plinelist: *mut *mut cavc_plinelist,
+) -> i32 {
+    ffi_catch_unwind!({
+        let pos_equal_eps = cavc_options::or_default(options).pos_equal_eps;
+        cavc_pline_boolean_with_options(
+            pline1,
+            pline2,
+            operation,
+            pline1_aabbindex,
+            pos_equal_eps,
+            collapsed_area_eps,
+            pos_plinelist,
+            neg_plinelist,
+        )
+    })
+}
+
+/// Compute the area of the intersection of two closed polylines without
+/// creating any result polylines.
+///
+/// The area is the same as the total area of the result of an intersection
+/// with `cavc_pline_boolean` (the areas of the positive polylines minus the
+/// areas of the holes), it is never negative. The orientation of the
+/// polylines does not matter.
+///
+/// `out_area` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline1`, `pline2` or `out_area` is null.
+/// * 2 = `pline1` or `pline2` is not closed.
+/// * 3 = `pos_equal_eps` is negative or not finite.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_overlap_area(
+    pline1: *const cavc_pline,
+    pline2: *const cavc_pline,
+    pos_equal_eps: f64,
+    out_area: *mut f64,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline1.is_null() || pline2.is_null() || out_area.is_null() {
+            return 1;
+        }
+        let pline1 = &(*pline1).0;
+        let pline2 = &(*pline2).0;
+        if !pline1.is_closed() || !pline2.is_closed() {
+            return 2;
+        }
+        if !valid_eps(pos_equal_eps) {
+            return 3;
+        }
+        let (pos, neg, _) = boolean_eps(pline1, pline2, BooleanOp::And, pos_equal_eps);
+        let total = |plines: &[Polyline<f64>]| plines.iter().map(|p| p.area().abs()).sum::<f64>();
+        *out_area = (total(&pos) - total(&neg)).max(0.0);
+        0
+    })
+}
+
+/// Root of the set of `i` in the disjoint set forest `parents`, compressing
+/// the path to it.
+fn set_root(parents: &mut [usize], mut i: usize) -> usize {
+    while parents[i] != i {
+        parents[i] = parents[parents[i]];
+        i = parents[i];
+    }
+    i
+}
+
+/// Merge the closed polylines of the list sharing part of an edge, removing
+/// the common boundaries.
+///
+/// Closed polylines with overlapping segments (coincident within `eps`, as
+/// reported by the overlapping intersects of `cavc_pline_find_intersects`) are
+/// grouped transitively and every group is replaced by its union as done by
+/// [cavc_plinelist_union_all]: the outer boundary (counter clockwise) of every
+/// merged region followed by its holes (clockwise). This is common line
+/// removal for parts nested edge to edge, the shared edges are cut only once
+/// as part of the merged outline. Polylines only touching at points are not
+/// merged. Polylines not sharing an edge with another one and open polylines
+/// are copied unchanged. The result keeps the order of the list, a merged
+/// group takes the place of its first polyline.
+///
+/// `result` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `plinelist` or `result` is null.
+/// * 2 = `eps` is not greater than 0 or not finite.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_plinelist_weld_shared_edges(
+    plinelist: *const cavc_plinelist,
+    eps: f64,
+    result: *mut *mut cavc_plinelist,
+) -> i32 {
+    ffi_catch_unwind!({
+        if plinelist.is_null() || result.is_null() {
+            return 1;
+        }
+        if !eps.is_finite() || eps <= 0.0 {
+            return 2;
+        }
+        let plines: Vec<&Polyline<f64>> = (*plinelist).0.iter().map(|&p| &(*p).0).collect();
+        let is_area = |p: &Polyline<f64>| p.is_closed() && p.vertex_count() > 1;
+        let mut parents: Vec<usize> = (0..plines.len()).collect();
+        for (i, j, intersects) in find_all_intersects(&plines, eps) {
+            let (i, j) = (i as usize, j as usize);
+            if intersects.overlapping.is_empty() || !is_area(plines[i]) || !is_area(plines[j]) {
+                continue;
+            }
+            let (root_i, root_j) = (set_root(&mut parents, i), set_root(&mut parents, j));
+            // the smaller index stays the root so groups are ordered by their
+            // first polyline
+            parents[root_i.max(root_j)] = root_i.min(root_j);
+        }
+        let mut groups: Vec<Vec<Polyline<f64>>> = vec![Vec::new(); plines.len()];
+        for (i, pline) in plines.iter().enumerate() {
+            let root = set_root(&mut parents, i);
+            groups[root].push((*pline).clone());
+        }
+        let mut plines_out = Vec::with_capacity(plines.len());
+        // tag of every output polyline, a merged group takes the tag of its
+        // first polyline
+        let mut tags = Vec::with_capacity(plines.len());
+        for (i, group) in groups.into_iter().enumerate() {
+            if group.len() > 1 {
+                plines_out.extend(region_plines(union_all(group, eps)));
+            } else {
+                plines_out.extend(group);
+            }
+            tags.resize(plines_out.len(), get_tag((*plinelist).0[i]));
+        }
+        *result = plinelist_into_raw(plines_out);
+        for (&pline, &tag) in (**result).0.iter().zip(&tags) {
+            set_tag(pline, tag);
+        }
+        0
+    })
+}
+
+/// Fill rules of [cavc_plinelist_boolean_o].
+pub(crate) const FILL_EVEN_ODD: u32 = 0;
+pub(crate) const FILL_ORIENTATION: u32 = 1;
+
+/// Options for [cavc_plinelist_boolean].
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct cavc_plinelist_boolean_o {
+    /// Fuzzy comparison epsilon used for determining if two positions are
+    /// equal.
+    pub pos_equal_eps: f64,
+    /// How the closed polylines of a list form regions: 0 = even-odd, a
+    /// polyline nested in an odd number of others is a hole, 1 = orientation,
+    /// counter clockwise polylines are filled and clockwise ones are holes.
+    pub fill_rule: u32,
+}
+
+impl Default for cavc_plinelist_boolean_o {
+    fn default() -> Self {
+        Self {
+            pos_equal_eps: PlineBooleanOptions::<f64>::new().pos_equal_eps,
+            fill_rule: FILL_EVEN_ODD,
+        }
+    }
+}
+
+/// Write the default [cavc_plinelist_boolean_o] values to `options`.
+///
+/// ## Specific Error Codes
+/// * 1 = `options` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_plinelist_boolean_o_init(
+    options: *mut cavc_plinelist_boolean_o,
+) -> i32 {
+    ffi_catch_unwind!({
+        if options.is_null() {
+            return 1;
+        }
+        *options = cavc_plinelist_boolean_o::default();
+        0
+    })
+}
+
+/// Copy the `pos_equal_eps` of the shared `options` into `list_options`, so
+/// [cavc_plinelist_boolean] uses the same tolerance as the other `_opt`
+/// functions. The `fill_rule` of `list_options` is left unchanged.
+///
+/// ## Specific Error Codes
+/// * 1 = `list_options` or `options` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_plinelist_boolean_o_set_options(
+    list_options: *mut cavc_plinelist_boolean_o,
+    options: *const cavc_options,
+) -> i32 {
+    ffi_catch_unwind!({
+        if list_options.is_null() || options.is_null() {
+            return 1;
+        }
+        (*list_options).pos_equal_eps = (*options).pos_equal_eps;
+        0
+    })
+}
+
+/// Copy of `pline` with clockwise orientation.
+fn to_cw(pline: &Polyline<f64>) -> Polyline<f64> {
+    let mut pline = to_ccw(pline);
+    pline.invert_direction_mut();
+    pline
+}
+
+/// Disjoint regions filled by the closed polylines `plines` under
+/// `fill_rule`.
+pub(crate) fn list_regions(
+    plines: &[&Polyline<f64>],
+    fill_rule: u32,
+    pos_equal_eps: f64,
+) -> Vec<Region> {
+    let parents = &parent_indexes(plines);
+    let ancestors = |mut i: usize| {
+        std::iter::from_fn(move || {
+            let parent = usize::try_from(parents[i]).ok()?;
+            i = parent;
+            Some(parent)
+        })
+    };
+    let is_outer: Vec<bool> = (0..plines.len())
+        .map(|i| match fill_rule {
+            FILL_EVEN_ODD => ancestors(i).count() % 2 == 0,
+            _ => plines[i].orientation() == PlineOrientation::CounterClockwise,
+        })
+        .collect();
+    let mut regions: Vec<Option<Region>> = (0..plines.len())
+        .map(|i| {
+            is_outer[i].then(|| Region {
+                outer: to_ccw(plines[i]),
+                holes: Vec::new(),
+            })
+        })
+        .collect();
+    for i in (0..plines.len()).filter(|&i| !is_outer[i]) {
+        // holes outside of every filled polyline cut nothing
+        if let Some(owner) = ancestors(i).find(|&j| is_outer[j]) {
+            if let Some(region) = regions[owner].as_mut() {
+                region.holes.push(to_cw(plines[i]));
+            }
+        }
+    }
+    // regions of the same list may overlap, their union makes them disjoint
+    let mut result = Vec::new();
+    for region in regions.into_iter().flatten() {
+        union_into(&mut result, region, pos_equal_eps);
+    }
+    result
+}
+
+/// Regions of the result of a boolean operation, every negative polyline
+/// becoming a hole of the positive polyline containing it.
+fn result_regions(pos: Vec<Polyline<f64>>, neg: Vec<Polyline<f64>>) -> Vec<Region> {
+    let all: Vec<&Polyline<f64>> = pos.iter().chain(neg.iter()).collect();
+    let parents = parent_indexes(&all);
+    let mut regions: Vec<Region> = pos
+        .iter()
+        .map(|p| Region {
+            outer: to_ccw(p),
+            holes: Vec::new(),
+        })
+        .collect();
+    for (i, hole) in neg.iter().enumerate() {
+        if let Some(region) = usize::try_from(parents[pos.len() + i])
+            .ok()
+            .and_then(|j| regions.get_mut(j))
+        {
+            region.holes.push(to_cw(hole));
+        }
+    }
+    regions
+}
+
+/// Area of `region` not covered by `other`, as disjoint regions.
+fn region_minus(region: &Region, other: &Region, pos_equal_eps: f64) -> Vec<Region> {
+    // (outer - other.outer) and (outer & other.holes) are disjoint, the holes
+    // of `region` are removed from both afterwards
+    let (pos, neg, _) = boolean_eps(&region.outer, &other.outer, BooleanOp::Not, pos_equal_eps);
+    let mut pieces = result_regions(pos, neg);
+    for hole in &other.holes {
+        let (pos, neg, _) =
+            boolean_eps(&region.outer, &to_ccw(hole), BooleanOp::And, pos_equal_eps);
+        pieces.extend(result_regions(pos, neg));
+    }
+    for hole in &region.holes {
+        let hole = Region {
+            outer: to_ccw(hole),
+            holes: Vec::new(),
+        };
+        pieces = pieces
+            .iter()
+            .flat_map(|piece| region_minus(piece, &hole, pos_equal_eps))
+            .collect();
+    }
+    pieces
+}
+
+/// Area of the disjoint `regions` not covered by the disjoint `others`.
+fn regions_minus(regions: &[Region], others: &[Region], pos_equal_eps: f64) -> Vec<Region> {
+    let mut result = regions.to_vec();
+    for other in others {
+        result = result
+            .iter()
+            .flat_map(|region| region_minus(region, other, pos_equal_eps))
+            .collect();
+    }
+    result
+}
+
+/// Area covered by both `region` and `other`, as disjoint regions.
+pub(crate) fn region_and(region: &Region, other: &Region, pos_equal_eps: f64) -> Vec<Region> {
+    let (pos, neg, _) = boolean_eps(&region.outer, &other.outer, BooleanOp::And, pos_equal_eps);
+    let holes = region
+        .holes
+        .iter()
+        .chain(other.holes.iter())
+        .map(|h| Region {
+            outer: to_ccw(h),
+            holes: Vec::new(),
+        });
+    regions_minus(
+        &result_regions(pos, neg),
+        &holes.collect::<Vec<_>>(),
+        pos_equal_eps,
+    )
+}
+
+/// Boolean operation between the regions formed by two lists of polylines.
+///
+/// The closed polylines of each list form regions with holes as given by the
+/// `fill_rule` of `options`, open polylines and polylines without area are
+/// ignored. The regions of one list may overlap, in which case their union is
+/// used. `operation` is one of 0 = union, 1 = intersection, 2 = difference
+/// (`plinelist1` minus `plinelist2`) or 3 = exclusive or. `result` receives
+/// the disjoint regions of the result, each as its outer boundary (counter
+/// clockwise) followed by its holes (clockwise), as by
+/// [cavc_plinelist_union_all]. `options` may be null in which case defaults
+/// are used.
+///
+/// `result` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `plinelist1`, `plinelist2` or `result` is null.
+/// * 2 = `operation` is not a valid boolean operation.
+/// * 3 = the `fill_rule` of `options` is not 0 or 1.
+/// * 4 = the `pos_equal_eps` of `options` is negative, NaN or infinite.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_plinelist_boolean(
+    plinelist1: *const cavc_plinelist,
+    plinelist2: *const cavc_plinelist,
+    operation: i32,
+    options: *const cavc_plinelist_boolean_o,
+    result: *mut *mut cavc_plinelist,
+) -> i32 {
+    ffi_catch_unwind!({
+        if plinelist1.is_null() || plinelist2.is_null() || result.is_null() {
+            return 1;
+        }
+        let op = match boolean_op_from_i32(operation) {
+            Some(op) => op,
+            None => return 2,
+        };
+        let options = options.as_ref().copied().unwrap_or_default();
+        if !matches!(options.fill_rule, FILL_EVEN_ODD | FILL_ORIENTATION) {
+            return 3;
+        }
+        if !valid_eps(options.pos_equal_eps) {
+            return 4;
+        }
+        let eps = options.pos_equal_eps;
+        let regions = |plinelist: *const cavc_plinelist| {
+            let plines = area_plines(plinelist);
+            let plines: Vec<&Polyline<f64>> = plines.iter().collect();
+            list_regions(&plines, options.fill_rule, eps)
+        };
+        let (regions1, regions2) = (regions(plinelist1), regions(plinelist2));
+        let regions = match op {
+            BooleanOp::Or => {
+                let mut union = regions1;
+                for region in regions2 {
+                    union_into(&mut union, region, eps);
+                }
+                union
+            }
+            BooleanOp::And => regions1
+                .iter()
+                .flat_map(|r1| regions2.iter().flat_map(move |r2| region_and(r1, r2, eps)))
+                .collect(),
+            BooleanOp::Not => regions_minus(&regions1, &regions2, eps),
+            BooleanOp::Xor => {
+                let mut regions = regions_minus(&regions1, &regions2, eps);
+                regions.extend(regions_minus(&regions2, &regions1, eps));
+                regions
+            }
+        };
+        *result = plinelist_into_raw(region_plines(regions));
+        0
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ptr::{null, null_mut};
+
+    use super::*;
+    use crate::handle::cavc_plinelist_release;
+    use crate::test_util::{cw, rect};
+
+    const EPS: f64 = 1e-5;
+
+    fn regions_area(regions: &[Region]) -> f64 {
+        let area = |r: &Region| r.outer.area() + r.holes.iter().map(|h| h.area()).sum::<f64>();
+        regions.iter().map(area).sum()
+    }
+
+    fn plines_area(plines: &[Polyline<f64>]) -> f64 {
+        plines.iter().map(|p| p.area()).sum()
+    }
+
+    fn list_boolean(
+        plines1: Vec<Polyline<f64>>,
+        plines2: Vec<Polyline<f64>>,
+        operation: i32,
+        options: Option<cavc_plinelist_boolean_o>,
+    ) -> Result<Vec<Polyline<f64>>, i32> {
+        let options = options.as_ref().map_or(null(), |o| o as *const _);
+        unsafe {
+            let (list1, list2) = (plinelist_into_raw(plines1), plinelist_into_raw(plines2));
+            let mut result: *mut cavc_plinelist = null_mut();
+            let code = cavc_plinelist_boolean(list1, list2, operation, options, &mut result);
+            cavc_plinelist_release(list1);
+            cavc_plinelist_release(list2);
+            match code {
+                0 => {
+                    let plines = (*result).0.iter().map(|&p| (*p).0.clone()).collect();
+                    cavc_plinelist_release(result);
+                    Ok(plines)
+                }
+                code => Err(code),
+            }
+        }
+    }
+
+    #[test]
+    fn union_merges_overlapping_polylines() {
+        let regions = union_all(
+            [rect(0.0, 0.0, 10.0, 10.0), cw(rect(5.0, 0.0, 15.0, 10.0))],
+            EPS,
+        );
+        assert_eq!(regions.len(), 1);
+        assert!((regions_area(&regions) - 150.0).abs() < 1e-9);
+        assert_eq!(
+            regions[0].outer.orientation(),
+            PlineOrientation::CounterClockwise
+        );
+
+        let regions = union_all([rect(0.0, 0.0, 1.0, 1.0), rect(2.0, 0.0, 3.0, 1.0)], EPS);
+        assert_eq!(regions.len(), 2);
+        assert!((regions_area(&regions) - 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn union_encloses_holes() {
+        // a ring of four bars leaves a hole in the middle
+        let bars = [
+            rect(0.0, 0.0, 10.0, 2.0),
+            rect(8.0, 0.0, 10.0, 10.0),
+            rect(0.0, 8.0, 10.0, 10.0),
+            rect(0.0, 0.0, 2.0, 10.0),
+        ];
+        let regions = union_all(bars.clone(), EPS);
+        assert_eq!(regions.len(), 1);
+        assert_eq!(regions[0].holes.len(), 1);
+        assert!((regions_area(&regions) - 64.0).abs() < 1e-9);
+        // an island in the hole stays a separate region
+        let with_island = bars.into_iter().chain([rect(4.0, 4.0, 6.0, 6.0)]);
+        let regions = union_all(with_island, EPS);
+        assert_eq!(regions.len(), 2);
+        assert!((regions_area(&regions) - 68.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn list_regions_fill_rules() {
+        let outer = rect(0.0, 0.0, 10.0, 10.0);
+        let hole = rect(2.0, 2.0, 8.0, 8.0);
+        let island = rect(4.0, 4.0, 6.0, 6.0);
+        let regions = list_regions(&[&outer, &hole, &island], FILL_EVEN_ODD, EPS);
+        assert_eq!(regions.len(), 2);
+        assert!((regions_area(&regions) - 68.0).abs() < 1e-9);
+        // by orientation counter clockwise polylines are filled
+        let regions = list_regions(&[&outer, &hole, &island], FILL_ORIENTATION, EPS);
+        assert_eq!(regions.len(), 1);
+        assert!((regions_area(&regions) - 100.0).abs() < 1e-9);
+        let hole = cw(hole);
+        let regions = list_regions(&[&outer, &hole, &island], FILL_ORIENTATION, EPS);
+        assert_eq!(regions.len(), 2);
+        assert!((regions_area(&regions) - 68.0).abs() < 1e-9);
+        // holes outside of every filled polyline are ignored
+        let regions = list_regions(&[&hole], FILL_ORIENTATION, EPS);
+        assert!(regions.is_empty());
+    }
+
+    #[test]
+    fn plinelist_boolean_operations() {
+        let a = || vec![rect(0.0, 0.0, 10.0, 10.0)];
+        let b = || vec![rect(5.0, 5.0, 15.0, 15.0)];
+        for (operation, area, count) in [(0, 175.0, 1), (1, 25.0, 1), (2, 75.0, 1), (3, 150.0, 2)] {
+            let result = list_boolean(a(), b(), operation, None).unwrap();
+            assert_eq!(result.len(), count, "{}", operation);
+            assert!((plines_area(&result) - area).abs() < 1e-9, "{}", operation);
+        }
+        // subtracting a region with a hole keeps the part in the hole
+        let frame = vec![rect(-1.0, -1.0, 11.0, 11.0), rect(2.0, 2.0, 8.0, 8.0)];
+        let result = list_boolean(a(), frame, 2, None).unwrap();
+        assert!((plines_area(&result) - 36.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn plinelist_boolean_degenerate_input() {
+        assert!(list_boolean(Vec::new(), Vec::new(), 0, None)
+            .unwrap()
+            .is_empty());
+        let square = || vec![rect(0.0, 0.0, 10.0, 10.0)];
+        let result = list_boolean(square(), Vec::new(), 2, None).unwrap();
+        assert!((plines_area(&result) - 100.0).abs() < 1e-9);
+        assert!(list_boolean(square(), Vec::new(), 1, None)
+            .unwrap()
+            .is_empty());
+        // open polylines and polylines without area are ignored
+        let mut open = rect(2.0, 2.0, 4.0, 4.0);
+        open.set_is_closed(false);
+        let flat = rect(0.0, 5.0, 10.0, 5.0);
+        let result = list_boolean(square(), vec![open, flat, Polyline::new()], 2, None).unwrap();
+        assert!((plines_area(&result) - 100.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn plinelist_boolean_rejects_invalid_parameters() {
+        let square = || vec![rect(0.0, 0.0, 10.0, 10.0)];
+        assert_eq!(list_boolean(square(), square(), 4, None).err(), Some(2));
+        assert_eq!(list_boolean(square(), square(), -1, None).err(), Some(2));
+        let options = cavc_plinelist_boolean_o {
+            fill_rule: 2,
+            ..Default::default()
+        };
+        assert_eq!(
+            list_boolean(square(), square(), 0, Some(options)).err(),
+            Some(3)
+        );
+        for pos_equal_eps in [-1.0, f64::NAN, f64::INFINITY] {
+            let options = cavc_plinelist_boolean_o {
+                pos_equal_eps,
+                ..Default::default()
+            };
+            assert_eq!(
+                list_boolean(square(), square(), 0, Some(options)).err(),
+                Some(4)
+            );
+        }
+        let mut result = null_mut();
+        let code = unsafe { cavc_plinelist_boolean(null(), null(), 0, null(), &mut result) };
+        assert_eq!(code, 1);
+    }
+}
@@ -0,0 +1,527 @@
+//! Bulk vertex data transfer through flat `f64` buffers.
+//!
+//! Buffers hold vertexes interleaved as `[x0, y0, bulge0, x1, y1, bulge1, ...]`,
+//! which is the memory layout of a C contiguous numpy array of shape (n, 3).
+//! Arrays produced by the library are returned as [cavc_buffer] handles.
+use std::os::raw::c_void;
+
+use cavalier_contours::polyline::{
+    seg_arc_radius_and_center, PlineCreation, PlineSource, PlineSourceMut, PlineVertex, Polyline,
+};
+use cavalier_contours_ffi::cavc_pline;
+
+use crate::cache::evict;
+use crate::geom::{seg_point_at, seg_sweep};
+use crate::util::{free_raw, into_raw, pline_into_raw};
+
+/// Copy all vertexes of the polyline into `buffer` as interleaved x, y, bulge
+/// values.
+///
+/// `buffer` must have room for `capacity` vertexes (`3 * capacity` values).
+/// `written` receives the number of vertexes of the polyline even when the
+/// buffer is too small, so the caller can size the buffer and call again.
+/// `buffer` may be null if `capacity` is 0.
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `written` is null.
+/// * 2 = `capacity` is less than the number of vertexes, nothing is copied.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_vertex_data_to_buffer(
+    pline: *const cavc_pline,
+    buffer: *mut f64,
+    capacity: u32,
+    written: *mut u32,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || written.is_null() {
+            return 1;
+        }
+        let pline = &(*pline).0;
+        let count = pline.vertex_count();
+        *written = count as u32;
+        if count > capacity as usize || (buffer.is_null() && count != 0) {
+            return 2;
+        }
+        for (i, v) in pline.iter_vertexes().enumerate() {
+            *buffer.add(3 * i) = v.x;
+            *buffer.add(3 * i + 1) = v.y;
+            *buffer.add(3 * i + 2) = v.bulge;
+        }
+        0
+    })
+}
+
+/// Iterate over `count` interleaved vertexes of `buffer`.
+pub(crate) unsafe fn buffer_vertexes(
+    buffer: *const f64,
+    count: usize,
+) -> impl Iterator<Item = PlineVertex<f64>> {
+    let values: &[f64] = if count == 0 {
+        &[]
+    } else {
+        std::slice::from_raw_parts(buffer, 3 * count)
+    };
+    values
+        .chunks_exact(3)
+        .map(|v| PlineVertex::new(v[0], v[1], v[2]))
+}
+
+/// Replace all vertexes of the polyline with `count` interleaved x, y, bulge
+/// vertexes from `buffer` and set whether it is closed.
+///
+/// The handle is reused, so this avoids freeing and recreating a polyline when
+/// its geometry is regenerated. Nothing is modified if an error is returned.
+/// `buffer` may be null if `count` is 0.
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` is null or `buffer` is null while `count` is not 0.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_set_vertex_data_from_buffer(
+    pline: *mut cavc_pline,
+    buffer: *const f64,
+    count: u32,
+    is_closed: u8,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || (buffer.is_null() && count != 0) {
+            return 1;
+        }
+        evict(pline);
+        let pline = &mut (*pline).0;
+        pline.clear();
+        pline.reserve(count as usize);
+        pline.extend_vertexes(buffer_vertexes(buffer, count as usize));
+        pline.set_is_closed(is_closed != 0);
+        0
+    })
+}
+
+/// Release unused vertex capacity of the polyline, e.g. after building it with
+/// `cavc_pline_reserve` and fewer vertexes than reserved.
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_shrink_to_fit(pline: *mut cavc_pline) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() {
+            return 1;
+        }
+        let pline = &mut (*pline).0;
+        // a clone allocates exactly the vertexes it holds
+        *pline = pline.clone();
+        0
+    })
+}
+
+/// Replace `remove_count` vertexes of the polyline starting at `start_index`
+/// with `insert_count` interleaved x, y, bulge vertexes from `insert_buffer`,
+/// like `Vec::splice`.
+///
+/// Either count may be 0 to only insert or only remove vertexes, vertexes
+/// before `start_index` are not touched. Nothing is modified if an error is
+/// returned. `insert_buffer` may be null if `insert_count` is 0.
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` is null or `insert_buffer` is null while `insert_count` is not
+///   0.
+/// * 2 = `start_index + remove_count` is greater than the vertex count.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_splice(
+    pline: *mut cavc_pline,
+    start_index: u32,
+    remove_count: u32,
+    insert_buffer: *const f64,
+    insert_count: u32,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || (insert_buffer.is_null() && insert_count != 0) {
+            return 1;
+        }
+        evict(pline);
+        let pline = &mut (*pline).0;
+        let start = start_index as usize;
+        let end = start + remove_count as usize;
+        if end > pline.vertex_count() {
+            return 2;
+        }
+        let tail: Vec<PlineVertex<f64>> = pline.iter_vertexes().skip(end).collect();
+        while pline.vertex_count() > start {
+            pline.remove_last();
+        }
+        pline.reserve(insert_count as usize + tail.len());
+        pline.extend_vertexes(buffer_vertexes(insert_buffer, insert_count as usize));
+        pline.extend_vertexes(tail);
+        0
+    })
+}
+
+/// Append `vertex_count` interleaved x, y, bulge vertexes from `buffer` to
+/// the end of the polyline.
+///
+/// Meant for building a polyline from chunks of vertexes as they arrive, the
+/// same as adding the vertexes one by one but with a single call per chunk.
+/// Whether the polyline is closed is not changed. `buffer` may be null if
+/// `vertex_count` is 0.
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` is null or `buffer` is null while `vertex_count` is not 0.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_extend_from_buffer(
+    pline: *mut cavc_pline,
+    buffer: *const f64,
+    vertex_count: u32,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || (buffer.is_null() && vertex_count != 0) {
+            return 1;
+        }
+        evict(pline);
+        let pline = &mut (*pline).0;
+        pline.reserve(vertex_count as usize);
+        pline.extend_vertexes(buffer_vertexes(buffer, vertex_count as usize));
+        0
+    })
+}
+
+/// Create a new polyline from `vertex_count` interleaved x, y, bulge vertexes
+/// in `buffer`.
+///
+/// `buffer` may be null if `vertex_count` is 0. `result` is only written to if
+/// the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `result` is null or `buffer` is null while `vertex_count` is not 0.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_create_from_buffer(
+    buffer: *const f64,
+    vertex_count: u32,
+    is_closed: u8,
+    result: *mut *mut cavc_pline,
+) -> i32 {
+    ffi_catch_unwind!({
+        if result.is_null() || (buffer.is_null() && vertex_count != 0) {
+            return 1;
+        }
+        let mut pline = Polyline::with_capacity(vertex_count as usize, is_closed != 0);
+        pline.extend_vertexes(buffer_vertexes(buffer, vertex_count as usize));
+        *result = pline_into_raw(pline);
+        0
+    })
+}
+
+/// Create a new polyline from separate arrays of `count` x, y and bulge
+/// values.
+///
+/// `bulges` may be null in which case all bulges are 0 (the polyline is made
+/// of lines only). `xs` and `ys` may be null if `count` is 0. `result` is only
+/// written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `result` is null or `xs` or `ys` is null while `count` is not 0.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_create_from_arrays(
+    xs: *const f64,
+    ys: *const f64,
+    bulges: *const f64,
+    count: u32,
+    is_closed: u8,
+    result: *mut *mut cavc_pline,
+) -> i32 {
+    ffi_catch_unwind!({
+        if result.is_null() || ((xs.is_null() || ys.is_null()) && count != 0) {
+            return 1;
+        }
+        let count = count as usize;
+        let mut pline = Polyline::with_capacity(count, is_closed != 0);
+        for i in 0..count {
+            let bulge = if bulges.is_null() {
+                0.0
+            } else {
+                *bulges.add(i)
+            };
+            pline.add(*xs.add(i), *ys.add(i), bulge);
+        }
+        *result = pline_into_raw(pline);
+        0
+    })
+}
+
+/// Values of a [cavc_buffer], one variant per element kind.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BufferData {
+    F64(Vec<f64>),
+    U32(Vec<u32>),
+    U8(Vec<u8>),
+}
+
+/// Element kinds of [cavc_buffer] reported by [cavc_buffer_kind].
+const KIND_F64: u32 = 0;
+const KIND_U32: u32 = 1;
+const KIND_U8: u32 = 2;
+
+/// Opaque handle to an array of values of any element kind produced by the
+/// library, with a single set of functions to access and free it.
+///
+/// All functions return their arrays as [cavc_buffer], so bulk data has one
+/// ownership and free convention.
+pub struct cavc_buffer(pub BufferData);
+
+impl From<Vec<f64>> for cavc_buffer {
+    fn from(values: Vec<f64>) -> Self {
+        Self(BufferData::F64(values))
+    }
+}
+
+impl From<Vec<u32>> for cavc_buffer {
+    fn from(values: Vec<u32>) -> Self {
+        Self(BufferData::U32(values))
+    }
+}
+
+impl From<Vec<u8>> for cavc_buffer {
+    fn from(values: Vec<u8>) -> Self {
+        Self(BufferData::U8(values))
+    }
+}
+
+/// Free an existing [cavc_buffer] object. Nothing happens if `buffer` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_buffer_f(buffer: *mut cavc_buffer) {
+    free_raw(buffer);
+}
+
+/// Get the element kind of the buffer: 0 = `f64`, 1 = `u32`, 2 = `u8`.
+///
+/// ## Specific Error Codes
+/// * 1 = `buffer` or `kind` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_buffer_kind(buffer: *const cavc_buffer, kind: *mut u32) -> i32 {
+    ffi_catch_unwind!({
+        if buffer.is_null() || kind.is_null() {
+            return 1;
+        }
+        *kind = match &(*buffer).0 {
+            BufferData::F64(_) => KIND_F64,
+            BufferData::U32(_) => KIND_U32,
+            BufferData::U8(_) => KIND_U8,
+        };
+        0
+    })
+}
+
+/// Get the number of elements (not bytes) in the buffer.
+///
+/// ## Specific Error Codes
+/// * 1 = `buffer` or `len` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_buffer_len(buffer: *const cavc_buffer, len: *mut u32) -> i32 {
+    ffi_catch_unwind!({
+        if buffer.is_null() || len.is_null() {
+            return 1;
+        }
+        *len = match &(*buffer).0 {
+            BufferData::F64(values) => values.len() as u32,
+            BufferData::U32(values) => values.len() as u32,
+            BufferData::U8(values) => values.len() as u32,
+        };
+        0
+    })
+}
+
+/// Get a pointer to the elements of the buffer, to be read as the kind given
+/// by [cavc_buffer_kind].
+///
+/// The pointer stays valid until the buffer is freed, which allows wrapping
+/// the values without copying them (e.g. as a numpy array).
+///
+/// ## Specific Error Codes
+/// * 1 = `buffer` or `data` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_buffer_data(
+    buffer: *const cavc_buffer,
+    data: *mut *const c_void,
+) -> i32 {
+    ffi_catch_unwind!({
+        if buffer.is_null() || data.is_null() {
+            return 1;
+        }
+        *data = match &(*buffer).0 {
+            BufferData::F64(values) => values.as_ptr() as *const c_void,
+            BufferData::U32(values) => values.as_ptr() as *const c_void,
+            BufferData::U8(values) => values.as_ptr() as *const c_void,
+        };
+        0
+    })
+}
+
+/// Maximum number of pieces an arc is divided into by [cavc_pline_flatten].
+const MAX_ARC_PIECES: f64 = 1_000_000.0;
+
+/// Linearize the polyline into points, recording for every point the index of
+/// the segment it comes from.
+///
+/// Arcs are divided into equal angle pieces so the chords deviate at most
+/// `error_distance` from the arc, line segments are kept as they are. The
+/// points are written to `out_points_buffer` as interleaved `[x, y]` values
+/// starting with the first vertex and followed by the points of every segment
+/// up to and including its end point, so closed polylines end with the first
+/// vertex again. `out_seg_index_buffer` receives one value per point: the
+/// index of the segment the piece ending at the point lies on (0 for the first
+/// point), so a hit on the piece between points `i - 1` and `i` maps back to
+/// segment `seg_index[i]`. A polyline without vertexes gives empty buffers.
+///
+/// Both buffers are only written to if the function returns 0 (success) and
+/// must be freed with [cavc_buffer_f].
+///
+/// ## Specific Error Codes
+/// * 1 = `pline`, `out_points_buffer` or `out_seg_index_buffer` is null.
+/// * 2 = `error_distance` is not greater than 0.
+/// * 3 = `error_distance` is too small for the radius of an arc, it would be
+///   divided into more than 1000000 pieces.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_flatten(
+    pline: *const cavc_pline,
+    error_distance: f64,
+    out_points_buffer: *mut *mut cavc_buffer,
+    out_seg_index_buffer: *mut *mut cavc_buffer,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || out_points_buffer.is_null() || out_seg_index_buffer.is_null() {
+            return 1;
+        }
+        if error_distance.is_nan() || error_distance <= 0.0 {
+            return 2;
+        }
+        let pline = &(*pline).0;
+        let mut points = Vec::new();
+        let mut seg_indexes = Vec::new();
+        if let Some(first) = pline.get(0) {
+            points.extend([first.x, first.y]);
+            seg_indexes.push(0);
+        }
+        for (i, (v1, v2)) in pline.iter_segments().enumerate() {
+            let count = if v1.bulge_is_zero() {
+                1
+            } else {
+                let (radius, _) = seg_arc_radius_and_center(v1, v2);
+                // a chord of the angle a deviates r * (1 - cos(a / 2)), the
+                // angle is 0 once error_distance / radius is below precision
+                let max_angle = 2.0 * (1.0 - error_distance / radius).max(-1.0).acos();
+                let count = (seg_sweep(v1).abs() / max_angle).ceil().max(1.0);
+                if count.is_nan() || count > MAX_ARC_PIECES {
+                    return 3;
+                }
+                count as usize
+            };
+            for k in 1..=count {
+                let p = if k == count {
+                    v2.pos()
+                } else {
+                    seg_point_at(v1, v2, k as f64 / count as f64)
+                };
+                points.extend([p.x, p.y]);
+                seg_indexes.push(i as u32);
+            }
+        }
+        *out_points_buffer = into_raw(cavc_buffer::from(points));
+        *out_seg_index_buffer = into_raw(cavc_buffer::from(seg_indexes));
+        0
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ptr::{null, null_mut};
+
+    use super::*;
+    use crate::test_util::pline;
+
+    unsafe fn take(buffer: *mut cavc_buffer) -> BufferData {
+        let data = (*buffer).0.clone();
+        cavc_buffer_f(buffer);
+        data
+    }
+
+    fn flatten(pline: Polyline<f64>, error_distance: f64) -> Result<(Vec<f64>, Vec<u32>), i32> {
+        let input = cavc_pline(pline);
+        let mut points = null_mut();
+        let mut seg_indexes = null_mut();
+        unsafe {
+            match cavc_pline_flatten(&input, error_distance, &mut points, &mut seg_indexes) {
+                0 => match (take(points), take(seg_indexes)) {
+                    (BufferData::F64(points), BufferData::U32(seg_indexes)) => {
+                        Ok((points, seg_indexes))
+                    }
+                    kinds => panic!("{:?}", kinds),
+                },
+                code => Err(code),
+            }
+        }
+    }
+
+    #[test]
+    fn flatten_keeps_lines() {
+        let open = pline(&[(0.0, 0.0, 0.0), (1.0, 0.0, 0.0), (1.0, 1.0, 0.0)], false);
+        let (points, seg_indexes) = flatten(open, 0.1).unwrap();
+        assert_eq!(points, [0.0, 0.0, 1.0, 0.0, 1.0, 1.0]);
+        assert_eq!(seg_indexes, [0, 0, 1]);
+
+        let closed = pline(&[(0.0, 0.0, 0.0), (1.0, 0.0, 0.0), (1.0, 1.0, 0.0)], true);
+        let (points, seg_indexes) = flatten(closed, 0.1).unwrap();
+        assert_eq!(points, [0.0, 0.0, 1.0, 0.0, 1.0, 1.0, 0.0, 0.0]);
+        assert_eq!(seg_indexes, [0, 0, 1, 2]);
+    }
+
+    #[test]
+    fn flatten_divides_arcs_within_error() {
+        // half circle of radius 1 around (1, 0) followed by a line
+        let half_circle = pline(&[(0.0, 0.0, 1.0), (2.0, 0.0, 0.0), (3.0, 0.0, 0.0)], false);
+        let (points, seg_indexes) = flatten(half_circle, 0.01).unwrap();
+        assert_eq!(seg_indexes.len(), 14);
+        assert!(seg_indexes[..13].iter().all(|&i| i == 0));
+        assert_eq!(seg_indexes[13], 1);
+        assert_eq!(&points[24..], [2.0, 0.0, 3.0, 0.0]);
+        let points: Vec<(f64, f64)> = points.chunks(2).map(|p| (p[0], p[1])).collect();
+        for pair in points[..13].windows(2) {
+            let (a, b) = (pair[0], pair[1]);
+            assert!(((a.0 - 1.0).hypot(a.1) - 1.0).abs() < 1e-12);
+            // counter clockwise from (0, 0) goes below the x axis
+            assert!(b.1 <= 1e-12);
+            let mid = ((a.0 + b.0) / 2.0 - 1.0).hypot((a.1 + b.1) / 2.0);
+            assert!(1.0 - mid <= 0.01);
+        }
+    }
+
+    #[test]
+    fn flatten_degenerate_input() {
+        let (points, seg_indexes) = flatten(Polyline::new(), 0.1).unwrap();
+        assert!(points.is_empty() && seg_indexes.is_empty());
+        let (points, seg_indexes) = flatten(pline(&[(1.0, 2.0, 0.0)], true), 0.1).unwrap();
+        assert_eq!(
+            (points, seg_indexes),
+            (vec![1.0, 2.0, 1.0, 2.0], vec![0, 0])
+        );
+        // zero length line segments are kept
+        let repeated = pline(&[(0.0, 0.0, 0.0), (0.0, 0.0, 0.0), (1.0, 0.0, 0.0)], false);
+        let (points, _) = flatten(repeated, 0.1).unwrap();
+        assert_eq!(points, [0.0, 0.0, 0.0, 0.0, 1.0, 0.0]);
+        // an error larger than the radius leaves the chord
+        let arc = pline(&[(0.0, 0.0, 1.0), (2.0, 0.0, 0.0)], false);
+        let (points, _) = flatten(arc, 5.0).unwrap();
+        assert_eq!(points, [0.0, 0.0, 2.0, 0.0]);
+    }
+
+    #[test]
+    fn flatten_rejects_invalid_parameters() {
+        let arc = || pline(&[(0.0, 0.0, 1.0), (2.0, 0.0, 0.0)], false);
+        for error_distance in [0.0, -1.0, f64::NAN] {
+            assert_eq!(flatten(arc(), error_distance), Err(2));
+        }
+        assert_eq!(flatten(arc(), 1e-300), Err(3));
+        let mut points = null_mut();
+        let mut seg_indexes = null_mut();
+        let code = unsafe { cavc_pline_flatten(null(), 0.1, &mut points, &mut seg_indexes) };
+        assert_eq!(code, 1);
+    }
+}
@@ -0,0 +1,240 @@
+//! Building polylines from end points and radii instead of bulges.
+//!
+//! A builder holds the path built so far starting at the point given when it
+//! was created, every `add_*_to` function appends a segment from the current
+//! end point. Nothing is appended if an error is returned.
+use cavalier_contours::core::math::Vector2;
+use cavalier_contours::polyline::{PlineSource, PlineSourceMut, Polyline};
+use cavalier_contours_ffi::cavc_pline;
+
+use crate::geom::{seg_tangent_at, three_point_bulge};
+use crate::util::{free_raw, into_raw, pline_into_raw, valid_eps};
+
+/// Opaque type that holds a polyline under construction.
+pub struct cavc_pline_builder(pub Polyline<f64>);
+
+impl cavc_pline_builder {
+    fn end_point(&self) -> Vector2<f64> {
+        self.0.at(self.0.vertex_count() - 1).pos()
+    }
+
+    /// Append the segment to `(x, y)` with `bulge` from the current end point.
+    fn push(&mut self, x: f64, y: f64, bulge: f64) {
+        let last = self.0.vertex_count() - 1;
+        let end = self.0.at(last);
+        self.0.set_vertex(last, end.with_bulge(bulge));
+        self.0.add(x, y, 0.0);
+    }
+}
+
+/// Create a builder with the path starting at `(x, y)`.
+///
+/// `builder` is only written to if the function returns 0 (success) and must
+/// be freed with [cavc_pline_builder_f].
+///
+/// ## Specific Error Codes
+/// * 1 = `builder` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_builder_create(
+    x: f64,
+    y: f64,
+    builder: *mut *mut cavc_pline_builder,
+) -> i32 {
+    ffi_catch_unwind!({
+        if builder.is_null() {
+            return 1;
+        }
+        let mut pline = Polyline::new();
+        pline.add(x, y, 0.0);
+        *builder = into_raw(cavc_pline_builder(pline));
+        0
+    })
+}
+
+/// Free an existing [cavc_pline_builder] object. Nothing happens if `builder`
+/// is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_builder_f(builder: *mut cavc_pline_builder) {
+    free_raw(builder);
+}
+
+/// Append a line from the current end point to `(x, y)`.
+///
+/// ## Specific Error Codes
+/// * 1 = `builder` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_builder_add_line_to(
+    builder: *mut cavc_pline_builder,
+    x: f64,
+    y: f64,
+) -> i32 {
+    ffi_catch_unwind!({
+        if builder.is_null() {
+            return 1;
+        }
+        (*builder).push(x, y, 0.0);
+        0
+    })
+}
+
+/// Append an arc of `radius` from the current end point to `(x, y)`.
+///
+/// Of the four arcs of `radius` between the points, the one running counter
+/// clockwise if `is_ccw` is non-zero (clockwise otherwise) and sweeping more
+/// than half a circle if `is_large` is non-zero is used, like the SVG elliptical
+/// arc flags. A radius short of half the distance between the points by a
+/// relative 1e-9 at most is accepted and gives a half circle.
+///
+/// ## Specific Error Codes
+/// * 1 = `builder` is null.
+/// * 2 = `radius` is not greater than 0, infinite or smaller than half the
+///   distance between the points.
+/// * 3 = `(x, y)` coincides with the current end point.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_builder_add_arc_to(
+    builder: *mut cavc_pline_builder,
+    x: f64,
+    y: f64,
+    radius: f64,
+    is_ccw: u8,
+    is_large: u8,
+) -> i32 {
+    ffi_catch_unwind!({
+        if builder.is_null() {
+            return 1;
+        }
+        let builder = &mut *builder;
+        let chord = (Vector2::new(x, y) - builder.end_point()).length();
+        if chord == 0.0 {
+            return 3;
+        }
+        if !radius.is_finite() || radius <= 0.0 {
+            return 2;
+        }
+        let ratio = chord / (2.0 * radius);
+        if ratio > 1.0 + 1e-9 {
+            return 2;
+        }
+        let small = 2.0 * ratio.min(1.0).asin();
+        let sweep = if is_large != 0 {
+            std::f64::consts::TAU - small
+        } else {
+            small
+        };
+        let bulge = (sweep / 4.0).tan();
+        builder.push(x, y, if is_ccw != 0 { bulge } else { -bulge });
+        0
+    })
+}
+
+/// Append the arc from the current end point through `(mid_x, mid_y)` to
+/// `(x, y)`.
+///
+/// ## Specific Error Codes
+/// * 1 = `builder` is null.
+/// * 2 = the points are collinear or coincide, so no arc passes through them.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_builder_add_arc_through(
+    builder: *mut cavc_pline_builder,
+    mid_x: f64,
+    mid_y: f64,
+    x: f64,
+    y: f64,
+) -> i32 {
+    ffi_catch_unwind!({
+        if builder.is_null() {
+            return 1;
+        }
+        let builder = &mut *builder;
+        let start = builder.end_point();
+        match three_point_bulge(start, Vector2::new(mid_x, mid_y), Vector2::new(x, y)) {
+            Some(bulge) => {
+                builder.push(x, y, bulge);
+                0
+            }
+            None => 2,
+        }
+    })
+}
+
+/// Append an arc from the current end point to `(x, y)` tangent to the last
+/// segment at the current end point.
+///
+/// A straight line is appended if `(x, y)` lies ahead on the tangent.
+///
+/// ## Specific Error Codes
+/// * 1 = `builder` is null.
+/// * 2 = there is no segment yet to be tangent to (or it has zero length).
+/// * 3 = `(x, y)` coincides with the current end point or lies behind it on the
+///   tangent, so no tangent arc reaches it.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_builder_add_tangent_arc_to(
+    builder: *mut cavc_pline_builder,
+    x: f64,
+    y: f64,
+) -> i32 {
+    ffi_catch_unwind!({
+        if builder.is_null() {
+            return 1;
+        }
+        let builder = &mut *builder;
+        let n = builder.0.vertex_count();
+        if n < 2 {
+            return 2;
+        }
+        let tangent = seg_tangent_at(builder.0.at(n - 2), builder.0.at(n - 1), 1.0);
+        if tangent.length() == 0.0 {
+            return 2;
+        }
+        let chord = Vector2::new(x, y) - builder.end_point();
+        if chord.length() == 0.0 {
+            return 3;
+        }
+        // the sweep is twice the angle between the tangent and the chord
+        let angle = tangent.perp_dot(chord).atan2(tangent.dot(chord));
+        if angle.abs() >= std::f64::consts::PI - 1e-9 {
+            return 3;
+        }
+        builder.push(x, y, (angle / 2.0).tan());
+        0
+    })
+}
+
+/// Copy the path built so far into a new polyline.
+///
+/// If `is_closed` is non-zero the polyline is closed and a final vertex
+/// coinciding with the start (within `pos_equal_eps`) is dropped, its segment
+/// being replaced by the closing segment. The builder is left unchanged and
+/// can continue to be used.
+///
+/// `result` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `builder` or `result` is null.
+/// * 2 = `pos_equal_eps` is negative, NaN or infinite.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_builder_build(
+    builder: *const cavc_pline_builder,
+    is_closed: u8,
+    pos_equal_eps: f64,
+    result: *mut *mut cavc_pline,
+) -> i32 {
+    ffi_catch_unwind!({
+        if builder.is_null() || result.is_null() {
+            return 1;
+        }
+        if !valid_eps(pos_equal_eps) {
+            return 2;
+        }
+        let mut pline = (*builder).0.clone();
+        if is_closed != 0 {
+            let n = pline.vertex_count();
+            if n > 1 && (pline.at(n - 1).pos() - pline.at(0).pos()).length() <= pos_equal_eps {
+                pline.remove_last();
+            }
+            pline.set_is_closed(true);
+        }
+        *result = pline_into_raw(pline);
+        0
+    })
+}
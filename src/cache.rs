@@ -0,0 +1,375 @@
+//! Cache of derived polyline properties.
+//!
+//! The `_cached` variants of queries are opt-in, they compute a value once per
+//! polyline state and return it from the cache until the polyline changes.
+//!
+//! Polyline handles are plain `cavc_pline` objects, so cached values are kept
+//! in a side table keyed by the handle address. Every function of this library
+//! modifying a polyline in place removes its entry, so a lookup is a single
+//! table access and an existing entry is always current. Functions of
+//! `cavalier_contours_ffi` (e.g. `cavc_pline_add` or `cavc_pline_set_vertex`)
+//! can not be observed, call [cavc_pline_cache_release] after modifying a
+//! polyline with them. Debug builds keep a fingerprint of the vertex data with
+//! every entry and assert on lookup that the polyline did not change behind
+//! the back of the cache.
+//!
+//! Values are computed without holding the lock of the table, so concurrent
+//! queries of other polylines are not blocked. Every entry carries a
+//! generation taken from a global counter when it is created, a computed value
+//! is only stored if the entry still has the generation it was looked up with,
+//! so a value computed from an older state of the polyline never lands in an
+//! entry created after the polyline was modified. Entries are also removed
+//! when a handle is created or released through [crate::handle], so a handle
+//! reusing the address of a freed one starts without cached values.
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use cavalier_contours::polyline::{PlineOrientation, PlineSource, Polyline};
+use cavalier_contours_ffi::{cavc_aabbindex, cavc_pline};
+
+use crate::util::{free_raw, into_raw};
+
+#[derive(Debug, Default)]
+struct CacheEntry {
+    generation: u64,
+    fingerprint: u64,
+    path_length: Option<f64>,
+    extents: Option<Option<[f64; 4]>>,
+    orientation: Option<PlineOrientation>,
+    aabb_index: Option<Option<Arc<cavc_aabbindex>>>,
+}
+
+static CACHE: Mutex<BTreeMap<usize, CacheEntry>> = Mutex::new(BTreeMap::new());
+
+/// Source of the generations of cache entries.
+static NEXT_GENERATION: AtomicU64 = AtomicU64::new(1);
+
+fn cache() -> std::sync::MutexGuard<'static, BTreeMap<usize, CacheEntry>> {
+    // the table is consistent whenever the lock is released, a panic can only
+    // come from the debug assertion of a stale entry so poisoning can be
+    // ignored
+    CACHE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Fingerprint of the closed flag and the exact bits of every vertex value,
+/// only checked by debug assertions.
+fn fingerprint(pline: &Polyline<f64>) -> u64 {
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325 ^ pline.is_closed() as u64;
+    for v in pline.iter_vertexes() {
+        for bits in [v.x.to_bits(), v.y.to_bits(), v.bulge.to_bits()] {
+            hash = (hash ^ bits).wrapping_mul(PRIME);
+            hash ^= hash >> 29;
+        }
+    }
+    hash
+}
+
+/// Get a cached value for `pline` or compute and cache it.
+///
+/// `field` selects the value in the cache entry. The value is computed
+/// outside the lock of the table.
+unsafe fn cached<T: Clone>(
+    pline: *const cavc_pline,
+    field: fn(&mut CacheEntry) -> &mut Option<T>,
+    compute: impl FnOnce(&Polyline<f64>) -> T,
+) -> T {
+    let pline_ref = &(*pline).0;
+    let current = if cfg!(debug_assertions) {
+        fingerprint(pline_ref)
+    } else {
+        0
+    };
+    let generation = {
+        let mut cache = cache();
+        let entry = cache.entry(pline as usize).or_insert_with(|| CacheEntry {
+            generation: NEXT_GENERATION.fetch_add(1, Ordering::Relaxed),
+            fingerprint: current,
+            ..Default::default()
+        });
+        debug_assert_eq!(
+            entry.fingerprint, current,
+            "polyline modified without cavc_pline_cache_release"
+        );
+        if let Some(value) = field(entry) {
+            return value.clone();
+        }
+        entry.generation
+    };
+    let value = compute(pline_ref);
+    if let Some(entry) = cache().get_mut(&(pline as usize)) {
+        if entry.generation == generation {
+            *field(entry) = Some(value.clone());
+        }
+    }
+    value
+}
+
+/// Remove the cache entry of a polyline handle, called when the handle is
+/// created, modified in place or freed.
+pub(crate) fn evict(pline: *const cavc_pline) {
+    cache().remove(&(pline as usize));
+}
+
+/// Compute the total path length of the polyline.
+///
+/// `path_length` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `path_length` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_path_length(
+    pline: *const cavc_pline,
+    path_length: *mut f64,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || path_length.is_null() {
+            return 1;
+        }
+        *path_length = (*pline).0.path_length();
+        0
+    })
+}
+
+/// Same as [cavc_pline_path_length] but the result is cached for the handle.
+///
+/// Repeated calls on an unchanged polyline return the cached value without
+/// looking at the vertexes. The functions of this library modifying the
+/// polyline drop the cached values, call [cavc_pline_cache_release] after
+/// modifying it with functions of `cavalier_contours_ffi` (see the module
+/// documentation). The cached values are also dropped when the polyline is
+/// freed with [crate::handle::cavc_pline_release], call
+/// [cavc_pline_cache_release] before freeing it with `cavc_pline_f` otherwise
+/// its cache entry lingers.
+///
+/// `path_length` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `path_length` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_path_length_cached(
+    pline: *const cavc_pline,
+    path_length: *mut f64,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || path_length.is_null() {
+            return 1;
+        }
+        *path_length = cached(pline, |e| &mut e.path_length, |p| p.path_length());
+        0
+    })
+}
+
+/// Same as `cavc_pline_eval_extents` but the result is cached for the handle
+/// (see [cavc_pline_path_length_cached]).
+///
+/// The out parameters are only written to if the function returns 0
+/// (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or any of the out parameters is null.
+/// * 2 = `pline` has no vertexes.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_extents_cached(
+    pline: *const cavc_pline,
+    min_x: *mut f64,
+    min_y: *mut f64,
+    max_x: *mut f64,
+    max_y: *mut f64,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null()
+            || min_x.is_null()
+            || min_y.is_null()
+            || max_x.is_null()
+            || max_y.is_null()
+        {
+            return 1;
+        }
+        let extents = cached(
+            pline,
+            |e| &mut e.extents,
+            |p| p.extents().map(|e| [e.min_x, e.min_y, e.max_x, e.max_y]),
+        );
+        match extents {
+            Some([x0, y0, x1, y1]) => {
+                *min_x = x0;
+                *min_y = y0;
+                *max_x = x1;
+                *max_y = y1;
+                0
+            }
+            None => 2,
+        }
+    })
+}
+
+/// Get the orientation of the polyline, cached for the handle (see
+/// [cavc_pline_path_length_cached]).
+///
+/// `orientation` receives 0 for an open polyline, 1 for a clockwise and 2 for
+/// a counter clockwise closed polyline.
+///
+/// `orientation` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `orientation` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_orientation_cached(
+    pline: *const cavc_pline,
+    orientation: *mut i32,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || orientation.is_null() {
+            return 1;
+        }
+        *orientation = match cached(pline, |e| &mut e.orientation, |p| p.orientation()) {
+            PlineOrientation::Open => 0,
+            PlineOrientation::Clockwise => 1,
+            PlineOrientation::CounterClockwise => 2,
+        };
+        0
+    })
+}
+
+/// Opaque handle sharing a spatial index cached for a polyline, see
+/// [cavc_pline_create_approx_aabb_index_cached].
+pub struct cavc_shared_aabbindex(Arc<cavc_aabbindex>);
+
+/// Free an existing [cavc_shared_aabbindex] object. Nothing happens if
+/// `shared` is null.
+///
+/// The index itself is freed once it is neither cached nor shared by any other
+/// handle.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_shared_aabbindex_f(shared: *mut cavc_shared_aabbindex) {
+    free_raw(shared);
+}
+
+/// Get the spatial index of a shared index handle, to be passed to the
+/// functions taking a `cavc_aabbindex`.
+///
+/// The index is read only and stays valid until `shared` is freed, it must not
+/// be freed with `cavc_aabbindex_f`.
+///
+/// `aabbindex` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `shared` or `aabbindex` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_shared_aabbindex_get(
+    shared: *const cavc_shared_aabbindex,
+    aabbindex: *mut *const cavc_aabbindex,
+) -> i32 {
+    ffi_catch_unwind!({
+        if shared.is_null() || aabbindex.is_null() {
+            return 1;
+        }
+        *aabbindex = Arc::as_ptr(&(*shared).0);
+        0
+    })
+}
+
+/// Same as `cavc_pline_create_approx_aabb_index` but the index is built once
+/// and cached for the handle (see [cavc_pline_path_length_cached]).
+///
+/// Every call returns a new handle sharing the cached index without copying
+/// it, use [cavc_shared_aabbindex_get] to query it. The index stays valid
+/// while the handle lives even if the polyline is modified or freed, it then
+/// describes the polyline as it was when the index was built.
+///
+/// `aabbindex` is only written to if the function returns 0 (success) and must
+/// be freed with [cavc_shared_aabbindex_f].
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `aabbindex` is null.
+/// * 2 = `pline` has no segments so there is nothing to index.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_create_approx_aabb_index_cached(
+    pline: *const cavc_pline,
+    aabbindex: *mut *mut cavc_shared_aabbindex,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || aabbindex.is_null() {
+            return 1;
+        }
+        let index = cached(
+            pline,
+            |e| &mut e.aabb_index,
+            |p| {
+                (p.segment_count() > 0)
+                    .then(|| Arc::new(cavc_aabbindex(p.create_approx_aabb_index())))
+            },
+        );
+        match index {
+            Some(index) => {
+                *aabbindex = into_raw(cavc_shared_aabbindex(index));
+                0
+            }
+            None => 2,
+        }
+    })
+}
+
+/// Drop all cached values of a polyline. Nothing happens if `pline` is null or
+/// has nothing cached.
+///
+/// Must be called after modifying a polyline with cached values through the
+/// functions of `cavalier_contours_ffi`, otherwise the stale values are
+/// returned.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_cache_release(pline: *const cavc_pline) {
+    if !pline.is_null() {
+        evict(pline);
+    }
+}
+
+/// Heap bytes held by the cache for `pline`, 0 if nothing is cached.
+///
+/// Index storage is counted by the number of its elements.
+pub(crate) fn cached_heap_bytes(pline: *const cavc_pline) -> usize {
+    let cache = cache();
+    let entry = match cache.get(&(pline as usize)) {
+        Some(entry) => entry,
+        None => return 0,
+    };
+    let index_bytes = match &entry.aabb_index {
+        Some(Some(index)) => {
+            let index = &index.0;
+            std::mem::size_of_val(index.boxes())
+                + std::mem::size_of_val(index.indices())
+                + std::mem::size_of_val(index.level_bounds())
+        }
+        _ => 0,
+    };
+    std::mem::size_of::<(usize, CacheEntry)>() + index_bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::cavc_pline_extend_from_buffer;
+    use crate::handle::cavc_pline_release;
+    use crate::test_util::pline;
+    use crate::util::pline_into_raw;
+
+    #[test]
+    fn modifying_functions_drop_cached_values() {
+        let handle = pline_into_raw(pline(&[(0.0, 0.0, 0.0), (3.0, 0.0, 0.0)], false));
+        let mut length = 0.0;
+        unsafe {
+            assert_eq!(cavc_pline_path_length_cached(handle, &mut length), 0);
+            assert_eq!(length, 3.0);
+            assert!(cache().contains_key(&(handle as usize)));
+            assert_eq!(
+                cavc_pline_extend_from_buffer(handle, [3.0, 4.0, 0.0].as_ptr(), 1),
+                0
+            );
+            assert!(!cache().contains_key(&(handle as usize)));
+            assert_eq!(cavc_pline_path_length_cached(handle, &mut length), 0);
+            assert_eq!(length, 7.0);
+            cavc_pline_release(handle);
+        }
+    }
+}
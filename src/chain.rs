@@ -0,0 +1,162 @@
+//! Chaining open polylines into longer paths and loops.
+use cavalier_contours::core::math::Vector2;
+use cavalier_contours::polyline::{PlineSource, PlineSourceMut, Polyline};
+use cavalier_contours::static_aabb2d_index::StaticAABB2DIndexBuilder;
+use cavalier_contours_ffi::cavc_plinelist;
+
+use crate::util::plinelist_into_raw;
+
+/// Append `piece` to the end of `chain`, the start of `piece` coincides with
+/// the end of `chain` and replaces it.
+pub(crate) fn append(chain: &mut Polyline<f64>, piece: &Polyline<f64>) {
+    let last = chain.vertex_count() - 1;
+    let end = chain.at(last);
+    chain.set_vertex(last, end.with_bulge(piece.at(0).bulge));
+    for v in piece.iter_vertexes().skip(1) {
+        chain.add_vertex(v);
+    }
+}
+
+/// Join open polylines whose end points are within `join_eps` of each other.
+///
+/// Every chain starts from the first unused polyline and is extended at its
+/// end and then at its start with the polyline whose end point is closest
+/// within `join_eps`, reversing it if needed. Chains whose ends meet are
+/// closed, dropping the duplicated end vertex. Closed polylines are passed
+/// through unchanged.
+pub(crate) fn chain_plines(plines: &[&Polyline<f64>], join_eps: f64) -> Vec<Polyline<f64>> {
+    let mut result = Vec::new();
+    let mut pieces = Vec::new();
+    for &p in plines {
+        if p.is_closed() {
+            result.push(p.clone());
+        } else if p.vertex_count() > 0 {
+            pieces.push(p);
+        }
+    }
+    if pieces.is_empty() {
+        return result;
+    }
+    // item 2 * i is the start and 2 * i + 1 the end of piece i
+    let end_point = |item: usize| {
+        let p = pieces[item / 2];
+        if item % 2 == 1 {
+            p.at(p.vertex_count() - 1).pos()
+        } else {
+            p.at(0).pos()
+        }
+    };
+    let mut builder = StaticAABB2DIndexBuilder::new(2 * pieces.len());
+    for item in 0..2 * pieces.len() {
+        let p = end_point(item);
+        builder.add(p.x, p.y, p.x, p.y);
+    }
+    let index = match builder.build() {
+        Ok(index) => index,
+        Err(_) => return result,
+    };
+
+    let mut used = vec![false; pieces.len()];
+    // closest unused end point within join_eps of `point`
+    let find = |used: &[bool], point: Vector2<f64>| {
+        index
+            .query(
+                point.x - join_eps,
+                point.y - join_eps,
+                point.x + join_eps,
+                point.y + join_eps,
+            )
+            .into_iter()
+            .filter(|&item| !used[item / 2])
+            .map(|item| (item, (end_point(item) - point).length()))
+            .filter(|&(_, dist)| dist <= join_eps)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(item, _)| item)
+    };
+
+    for seed in 0..pieces.len() {
+        if used[seed] {
+            continue;
+        }
+        used[seed] = true;
+        let mut chain = pieces[seed].clone();
+        // extend the end, then the start by extending the end of the
+        // reversed chain
+        let mut reversed = false;
+        for _ in 0..2 {
+            while !is_loop(&chain, join_eps) {
+                let end = chain.at(chain.vertex_count() - 1).pos();
+                let item = match find(&used, end) {
+                    Some(item) => item,
+                    None => break,
+                };
+                used[item / 2] = true;
+                let mut piece = pieces[item / 2].clone();
+                if item % 2 == 1 {
+                    piece.invert_direction_mut();
+                }
+                append(&mut chain, &piece);
+            }
+            if is_loop(&chain, join_eps) {
+                break;
+            }
+            chain.invert_direction_mut();
+            reversed = !reversed;
+        }
+        if reversed {
+            chain.invert_direction_mut();
+        }
+        if is_loop(&chain, join_eps) {
+            chain.remove_last();
+            chain.set_is_closed(true);
+        }
+        result.push(chain);
+    }
+    result
+}
+
+/// Whether the open `chain` ends within `join_eps` of its start and has
+/// enough vertexes to form a loop.
+fn is_loop(chain: &Polyline<f64>, join_eps: f64) -> bool {
+    let n = chain.vertex_count();
+    if n < 3 {
+        return false;
+    }
+    let start = chain.at(0).pos();
+    let end = chain.at(n - 1).pos();
+    (start - end).length() <= join_eps
+}
+
+/// Chain open polylines of the list whose end points coincide within
+/// `join_eps` into longer polylines.
+///
+/// Polylines are reversed as needed to join them end to start and chains
+/// whose ends meet become closed polylines (without repeating the start
+/// vertex). Polylines are joined with the closest matching end point, at
+/// junctions of more than two end points the remaining polylines start new
+/// chains. Closed polylines are copied to `result` first, followed by the
+/// chains in the order of their first polyline in the list.
+///
+/// `result` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `plinelist` or `result` is null.
+/// * 2 = `join_eps` is negative, NaN or infinite.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_plinelist_chain(
+    plinelist: *const cavc_plinelist,
+    join_eps: f64,
+    result: *mut *mut cavc_plinelist,
+) -> i32 {
+    ffi_catch_unwind!({
+        if plinelist.is_null() || result.is_null() {
+            return 1;
+        }
+        if !join_eps.is_finite() || join_eps < 0.0 {
+            return 2;
+        }
+        let plines: Vec<&Polyline<f64>> = (*plinelist).0.iter().map(|&p| &(*p).0).collect();
+        *result = plinelist_into_raw(chain_plines(&plines, join_eps));
+        0
+    })
+}
@@ -0,0 +1,153 @@
+//! Positional cleanup of polyline vertexes.
+use std::f64::consts::TAU;
+
+use cavalier_contours::core::math::Vector2;
+use cavalier_contours::polyline::{
+    seg_arc_radius_and_center, PlineCreation, PlineSource, PlineSourceMut, PlineVertex, Polyline,
+};
+use cavalier_contours_ffi::cavc_pline;
+
+use crate::cache::evict;
+use crate::geom::{arc_bulge, seg_sweep};
+
+/// Round the vertex positions of `pline` to multiples of `grid_size`.
+///
+/// If `adjust_bulges` is set the bulges of arcs are recomputed to keep their
+/// centers, otherwise they are left as they are (keeping the sweep angles).
+fn snap_to_grid(pline: &mut Polyline<f64>, grid_size: f64, adjust_bulges: bool) {
+    let snap = |value: f64| (value / grid_size).round() * grid_size;
+    let n = pline.vertex_count();
+    let original = pline.clone();
+    for i in 0..n {
+        let v = original.at(i);
+        pline.set_vertex(i, PlineVertex::new(snap(v.x), snap(v.y), v.bulge));
+    }
+    if !adjust_bulges {
+        return;
+    }
+    for i in 0..pline.segment_count() {
+        let next = (i + 1) % n;
+        let (v1, v2) = (original.at(i), original.at(next));
+        let (start, end) = (pline.at(i).pos(), pline.at(next).pos());
+        if v1.bulge_is_zero() || v1.pos() == v2.pos() || start == end {
+            continue;
+        }
+        // project the original center onto the perpendicular bisector of the
+        // snapped end points, the closest center of an arc through them
+        let (_, center) = seg_arc_radius_and_center(v1, v2);
+        let mid = (start + end).scale(0.5);
+        let chord = end - start;
+        let normal = Vector2::new(-chord.y, chord.x);
+        let center = mid + normal.scale((center - mid).dot(normal) / normal.dot(normal));
+        let bulge = arc_bulge(center, start, end, v1.bulge_is_pos());
+        pline.set_vertex(i, pline.at(i).with_bulge(bulge));
+    }
+}
+
+/// Snap the vertex positions of the polyline to a square grid with the
+/// spacing `grid_size`, in place.
+///
+/// Every coordinate is rounded to the nearest multiple of `grid_size`, e.g. a
+/// `grid_size` of 1e-6 in millimeter units gives whole nanometers. Since a
+/// bulge describes the arc between whatever end points it has, arcs always
+/// stay connected. If `adjust_bulges` is 0 the bulges are kept, so every arc
+/// keeps its sweep angle while its radius and center follow the end points,
+/// which changes the radius of flat arcs noticeably. Otherwise the bulge of
+/// every arc is recomputed so its center stays as close as possible to the
+/// original one (projected onto the perpendicular bisector of the snapped end
+/// points) and its direction is kept, so the radius changes about as much as
+/// the end points move. Vertexes snapping to the same position are kept and
+/// give zero length segments, use [cavc_pline_weld] to merge them.
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` is null.
+/// * 2 = `grid_size` is not greater than 0 or not finite.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_snap_to_grid(
+    pline: *mut cavc_pline,
+    grid_size: f64,
+    adjust_bulges: u8,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() {
+            return 1;
+        }
+        if !grid_size.is_finite() || grid_size <= 0.0 {
+            return 2;
+        }
+        evict(pline);
+        snap_to_grid(&mut (*pline).0, grid_size, adjust_bulges != 0);
+        0
+    })
+}
+
+/// Bulge of the segment replacing the segment of `a` collapsed to a point and
+/// the following segment of `b`, turning by the sum of both sweep angles.
+///
+/// Falls back to the bulge of `b` if the sum reaches a full turn.
+fn merged_bulge(a: PlineVertex<f64>, b: PlineVertex<f64>) -> f64 {
+    let sweep = seg_sweep(a) + seg_sweep(b);
+    if sweep.abs() < TAU {
+        (sweep / 4.0).tan()
+    } else {
+        b.bulge
+    }
+}
+
+/// Merge runs of consecutive vertexes of `pline` closer than `eps` into the
+/// first vertex of the run.
+fn weld(pline: &Polyline<f64>, eps: f64) -> Polyline<f64> {
+    let mut result = Polyline::with_capacity(pline.vertex_count(), pline.is_closed());
+    for v in pline.iter_vertexes() {
+        match result.last() {
+            Some(last) if (v.pos() - last.pos()).length() < eps => {
+                let i = result.vertex_count() - 1;
+                result.set_vertex(i, last.with_bulge(merged_bulge(last, v)));
+            }
+            _ => result.add_vertex(v),
+        }
+    }
+    // the run may continue from the last to the first vertex
+    while result.is_closed() && result.vertex_count() > 1 {
+        let first = result.at(0);
+        let last = result.at(result.vertex_count() - 1);
+        if (first.pos() - last.pos()).length() >= eps {
+            break;
+        }
+        result.set_vertex(0, first.with_bulge(merged_bulge(last, first)));
+        result.remove_last();
+    }
+    result
+}
+
+/// Merge consecutive vertexes of the polyline closer than `eps` to each
+/// other, in place.
+///
+/// Vertexes are visited in order and every vertex closer than `eps` to the
+/// previous kept vertex is merged into it, so no segment shorter than `eps`
+/// remains (for closed polylines this includes the closing segment). The kept
+/// vertex is the first of every run and keeps its position. The segment
+/// leaving it turns by the sum of the sweep angles of the merged segments, so
+/// a run of tiny arc pieces keeps its total change of direction and tiny line
+/// pieces leave the following segment as it was. Unlike
+/// `cavc_pline_remove_redundant` this never merges collinear segments or arcs
+/// of the same circle, only vertexes close to each other.
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` is null.
+/// * 2 = `eps` is not greater than 0 or not finite.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_weld(pline: *mut cavc_pline, eps: f64) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() {
+            return 1;
+        }
+        if !eps.is_finite() || eps <= 0.0 {
+            return 2;
+        }
+        evict(pline);
+        let pline = &mut (*pline).0;
+        *pline = weld(pline, eps);
+        0
+    })
+}
@@ -0,0 +1,479 @@
+//! Clipping polylines to rectangles and regions.
+use cavalier_contours::core::math::Vector2;
+use cavalier_contours::polyline::{
+    seg_fast_approx_bounding_box, seg_intersect, FindIntersectsOptions, PlineCreation,
+    PlineOrientation, PlineSource, PlineSourceMut, PlineVertex, Polyline,
+};
+use cavalier_contours_ffi::{cavc_pline, cavc_plinelist};
+
+use crate::geom::{intr_points, seg_param_at_point, seg_point_at, seg_sub, seg_tangent_at};
+use crate::measure::closest_point_indexed;
+use crate::util::plinelist_into_raw;
+
+/// Axis aligned rectangle given by its minimum and maximum corners.
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct Rect {
+    pub min_x: f64,
+    pub min_y: f64,
+    pub max_x: f64,
+    pub max_y: f64,
+}
+
+impl Rect {
+    fn contains(&self, x: f64, y: f64) -> bool {
+        x >= self.min_x && x <= self.max_x && y >= self.min_y && y <= self.max_y
+    }
+
+    fn width(&self) -> f64 {
+        self.max_x - self.min_x
+    }
+
+    fn height(&self) -> f64 {
+        self.max_y - self.min_y
+    }
+
+    /// Inward normal of the edge all `points` lie on (within `eps`), None if
+    /// they are not on a common edge.
+    fn edge_normal(&self, points: &[Vector2<f64>], eps: f64) -> Option<Vector2<f64>> {
+        let on = |f: &dyn Fn(Vector2<f64>) -> f64| points.iter().all(|&p| f(p).abs() <= eps);
+        if on(&|p| p.y - self.min_y) {
+            Some(Vector2::new(0.0, 1.0))
+        } else if on(&|p| p.x - self.max_x) {
+            Some(Vector2::new(-1.0, 0.0))
+        } else if on(&|p| p.y - self.max_y) {
+            Some(Vector2::new(0.0, -1.0))
+        } else if on(&|p| p.x - self.min_x) {
+            Some(Vector2::new(1.0, 0.0))
+        } else {
+            None
+        }
+    }
+
+    /// Distance along the boundary, counter clockwise from the minimum
+    /// corner, of the boundary point closest to `p`.
+    fn boundary_pos(&self, p: Vector2<f64>) -> f64 {
+        let (w, h) = (self.width(), self.height());
+        let distances = [
+            (p.y - self.min_y).abs(),
+            (self.max_x - p.x).abs(),
+            (self.max_y - p.y).abs(),
+            (p.x - self.min_x).abs(),
+        ];
+        let edge = (0..4)
+            .min_by(|&a, &b| distances[a].total_cmp(&distances[b]))
+            .unwrap_or(0);
+        match edge {
+            0 => (p.x - self.min_x).clamp(0.0, w),
+            1 => w + (p.y - self.min_y).clamp(0.0, h),
+            2 => w + h + (self.max_x - p.x).clamp(0.0, w),
+            _ => 2.0 * w + h + (self.max_y - p.y).clamp(0.0, h),
+        }
+    }
+
+    /// Corners with their boundary positions (see [Rect::boundary_pos]).
+    fn corners(&self) -> [(f64, Vector2<f64>); 4] {
+        let (w, h) = (self.width(), self.height());
+        [
+            (0.0, Vector2::new(self.min_x, self.min_y)),
+            (w, Vector2::new(self.max_x, self.min_y)),
+            (w + h, Vector2::new(self.max_x, self.max_y)),
+            (2.0 * w + h, Vector2::new(self.min_x, self.max_y)),
+        ]
+    }
+
+    /// Closed counter clockwise polyline of the rectangle.
+    pub fn to_pline(self) -> Polyline<f64> {
+        let mut pline = Polyline::with_capacity(4, true);
+        pline.add(self.min_x, self.min_y, 0.0);
+        pline.add(self.max_x, self.min_y, 0.0);
+        pline.add(self.max_x, self.max_y, 0.0);
+        pline.add(self.min_x, self.max_y, 0.0);
+        pline
+    }
+}
+
+/// Pieces of the open path of `pline` (the closing segment of closed
+/// polylines included) that are kept.
+///
+/// Every segment is cut at the parameters returned by `cut_params` and the
+/// parts passing `keep` (given the segment and the parameters of the part)
+/// are joined into pieces.
+fn clip_path(
+    pline: &Polyline<f64>,
+    cut_params: impl Fn(PlineVertex<f64>, PlineVertex<f64>) -> Vec<f64>,
+    keep: impl Fn(PlineVertex<f64>, PlineVertex<f64>, f64, f64) -> bool,
+) -> Vec<Polyline<f64>> {
+    let mut pieces = Vec::new();
+    let mut current: Option<Polyline<f64>> = None;
+    if pline.vertex_count() == 1 {
+        let v = pline.at(0);
+        if keep(v, v, 0.0, 0.0) {
+            pieces.push(Polyline::from_iter(std::iter::once(v), false));
+        }
+        return pieces;
+    }
+    for (v1, v2) in pline.iter_segments() {
+        let mut params = cut_params(v1, v2);
+        params.extend([0.0, 1.0]);
+        params.sort_by(|a, b| a.total_cmp(b));
+        params.dedup_by(|a, b| (*a - *b).abs() < 1e-12);
+
+        for t in params.windows(2) {
+            if !keep(v1, v2, t[0], t[1]) {
+                pieces.extend(current.take().filter(|p| p.vertex_count() > 1));
+                continue;
+            }
+            let (start, end) = seg_sub(v1, v2, t[0], t[1]);
+            let piece = current.get_or_insert_with(|| {
+                Polyline::from_iter(
+                    std::iter::once(PlineVertex::new(start.x, start.y, 0.0)),
+                    false,
+                )
+            });
+            let last = piece.vertex_count() - 1;
+            piece.set_vertex(last, start);
+            piece.add(end.x, end.y, 0.0);
+        }
+    }
+    pieces.extend(current.filter(|p| p.vertex_count() > 1));
+    pieces
+}
+
+/// Pieces of the open path of `pline` (the closing segment of closed
+/// polylines included) lying inside `rect`.
+pub(crate) fn clip_path_to_rect(
+    pline: &Polyline<f64>,
+    rect: Rect,
+    pos_equal_eps: f64,
+) -> Vec<Polyline<f64>> {
+    let keep = |v1, v2, t0: f64, t1: f64| {
+        let p = seg_point_at(v1, v2, 0.5 * (t0 + t1));
+        rect.contains(p.x, p.y)
+    };
+    clip_path(pline, rect_cut_params(rect, pos_equal_eps), keep)
+}
+
+/// Parameters where segments cross the boundary of `rect`.
+fn rect_cut_params(
+    rect: Rect,
+    pos_equal_eps: f64,
+) -> impl Fn(PlineVertex<f64>, PlineVertex<f64>) -> Vec<f64> {
+    let edges = rect.to_pline();
+    move |v1: PlineVertex<f64>, v2: PlineVertex<f64>| {
+        edges
+            .iter_segments()
+            .flat_map(|(e1, e2)| intr_points(seg_intersect(v1, v2, e1, e2, pos_equal_eps)))
+            .map(|p| seg_param_at_point(v1, v2, p))
+            .collect()
+    }
+}
+
+/// Append `v` to `pline`, replacing the last vertex if it is at the same
+/// position.
+fn push_vertex(pline: &mut Polyline<f64>, v: PlineVertex<f64>, pos_equal_eps: f64) {
+    match pline.last() {
+        Some(last) if last.pos().fuzzy_eq_eps(v.pos(), pos_equal_eps) => {
+            let last = pline.vertex_count() - 1;
+            pline.set_vertex(last, v);
+        }
+        _ => pline.add_vertex(v),
+    }
+}
+
+/// Regions of the area of the closed counter clockwise `pline` (which must
+/// not self intersect) inside `rect`.
+///
+/// The parts of the boundary of `pline` inside the rectangle are kept, parts
+/// running along the rectangle boundary only if the area is on the inner
+/// side. Every kept piece ends where the boundary leaves the rectangle and
+/// is joined to the piece entering next counter clockwise along the
+/// rectangle boundary, adding the corners passed on the way (clipping
+/// against a convex window as in Weiler-Atherton).
+fn clip_area_to_rect(pline: &Polyline<f64>, rect: Rect, pos_equal_eps: f64) -> Vec<Polyline<f64>> {
+    let keep = |v1, v2, t0: f64, t1: f64| {
+        let tm = 0.5 * (t0 + t1);
+        let mid = seg_point_at(v1, v2, tm);
+        if !rect.contains(mid.x, mid.y) {
+            return false;
+        }
+        let points = [seg_point_at(v1, v2, t0), mid, seg_point_at(v1, v2, t1)];
+        match rect.edge_normal(&points, pos_equal_eps) {
+            // the area is on the left of counter clockwise polylines
+            Some(normal) => seg_tangent_at(v1, v2, tm).perp_dot(normal) > 0.0,
+            None => true,
+        }
+    };
+    let mut pieces = clip_path(pline, rect_cut_params(rect, pos_equal_eps), keep);
+
+    // the path starts at the first vertex, a piece running through it is
+    // split in two
+    let start = pline.at(0).pos();
+    let start_on_boundary = rect.edge_normal(&[start], pos_equal_eps).is_some();
+    if pieces.len() > 1
+        && !start_on_boundary
+        && pieces[0].at(0).pos().fuzzy_eq_eps(start, pos_equal_eps)
+    {
+        let first = pieces.remove(0);
+        let last = pieces.last_mut().unwrap();
+        if last
+            .last()
+            .map_or(false, |v| v.pos().fuzzy_eq_eps(start, pos_equal_eps))
+        {
+            for v in first.iter_vertexes() {
+                push_vertex(last, v, pos_equal_eps);
+            }
+        } else {
+            pieces.insert(0, first);
+        }
+    }
+
+    let perimeter = 2.0 * (rect.width() + rect.height());
+    let ccw_dist = |from: f64, to: f64| {
+        let d = (to - from).rem_euclid(perimeter);
+        if d > perimeter - pos_equal_eps {
+            0.0
+        } else {
+            d
+        }
+    };
+    let corners = rect.corners();
+    let starts: Vec<f64> = pieces
+        .iter()
+        .map(|p| rect.boundary_pos(p.at(0).pos()))
+        .collect();
+    let mut used = vec![false; pieces.len()];
+    let mut regions = Vec::new();
+    for first in 0..pieces.len() {
+        if used[first] {
+            continue;
+        }
+        let mut region = Polyline::new_closed();
+        let mut i = first;
+        loop {
+            used[i] = true;
+            for v in pieces[i].iter_vertexes() {
+                push_vertex(&mut region, v, pos_equal_eps);
+            }
+            let end = pieces[i].at(pieces[i].vertex_count() - 1).pos();
+            if end.fuzzy_eq_eps(pieces[i].at(0).pos(), pos_equal_eps) && i == first {
+                // a loop inside touching the boundary
+                break;
+            }
+            let end_pos = rect.boundary_pos(end);
+            let next = (0..pieces.len())
+                .filter(|&j| !used[j] || j == first)
+                .min_by(|&a, &b| {
+                    ccw_dist(end_pos, starts[a]).total_cmp(&ccw_dist(end_pos, starts[b]))
+                })
+                .unwrap_or(first);
+            let span = ccw_dist(end_pos, starts[next]);
+            let mut passed: Vec<_> = corners
+                .iter()
+                .map(|&(pos, corner)| (ccw_dist(end_pos, pos), corner))
+                .filter(|&(d, _)| d > 0.0 && d < span)
+                .collect();
+            passed.sort_by(|a, b| a.0.total_cmp(&b.0));
+            for (_, corner) in passed {
+                push_vertex(
+                    &mut region,
+                    PlineVertex::from_vector2(corner, 0.0),
+                    pos_equal_eps,
+                );
+            }
+            if next == first {
+                break;
+            }
+            i = next;
+        }
+        let n = region.vertex_count();
+        if n > 1
+            && region
+                .at(n - 1)
+                .pos()
+                .fuzzy_eq_eps(region.at(0).pos(), pos_equal_eps)
+        {
+            region.remove_last();
+        }
+        if region.vertex_count() > 1 && region.area() > 0.0 {
+            regions.push(region);
+        }
+    }
+    if regions.is_empty() && pieces.is_empty() {
+        // no boundary inside, the rectangle is either inside of the area or
+        // outside of it
+        let center = Vector2::new(
+            0.5 * (rect.min_x + rect.max_x),
+            0.5 * (rect.min_y + rect.max_y),
+        );
+        if pline.winding_number(center) != 0 {
+            regions.push(rect.to_pline());
+        }
+    }
+    regions
+}
+
+/// Pieces of the open path of `pline` (the closing segment of closed
+/// polylines included) lying inside (or outside if `keep_inside` is false) the
+/// area bounded by the closed `boundaries`, parts on a boundary count as
+/// inside.
+///
+/// A point is inside if the winding numbers of all `boundaries` do not sum up
+/// to 0, so holes have to be oriented opposite to the polylines containing
+/// them.
+pub(crate) fn clip_path_to_region(
+    pline: &Polyline<f64>,
+    boundaries: &[&Polyline<f64>],
+    keep_inside: bool,
+    pos_equal_eps: f64,
+) -> Vec<Polyline<f64>> {
+    let indexes: Vec<_> = boundaries
+        .iter()
+        .map(|b| b.create_approx_aabb_index())
+        .collect();
+    let cut_params = |v1: PlineVertex<f64>, v2: PlineVertex<f64>| -> Vec<f64> {
+        let b = seg_fast_approx_bounding_box(v1, v2);
+        let mut params = Vec::new();
+        for (region, index) in boundaries.iter().zip(&indexes) {
+            index.visit_query(b.min_x, b.min_y, b.max_x, b.max_y, &mut |j| {
+                let next = region.next_wrapping_index(j);
+                let intr = seg_intersect(v1, v2, region.at(j), region.at(next), pos_equal_eps);
+                params.extend(
+                    intr_points(intr)
+                        .into_iter()
+                        .map(|p| seg_param_at_point(v1, v2, p)),
+                );
+            });
+        }
+        params
+    };
+    let keep = |v1, v2, t0: f64, t1: f64| {
+        let p = seg_point_at(v1, v2, 0.5 * (t0 + t1));
+        let on_boundary = boundaries.iter().zip(&indexes).any(|(region, index)| {
+            closest_point_indexed(region, index, p, pos_equal_eps)
+                .map_or(false, |(_, _, dist)| dist <= pos_equal_eps)
+        });
+        let winding: i32 = boundaries.iter().map(|b| b.winding_number(p)).sum();
+        (on_boundary || winding != 0) == keep_inside
+    };
+    clip_path(pline, cut_params, keep)
+}
+
+/// Clip a polyline to an axis aligned rectangle.
+///
+/// Open polylines are split where they cross the rectangle boundary and
+/// `result_plinelist` receives the open pieces inside the rectangle in path
+/// order. Closed polylines give the closed regions of their area inside the
+/// rectangle, with the orientation of `pline`: the parts of the polyline
+/// inside the rectangle are joined along the rectangle boundary, without a
+/// general boolean operation, so closed polylines must not self intersect. A
+/// polyline entirely inside the rectangle is returned as is and one with
+/// extents outside of it gives an empty list without any intersection work.
+/// Segments on the rectangle boundary count as inside (for closed polylines
+/// if their area is on the inner side). Arcs stay arcs.
+///
+/// `result_plinelist` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `result_plinelist` is null.
+/// * 2 = the rectangle is empty (a minimum is not less than its maximum) or
+///   not finite.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_clip_rect(
+    pline: *const cavc_pline,
+    min_x: f64,
+    min_y: f64,
+    max_x: f64,
+    max_y: f64,
+    result_plinelist: *mut *mut cavc_plinelist,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || result_plinelist.is_null() {
+            return 1;
+        }
+        if ![min_x, min_y, max_x, max_y].iter().all(|v| v.is_finite())
+            || min_x >= max_x
+            || min_y >= max_y
+        {
+            return 2;
+        }
+        let rect = Rect {
+            min_x,
+            min_y,
+            max_x,
+            max_y,
+        };
+        let pline = &(*pline).0;
+        let extents = match pline.extents() {
+            Some(extents) => extents,
+            None => {
+                *result_plinelist = plinelist_into_raw(Vec::new());
+                return 0;
+            }
+        };
+        let plines = if rect.contains(extents.min_x, extents.min_y)
+            && rect.contains(extents.max_x, extents.max_y)
+        {
+            vec![pline.clone()]
+        } else if extents.min_x > max_x
+            || extents.max_x < min_x
+            || extents.min_y > max_y
+            || extents.max_y < min_y
+        {
+            Vec::new()
+        } else if pline.is_closed() && pline.vertex_count() > 1 {
+            let pos_equal_eps = FindIntersectsOptions::<f64>::new().pos_equal_eps;
+            if pline.orientation() == PlineOrientation::Clockwise {
+                let mut ccw = pline.clone();
+                ccw.invert_direction_mut();
+                let mut regions = clip_area_to_rect(&ccw, rect, pos_equal_eps);
+                regions.iter_mut().for_each(|p| p.invert_direction_mut());
+                regions
+            } else {
+                clip_area_to_rect(pline, rect, pos_equal_eps)
+            }
+        } else {
+            let pos_equal_eps = FindIntersectsOptions::<f64>::new().pos_equal_eps;
+            clip_path_to_rect(pline, rect, pos_equal_eps)
+        };
+        *result_plinelist = plinelist_into_raw(plines);
+        0
+    })
+}
+
+/// Clip a polyline by the area of a closed polyline.
+///
+/// `open_pline` is split where it crosses the boundary of `closed_pline` and
+/// `result_plinelist` receives the open pieces inside the region if
+/// `keep_inside` is not 0, otherwise the pieces outside of it, in path order.
+/// Parts running along the boundary count as inside. The orientation of
+/// `closed_pline` does not matter and arcs stay arcs. If `open_pline` is
+/// closed its closing segment is clipped as part of the path, the pieces are
+/// still open. Both polylines are left unchanged.
+///
+/// `result_plinelist` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `open_pline`, `closed_pline` or `result_plinelist` is null.
+/// * 2 = `closed_pline` is not closed or has less than 2 vertexes.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_clip_by_region(
+    open_pline: *const cavc_pline,
+    closed_pline: *const cavc_pline,
+    keep_inside: u8,
+    result_plinelist: *mut *mut cavc_plinelist,
+) -> i32 {
+    ffi_catch_unwind!({
+        if open_pline.is_null() || closed_pline.is_null() || result_plinelist.is_null() {
+            return 1;
+        }
+        let region = &(*closed_pline).0;
+        if !region.is_closed() || region.vertex_count() < 2 {
+            return 2;
+        }
+        let pos_equal_eps = FindIntersectsOptions::<f64>::new().pos_equal_eps;
+        let plines =
+            clip_path_to_region(&(*open_pline).0, &[region], keep_inside != 0, pos_equal_eps);
+        *result_plinelist = plinelist_into_raw(plines);
+        0
+    })
+}
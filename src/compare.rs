@@ -0,0 +1,341 @@
+//! Comparison of polyline geometry.
+use cavalier_contours::core::math::Vector2;
+use cavalier_contours::polyline::{BooleanOp, FindIntersectsOptions, PlineSource, Polyline};
+use cavalier_contours_ffi::cavc_pline;
+
+use crate::boolean::{boolean_eps, to_ccw};
+use crate::geom::seg_point_at;
+use crate::util::valid_eps;
+
+/// Segment described by its start, middle and end points.
+type SegPoints = [Vector2<f64>; 3];
+
+/// Start, middle and end points of all segments of `pline`.
+fn seg_points(pline: &Polyline<f64>) -> Vec<SegPoints> {
+    pline
+        .iter_segments()
+        .map(|(v1, v2)| [v1.pos(), seg_point_at(v1, v2, 0.5), v2.pos()])
+        .collect()
+}
+
+/// True if `segs1` and `segs2` rotated by `offset` match point by point.
+fn segs_match(segs1: &[SegPoints], segs2: &[SegPoints], offset: usize, eps: f64) -> bool {
+    let n = segs2.len();
+    segs1.iter().enumerate().all(|(i, s1)| {
+        let s2 = &segs2[(i + offset) % n];
+        s1.iter()
+            .zip(s2.iter())
+            .all(|(&p1, &p2)| p1.fuzzy_eq_eps(p2, eps))
+    })
+}
+
+/// Test if the polylines describe the same geometry within `eps`.
+fn fuzzy_equal(
+    pline1: &Polyline<f64>,
+    pline2: &Polyline<f64>,
+    eps: f64,
+    allow_index_offset: bool,
+) -> bool {
+    let n = pline1.vertex_count();
+    if n != pline2.vertex_count() || pline1.is_closed() != pline2.is_closed() {
+        return false;
+    }
+    if n == 0 {
+        return true;
+    }
+    if n == 1 {
+        return pline1.at(0).pos().fuzzy_eq_eps(pline2.at(0).pos(), eps);
+    }
+    let segs1 = seg_points(pline1);
+    let segs2 = seg_points(pline2);
+    if !(allow_index_offset && pline1.is_closed()) {
+        return segs_match(&segs1, &segs2, 0, eps);
+    }
+    // the opposite direction visits the same segments backward with swapped
+    // end points
+    let reversed: Vec<SegPoints> = segs2.iter().rev().map(|&[a, m, b]| [b, m, a]).collect();
+    [segs2, reversed].iter().any(|segs| {
+        (0..n).any(|offset| {
+            segs[offset][0].fuzzy_eq_eps(segs1[0][0], eps) && segs_match(&segs1, segs, offset, eps)
+        })
+    })
+}
+
+/// Test if two polylines describe the same geometry within `pos_equal_eps`.
+///
+/// The polylines are equal if they have the same number of vertexes, are both
+/// open or both closed and every pair of corresponding segments has its start,
+/// middle and end points within `pos_equal_eps` of each other. Comparing the
+/// middle points compares arcs by their geometry rather than by their bulge
+/// values, which are sensitive to rounding for short segments. If
+/// `allow_index_offset` is not 0 closed polylines are also equal if they
+/// start at a different vertex or run in the opposite direction. Polylines
+/// splitting the same geometry at different vertexes are not equal, use
+/// `cavc_pline_remove_redundant` on both first to compare those. `is_equal`
+/// is set to 1 if the polylines are equal and 0 otherwise.
+///
+/// ## Specific Error Codes
+/// * 1 = `pline1`, `pline2` or `is_equal` is null.
+/// * 2 = `pos_equal_eps` is negative or not finite.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_fuzzy_equal(
+    pline1: *const cavc_pline,
+    pline2: *const cavc_pline,
+    pos_equal_eps: f64,
+    allow_index_offset: u8,
+    is_equal: *mut u8,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline1.is_null() || pline2.is_null() || is_equal.is_null() {
+            return 1;
+        }
+        if !valid_eps(pos_equal_eps) {
+            return 2;
+        }
+        let equal = fuzzy_equal(
+            &(*pline1).0,
+            &(*pline2).0,
+            pos_equal_eps,
+            allow_index_offset != 0,
+        );
+        *is_equal = equal as u8;
+        0
+    })
+}
+
+/// FNV-1a hash, stable across platforms and releases unlike the hashers of
+/// the standard library.
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write_i64(&mut self, value: i64) {
+        for byte in value.to_le_bytes() {
+            self.0 ^= byte as u64;
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+/// Quantized key of every vertex: its position and the middle point of the
+/// segment it starts (its own position for the last vertex of open
+/// polylines), or None if a value is not finite.
+fn quantized_keys(pline: &Polyline<f64>, quantization: f64) -> Option<Vec<[i64; 4]>> {
+    let quantize = |p: Vector2<f64>| {
+        if p.x.is_finite() && p.y.is_finite() {
+            // saturating casts keep far away values deterministic
+            Some([
+                (p.x / quantization).round() as i64,
+                (p.y / quantization).round() as i64,
+            ])
+        } else {
+            None
+        }
+    };
+    let n = pline.vertex_count();
+    (0..n)
+        .map(|i| {
+            let v1 = pline.at(i);
+            let mid = if i + 1 < n || pline.is_closed() {
+                seg_point_at(v1, pline.at((i + 1) % n), 0.5)
+            } else {
+                v1.pos()
+            };
+            let [x, y] = quantize(v1.pos())?;
+            let [mx, my] = quantize(mid)?;
+            Some([x, y, mx, my])
+        })
+        .collect()
+}
+
+/// Index the lexicographically smallest rotation of `keys` starts at.
+fn min_rotation(keys: &[[i64; 4]]) -> usize {
+    let n = keys.len();
+    let rotated = |start: usize| (0..n).map(move |i| keys[(start + i) % n]);
+    (1..n).fold(0, |best, start| {
+        if rotated(start).lt(rotated(best)) {
+            start
+        } else {
+            best
+        }
+    })
+}
+
+/// Compute a stable 64 bit hash of the geometry of the polyline.
+///
+/// Every vertex position and the middle point of every segment are rounded to
+/// multiples of `quantization` and hashed together with the vertex count and
+/// whether the polyline is closed, so arcs are hashed by their geometry
+/// rather than by their bulge values. Closed polylines are hashed from the
+/// rotation of their vertexes that compares smallest, so the hash does not
+/// depend on the start vertex (the direction still matters). Equal polylines
+/// always have equal hashes, while geometry differing by less than
+/// `quantization` can still hash differently when a coordinate lies near a
+/// rounding boundary. The hash is the same on all platforms and does not
+/// change between releases.
+///
+/// `out_u64` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `out_u64` is null.
+/// * 2 = `quantization` is not greater than 0 or not finite.
+/// * 3 = `pline` contains a NaN or infinite value.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_fingerprint(
+    pline: *const cavc_pline,
+    quantization: f64,
+    out_u64: *mut u64,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || out_u64.is_null() {
+            return 1;
+        }
+        if !quantization.is_finite() || quantization <= 0.0 {
+            return 2;
+        }
+        let pline = &(*pline).0;
+        let keys = match quantized_keys(pline, quantization) {
+            Some(keys) => keys,
+            None => return 3,
+        };
+        let start = if pline.is_closed() {
+            min_rotation(&keys)
+        } else {
+            0
+        };
+        let mut hasher = Fnv1a::new();
+        hasher.write_i64(keys.len() as i64);
+        hasher.write_i64(pline.is_closed() as i64);
+        for i in 0..keys.len() {
+            for value in keys[(start + i) % keys.len()] {
+                hasher.write_i64(value);
+            }
+        }
+        *out_u64 = hasher.0;
+        0
+    })
+}
+
+/// Containment relation of two closed polylines, see [cavc_pline_relate].
+fn relate(pline1: &Polyline<f64>, pline2: &Polyline<f64>, eps: f64) -> u8 {
+    if fuzzy_equal(pline1, pline2, eps, true) {
+        return 4;
+    }
+    let mut options = FindIntersectsOptions::new();
+    options.pos_equal_eps = eps;
+    let intersects = pline1.find_intersects_opt(pline2, &options);
+    if !intersects.basic_intersects.is_empty() || !intersects.overlapping_intersects.is_empty() {
+        return 3;
+    }
+    // without intersects a polyline is either completely inside the other or
+    // completely outside of it, so any vertex decides
+    if pline2.winding_number(pline1.at(0).pos()) != 0 {
+        1
+    } else if pline1.winding_number(pline2.at(0).pos()) != 0 {
+        2
+    } else {
+        0
+    }
+}
+
+/// Classify how the areas of two closed polylines relate to each other.
+///
+/// `out_relation` is set to:
+/// * 0 = disjoint, neither polyline touches or contains the other.
+/// * 1 = `pline1` lies inside `pline2`.
+/// * 2 = `pline2` lies inside `pline1`.
+/// * 3 = the polylines intersect, including touching at a point or sharing
+///   part of their boundaries.
+/// * 4 = the polylines are equal as described by [cavc_pline_fuzzy_equal]
+///   with `allow_index_offset` set.
+///
+/// This only intersects the polylines and tests the winding number of a
+/// single vertex, which is much cheaper than a boolean operation. The
+/// orientation of the polylines does not matter.
+///
+/// `out_relation` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline1`, `pline2` or `out_relation` is null.
+/// * 2 = `pline1` or `pline2` is not closed or has less than 2 vertexes.
+/// * 3 = `pos_equal_eps` is negative or not finite.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_relate(
+    pline1: *const cavc_pline,
+    pline2: *const cavc_pline,
+    pos_equal_eps: f64,
+    out_relation: *mut u8,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline1.is_null() || pline2.is_null() || out_relation.is_null() {
+            return 1;
+        }
+        let pline1 = &(*pline1).0;
+        let pline2 = &(*pline2).0;
+        if [pline1, pline2]
+            .iter()
+            .any(|p| !p.is_closed() || p.vertex_count() < 2)
+        {
+            return 2;
+        }
+        if !valid_eps(pos_equal_eps) {
+            return 3;
+        }
+        *out_relation = relate(pline1, pline2, pos_equal_eps);
+        0
+    })
+}
+
+/// Area of the symmetric difference of the areas of two closed polylines
+/// relative to the area of their union, 0 for equal areas and 1 for disjoint
+/// ones.
+///
+/// Both areas are only intersected once, the areas of the union and the
+/// symmetric difference follow from the intersection area and no polylines of
+/// the result are created. The orientation of the polylines does not matter.
+/// `out_ratio` receives 0 if both polylines have zero area.
+///
+/// `out_ratio` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline1`, `pline2` or `out_ratio` is null.
+/// * 2 = `pline1` or `pline2` is not closed or has less than 2 vertexes.
+/// * 3 = `pos_equal_eps` is negative or not finite.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_shape_similarity(
+    pline1: *const cavc_pline,
+    pline2: *const cavc_pline,
+    pos_equal_eps: f64,
+    out_ratio: *mut f64,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline1.is_null() || pline2.is_null() || out_ratio.is_null() {
+            return 1;
+        }
+        let pline1 = &(*pline1).0;
+        let pline2 = &(*pline2).0;
+        if [pline1, pline2]
+            .iter()
+            .any(|p| !p.is_closed() || p.vertex_count() < 2)
+        {
+            return 2;
+        }
+        if !valid_eps(pos_equal_eps) {
+            return 3;
+        }
+        let (pline1, pline2) = (to_ccw(pline1), to_ccw(pline2));
+        let (pos, neg, _) = boolean_eps(&pline1, &pline2, BooleanOp::And, pos_equal_eps);
+        let area = |plines: &[Polyline<f64>]| plines.iter().map(|p| p.area().abs()).sum::<f64>();
+        let and_area = area(&pos) - area(&neg);
+        let union_area = pline1.area() + pline2.area() - and_area;
+        *out_ratio = if union_area > 0.0 {
+            ((union_area - and_area) / union_area).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        0
+    })
+}
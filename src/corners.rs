@@ -0,0 +1,546 @@
+//! Corner modification (fillets and chamfers).
+//!
+//! Corners are the vertexes joining two line segments, the first and last
+//! vertexes of open polylines are not corners. All corner operations select
+//! corners the same way: `indices` lists the vertex indexes of the corners to
+//! modify, or is null to modify every corner. Corners where the cut does not
+//! fit (the segments are too short, also taking into account cuts at their
+//! other ends) or that are not between two lines are left unchanged.
+//! [cavc_pline_find_sharp_corners] finds corners to select by their angle.
+use cavalier_contours::core::math::Vector2;
+use cavalier_contours::polyline::{
+    PlineCreation, PlineOffsetOptions, PlineSource, PlineSourceMut, Polyline,
+};
+use cavalier_contours_ffi::cavc_pline;
+
+use crate::buffer::cavc_buffer;
+use crate::geom::seg_turn_angle;
+use crate::path::seg_lengths;
+use crate::util::{into_raw, pline_into_raw};
+
+/// Cut applied to a corner.
+#[derive(Debug, Copy, Clone)]
+pub(crate) enum CornerCut {
+    /// Tangent arc of the radius.
+    Fillet(f64),
+    /// Line from the distance along the incoming segment to the distance
+    /// along the outgoing segment.
+    Chamfer(f64, f64),
+    /// Tangent arc of the largest radius up to the given one that fits in
+    /// half of each segment, leaving the other half to the neighboring
+    /// corner.
+    FilletUpTo(f64),
+}
+
+/// Corner cut that fits, given by the points on both segments and the bulge
+/// between them.
+struct CutPoints {
+    start: Vector2<f64>,
+    end: Vector2<f64>,
+    bulge: f64,
+    /// Lengths trimmed from the incoming and outgoing segments.
+    trim_in: f64,
+    trim_out: f64,
+}
+
+/// Cut of the corner at vertex `i` ignoring neighboring cuts, None if it is
+/// not a corner between two lines or it is straight.
+fn corner_cut(pline: &Polyline<f64>, i: usize, cut: CornerCut) -> Option<CutPoints> {
+    let prev = pline.at(pline.prev_wrapping_index(i));
+    let v = pline.at(i);
+    let next = pline.at(pline.next_wrapping_index(i));
+    if !prev.bulge_is_zero() || !v.bulge_is_zero() {
+        return None;
+    }
+    let back = prev.pos() - v.pos();
+    let ahead = next.pos() - v.pos();
+    let (back_len, ahead_len) = (back.length(), ahead.length());
+    if back_len == 0.0 || ahead_len == 0.0 {
+        return None;
+    }
+    let u1 = back.scale(1.0 / back_len);
+    let u2 = ahead.scale(1.0 / ahead_len);
+    // angle between the segments at the corner, pi if straight
+    let angle = u1.perp_dot(u2).abs().atan2(u1.dot(u2));
+    if angle >= std::f64::consts::PI - 1e-9 || angle <= 1e-9 {
+        return None;
+    }
+    // turning left gives a counter clockwise fillet
+    let turns_left = u1.perp_dot(u2) < 0.0;
+    let (trim_in, trim_out, bulge) = match cut {
+        CornerCut::Fillet(radius) => {
+            let d = radius / (0.5 * angle).tan();
+            let sweep = std::f64::consts::PI - angle;
+            let bulge = (sweep / 4.0).tan();
+            (d, d, if turns_left { bulge } else { -bulge })
+        }
+        CornerCut::Chamfer(d1, d2) => (d1, d2, 0.0),
+        CornerCut::FilletUpTo(max_radius) => {
+            let half_tan = (0.5 * angle).tan();
+            let d = (max_radius / half_tan).min(0.5 * back_len.min(ahead_len));
+            let sweep = std::f64::consts::PI - angle;
+            let bulge = (sweep / 4.0).tan();
+            (d, d, if turns_left { bulge } else { -bulge })
+        }
+    };
+    Some(CutPoints {
+        start: v.pos() + u1.scale(trim_in),
+        end: v.pos() + u2.scale(trim_out),
+        bulge,
+        trim_in,
+        trim_out,
+    })
+}
+
+/// Apply `cut` to the selected corners of `pline` (all if `selected` is
+/// None), returning the new polyline and the number of corners cut.
+///
+/// Cut points within the default `pos_equal_eps` of the previous point (cuts
+/// using up a whole segment) are merged.
+pub(crate) fn cut_corners(
+    pline: &Polyline<f64>,
+    selected: Option<&[u32]>,
+    cut: CornerCut,
+) -> (Polyline<f64>, usize) {
+    let n = pline.vertex_count();
+    let is_corner = |i: usize| (pline.is_closed() && n > 2) || (i > 0 && i + 1 < n);
+    let mut wanted = vec![selected.is_none(); n];
+    for &i in selected.unwrap_or(&[]) {
+        wanted[i as usize] = true;
+    }
+    // length already trimmed from the start of every segment by accepted cuts
+    let mut trimmed_start = vec![0.0; n];
+    let mut trimmed_end = vec![0.0; n];
+    let mut cuts: Vec<Option<CutPoints>> = (0..n).map(|_| None).collect();
+    let seg_len =
+        |i: usize| (pline.at(pline.next_wrapping_index(i)).pos() - pline.at(i).pos()).length();
+    for i in (0..n).filter(|&i| wanted[i] && is_corner(i)) {
+        let c = match corner_cut(pline, i, cut) {
+            Some(c) => c,
+            None => continue,
+        };
+        let seg_in = pline.prev_wrapping_index(i);
+        let fits_in = c.trim_in + trimmed_start[seg_in] <= seg_len(seg_in);
+        let fits_out = c.trim_out + trimmed_end[i] <= seg_len(i);
+        if fits_in && fits_out {
+            trimmed_end[seg_in] += c.trim_in;
+            trimmed_start[i] += c.trim_out;
+            cuts[i] = Some(c);
+        }
+    }
+
+    let count = cuts.iter().filter(|c| c.is_some()).count();
+    let mut result = Polyline::with_capacity(n + count, pline.is_closed());
+    // cuts using up a whole segment meet the neighboring cut, the shared
+    // point is only added once
+    let pos_equal_eps = PlineOffsetOptions::<f64>::new().pos_equal_eps;
+    let add = |result: &mut Polyline<f64>, p: Vector2<f64>, bulge: f64| match result.last() {
+        Some(last) if last.pos().fuzzy_eq_eps(p, pos_equal_eps) => {
+            let i = result.vertex_count() - 1;
+            result.set_vertex(i, last.with_bulge(bulge));
+        }
+        _ => result.add(p.x, p.y, bulge),
+    };
+    for (v, c) in pline.iter_vertexes().zip(cuts) {
+        match c {
+            Some(c) => {
+                add(&mut result, c.start, c.bulge);
+                add(&mut result, c.end, 0.0);
+            }
+            None => add(&mut result, v.pos(), v.bulge),
+        }
+    }
+    if let (true, Some(first), Some(last)) = (result.is_closed(), result.get(0), result.last()) {
+        if result.vertex_count() > 1 && first.pos().fuzzy_eq_eps(last.pos(), pos_equal_eps) {
+            result.remove_last();
+        }
+    }
+    (result, count)
+}
+
+/// Read the corner selection of the FFI functions, Err if an index is out of
+/// bounds.
+unsafe fn read_selection<'a>(
+    indices: *const u32,
+    index_count: u32,
+    vertex_count: usize,
+) -> Result<Option<&'a [u32]>, ()> {
+    if indices.is_null() {
+        return Ok(None);
+    }
+    let selected = std::slice::from_raw_parts(indices, index_count as usize);
+    if selected.iter().any(|&i| i as usize >= vertex_count) {
+        return Err(());
+    }
+    Ok(Some(selected))
+}
+
+/// Round corners of the polyline with tangent arcs of `radius`.
+///
+/// See the module documentation for how corners are selected, `index_count`
+/// is ignored if `indices` is null. Every rounded corner is replaced by an arc
+/// segment tangent to both lines. `cut_count` receives the number of corners
+/// that were rounded, it may be null.
+///
+/// `result` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `result` is null.
+/// * 2 = an index of `indices` is out of bounds.
+/// * 3 = `radius` is not greater than 0 or infinite.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_fillet(
+    pline: *const cavc_pline,
+    radius: f64,
+    indices: *const u32,
+    index_count: u32,
+    cut_count: *mut u32,
+    result: *mut *mut cavc_pline,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || result.is_null() {
+            return 1;
+        }
+        let pline = &(*pline).0;
+        let selected = match read_selection(indices, index_count, pline.vertex_count()) {
+            Ok(selected) => selected,
+            Err(()) => return 2,
+        };
+        if !radius.is_finite() || radius <= 0.0 {
+            return 3;
+        }
+        let (filleted, count) = cut_corners(pline, selected, CornerCut::Fillet(radius));
+        if !cut_count.is_null() {
+            *cut_count = count as u32;
+        }
+        *result = pline_into_raw(filleted);
+        0
+    })
+}
+
+/// Round every corner of the polyline with the largest tangent arc of radius
+/// up to `max_radius` that fits, e.g. to smooth tool paths.
+///
+/// Each corner may use up half of each of its segments, so neighboring
+/// corners on a short segment get smaller arcs and arcs of corners sharing a
+/// segment which is used up completely meet. Corners with a zero length
+/// segment or that are not between two lines are left unchanged, see the
+/// module documentation. `cut_count` receives the number of corners that were
+/// rounded, it may be null.
+///
+/// `result` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `result` is null.
+/// * 2 = `max_radius` is not greater than 0 or infinite.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_round_corners(
+    pline: *const cavc_pline,
+    max_radius: f64,
+    cut_count: *mut u32,
+    result: *mut *mut cavc_pline,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || result.is_null() {
+            return 1;
+        }
+        if !max_radius.is_finite() || max_radius <= 0.0 {
+            return 2;
+        }
+        let (rounded, count) = cut_corners(&(*pline).0, None, CornerCut::FilletUpTo(max_radius));
+        if !cut_count.is_null() {
+            *cut_count = count as u32;
+        }
+        *result = pline_into_raw(rounded);
+        0
+    })
+}
+
+/// Cut corners of the polyline with straight chamfer segments.
+///
+/// See the module documentation for how corners are selected, `index_count`
+/// is ignored if `indices` is null. Every chamfered corner is replaced by a
+/// line from `distance1` before the corner along the incoming segment to
+/// `distance2` after the corner along the outgoing segment. `cut_count`
+/// receives the number of corners that were chamfered, it may be null.
+///
+/// `result` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `result` is null.
+/// * 2 = an index of `indices` is out of bounds.
+/// * 3 = `distance1` or `distance2` is not greater than 0 or infinite.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_chamfer(
+    pline: *const cavc_pline,
+    distance1: f64,
+    distance2: f64,
+    indices: *const u32,
+    index_count: u32,
+    cut_count: *mut u32,
+    result: *mut *mut cavc_pline,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || result.is_null() {
+            return 1;
+        }
+        let pline = &(*pline).0;
+        let selected = match read_selection(indices, index_count, pline.vertex_count()) {
+            Ok(selected) => selected,
+            Err(()) => return 2,
+        };
+        if [distance1, distance2]
+            .iter()
+            .any(|d| !d.is_finite() || *d <= 0.0)
+        {
+            return 3;
+        }
+        let cut = CornerCut::Chamfer(distance1, distance2);
+        let (chamfered, count) = cut_corners(pline, selected, cut);
+        if !cut_count.is_null() {
+            *cut_count = count as u32;
+        }
+        *result = pline_into_raw(chamfered);
+        0
+    })
+}
+
+/// Signed turning angles at the vertexes of `pline` (with `lengths` of its
+/// segments from [seg_lengths]) as `(vertex index, angle)` in increasing index
+/// order.
+///
+/// Vertexes starting a zero length segment are skipped, so a run of
+/// coincident vertexes gives a single angle at its last vertex, measured from
+/// the segment before the run. The first and last vertexes of open polylines
+/// have no angle.
+pub(crate) fn turn_angles(pline: &Polyline<f64>, lengths: &[f64]) -> Vec<(usize, f64)> {
+    lengths
+        .iter()
+        .enumerate()
+        .filter(|(_, &len)| len > 0.0)
+        .filter_map(|(i, _)| Some((i, turn_angle_at(pline, lengths, i)?)))
+        .collect()
+}
+
+/// Turning angle at vertex `i` from the closest non-zero length segment
+/// ending at or before it, None if there is none (e.g. at the start of an open
+/// polyline or on a polyline of zero length).
+fn turn_angle_at(pline: &Polyline<f64>, lengths: &[f64], i: usize) -> Option<f64> {
+    let n = lengths.len();
+    let seg = |j: usize| (pline.at(j), pline.at(pline.next_wrapping_index(j)));
+    let mut j = i;
+    for _ in 0..n {
+        if j == 0 && !pline.is_closed() {
+            return None;
+        }
+        j = pline.prev_wrapping_index(j);
+        if lengths[j] > 0.0 {
+            let (v0, _) = seg(j);
+            let (v1, v2) = seg(i);
+            return Some(seg_turn_angle(v0, v1, v2));
+        }
+    }
+    None
+}
+
+/// Find the corners of the polyline sharper than `angle_threshold`.
+///
+/// The turning angle at a vertex is the angle between the direction of travel
+/// at the end of the incoming segment and at the start of the outgoing one,
+/// taken from the exact tangents of lines and arcs, so tangent arcs give no
+/// corner. It is positive for left turns and in `[-pi, pi]`, a vertex is a
+/// sharp corner if the absolute turning angle is greater than
+/// `angle_threshold`. Zero length segments are skipped: the corner is reported
+/// at the last of a run of coincident vertexes, with the incoming direction
+/// taken from the segment before the run. The first and last vertexes of open
+/// polylines are never corners.
+///
+/// `result_indexes` receives the vertex indexes of the corners in increasing
+/// order as a `u32` [cavc_buffer] and `result_angles` the signed turning
+/// angles of the same corners as an `f64` [cavc_buffer], both must be freed
+/// with [crate::buffer::cavc_buffer_f]. They are only written to if the
+/// function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline`, `result_indexes` or `result_angles` is null.
+/// * 2 = `angle_threshold` is negative or not finite.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_find_sharp_corners(
+    pline: *const cavc_pline,
+    angle_threshold: f64,
+    result_indexes: *mut *mut cavc_buffer,
+    result_angles: *mut *mut cavc_buffer,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || result_indexes.is_null() || result_angles.is_null() {
+            return 1;
+        }
+        if !angle_threshold.is_finite() || angle_threshold < 0.0 {
+            return 2;
+        }
+        let pline = &(*pline).0;
+        let lengths = seg_lengths(pline);
+        let mut indexes: Vec<u32> = Vec::new();
+        let mut angles: Vec<f64> = Vec::new();
+        for (i, angle) in turn_angles(pline, &lengths) {
+            if angle.abs() > angle_threshold {
+                indexes.push(i as u32);
+                angles.push(angle);
+            }
+        }
+        *result_indexes = into_raw(cavc_buffer::from(indexes));
+        *result_angles = into_raw(cavc_buffer::from(angles));
+        0
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+    use std::ptr::{null, null_mut};
+
+    use super::*;
+    use crate::handle::cavc_pline_release;
+    use crate::test_util::{cw, pline, square};
+
+    #[test]
+    fn fillets_are_tangent_arcs() {
+        let (filleted, count) = cut_corners(&square(10.0), None, CornerCut::Fillet(1.0));
+        assert_eq!(count, 4);
+        assert_eq!(filleted.vertex_count(), 8);
+        assert_eq!(filleted.at(0).pos(), Vector2::new(0.0, 1.0));
+        assert_eq!(filleted.at(1).pos(), Vector2::new(1.0, 0.0));
+        // quarter circle arcs turning left on a counter clockwise square
+        for (i, v) in filleted.iter_vertexes().enumerate() {
+            let bulge = if i % 2 == 0 { (PI / 8.0).tan() } else { 0.0 };
+            assert!((v.bulge - bulge).abs() < 1e-12, "{}: {}", i, v.bulge);
+        }
+        assert!((filleted.area() - (96.0 + PI)).abs() < 1e-9);
+
+        let (filleted, _) = cut_corners(&cw(square(10.0)), None, CornerCut::Fillet(1.0));
+        assert!((filleted.area() + 96.0 + PI).abs() < 1e-9);
+    }
+
+    #[test]
+    fn chamfers_cut_triangles() {
+        let (chamfered, count) = cut_corners(&square(10.0), None, CornerCut::Chamfer(2.0, 3.0));
+        assert_eq!(count, 4);
+        assert_eq!(chamfered.vertex_count(), 8);
+        assert!(chamfered.iter_vertexes().all(|v| v.bulge_is_zero()));
+        assert!((chamfered.area() - 88.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn selects_corners() {
+        let (filleted, count) = cut_corners(&square(10.0), Some(&[1]), CornerCut::Fillet(1.0));
+        assert_eq!(count, 1);
+        assert_eq!(filleted.vertex_count(), 5);
+        assert_eq!(filleted.at(0).pos(), Vector2::new(0.0, 0.0));
+        // the ends of open polylines are not corners
+        let open = pline(
+            &[(0.0, 0.0, 0.0), (10.0, 0.0, 0.0), (10.0, 10.0, 0.0)],
+            false,
+        );
+        let (filleted, count) = cut_corners(&open, Some(&[0, 1, 2]), CornerCut::Fillet(1.0));
+        assert_eq!(count, 1);
+        assert_eq!(filleted.vertex_count(), 4);
+    }
+
+    #[test]
+    fn skips_cuts_that_do_not_fit() {
+        // every other corner is left with too little of its segments
+        let (filleted, count) = cut_corners(&square(10.0), None, CornerCut::Fillet(6.0));
+        assert_eq!(count, 2);
+        assert_eq!(filleted.vertex_count(), 6);
+        let (_, count) = cut_corners(&square(10.0), None, CornerCut::Fillet(11.0));
+        assert_eq!(count, 0);
+    }
+
+    #[test]
+    fn round_corners_meet_on_short_segments() {
+        let (rounded, count) = cut_corners(&square(10.0), None, CornerCut::FilletUpTo(100.0));
+        assert_eq!(count, 4);
+        // the arcs meet at the middle of the sides and form a circle
+        assert_eq!(rounded.vertex_count(), 4);
+        assert!(rounded
+            .iter_vertexes()
+            .all(|v| (v.bulge - (PI / 8.0).tan()).abs() < 1e-12));
+        assert!((rounded.area() - 25.0 * PI).abs() < 1e-9);
+    }
+
+    #[test]
+    fn degenerate_input() {
+        let (result, count) = cut_corners(&Polyline::new(), None, CornerCut::Fillet(1.0));
+        assert_eq!((result.vertex_count(), count), (0, 0));
+        let (result, count) = cut_corners(
+            &pline(&[(1.0, 1.0, 0.0)], true),
+            None,
+            CornerCut::Fillet(1.0),
+        );
+        assert_eq!((result.vertex_count(), count), (1, 0));
+        // corners at a zero length segment are left unchanged
+        let points = [
+            (0.0, 0.0, 0.0),
+            (10.0, 0.0, 0.0),
+            (10.0, 0.0, 0.0),
+            (10.0, 10.0, 0.0),
+            (0.0, 10.0, 0.0),
+        ];
+        let (result, count) = cut_corners(&pline(&points, true), None, CornerCut::Fillet(1.0));
+        assert_eq!((result.vertex_count(), count), (7, 3));
+        // straight corners are not cut
+        let points = [(0.0, 0.0, 0.0), (5.0, 0.0, 0.0), (10.0, 0.0, 0.0)];
+        let (_, count) = cut_corners(&pline(&points, false), None, CornerCut::Fillet(1.0));
+        assert_eq!(count, 0);
+        // cut points of tiny fillets merge with the corner
+        let (result, count) = cut_corners(&square(10.0), None, CornerCut::Fillet(1e-12));
+        assert_eq!((result.vertex_count(), count), (4, 4));
+        assert!(result.iter_vertexes().all(|v| v.bulge_is_zero()));
+    }
+
+    #[test]
+    fn rejects_invalid_parameters() {
+        let input = cavc_pline(square(10.0));
+        let mut result: *mut cavc_pline = null_mut();
+        unsafe {
+            for radius in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+                assert_eq!(
+                    cavc_pline_fillet(&input, radius, null(), 0, null_mut(), &mut result),
+                    3
+                );
+                assert_eq!(
+                    cavc_pline_round_corners(&input, radius, null_mut(), &mut result),
+                    2
+                );
+                assert_eq!(
+                    cavc_pline_chamfer(&input, 1.0, radius, null(), 0, null_mut(), &mut result),
+                    3
+                );
+            }
+            let out_of_bounds = [4u32];
+            let code = cavc_pline_fillet(
+                &input,
+                1.0,
+                out_of_bounds.as_ptr(),
+                1,
+                null_mut(),
+                &mut result,
+            );
+            assert_eq!(code, 2);
+            assert_eq!(
+                cavc_pline_fillet(null(), 1.0, null(), 0, null_mut(), &mut result),
+                1
+            );
+            assert!(result.is_null());
+
+            let mut count = 0;
+            assert_eq!(
+                cavc_pline_chamfer(&input, 1.0, 1.0, null(), 0, &mut count, &mut result),
+                0
+            );
+            assert_eq!(count, 4);
+            assert_eq!((*result).0.vertex_count(), 8);
+            cavc_pline_release(result);
+        }
+    }
+}
@@ -0,0 +1,229 @@
+//! Runtime-enabled registry of live handles for tracking down leaks.
+//!
+//! Every handle created by a function of this library is registered
+//! automatically and unregistered by the matching free function of this
+//! library (for polylines and polyline lists [crate::handle::cavc_pline_release]
+//! and [crate::handle::cavc_plinelist_release]). The functions of
+//! `cavalier_contours_ffi` cannot report to this library, so handles they
+//! create are registered by the caller right after receiving them, and
+//! handles freed with their free functions (e.g. `cavc_pline_f`) are
+//! unregistered by the caller right before freeing them. Doing so where a
+//! wrapper takes ownership of handles also catches double frees
+//! (unregistering a handle that is not registered). Tracking is disabled by
+//! default and the registration functions do nothing until it is enabled.
+use std::collections::BTreeMap;
+use std::os::raw::c_void;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+/// Handle kinds of the registry.
+pub(crate) const KIND_PLINE: u32 = 0;
+pub(crate) const KIND_PLINELIST: u32 = 1;
+pub(crate) const KIND_RESULT: u32 = 2;
+
+/// Registered handle with the sequence number of its registration.
+#[derive(Debug, Copy, Clone)]
+struct Entry {
+    kind: u32,
+    sequence: u64,
+}
+
+#[derive(Debug)]
+struct Registry {
+    handles: BTreeMap<usize, Entry>,
+    next_sequence: u64,
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+static REGISTRY: Mutex<Registry> = Mutex::new(Registry {
+    handles: BTreeMap::new(),
+    next_sequence: 0,
+});
+
+fn registry() -> std::sync::MutexGuard<'static, Registry> {
+    // the registry is consistent after every operation so poisoning can be
+    // ignored
+    REGISTRY.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Register a handle created by this library, if tracking is enabled.
+///
+/// A stale entry at the same address (a handle freed without being
+/// unregistered) is replaced.
+pub(crate) fn track_created<T>(kind: u32, handle: *const T) {
+    if !ENABLED.load(Ordering::SeqCst) {
+        return;
+    }
+    let mut registry = registry();
+    let sequence = registry.next_sequence;
+    registry.next_sequence += 1;
+    registry
+        .handles
+        .insert(handle as usize, Entry { kind, sequence });
+}
+
+/// Unregister a handle about to be freed by this library, if tracking is
+/// enabled.
+pub(crate) fn track_freed<T>(handle: *const T) {
+    if ENABLED.load(Ordering::SeqCst) {
+        registry().handles.remove(&(handle as usize));
+    }
+}
+
+/// Number of live handles of every kind in the registry.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default)]
+pub struct cavc_debug_handle_counts {
+    pub plines: u64,
+    pub plinelists: u64,
+    /// All other handles (results, buffers, shapes, ...).
+    pub results: u64,
+}
+
+/// Callback invoked by [cavc_debug_dump_handles] with the kind of a handle
+/// (0 = polyline, 1 = polyline list, 2 = other), its address, the sequence
+/// number of its registration (counting from 0 since tracking was enabled)
+/// and the user data.
+pub type cavc_debug_handle_visitor_fn =
+    Option<extern "C" fn(kind: u32, handle: *const c_void, sequence: u64, user_data: *mut c_void)>;
+
+/// Enable (`enabled` not 0) or disable tracking of live handles.
+///
+/// Disabling clears the registry, enabling starts with an empty registry.
+/// Tracking is meant for debugging, every registration takes a global lock.
+///
+/// ## Specific Error Codes
+/// None, always returns 0 unless a panic is caught.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_debug_set_handle_tracking(enabled: u8) -> i32 {
+    ffi_catch_unwind!({
+        let mut registry = registry();
+        registry.handles.clear();
+        registry.next_sequence = 0;
+        ENABLED.store(enabled != 0, Ordering::SeqCst);
+        0
+    })
+}
+
+/// Register a live handle of `kind` (0 = polyline, 1 = polyline list, 2 =
+/// any other handle) created by `cavalier_contours_ffi`, handles created by
+/// this library are registered automatically. Does nothing if tracking is
+/// disabled.
+///
+/// ## Specific Error Codes
+/// * 1 = `handle` is null.
+/// * 2 = `kind` is not 0, 1 or 2.
+/// * 3 = `handle` is already registered, it was registered twice, created by
+///   this library or freed without being unregistered.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_debug_track_handle(kind: u32, handle: *const c_void) -> i32 {
+    ffi_catch_unwind!({
+        if handle.is_null() {
+            return 1;
+        }
+        if !matches!(kind, KIND_PLINE | KIND_PLINELIST | KIND_RESULT) {
+            return 2;
+        }
+        if !ENABLED.load(Ordering::SeqCst) {
+            return 0;
+        }
+        let mut registry = registry();
+        let sequence = registry.next_sequence;
+        if registry.handles.contains_key(&(handle as usize)) {
+            return 3;
+        }
+        registry.next_sequence += 1;
+        registry
+            .handles
+            .insert(handle as usize, Entry { kind, sequence });
+        0
+    })
+}
+
+/// Unregister a handle about to be freed by a free function of
+/// `cavalier_contours_ffi`, the free functions of this library unregister the
+/// handles they free. Does nothing if tracking is disabled.
+///
+/// ## Specific Error Codes
+/// * 1 = `handle` is null.
+/// * 2 = `handle` is not registered, it was freed already or never registered.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_debug_untrack_handle(handle: *const c_void) -> i32 {
+    ffi_catch_unwind!({
+        if handle.is_null() {
+            return 1;
+        }
+        if !ENABLED.load(Ordering::SeqCst) {
+            return 0;
+        }
+        match registry().handles.remove(&(handle as usize)) {
+            Some(_) => 0,
+            None => 2,
+        }
+    })
+}
+
+/// Get the number of registered live handles of every kind.
+///
+/// All counts are 0 while tracking is disabled. `counts` is only written to if
+/// the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `counts` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_debug_live_handle_counts(
+    counts: *mut cavc_debug_handle_counts,
+) -> i32 {
+    ffi_catch_unwind!({
+        if counts.is_null() {
+            return 1;
+        }
+        let mut result = cavc_debug_handle_counts::default();
+        for entry in registry().handles.values() {
+            match entry.kind {
+                KIND_PLINE => result.plines += 1,
+                KIND_PLINELIST => result.plinelists += 1,
+                _ => result.results += 1,
+            }
+        }
+        *counts = result;
+        0
+    })
+}
+
+/// Invoke `visitor` for every registered live handle in the order they were
+/// registered, e.g. to log the handles still alive at shutdown.
+///
+/// The registry is copied before the first call, so the visitor may call the
+/// other debug functions.
+///
+/// ## Specific Error Codes
+/// * 1 = `visitor` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_debug_dump_handles(
+    visitor: cavc_debug_handle_visitor_fn,
+    user_data: *mut c_void,
+) -> i32 {
+    ffi_catch_unwind!({
+        let visitor = match visitor {
+            Some(visitor) => visitor,
+            None => return 1,
+        };
+        let mut handles: Vec<(usize, Entry)> = registry()
+            .handles
+            .iter()
+            .map(|(&address, &entry)| (address, entry))
+            .collect();
+        handles.sort_by_key(|(_, entry)| entry.sequence);
+        for (address, entry) in handles {
+            visitor(
+                entry.kind,
+                address as *const c_void,
+                entry.sequence,
+                user_data,
+            );
+        }
+        0
+    })
+}
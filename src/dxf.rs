@@ -0,0 +1,429 @@
+//! DXF interchange of polylines.
+//!
+//! DXF LWPOLYLINE entities store the same vertex and bulge representation as
+//! cavalier_contours, so polylines are exchanged without any approximation.
+use std::fmt::Write;
+use std::os::raw::c_char;
+
+use cavalier_contours::polyline::{
+    PlineCreation, PlineSource, PlineSourceMut, PlineVertex, Polyline,
+};
+use cavalier_contours_ffi::cavc_plinelist;
+
+use crate::error::set_last_error;
+use crate::json::write_number;
+use crate::util::{plinelist_into_raw, write_c_string};
+
+/// Append a group code and its value line to `out`.
+fn write_group(out: &mut String, code: i32, value: &str) {
+    writeln!(out, "{}\n{}", code, value).unwrap();
+}
+
+/// Append a group code with a floating point value, returns false if the value
+/// is not finite.
+fn write_float_group(out: &mut String, code: i32, value: f64) -> bool {
+    writeln!(out, "{}", code).unwrap();
+    let ok = write_number(out, value);
+    out.push('\n');
+    ok
+}
+
+/// Append `pline` as an LWPOLYLINE entity on layer 0 to `out`.
+///
+/// Returns false if any vertex holds a non-finite value.
+pub(crate) fn write_lwpolyline(out: &mut String, pline: &Polyline<f64>) -> bool {
+    write_group(out, 0, "LWPOLYLINE");
+    write_group(out, 100, "AcDbEntity");
+    write_group(out, 8, "0");
+    write_group(out, 100, "AcDbPolyline");
+    write_group(out, 90, &pline.vertex_count().to_string());
+    write_group(out, 70, if pline.is_closed() { "1" } else { "0" });
+    for v in pline.iter_vertexes() {
+        if !write_float_group(out, 10, v.x) || !write_float_group(out, 20, v.y) {
+            return false;
+        }
+        if !v.bulge_is_zero() && !write_float_group(out, 42, v.bulge) {
+            return false;
+        }
+    }
+    true
+}
+
+/// Write the polylines of the list as a minimal DXF file of LWPOLYLINE
+/// entities.
+///
+/// The file holds only a header declaring the DXF version (R2000, the first
+/// version with LWPOLYLINE) and the entities section, all entities are on
+/// layer 0. This is accepted by common CAD packages and DXF libraries.
+///
+/// The nul terminated DXF text is written to `out` which has room for
+/// `out_cap` bytes. `out_len` receives the length of the text (not counting
+/// the nul terminator) even when `out` is too small, so the caller can
+/// allocate a large enough buffer and call again. `out` may be null if
+/// `out_cap` is 0.
+///
+/// ## Specific Error Codes
+/// * 1 = `plinelist` or `out_len` is null.
+/// * 2 = `out_cap` is too small to hold the text and its nul terminator.
+/// * 3 = a polyline contains a NaN or infinite value.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_plinelist_to_dxf(
+    plinelist: *const cavc_plinelist,
+    out: *mut c_char,
+    out_cap: u32,
+    out_len: *mut u32,
+) -> i32 {
+    ffi_catch_unwind!({
+        if plinelist.is_null() || out_len.is_null() {
+            return 1;
+        }
+        let mut dxf = String::new();
+        write_group(&mut dxf, 0, "SECTION");
+        write_group(&mut dxf, 2, "HEADER");
+        write_group(&mut dxf, 9, "$ACADVER");
+        write_group(&mut dxf, 1, "AC1015");
+        write_group(&mut dxf, 0, "ENDSEC");
+        write_group(&mut dxf, 0, "SECTION");
+        write_group(&mut dxf, 2, "ENTITIES");
+        for &pline in (*plinelist).0.iter() {
+            if !write_lwpolyline(&mut dxf, &(*pline).0) {
+                return 3;
+            }
+        }
+        write_group(&mut dxf, 0, "ENDSEC");
+        write_group(&mut dxf, 0, "EOF");
+        *out_len = dxf.len() as u32;
+        if !write_c_string(&dxf, out, out_cap as usize) {
+            return 2;
+        }
+        0
+    })
+}
+
+/// Error returned by [parse_dxf] for input that is not ASCII DXF.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct DxfError;
+
+/// Entity of the ENTITIES section with its group codes and values.
+struct Entity<'a> {
+    kind: &'a str,
+    groups: Vec<(i32, &'a str)>,
+}
+
+impl Entity<'_> {
+    fn float(&self, code: i32) -> Result<Option<f64>, DxfError> {
+        match self.groups.iter().find(|(c, _)| *c == code) {
+            Some((_, value)) => value.parse().map(Some).map_err(|_| DxfError),
+            None => Ok(None),
+        }
+    }
+
+    fn float_or(&self, code: i32, default: f64) -> Result<f64, DxfError> {
+        Ok(self.float(code)?.unwrap_or(default))
+    }
+
+    fn flags(&self) -> Result<i32, DxfError> {
+        match self.groups.iter().find(|(c, _)| *c == 70) {
+            Some((_, value)) => value.parse().map_err(|_| DxfError),
+            None => Ok(0),
+        }
+    }
+
+    /// Polylines with a negative z extrusion direction are mirrored in x
+    /// (following the DXF arbitrary axis algorithm) which flips arc
+    /// directions.
+    fn to_world(&self, mut pline: Polyline<f64>) -> Result<Polyline<f64>, DxfError> {
+        if self.float_or(230, 1.0)? < 0.0 {
+            for i in 0..pline.vertex_count() {
+                let v = pline.at(i);
+                pline.set_vertex(i, PlineVertex::new(-v.x, v.y, -v.bulge));
+            }
+        }
+        Ok(pline)
+    }
+}
+
+/// Split ASCII DXF text into the entities of its ENTITIES section.
+fn read_entities(text: &str) -> Result<Vec<Entity<'_>>, DxfError> {
+    let mut lines = text.lines();
+    let mut groups = Vec::new();
+    while let Some(code) = lines.next() {
+        if code.trim().is_empty() {
+            continue;
+        }
+        let code: i32 = code.trim().parse().map_err(|_| DxfError)?;
+        let value = lines.next().ok_or(DxfError)?.trim();
+        groups.push((code, value));
+    }
+
+    let mut entities = Vec::new();
+    let mut in_entities = false;
+    let mut i = 0;
+    while i < groups.len() {
+        let (code, value) = groups[i];
+        i += 1;
+        if code != 0 {
+            continue;
+        }
+        match value {
+            "SECTION" => {
+                in_entities = groups.get(i) == Some(&(2, "ENTITIES"));
+            }
+            "ENDSEC" => in_entities = false,
+            "EOF" => break,
+            kind if in_entities => {
+                let start = i;
+                while i < groups.len() && groups[i].0 != 0 {
+                    i += 1;
+                }
+                entities.push(Entity {
+                    kind,
+                    groups: groups[start..i].to_vec(),
+                });
+            }
+            _ => {}
+        }
+    }
+    Ok(entities)
+}
+
+fn read_lwpolyline(entity: &Entity) -> Result<Polyline<f64>, DxfError> {
+    let parse = |value: &str| value.parse::<f64>().map_err(|_| DxfError);
+    let mut pline = Polyline::new();
+    pline.set_is_closed(entity.flags()? & 1 != 0);
+    // each vertex starts with its x coordinate, the y coordinate and the
+    // optional bulge follow
+    let mut vertex: Option<PlineVertex<f64>> = None;
+    for &(code, value) in &entity.groups {
+        match code {
+            10 => {
+                if let Some(v) = vertex.take() {
+                    pline.add_vertex(v);
+                }
+                vertex = Some(PlineVertex::new(parse(value)?, 0.0, 0.0));
+            }
+            20 => vertex.as_mut().ok_or(DxfError)?.y = parse(value)?,
+            42 => vertex.as_mut().ok_or(DxfError)?.bulge = parse(value)?,
+            _ => {}
+        }
+    }
+    if let Some(v) = vertex {
+        pline.add_vertex(v);
+    }
+    entity.to_world(pline)
+}
+
+fn read_circle(entity: &Entity) -> Result<Polyline<f64>, DxfError> {
+    let cx = entity.float_or(10, 0.0)?;
+    let cy = entity.float_or(20, 0.0)?;
+    let r = entity.float_or(40, 0.0)?;
+    let mut pline = Polyline::with_capacity(2, true);
+    pline.add(cx - r, cy, 1.0);
+    pline.add(cx + r, cy, 1.0);
+    entity.to_world(pline)
+}
+
+fn read_arc(entity: &Entity) -> Result<Polyline<f64>, DxfError> {
+    let cx = entity.float_or(10, 0.0)?;
+    let cy = entity.float_or(20, 0.0)?;
+    let r = entity.float_or(40, 0.0)?;
+    let start = entity.float_or(50, 0.0)?;
+    let end = entity.float_or(51, 360.0)?;
+    // arcs run counter clockwise from the start to the end angle
+    let mut sweep = (end - start).rem_euclid(360.0);
+    if sweep == 0.0 {
+        sweep = 360.0;
+    }
+    // split in two so the bulge stays bounded for sweeps close to a full circle
+    let count = if sweep > 180.0 { 2 } else { 1 };
+    let step = sweep / count as f64;
+    let bulge = (step.to_radians() / 4.0).tan();
+    let mut pline = Polyline::with_capacity(count + 1, false);
+    for k in 0..=count {
+        let angle = (start + step * k as f64).to_radians();
+        let b = if k < count { bulge } else { 0.0 };
+        pline.add(cx + r * angle.cos(), cy + r * angle.sin(), b);
+    }
+    entity.to_world(pline)
+}
+
+/// Read the 2D polylines, circles and arcs of ASCII DXF text.
+///
+/// 3D polylines and polygon meshes are skipped, as are all other entity
+/// types.
+pub(crate) fn parse_dxf(text: &str) -> Result<Vec<Polyline<f64>>, DxfError> {
+    let entities = read_entities(text)?;
+    let mut plines = Vec::new();
+    let mut i = 0;
+    while i < entities.len() {
+        let entity = &entities[i];
+        i += 1;
+        match entity.kind {
+            "LWPOLYLINE" => plines.push(read_lwpolyline(entity)?),
+            "CIRCLE" => plines.push(read_circle(entity)?),
+            "ARC" => plines.push(read_arc(entity)?),
+            "POLYLINE" => {
+                let flags = entity.flags()?;
+                let mut pline = Polyline::new();
+                pline.set_is_closed(flags & 1 != 0);
+                while i < entities.len() && entities[i].kind == "VERTEX" {
+                    let vertex = &entities[i];
+                    i += 1;
+                    // spline frame control points are not part of the path
+                    if vertex.flags()? & 16 == 0 {
+                        pline.add(
+                            vertex.float_or(10, 0.0)?,
+                            vertex.float_or(20, 0.0)?,
+                            vertex.float_or(42, 0.0)?,
+                        );
+                    }
+                }
+                if i < entities.len() && entities[i].kind == "SEQEND" {
+                    i += 1;
+                }
+                // 3D polylines and polygon meshes
+                if flags & (8 | 16 | 64) == 0 {
+                    plines.push(entity.to_world(pline)?);
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(plines)
+}
+
+/// Read polylines from the `len` bytes of an ASCII DXF file in `buffer`.
+///
+/// LWPOLYLINE and 2D POLYLINE entities of the ENTITIES section become
+/// polylines with their bulges, CIRCLE entities become closed counter
+/// clockwise polylines of two half circle arcs and ARC entities become open
+/// polylines of one or two arc segments. Entities with a negative z extrusion
+/// direction are mirrored into world coordinates. Elevations, widths and all
+/// other entity types are ignored. Binary DXF is not supported.
+///
+/// `result` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `result` is null or `buffer` is null while `len` is not 0.
+/// * 2 = the buffer is not valid ASCII DXF.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_plinelist_from_dxf(
+    buffer: *const u8,
+    len: u32,
+    result: *mut *mut cavc_plinelist,
+) -> i32 {
+    ffi_catch_unwind!({
+        if result.is_null() || (buffer.is_null() && len != 0) {
+            return 1;
+        }
+        let bytes: &[u8] = if len == 0 {
+            &[]
+        } else {
+            std::slice::from_raw_parts(buffer, len as usize)
+        };
+        // DXF before R2007 uses the code page of the drawing, only the
+        // structure (which is ASCII) matters here
+        let text = String::from_utf8_lossy(bytes);
+        match parse_dxf(&text) {
+            Ok(plines) => {
+                *result = plinelist_into_raw(plines);
+                0
+            }
+            Err(DxfError) => {
+                set_last_error("invalid or binary DXF");
+                2
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// DXF text with `body` as its ENTITIES section.
+    fn dxf(body: &str) -> String {
+        format!("0\nSECTION\n2\nENTITIES\n{}0\nENDSEC\n0\nEOF\n", body)
+    }
+
+    #[test]
+    fn round_trips_lwpolylines() {
+        let mut pline = Polyline::new_closed();
+        pline.add(0.0, 0.0, 0.25);
+        pline.add(10.5, -3.0, 0.0);
+        pline.add(1e-7, 12.0, -2.0);
+        let mut body = String::new();
+        assert!(write_lwpolyline(&mut body, &pline));
+        let plines = parse_dxf(&dxf(&body)).unwrap();
+        assert_eq!(plines.len(), 1);
+        assert!(plines[0].is_closed());
+        let vertexes: Vec<_> = plines[0].iter_vertexes().collect();
+        let expected: Vec<_> = pline.iter_vertexes().collect();
+        assert_eq!(vertexes, expected);
+    }
+
+    #[test]
+    fn reads_circles_and_arcs() {
+        let body = "0\nCIRCLE\n10\n1\n20\n2\n40\n3\n0\nARC\n10\n0\n20\n0\n40\n1\n50\n0\n51\n270\n";
+        let plines = parse_dxf(&dxf(body)).unwrap();
+        assert_eq!(plines.len(), 2);
+        let circle = &plines[0];
+        assert!(circle.is_closed());
+        assert_eq!(circle.at(0), PlineVertex::new(-2.0, 2.0, 1.0));
+        assert_eq!(circle.at(1), PlineVertex::new(4.0, 2.0, 1.0));
+        // arcs over 180 degrees are split in two
+        let arc = &plines[1];
+        assert!(!arc.is_closed());
+        assert_eq!(arc.vertex_count(), 3);
+        assert!((arc.at(0).bulge - (135f64.to_radians() / 4.0).tan()).abs() < 1e-12);
+        let end = arc.at(2);
+        assert!(end.x.abs() < 1e-12 && (end.y + 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn reads_polyline_vertexes() {
+        let body = "0\nPOLYLINE\n70\n1\n0\nVERTEX\n10\n0\n20\n0\n42\n0.5\n\
+                    0\nVERTEX\n70\n16\n10\n5\n20\n5\n0\nVERTEX\n10\n1\n20\n0\n0\nSEQEND\n\
+                    0\nPOLYLINE\n70\n8\n0\nVERTEX\n10\n0\n20\n0\n0\nSEQEND\n";
+        let plines = parse_dxf(&dxf(body)).unwrap();
+        // the spline frame vertex and the 3D polyline are skipped
+        assert_eq!(plines.len(), 1);
+        assert!(plines[0].is_closed());
+        assert_eq!(plines[0].vertex_count(), 2);
+        assert_eq!(plines[0].at(0), PlineVertex::new(0.0, 0.0, 0.5));
+    }
+
+    #[test]
+    fn mirrors_negative_extrusion() {
+        let body = "0\nLWPOLYLINE\n90\n2\n70\n0\n10\n1\n20\n2\n42\n0.5\n10\n3\n20\n4\n230\n-1\n";
+        let plines = parse_dxf(&dxf(body)).unwrap();
+        assert_eq!(plines[0].at(0), PlineVertex::new(-1.0, 2.0, -0.5));
+        assert_eq!(plines[0].at(1), PlineVertex::new(-3.0, 4.0, 0.0));
+    }
+
+    #[test]
+    fn degenerate_input() {
+        assert_eq!(parse_dxf("").unwrap().len(), 0);
+        assert_eq!(parse_dxf(&dxf("")).unwrap().len(), 0);
+        // entities outside of the ENTITIES section are ignored
+        let blocks = "0\nSECTION\n2\nBLOCKS\n0\nCIRCLE\n40\n1\n0\nENDSEC\n0\nEOF\n";
+        assert_eq!(parse_dxf(blocks).unwrap().len(), 0);
+        // zero radius circles and vertexless polylines are still returned
+        let plines = parse_dxf(&dxf("0\nCIRCLE\n40\n0\n0\nLWPOLYLINE\n90\n0\n")).unwrap();
+        assert_eq!(plines.len(), 2);
+        assert_eq!(plines[1].vertex_count(), 0);
+    }
+
+    #[test]
+    fn rejects_malformed_text() {
+        for text in [
+            "0",
+            "x\nSECTION\n",
+            dxf("0\nCIRCLE\n40\nabc\n").as_str(),
+            dxf("0\nLWPOLYLINE\n20\n1\n").as_str(),
+            dxf("0\nPOLYLINE\n70\nclosed\n").as_str(),
+        ] {
+            assert!(parse_dxf(text).is_err(), "{:?}", text);
+        }
+    }
+}
@@ -0,0 +1,816 @@
+//! CAD style editing of polylines.
+use cavalier_contours::core::math::Vector2;
+use cavalier_contours::polyline::{
+    seg_arc_radius_and_center, seg_intersect, FindIntersectsOptions, PlineCreation, PlineSource,
+    PlineSourceMut, PlineVertex, Polyline,
+};
+use cavalier_contours_ffi::{cavc_pline, cavc_plinelist};
+
+use crate::cache::evict;
+use crate::geom::{intr_points, seg_param_at_point, seg_sub, seg_sweep};
+use crate::path::{path_length_at_point, seg_lengths, sub_path};
+use crate::util::{pline_into_raw, plinelist_into_raw, valid_eps, SplitMix64};
+
+/// Trim an open polyline at its first intersection with `cutter`.
+///
+/// `result` receives the part from the start of `pline` to the first
+/// intersection along its path if `keep_start` is non-zero, otherwise the
+/// part from the first intersection to the end. Intersections within
+/// `pos_equal_eps` of the start of `pline` are ignored, so a polyline starting
+/// on the cutter is trimmed at the next intersection.
+///
+/// `result` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline`, `cutter` or `result` is null.
+/// * 2 = `pline` is closed or has less than 2 vertexes.
+/// * 3 = `pline` does not intersect `cutter`.
+/// * 4 = `pos_equal_eps` is negative, NaN or infinite.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_trim_to(
+    pline: *const cavc_pline,
+    cutter: *const cavc_pline,
+    keep_start: u8,
+    pos_equal_eps: f64,
+    result: *mut *mut cavc_pline,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || cutter.is_null() || result.is_null() {
+            return 1;
+        }
+        if !valid_eps(pos_equal_eps) {
+            return 4;
+        }
+        let pline = &(*pline).0;
+        if pline.is_closed() || pline.vertex_count() < 2 {
+            return 2;
+        }
+        let mut options = FindIntersectsOptions::new();
+        options.pos_equal_eps = pos_equal_eps;
+        let intersects = pline.find_intersects_opt(&(*cutter).0, &options);
+        let lengths = seg_lengths(pline);
+        let at = |seg_index, point| {
+            path_length_at_point(pline, &lengths, seg_index, point, pos_equal_eps)
+        };
+        let basic = intersects
+            .basic_intersects
+            .iter()
+            .map(|i| at(i.start_index1, i.point));
+        let overlapping = intersects
+            .overlapping_intersects
+            .iter()
+            .flat_map(|i| [at(i.start_index1, i.point1), at(i.start_index1, i.point2)]);
+        let cut = match basic
+            .chain(overlapping)
+            .filter(|&s| s > pos_equal_eps)
+            .min_by(|a, b| a.total_cmp(b))
+        {
+            Some(cut) => cut,
+            None => return 3,
+        };
+        let total: f64 = lengths.iter().sum();
+        let trimmed = if keep_start != 0 {
+            sub_path(pline, 0.0, cut)
+        } else {
+            sub_path(pline, cut, total - cut)
+        };
+        *result = pline_into_raw(trimmed);
+        0
+    })
+}
+
+/// Distance along the continuation of the last segment of `pline` (past its
+/// end) to the closest intersection with `cutter`, measured as a parameter
+/// (line length or arc sweep in radians), with the point.
+fn first_extension_hit(
+    pline: &Polyline<f64>,
+    cutter: &Polyline<f64>,
+    pos_equal_eps: f64,
+) -> Option<(f64, Vector2<f64>)> {
+    let n = pline.vertex_count();
+    let v1 = pline.at(n - 2);
+    let end = pline.at(n - 1);
+    // continuation pieces as segments with the parameter range they cover
+    let mut pieces: Vec<(PlineVertex<f64>, PlineVertex<f64>, f64, f64)> = Vec::new();
+    if v1.bulge_is_zero() {
+        let dir = end.pos() - v1.pos();
+        let len = dir.length();
+        if len == 0.0 {
+            return None;
+        }
+        let extents = cutter.extents()?;
+        // long enough to cross the whole cutter from anywhere
+        let reach = (Vector2::new(extents.min_x, extents.min_y) - end.pos()).length()
+            + (Vector2::new(extents.max_x, extents.max_y) - end.pos()).length();
+        let far = end.pos() + dir.scale(reach / len);
+        pieces.push((
+            PlineVertex::new(end.x, end.y, 0.0),
+            PlineVertex::from_vector2(far, 0.0),
+            0.0,
+            reach,
+        ));
+    } else {
+        // the rest of the circle, in two halves to keep the bulges bounded
+        let sweep = seg_sweep(v1);
+        let rest = std::f64::consts::TAU - sweep.abs();
+        let (radius, center) = seg_arc_radius_and_center(v1, end);
+        let start_angle = (end.y - center.y).atan2(end.x - center.x);
+        let half = 0.5 * rest * sweep.signum();
+        let point = |a: f64| center + Vector2::new(a.cos(), a.sin()).scale(radius);
+        let bulge = (half / 4.0).tan();
+        let mid = point(start_angle + half);
+        let stop = point(start_angle + 2.0 * half);
+        pieces.push((
+            PlineVertex::new(end.x, end.y, bulge),
+            PlineVertex::from_vector2(mid, 0.0),
+            0.0,
+            half.abs(),
+        ));
+        pieces.push((
+            PlineVertex::from_vector2(mid, bulge),
+            PlineVertex::from_vector2(stop, 0.0),
+            half.abs(),
+            half.abs(),
+        ));
+    }
+
+    let mut best: Option<(f64, Vector2<f64>)> = None;
+    for &(p1, p2, offset, range) in &pieces {
+        for (c1, c2) in cutter.iter_segments() {
+            for point in intr_points(seg_intersect(p1, p2, c1, c2, pos_equal_eps)) {
+                if (point - end.pos()).length() <= pos_equal_eps {
+                    continue;
+                }
+                let s = offset + range * seg_param_at_point(p1, p2, point);
+                if !matches!(best, Some((b, _)) if b <= s) {
+                    best = Some((s, point));
+                }
+            }
+        }
+    }
+    best
+}
+
+/// Extend the last segment of an open polyline until it hits `cutter`.
+///
+/// A final line is lengthened along its direction and a final arc is
+/// continued along its circle (for less than a full circle), up to the
+/// closest intersection with `cutter` past the current end. Intersections
+/// within `pos_equal_eps` of the current end are ignored.
+///
+/// `result` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline`, `cutter` or `result` is null.
+/// * 2 = `pline` is closed or has less than 2 vertexes.
+/// * 3 = the extended segment never hits `cutter`.
+/// * 4 = `pos_equal_eps` is negative, NaN or infinite.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_extend_to(
+    pline: *const cavc_pline,
+    cutter: *const cavc_pline,
+    pos_equal_eps: f64,
+    result: *mut *mut cavc_pline,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || cutter.is_null() || result.is_null() {
+            return 1;
+        }
+        if !valid_eps(pos_equal_eps) {
+            return 4;
+        }
+        let pline = &(*pline).0;
+        if pline.is_closed() || pline.vertex_count() < 2 {
+            return 2;
+        }
+        let (extra, point) = match first_extension_hit(pline, &(*cutter).0, pos_equal_eps) {
+            Some(hit) => hit,
+            None => return 3,
+        };
+        let mut extended = pline.clone();
+        let n = extended.vertex_count();
+        let v1 = extended.at(n - 2);
+        if !v1.bulge_is_zero() {
+            let sweep = seg_sweep(v1);
+            let bulge = ((sweep + extra * sweep.signum()) / 4.0).tan();
+            extended.set_vertex(n - 2, v1.with_bulge(bulge));
+        }
+        extended.set_vertex(n - 1, PlineVertex::from_vector2(point, 0.0));
+        *result = pline_into_raw(extended);
+        0
+    })
+}
+
+/// Continue the last segment of the open `pline` past its end by path length
+/// `length`, a line along its direction and an arc along its circle.
+///
+/// Returns false and leaves `pline` unchanged if the last segment has zero
+/// length.
+fn extend_end(pline: &mut Polyline<f64>, length: f64) -> bool {
+    let n = pline.vertex_count();
+    let v1 = pline.at(n - 2);
+    let end = pline.at(n - 1);
+    if (end.pos() - v1.pos()).length() == 0.0 {
+        return false;
+    }
+    if v1.bulge_is_zero() {
+        let dir = (end.pos() - v1.pos()).normalize();
+        pline.set_vertex(
+            n - 1,
+            PlineVertex::from_vector2(end.pos() + dir.scale(length), 0.0),
+        );
+        return true;
+    }
+    let sweep = seg_sweep(v1);
+    let (radius, center) = seg_arc_radius_and_center(v1, end);
+    let end_angle = (end.y - center.y).atan2(end.x - center.x);
+    let extra = length / radius * sweep.signum();
+    let point = |a: f64| center + Vector2::new(a.cos(), a.sin()).scale(radius);
+    if (sweep + extra).abs() <= std::f64::consts::PI {
+        pline.set_vertex(n - 2, v1.with_bulge(((sweep + extra) / 4.0).tan()));
+        pline.set_vertex(
+            n - 1,
+            PlineVertex::from_vector2(point(end_angle + extra), 0.0),
+        );
+        return true;
+    }
+    // longer continuations are added as new arcs of at most half a circle
+    let count = (extra.abs() / std::f64::consts::PI).ceil().max(1.0) as usize;
+    let step = extra / count as f64;
+    pline.set_vertex(n - 1, end.with_bulge((step / 4.0).tan()));
+    for k in 1..=count {
+        let p = point(end_angle + k as f64 * step);
+        let bulge = if k < count { (step / 4.0).tan() } else { 0.0 };
+        pline.add(p.x, p.y, bulge);
+    }
+    true
+}
+
+/// Lengthen or shorten both ends of an open polyline by path length.
+///
+/// A positive `start_delta` or `end_delta` continues the first or last
+/// segment past that end by the given path length, a line along its direction
+/// and an arc along its circle (as new arc segments if it grows past half a
+/// circle, a long continuation may overlap the arc itself). A negative value
+/// trims that much path length off that end, cutting arcs exactly. `pline` is
+/// left unchanged.
+///
+/// `result` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `result` is null.
+/// * 2 = `pline` is closed or has less than 2 vertexes.
+/// * 3 = `start_delta` or `end_delta` is not finite.
+/// * 4 = the trims remove the whole path length of `pline`.
+/// * 5 = an end to lengthen is on a segment of zero length.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_extend_length(
+    pline: *const cavc_pline,
+    start_delta: f64,
+    end_delta: f64,
+    result: *mut *mut cavc_pline,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || result.is_null() {
+            return 1;
+        }
+        let pline = &(*pline).0;
+        if pline.is_closed() || pline.vertex_count() < 2 {
+            return 2;
+        }
+        if !start_delta.is_finite() || !end_delta.is_finite() {
+            return 3;
+        }
+        let start_trim = (-start_delta).max(0.0);
+        let end_trim = (-end_delta).max(0.0);
+        let mut changed = if start_trim > 0.0 || end_trim > 0.0 {
+            let total: f64 = seg_lengths(pline).iter().sum();
+            let length = total - start_trim - end_trim;
+            if length <= 0.0 {
+                return 4;
+            }
+            sub_path(pline, start_trim, length)
+        } else {
+            pline.clone()
+        };
+        if end_delta > 0.0 && !extend_end(&mut changed, end_delta) {
+            return 5;
+        }
+        if start_delta > 0.0 {
+            changed.invert_direction_mut();
+            if !extend_end(&mut changed, start_delta) {
+                return 5;
+            }
+            changed.invert_direction_mut();
+        }
+        *result = pline_into_raw(changed);
+        0
+    })
+}
+
+/// Append the segments of `src` to the end of the open polyline `dest`.
+///
+/// If the end of `dest` coincides with the start of `src` within
+/// `pos_equal_eps` the junction is merged into one vertex, otherwise a line
+/// joins them. A closed `src` is appended including its closing segment, so it
+/// ends back at its first vertex. `src` is left unchanged and may be the same
+/// polyline as `dest`.
+///
+/// ## Specific Error Codes
+/// * 1 = `dest` or `src` is null.
+/// * 2 = `dest` is closed.
+/// * 3 = `pos_equal_eps` is negative, NaN or infinite.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_extend(
+    dest: *mut cavc_pline,
+    src: *const cavc_pline,
+    pos_equal_eps: f64,
+) -> i32 {
+    ffi_catch_unwind!({
+        if dest.is_null() || src.is_null() {
+            return 1;
+        }
+        if !valid_eps(pos_equal_eps) {
+            return 3;
+        }
+        if (*dest).0.is_closed() {
+            return 2;
+        }
+        let src = (*src).0.clone();
+        evict(dest);
+        let dest = &mut (*dest).0;
+        let mut vertexes: Vec<PlineVertex<f64>> = src.iter_vertexes().collect();
+        if src.is_closed() && !vertexes.is_empty() {
+            vertexes.push(vertexes[0].with_bulge(0.0));
+        }
+        let mut skip = 0;
+        if let (Some(first), Some(last)) = (vertexes.first(), dest.vertex_count().checked_sub(1)) {
+            let end = dest.at(last);
+            if end.pos().fuzzy_eq_eps(first.pos(), pos_equal_eps) {
+                dest.set_vertex(last, end.with_bulge(first.bulge));
+                skip = 1;
+            } else {
+                // the bulge of the last vertex of an open polyline is unused
+                // until now, the joining segment is a line
+                dest.set_vertex(last, end.with_bulge(0.0));
+            }
+        }
+        dest.reserve(vertexes.len() - skip);
+        dest.extend_vertexes(vertexes.into_iter().skip(skip));
+        0
+    })
+}
+
+/// Make the closed polyline run in the `desired` direction, 1 = clockwise and
+/// 2 = counter clockwise, inverting it in place if needed.
+///
+/// `flipped` receives 1 if the polyline was inverted and 0 if it already had
+/// the desired orientation, it may be null. Polylines without area keep their
+/// direction.
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` is null.
+/// * 2 = `pline` is open.
+/// * 3 = `desired` is not a valid orientation.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_set_orientation(
+    pline: *mut cavc_pline,
+    desired: i32,
+    flipped: *mut u8,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() {
+            return 1;
+        }
+        evict(pline);
+        let pline = &mut (*pline).0;
+        if !pline.is_closed() {
+            return 2;
+        }
+        let want_ccw = match desired {
+            1 => false,
+            2 => true,
+            _ => return 3,
+        };
+        let area = pline.area();
+        let flip = area != 0.0 && (area > 0.0) != want_ccw;
+        if flip {
+            pline.invert_direction_mut();
+        }
+        if !flipped.is_null() {
+            *flipped = flip as u8;
+        }
+        0
+    })
+}
+
+/// Close an open polyline, handling an end that (almost) meets the start.
+///
+/// `mode` selects how the end is joined to the start:
+/// * 0 = if the last vertex is within `join_eps` of the first it is dropped so
+///   the final segment ends on the first vertex (snapped), otherwise a closing
+///   line segment is added.
+/// * 1 = always snap, failing if the last vertex is further than `join_eps`
+///   from the first.
+/// * 2 = always add a closing line segment, keeping every vertex.
+///
+/// A polyline that is already closed is left unchanged.
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` is null.
+/// * 2 = `mode` is not a valid mode or `join_eps` is negative or NaN.
+/// * 3 = `mode` is 1 and the end is further than `join_eps` from the start.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_close(pline: *mut cavc_pline, join_eps: f64, mode: i32) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() {
+            return 1;
+        }
+        if !(0..=2).contains(&mode) || join_eps.is_nan() || join_eps < 0.0 {
+            return 2;
+        }
+        evict(pline);
+        let pline = &mut (*pline).0;
+        if pline.is_closed() {
+            return 0;
+        }
+        let n = pline.vertex_count();
+        let meets = n > 1 && (pline.at(n - 1).pos() - pline.at(0).pos()).length() <= join_eps;
+        if mode == 1 && n > 1 && !meets {
+            return 3;
+        }
+        if meets && mode != 2 {
+            pline.remove_last();
+        } else if n > 0 {
+            // the closing segment is a line
+            let last = pline.at(n - 1);
+            pline.set_vertex(n - 1, last.with_bulge(0.0));
+        }
+        pline.set_is_closed(true);
+        0
+    })
+}
+
+/// Smallest `max_sweep_radians` of [cavc_pline_subdivide_arcs], bounding the
+/// pieces of an arc (a full circle is split into at most 62832 arcs).
+const MIN_SUBDIVIDE_SWEEP: f64 = 1e-4;
+
+/// Split every arc segment of `pline` sweeping more than `max_sweep` into
+/// equal arcs.
+fn subdivide_arcs(pline: &Polyline<f64>, max_sweep: f64) -> Polyline<f64> {
+    let mut result = Polyline::with_capacity(pline.vertex_count(), pline.is_closed());
+    let n = pline.vertex_count();
+    for i in 0..n {
+        let v1 = pline.at(i);
+        let is_segment = i + 1 < n || pline.is_closed();
+        let count = (seg_sweep(v1).abs() / max_sweep).ceil() as usize;
+        if !is_segment || v1.bulge_is_zero() || count <= 1 {
+            result.add_vertex(v1);
+            continue;
+        }
+        let v2 = pline.at(pline.next_wrapping_index(i));
+        for k in 0..count {
+            let (start, _) = seg_sub(
+                v1,
+                v2,
+                k as f64 / count as f64,
+                (k + 1) as f64 / count as f64,
+            );
+            result.add_vertex(start);
+        }
+    }
+    result
+}
+
+/// Split every arc segment sweeping more than `max_sweep_radians` into equal
+/// arc segments, each sweeping at most `max_sweep_radians`.
+///
+/// The arcs are split exactly, every piece lies on the circle of the original
+/// arc, unlike with `arcs_to_approx_lines`. E.g. a `max_sweep_radians`
+/// of pi / 2 limits all arcs to quarter circles as required by some machine
+/// controllers. Line segments and arcs within the limit are kept as they are.
+/// `pline` is left unchanged.
+///
+/// `result` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `result` is null.
+/// * 2 = `max_sweep_radians` is not greater than 0 or not finite.
+/// * 3 = `max_sweep_radians` is less than 1e-4, the arcs would be split into
+///   too many pieces.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_subdivide_arcs(
+    pline: *const cavc_pline,
+    max_sweep_radians: f64,
+    result: *mut *mut cavc_pline,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || result.is_null() {
+            return 1;
+        }
+        if !max_sweep_radians.is_finite() || max_sweep_radians <= 0.0 {
+            return 2;
+        }
+        if max_sweep_radians < MIN_SUBDIVIDE_SWEEP {
+            return 3;
+        }
+        *result = pline_into_raw(subdivide_arcs(&(*pline).0, max_sweep_radians));
+        0
+    })
+}
+
+/// Explode the polyline into its segments, each as an open polyline of two
+/// vertexes (the first holding the bulge of the segment).
+///
+/// The segments are returned in path order, including the closing segment of
+/// closed polylines, e.g. for formats storing every line and arc as its own
+/// entity. A polyline with less than 2 vertexes gives an empty list. `pline`
+/// is left unchanged.
+///
+/// `result` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `result` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_explode(
+    pline: *const cavc_pline,
+    result: *mut *mut cavc_plinelist,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || result.is_null() {
+            return 1;
+        }
+        let segments = (*pline)
+            .0
+            .iter_segments()
+            .map(|(v1, v2)| {
+                let mut segment = Polyline::with_capacity(2, false);
+                segment.add_vertex(v1);
+                segment.add_vertex(v2.with_bulge(0.0));
+                segment
+            })
+            .collect();
+        *result = plinelist_into_raw(segments);
+        0
+    })
+}
+
+/// Midpoint of the arc segment, from the chord and the sagitta
+/// (`bulge * chord / 2`) without going through angles.
+fn arc_midpoint(v1: PlineVertex<f64>, v2: PlineVertex<f64>) -> Vector2<f64> {
+    let chord = v2.pos() - v1.pos();
+    (v1.pos() + v2.pos()).scale(0.5) + Vector2::new(chord.y, -chord.x).scale(0.5 * v1.bulge)
+}
+
+/// Bulge of the arc from `start` to `end` passing through `mid`, measured
+/// from the sagitta of `mid` over the chord. Falls back to `bulge` if the
+/// chord has collapsed.
+fn sagitta_bulge(start: Vector2<f64>, end: Vector2<f64>, mid: Vector2<f64>, bulge: f64) -> f64 {
+    let chord = end - start;
+    let chord_sq = chord.dot(chord);
+    if chord_sq <= 0.0 {
+        return bulge;
+    }
+    -2.0 * chord.perp_dot(mid - start) / chord_sq
+}
+
+/// Move every vertex of the polyline by a small pseudo random offset, e.g. to
+/// break exactly coincident geometry before a boolean operation or to stress
+/// test downstream code.
+///
+/// Every vertex is moved to a point uniformly distributed in the disk of
+/// radius `amplitude` around it. The disk is sampled by rejection using only
+/// basic arithmetic, so the same `seed` always gives bit identical offsets on
+/// every platform. Arc bulges are recomputed so every arc passes through its
+/// original midpoint moved by the mean offset of its ends, keeping it within
+/// about `amplitude` of the original arc even for short chords with a large
+/// sweep. The polyline stays closed or open, and if the first and last
+/// vertexes of an open polyline coincide they are moved together so they still
+/// coincide. `pline` is left unchanged.
+///
+/// `result` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `result` is null.
+/// * 2 = `amplitude` is negative or not finite.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_perturb(
+    pline: *const cavc_pline,
+    amplitude: f64,
+    seed: u64,
+    result: *mut *mut cavc_pline,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || result.is_null() {
+            return 1;
+        }
+        if !amplitude.is_finite() || amplitude < 0.0 {
+            return 2;
+        }
+        let pline = &(*pline).0;
+        let mut rng = SplitMix64(seed);
+        let mut offsets: Vec<Vector2<f64>> = (0..pline.vertex_count())
+            .map(|_| loop {
+                let x = 2.0 * rng.next_f64() - 1.0;
+                let y = 2.0 * rng.next_f64() - 1.0;
+                if x * x + y * y <= 1.0 {
+                    break Vector2::new(amplitude * x, amplitude * y);
+                }
+            })
+            .collect();
+        let n = pline.vertex_count();
+        if !pline.is_closed() && n > 1 {
+            let (first, last) = (pline.at(0), pline.at(n - 1));
+            if first.x == last.x && first.y == last.y {
+                offsets[n - 1] = offsets[0];
+            }
+        }
+        let perturbed = Polyline::from_iter(
+            (0..n).map(|i| {
+                let v = pline.at(i);
+                let pos = v.pos() + offsets[i];
+                if v.bulge_is_zero() || (!pline.is_closed() && i + 1 == n) {
+                    return PlineVertex::from_vector2(pos, v.bulge);
+                }
+                let j = if i + 1 == n { 0 } else { i + 1 };
+                let mid = arc_midpoint(v, pline.at(j)) + (offsets[i] + offsets[j]).scale(0.5);
+                PlineVertex::from_vector2(
+                    pos,
+                    sagitta_bulge(pos, pline.at(j).pos() + offsets[j], mid, v.bulge),
+                )
+            }),
+            pline.is_closed(),
+        );
+        *result = pline_into_raw(perturbed);
+        0
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+    use std::ptr::{null, null_mut};
+
+    use super::*;
+    use crate::handle::cavc_pline_release;
+    use crate::test_util::pline;
+
+    fn extend_length(
+        pline: Polyline<f64>,
+        start_delta: f64,
+        end_delta: f64,
+    ) -> Result<Polyline<f64>, i32> {
+        let input = cavc_pline(pline);
+        let mut result: *mut cavc_pline = null_mut();
+        unsafe {
+            match cavc_pline_extend_length(&input, start_delta, end_delta, &mut result) {
+                0 => {
+                    let pline = (*result).0.clone();
+                    cavc_pline_release(result);
+                    Ok(pline)
+                }
+                code => Err(code),
+            }
+        }
+    }
+
+    fn assert_pos(v: PlineVertex<f64>, x: f64, y: f64) {
+        assert!(v.pos().fuzzy_eq_eps(Vector2::new(x, y), 1e-12), "{:?}", v);
+    }
+
+    /// Circle of radius 1 around (1, 0) made of two half circles.
+    fn circle() -> Polyline<f64> {
+        pline(&[(0.0, 0.0, 1.0), (2.0, 0.0, 1.0)], true)
+    }
+
+    #[test]
+    fn subdivides_arcs_into_equal_pieces() {
+        let quarters = subdivide_arcs(&circle(), PI / 2.0);
+        assert_eq!(quarters.vertex_count(), 4);
+        let expected = [(0.0, 0.0), (1.0, -1.0), (2.0, 0.0), (1.0, 1.0)];
+        for (v, (x, y)) in quarters.iter_vertexes().zip(expected) {
+            assert!(v.pos().fuzzy_eq_eps(Vector2::new(x, y), 1e-12), "{:?}", v);
+            assert!((v.bulge - (PI / 8.0).tan()).abs() < 1e-12);
+        }
+        assert!((quarters.area() - PI).abs() < 1e-12);
+        // uneven sweeps are split into the fewest equal pieces
+        let pieces = subdivide_arcs(&circle(), 1.0);
+        assert_eq!(pieces.vertex_count(), 8);
+        assert!((pieces.area() - PI).abs() < 1e-12);
+    }
+
+    #[test]
+    fn keeps_lines_and_arcs_within_the_limit() {
+        let mixed = pline(&[(0.0, 0.0, 0.0), (1.0, 0.0, 0.5), (2.0, 0.0, 1.0)], false);
+        let result = subdivide_arcs(&mixed, PI);
+        assert_eq!(result.vertex_count(), 3);
+        for (a, b) in result.iter_vertexes().zip(mixed.iter_vertexes()) {
+            assert_eq!(a, b);
+        }
+        // the bulge of the last vertex of an open polyline is no segment
+        let result = subdivide_arcs(&mixed, 0.1);
+        assert_eq!(
+            result.vertex_count(),
+            2 + (4.0 * 0.5f64.atan() / 0.1).ceil() as usize
+        );
+        assert_eq!(result.last(), mixed.last());
+    }
+
+    #[test]
+    fn subdivide_degenerate_input() {
+        assert_eq!(subdivide_arcs(&Polyline::new(), 0.1).vertex_count(), 0);
+        let single = pline(&[(1.0, 1.0, 1.0)], false);
+        assert_eq!(subdivide_arcs(&single, 0.1).vertex_count(), 1);
+        // zero length line segments are kept
+        let repeated = pline(&[(0.0, 0.0, 0.0), (0.0, 0.0, 0.0), (1.0, 0.0, 0.0)], true);
+        assert_eq!(subdivide_arcs(&repeated, 0.1).vertex_count(), 3);
+    }
+
+    #[test]
+    fn subdivide_rejects_invalid_parameters() {
+        let input = cavc_pline(circle());
+        let mut result: *mut cavc_pline = null_mut();
+        unsafe {
+            for max_sweep in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+                assert_eq!(cavc_pline_subdivide_arcs(&input, max_sweep, &mut result), 2);
+            }
+            assert_eq!(cavc_pline_subdivide_arcs(&input, 1e-5, &mut result), 3);
+            assert_eq!(cavc_pline_subdivide_arcs(null(), 1.0, &mut result), 1);
+        }
+        assert!(result.is_null());
+    }
+
+    #[test]
+    fn extends_and_trims_lines() {
+        let line = || pline(&[(0.0, 0.0, 0.0), (10.0, 0.0, 0.0)], false);
+        let extended = extend_length(line(), 1.0, 2.0).unwrap();
+        assert_eq!(extended.vertex_count(), 2);
+        assert_pos(extended.at(0), -1.0, 0.0);
+        assert_pos(extended.at(1), 12.0, 0.0);
+        let trimmed = extend_length(line(), -2.0, -3.0).unwrap();
+        assert_pos(trimmed.at(0), 2.0, 0.0);
+        assert_pos(trimmed.at(trimmed.vertex_count() - 1), 7.0, 0.0);
+        // trimming one end and extending the other
+        let moved = extend_length(line(), -2.0, 2.0).unwrap();
+        assert_pos(moved.at(0), 2.0, 0.0);
+        assert_pos(moved.at(moved.vertex_count() - 1), 12.0, 0.0);
+        let unchanged = extend_length(line(), 0.0, 0.0).unwrap();
+        assert_eq!(unchanged.at(1), line().at(1));
+    }
+
+    #[test]
+    fn extends_arcs_along_their_circle() {
+        // quarter circle of radius 1 around the origin
+        let quarter = || pline(&[(1.0, 0.0, (PI / 8.0).tan()), (0.0, 1.0, 0.0)], false);
+        let longer = extend_length(quarter(), 0.0, PI / 4.0).unwrap();
+        assert_eq!(longer.vertex_count(), 2);
+        assert!((longer.at(0).bulge - (3.0 * PI / 16.0).tan()).abs() < 1e-12);
+        assert_pos(longer.at(1), -(0.5f64.sqrt()), 0.5f64.sqrt());
+        // past half a circle new arcs are added
+        let long = extend_length(quarter(), 0.0, 2.0 * PI).unwrap();
+        assert_eq!(long.vertex_count(), 4);
+        assert!((long.path_length() - 2.5 * PI).abs() < 1e-9);
+        assert_pos(long.at(3), 0.0, 1.0);
+        // the start continues backwards along the circle
+        let back = extend_length(quarter(), PI / 4.0, 0.0).unwrap();
+        assert_pos(back.at(0), 0.5f64.sqrt(), -(0.5f64.sqrt()));
+        assert!((back.path_length() - 0.75 * PI).abs() < 1e-9);
+        // trims cut the arc exactly
+        let trimmed = extend_length(quarter(), -PI / 4.0, 0.0).unwrap();
+        assert_pos(trimmed.at(0), 0.5f64.sqrt(), 0.5f64.sqrt());
+    }
+
+    #[test]
+    fn extend_length_degenerate_input() {
+        let line = || pline(&[(0.0, 0.0, 0.0), (10.0, 0.0, 0.0)], false);
+        assert_eq!(extend_length(line(), -5.0, -5.0).err(), Some(4));
+        assert_eq!(extend_length(line(), -20.0, 0.0).err(), Some(4));
+        // a tiny remainder is kept
+        let tiny = extend_length(line(), -5.0, -(5.0 - 1e-9)).unwrap();
+        assert!((tiny.path_length() - 1e-9).abs() < 1e-12);
+        // ends on zero length segments have no direction
+        let repeated = pline(
+            &[(0.0, 0.0, 0.0), (10.0, 0.0, 0.0), (10.0, 0.0, 0.0)],
+            false,
+        );
+        assert_eq!(extend_length(repeated.clone(), 0.0, 1.0).err(), Some(5));
+        assert_eq!(extend_length(repeated, 1.0, 0.0).unwrap().at(0).x, -1.0);
+        let point = pline(&[(1.0, 1.0, 0.0), (1.0, 1.0, 0.0)], false);
+        assert_eq!(extend_length(point, 1.0, 0.0).err(), Some(5));
+    }
+
+    #[test]
+    fn extend_length_rejects_invalid_input() {
+        assert_eq!(extend_length(circle(), 1.0, 1.0).err(), Some(2));
+        let single = pline(&[(0.0, 0.0, 0.0)], false);
+        assert_eq!(extend_length(single, 1.0, 1.0).err(), Some(2));
+        let line = || pline(&[(0.0, 0.0, 0.0), (10.0, 0.0, 0.0)], false);
+        for delta in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            assert_eq!(extend_length(line(), delta, 0.0).err(), Some(3));
+            assert_eq!(extend_length(line(), 0.0, delta).err(), Some(3));
+        }
+        let mut result = null_mut();
+        let code = unsafe { cavc_pline_extend_length(null(), 1.0, 1.0, &mut result) };
+        assert_eq!(code, 1);
+    }
+}
@@ -0,0 +1,157 @@
+//! Thread local error messages for failed calls.
+//!
+//! Every function wrapped in `ffi_catch_unwind!` that returns a non-zero code
+//! stores a message describing the failure for the calling thread, which the
+//! caller can retrieve with [cavc_last_error_message] right after the failing
+//! call. Functions can provide a specific message with [set_last_error] before
+//! returning the error code, otherwise a generic message naming the code is
+//! stored. Successful calls leave the last message untouched.
+//!
+//! After [cavc_init] a panic caught by `ffi_catch_unwind!` is reported with its
+//! message, location and a backtrace instead of a generic message.
+use std::backtrace::Backtrace;
+use std::cell::RefCell;
+use std::ffi::CString;
+use std::os::raw::c_char;
+use std::sync::Once;
+
+#[derive(Default)]
+struct ErrorSlot {
+    /// Message of the last failed call.
+    last: Option<CString>,
+    /// Message set by the call in progress.
+    pending: Option<String>,
+    /// Number of FFI calls in progress on the thread (calls may nest).
+    depth: u32,
+}
+
+thread_local! {
+    static ERROR_SLOT: RefCell<ErrorSlot> = RefCell::new(ErrorSlot::default());
+}
+
+/// Set the message reported if the call in progress fails.
+pub(crate) fn set_last_error(message: impl Into<String>) {
+    let message = message.into();
+    ERROR_SLOT.with(|slot| slot.borrow_mut().pending = Some(message));
+}
+
+/// Called by `ffi_catch_unwind!` before running a function body.
+pub(crate) fn begin_call() {
+    ERROR_SLOT.with(|slot| {
+        let mut slot = slot.borrow_mut();
+        slot.pending = None;
+        slot.depth += 1;
+    });
+}
+
+/// Called by `ffi_catch_unwind!` with the code returned by a function body,
+/// -1 if it panicked.
+pub(crate) fn end_call(code: i32) {
+    ERROR_SLOT.with(|slot| {
+        let mut slot = slot.borrow_mut();
+        let pending = slot.pending.take();
+        slot.depth = slot.depth.saturating_sub(1);
+        if code == 0 {
+            return;
+        }
+        let message = pending.unwrap_or_else(|| match code {
+            -1 => String::from("internal error (panic)"),
+            _ => format!("error code {}", code),
+        });
+        // messages never contain nul bytes except by mistake, drop them rather
+        // than losing the message
+        let message = message.replace('\0', "");
+        slot.last = Some(CString::new(message).unwrap());
+    });
+}
+
+/// Get the message describing the last failed call on the calling thread.
+///
+/// `out_ptr` receives a pointer to a nul terminated UTF-8 string owned by the
+/// library and `out_len` its length in bytes (not counting the terminator).
+/// The string stays valid until the next failing call or
+/// [cavc_clear_last_error] on the same thread. If no call failed since the
+/// thread started or the message was cleared `out_ptr` receives null and
+/// `out_len` 0.
+///
+/// ## Specific Error Codes
+/// * 1 = `out_ptr` or `out_len` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_last_error_message(
+    out_ptr: *mut *const c_char,
+    out_len: *mut u32,
+) -> i32 {
+    if out_ptr.is_null() || out_len.is_null() {
+        return 1;
+    }
+    ERROR_SLOT.with(|slot| match &slot.borrow().last {
+        Some(message) => {
+            *out_ptr = message.as_ptr();
+            *out_len = message.as_bytes().len() as u32;
+        }
+        None => {
+            *out_ptr = std::ptr::null();
+            *out_len = 0;
+        }
+    });
+    0
+}
+
+/// Clear the last error message of the calling thread.
+#[no_mangle]
+pub extern "C" fn cavc_clear_last_error() {
+    ERROR_SLOT.with(|slot| slot.borrow_mut().last = None);
+}
+
+static INSTALL_PANIC_HOOK: Once = Once::new();
+
+/// Initialize the library, call once before any other function (calling it
+/// again does nothing).
+///
+/// Installs a panic hook which records the message, location and backtrace of
+/// a panic inside of a library function as the last error message of the
+/// thread, so a -1 return can be reported with its cause (see
+/// [cavc_last_error_message]). Such panics are no longer printed to stderr,
+/// panics outside of library calls are passed on to the previously installed
+/// hook. Without calling this a caught panic is only reported as an internal
+/// error. The hook is process wide, a hook installed afterwards replaces it.
+///
+/// ## Specific Error Codes
+/// None, always returns 0.
+#[no_mangle]
+pub extern "C" fn cavc_init() -> i32 {
+    INSTALL_PANIC_HOOK.call_once(|| {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            let in_call = ERROR_SLOT
+                .try_with(|slot| slot.try_borrow().is_ok_and(|s| s.depth > 0))
+                .unwrap_or(false);
+            if !in_call {
+                previous(info);
+                return;
+            }
+            let payload = info.payload();
+            let message = payload
+                .downcast_ref::<&str>()
+                .copied()
+                .or_else(|| payload.downcast_ref::<String>().map(|s| s.as_str()))
+                .unwrap_or("non-string payload");
+            let location = info
+                .location()
+                .map(|l| format!(" at {}:{}:{}", l.file(), l.line(), l.column()))
+                .unwrap_or_default();
+            let report = format!(
+                "internal error (panic): {}{}\n\nstack backtrace:\n{}",
+                message,
+                location,
+                Backtrace::force_capture()
+            );
+            let _ = ERROR_SLOT.try_with(|slot| {
+                if let Ok(mut slot) = slot.try_borrow_mut() {
+                    slot.pending = Some(report);
+                }
+            });
+        }));
+    });
+    0
+}
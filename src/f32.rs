@@ -0,0 +1,407 @@
+//! Single precision variant of the core polyline API.
+//!
+//! Mirrors the `f64` polyline functions with `*f` handle and struct names,
+//! holding `Polyline<f32>` which halves the memory used by the vertex data.
+//! All operations run in single precision, so the fuzzy comparison epsilons
+//! of the default options should be kept in mind for large coordinates.
+use cavalier_contours::polyline::{
+    FindIntersectsOptions, PlineCreation, PlineOffsetOptions, PlineSource, PlineSourceMut, Polyline,
+};
+
+use crate::boolean::boolean_op_from_i32;
+use crate::util::{free_raw, into_raw};
+
+/// Vertex of a [cavc_plinef].
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct cavc_vertexf {
+    pub x: f32,
+    pub y: f32,
+    pub bulge: f32,
+}
+
+/// Point in single precision.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct cavc_pointf {
+    pub x: f32,
+    pub y: f32,
+}
+
+/// Opaque single precision polyline handle.
+#[derive(Debug, Clone)]
+pub struct cavc_plinef(pub Polyline<f32>);
+
+/// Opaque list of single precision polyline handles.
+pub struct cavc_plinelistf(pub Vec<*mut cavc_plinef>);
+
+/// Basic (single point) intersect between two single precision polylines.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct cavc_basic_intersectf {
+    pub start_index1: u32,
+    pub start_index2: u32,
+    pub point: cavc_pointf,
+}
+
+/// Overlapping intersect between two single precision polylines.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct cavc_overlapping_intersectf {
+    pub start_index1: u32,
+    pub start_index2: u32,
+    pub point1: cavc_pointf,
+    pub point2: cavc_pointf,
+}
+
+/// Opaque result of [cavc_plinef_find_intersects].
+pub struct cavc_intersects_resultf {
+    pub basic: Vec<cavc_basic_intersectf>,
+    pub overlapping: Vec<cavc_overlapping_intersectf>,
+}
+
+fn plinef_into_raw(pline: Polyline<f32>) -> *mut cavc_plinef {
+    into_raw(cavc_plinef(pline))
+}
+
+fn plinelistf_into_raw(plines: Vec<Polyline<f32>>) -> *mut cavc_plinelistf {
+    let list = plines.into_iter().map(plinef_into_raw).collect();
+    into_raw(cavc_plinelistf(list))
+}
+
+/// Create a new single precision polyline from `n_vertexes` vertexes.
+///
+/// `vertexes` may be null if `n_vertexes` is 0. `result` is only written to if
+/// the function returns 0 (success) and must be freed with [cavc_plinef_f].
+///
+/// ## Specific Error Codes
+/// * 1 = `result` is null or `vertexes` is null while `n_vertexes` is not 0.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_plinef_create(
+    vertexes: *const cavc_vertexf,
+    n_vertexes: u32,
+    is_closed: u8,
+    result: *mut *mut cavc_plinef,
+) -> i32 {
+    ffi_catch_unwind!({
+        if result.is_null() || (vertexes.is_null() && n_vertexes != 0) {
+            return 1;
+        }
+        let count = n_vertexes as usize;
+        let mut pline = Polyline::<f32>::with_capacity(count, is_closed != 0);
+        for i in 0..count {
+            let v = *vertexes.add(i);
+            pline.add(v.x, v.y, v.bulge);
+        }
+        *result = plinef_into_raw(pline);
+        0
+    })
+}
+
+/// Free an existing [cavc_plinef] object. Nothing happens if `pline` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_plinef_f(pline: *mut cavc_plinef) {
+    free_raw(pline);
+}
+
+/// Get the number of vertexes of the polyline.
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `count` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_plinef_get_vertex_count(
+    pline: *const cavc_plinef,
+    count: *mut u32,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || count.is_null() {
+            return 1;
+        }
+        *count = (*pline).0.vertex_count() as u32;
+        0
+    })
+}
+
+/// Copy all vertexes of the polyline to `vertex_data`, which must have room
+/// for the number of vertexes given by [cavc_plinef_get_vertex_count].
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `vertex_data` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_plinef_get_vertex_data(
+    pline: *const cavc_plinef,
+    vertex_data: *mut cavc_vertexf,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || vertex_data.is_null() {
+            return 1;
+        }
+        let pline = &(*pline).0;
+        for (i, v) in pline.iter_vertexes().enumerate() {
+            *vertex_data.add(i) = cavc_vertexf {
+                x: v.x,
+                y: v.y,
+                bulge: v.bulge,
+            };
+        }
+        0
+    })
+}
+
+/// Get whether the polyline is closed (1) or open (0).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `is_closed` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_plinef_get_is_closed(
+    pline: *const cavc_plinef,
+    is_closed: *mut u8,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || is_closed.is_null() {
+            return 1;
+        }
+        *is_closed = (*pline).0.is_closed() as u8;
+        0
+    })
+}
+
+/// Compute the parallel offset of the polyline with default options.
+///
+/// `handle_self_intersects` should be non-zero if the polyline may intersect
+/// itself. `result` is only written to if the function returns 0 (success)
+/// and must be freed with [cavc_plinelistf_f].
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `result` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_plinef_parallel_offset(
+    pline: *const cavc_plinef,
+    offset: f32,
+    handle_self_intersects: u8,
+    result: *mut *mut cavc_plinelistf,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || result.is_null() {
+            return 1;
+        }
+        let mut options = PlineOffsetOptions::new();
+        options.handle_self_intersects = handle_self_intersects != 0;
+        let plines = (*pline).0.parallel_offset_opt(offset, &options);
+        *result = plinelistf_into_raw(plines);
+        0
+    })
+}
+
+/// Compute a boolean operation between two closed polylines, `operation` uses
+/// the same codes as `cavc_pline_boolean` (0 = or, 1 = and, 2 = not, 3 = xor).
+///
+/// `pos_plinelist` and `neg_plinelist` are only written to if the function
+/// returns 0 (success) and must be freed with [cavc_plinelistf_f].
+///
+/// ## Specific Error Codes
+/// * 1 = `pline1`, `pline2`, `pos_plinelist` or `neg_plinelist` is null.
+/// * 2 = `operation` is not a valid operation code.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_plinef_boolean(
+    pline1: *const cavc_plinef,
+    pline2: *const cavc_plinef,
+    operation: i32,
+    pos_plinelist: *mut *mut cavc_plinelistf,
+    neg_plinelist: *mut *mut cavc_plinelistf,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline1.is_null()
+            || pline2.is_null()
+            || pos_plinelist.is_null()
+            || neg_plinelist.is_null()
+        {
+            return 1;
+        }
+        let op = match boolean_op_from_i32(operation) {
+            Some(op) => op,
+            None => return 2,
+        };
+        let r = (*pline1).0.boolean(&(*pline2).0, op);
+        *pos_plinelist = plinelistf_into_raw(r.pos_plines.into_iter().map(|p| p.pline).collect());
+        *neg_plinelist = plinelistf_into_raw(r.neg_plines.into_iter().map(|p| p.pline).collect());
+        0
+    })
+}
+
+/// Find all intersects between two polylines.
+///
+/// `result` is only written to if the function returns 0 (success) and must be
+/// freed with [cavc_intersects_resultf_f].
+///
+/// ## Specific Error Codes
+/// * 1 = `pline1`, `pline2` or `result` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_plinef_find_intersects(
+    pline1: *const cavc_plinef,
+    pline2: *const cavc_plinef,
+    result: *mut *mut cavc_intersects_resultf,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline1.is_null() || pline2.is_null() || result.is_null() {
+            return 1;
+        }
+        let options = FindIntersectsOptions::new();
+        let intrs = (*pline1).0.find_intersects_opt(&(*pline2).0, &options);
+        let basic = intrs
+            .basic_intersects
+            .iter()
+            .map(|i| cavc_basic_intersectf {
+                start_index1: i.start_index1 as u32,
+                start_index2: i.start_index2 as u32,
+                point: cavc_pointf {
+                    x: i.point.x,
+                    y: i.point.y,
+                },
+            })
+            .collect();
+        let overlapping = intrs
+            .overlapping_intersects
+            .iter()
+            .map(|i| cavc_overlapping_intersectf {
+                start_index1: i.start_index1 as u32,
+                start_index2: i.start_index2 as u32,
+                point1: cavc_pointf {
+                    x: i.point1.x,
+                    y: i.point1.y,
+                },
+                point2: cavc_pointf {
+                    x: i.point2.x,
+                    y: i.point2.y,
+                },
+            })
+            .collect();
+        *result = into_raw(cavc_intersects_resultf { basic, overlapping });
+        0
+    })
+}
+
+/// Free an existing [cavc_intersects_resultf] object. Nothing happens if
+/// `result` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_intersects_resultf_f(result: *mut cavc_intersects_resultf) {
+    free_raw(result);
+}
+
+/// Get the number of basic and overlapping intersects of the result.
+///
+/// ## Specific Error Codes
+/// * 1 = `result`, `basic_count` or `overlapping_count` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_intersects_resultf_get_counts(
+    result: *const cavc_intersects_resultf,
+    basic_count: *mut u32,
+    overlapping_count: *mut u32,
+) -> i32 {
+    ffi_catch_unwind!({
+        if result.is_null() || basic_count.is_null() || overlapping_count.is_null() {
+            return 1;
+        }
+        *basic_count = (*result).basic.len() as u32;
+        *overlapping_count = (*result).overlapping.len() as u32;
+        0
+    })
+}
+
+/// Copy all basic intersects of the result to `basic`, which must have room
+/// for the count given by [cavc_intersects_resultf_get_counts].
+///
+/// ## Specific Error Codes
+/// * 1 = `result` or `basic` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_intersects_resultf_get_basic(
+    result: *const cavc_intersects_resultf,
+    basic: *mut cavc_basic_intersectf,
+) -> i32 {
+    ffi_catch_unwind!({
+        if result.is_null() || basic.is_null() {
+            return 1;
+        }
+        let intrs = &(*result).basic;
+        std::ptr::copy_nonoverlapping(intrs.as_ptr(), basic, intrs.len());
+        0
+    })
+}
+
+/// Copy all overlapping intersects of the result to `overlapping`, which must
+/// have room for the count given by [cavc_intersects_resultf_get_counts].
+///
+/// ## Specific Error Codes
+/// * 1 = `result` or `overlapping` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_intersects_resultf_get_overlapping(
+    result: *const cavc_intersects_resultf,
+    overlapping: *mut cavc_overlapping_intersectf,
+) -> i32 {
+    ffi_catch_unwind!({
+        if result.is_null() || overlapping.is_null() {
+            return 1;
+        }
+        let intrs = &(*result).overlapping;
+        std::ptr::copy_nonoverlapping(intrs.as_ptr(), overlapping, intrs.len());
+        0
+    })
+}
+
+/// Free an existing [cavc_plinelistf] object and all polylines it still
+/// holds. Nothing happens if `plinelist` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_plinelistf_f(plinelist: *mut cavc_plinelistf) {
+    if !plinelist.is_null() {
+        for &pline in (*plinelist).0.iter() {
+            cavc_plinef_f(pline);
+        }
+        free_raw(plinelist);
+    }
+}
+
+/// Get the number of polylines in the list.
+///
+/// ## Specific Error Codes
+/// * 1 = `plinelist` or `count` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_plinelistf_get_count(
+    plinelist: *const cavc_plinelistf,
+    count: *mut u32,
+) -> i32 {
+    ffi_catch_unwind!({
+        if plinelist.is_null() || count.is_null() {
+            return 1;
+        }
+        *count = (*plinelist).0.len() as u32;
+        0
+    })
+}
+
+/// Remove the polyline at `index` from the list, transferring its ownership to
+/// the caller. The polylines after it move down one position.
+///
+/// `pline` is only written to if the function returns 0 (success) and must be
+/// freed with [cavc_plinef_f].
+///
+/// ## Specific Error Codes
+/// * 1 = `plinelist` or `pline` is null.
+/// * 2 = `index` is out of bounds.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_plinelistf_take(
+    plinelist: *mut cavc_plinelistf,
+    index: u32,
+    pline: *mut *mut cavc_plinef,
+) -> i32 {
+    ffi_catch_unwind!({
+        if plinelist.is_null() || pline.is_null() {
+            return 1;
+        }
+        let list = &mut (*plinelist).0;
+        if index as usize >= list.len() {
+            return 2;
+        }
+        *pline = list.remove(index as usize);
+        0
+    })
+}
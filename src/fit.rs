@@ -0,0 +1,336 @@
+//! Recovery of arc segments from tessellated geometry and curves.
+use std::f64::consts::{PI, TAU};
+
+use cavalier_contours::core::math::Vector2;
+use cavalier_contours::polyline::{
+    seg_closest_point, PlineCreation, PlineOffsetOptions, PlineSource, PlineSourceMut, PlineVertex,
+    Polyline,
+};
+use cavalier_contours_ffi::{cavc_pline, cavc_point};
+
+use crate::cache::evict;
+use crate::geom::{arc_bulge, circle_from_points};
+use crate::util::pline_into_raw;
+
+/// Minimum number of line segments replaced by a single arc, shorter runs are
+/// as likely to be corners as arcs.
+const MIN_ARC_SEGMENTS: usize = 3;
+
+/// Bulge of an arc from `points[0]` to the last point passing within
+/// `max_error` of all points and of the midpoints between them, or None if no
+/// such arc exists.
+fn fit_arc(points: &[Vector2<f64>], max_error: f64) -> Option<f64> {
+    let first = points[0];
+    let last = *points.last().unwrap();
+    let mid = points[points.len() / 2];
+    let (center, radius) = circle_from_points(first, mid, last)?;
+    let is_ccw = (mid - first).perp_dot(last - first) > 0.0;
+    let bulge = arc_bulge(center, first, last, is_ccw);
+    let sweep = 4.0 * bulge.atan();
+    // a (near) straight run is left as lines
+    if 0.5 * (last - first).length() * bulge.abs() <= max_error || sweep.abs() >= 0.99 * TAU {
+        return None;
+    }
+    let dir = if is_ccw { 1.0 } else { -1.0 };
+    let a0 = (first.y - center.y).atan2(first.x - center.x);
+    let angle_along =
+        |p: Vector2<f64>| (dir * ((p.y - center.y).atan2(p.x - center.x) - a0)).rem_euclid(TAU);
+    let off_circle = |p: Vector2<f64>| ((p - center).length() - radius).abs() > max_error;
+    let mut prev_angle = 0.0;
+    for (i, &p) in points.iter().enumerate().skip(1) {
+        let chord_mid = (points[i - 1] + p).scale(0.5);
+        if off_circle(p) || off_circle(chord_mid) {
+            return None;
+        }
+        // points must progress along the arc without backtracking
+        let angle = if i + 1 == points.len() {
+            sweep.abs()
+        } else {
+            angle_along(p)
+        };
+        if angle < prev_angle || angle > sweep.abs() {
+            return None;
+        }
+        prev_angle = angle;
+    }
+    Some(bulge)
+}
+
+/// Replace runs of line segments approximating circular arcs with arc
+/// segments, greedily taking the longest run from each vertex.
+pub(crate) fn fit_arcs(pline: &Polyline<f64>, max_error: f64) -> Polyline<f64> {
+    let n = pline.vertex_count();
+    let seg_count = pline.segment_count();
+    let mut result = Polyline::with_capacity(n, pline.is_closed());
+    let mut points = Vec::new();
+    let mut i = 0;
+    while i < n {
+        let v = pline.at(i);
+        let mut end = i;
+        let mut bulge = v.bulge;
+        points.clear();
+        points.push(v.pos());
+        // grow the run one line segment at a time while an arc still fits
+        let mut j = i;
+        while j < seg_count && pline.at(j).bulge_is_zero() {
+            points.push(pline.at((j + 1) % n).pos());
+            j += 1;
+            if j - i >= MIN_ARC_SEGMENTS {
+                match fit_arc(&points, max_error) {
+                    Some(b) => {
+                        end = j;
+                        bulge = b;
+                    }
+                    // allow a failed fit to recover (e.g. on a noisy point)
+                    // but not indefinitely
+                    None if j - end > MIN_ARC_SEGMENTS => break,
+                    None => {}
+                }
+            }
+        }
+        result.add_vertex(v.with_bulge(bulge));
+        i = if end > i { end } else { i + 1 };
+    }
+    result
+}
+
+/// Replace runs of short line segments approximating circular arcs with true
+/// arc segments, the inverse of `arcs_to_approx_lines`.
+///
+/// A run of at least 3 consecutive line segments is replaced by a single arc
+/// if the arc passes within `max_error` of all vertexes of the run and of the
+/// midpoints of its segments. Runs are grown greedily from the first vertex,
+/// existing arc segments are kept and the start vertex of closed polylines is
+/// always retained. Straight runs are left as they are, use
+/// `cavc_pline_simplify` to reduce those. `pline` is left unchanged.
+///
+/// `result` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `result` is null.
+/// * 2 = `max_error` is not greater than 0.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_fit_arcs(
+    pline: *const cavc_pline,
+    max_error: f64,
+    result: *mut *mut cavc_pline,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || result.is_null() {
+            return 1;
+        }
+        if max_error.is_nan() || max_error <= 0.0 {
+            return 2;
+        }
+        *result = pline_into_raw(fit_arcs(&(*pline).0, max_error));
+        0
+    })
+}
+
+/// Maximum depth of Bézier subdivision when fitting biarcs.
+const MAX_BIARC_DEPTH: u32 = 16;
+
+/// Number of interior points of a Bézier piece checked against its fit.
+const BIARC_CHECK_POINTS: usize = 8;
+
+/// Cubic Bézier curve given by its four control points.
+#[derive(Debug, Copy, Clone)]
+struct CubicBezier([Vector2<f64>; 4]);
+
+impl CubicBezier {
+    fn point(&self, t: f64) -> Vector2<f64> {
+        let [p0, p1, p2, p3] = self.0;
+        let mt = 1.0 - t;
+        p0.scale(mt * mt * mt)
+            + p1.scale(3.0 * mt * mt * t)
+            + p2.scale(3.0 * mt * t * t)
+            + p3.scale(t * t * t)
+    }
+
+    fn derivative(&self, t: f64) -> Vector2<f64> {
+        let [p0, p1, p2, p3] = self.0;
+        let mt = 1.0 - t;
+        (p1 - p0).scale(3.0 * mt * mt)
+            + (p2 - p1).scale(6.0 * mt * t)
+            + (p3 - p2).scale(3.0 * t * t)
+    }
+
+    /// Unit direction of the curve leaving `t` toward `toward`, falling back
+    /// to a short chord where the derivative vanishes (coinciding control
+    /// points).
+    fn direction(&self, t: f64, toward: f64) -> Option<Vector2<f64>> {
+        let sign = if toward > t { 1.0 } else { -1.0 };
+        let mut d = self.derivative(t).scale(sign);
+        if d.length() == 0.0 {
+            d = self.point(t + 1e-3 * (toward - t)) - self.point(t);
+        }
+        let len = d.length();
+        if len > 0.0 && len.is_finite() {
+            Some(d.scale(1.0 / len))
+        } else {
+            None
+        }
+    }
+}
+
+/// Biarc from `p0` leaving in the unit direction `t0` to `p1` arriving in the
+/// unit direction `t1`, as the bulges of both arcs and the point joining
+/// them. Both arcs have equal tangent lengths. None if there is no such biarc
+/// with arcs of at most a half circle.
+pub(crate) fn biarc(
+    p0: Vector2<f64>,
+    t0: Vector2<f64>,
+    p1: Vector2<f64>,
+    t1: Vector2<f64>,
+) -> Option<(f64, Vector2<f64>, f64)> {
+    let v = p1 - p0;
+    let vv = v.dot(v);
+    let denom = 2.0 * (1.0 - t0.dot(t1));
+    // tangent length d with |(p1 - d t1) - (p0 + d t0)| = 2 d
+    let d = if denom.abs() <= 1e-12 {
+        vv / (4.0 * v.dot(t1))
+    } else {
+        let vt = v.dot(t0 + t1);
+        (-vt + (vt * vt + denom * vv).sqrt()) / denom
+    };
+    if !d.is_finite() || d <= 0.0 {
+        return None;
+    }
+    let joint = (p0 + t0.scale(d) + p1 - t1.scale(d)).scale(0.5);
+    let c0 = joint - p0;
+    let c1 = p1 - joint;
+    // the sweep of an arc is twice the angle between its tangent and chord
+    let half_sweep0 = t0.perp_dot(c0).atan2(t0.dot(c0));
+    let half_sweep1 = c1.perp_dot(t1).atan2(c1.dot(t1));
+    if half_sweep0.abs() > 0.5 * PI || half_sweep1.abs() > 0.5 * PI {
+        return None;
+    }
+    Some(((0.5 * half_sweep0).tan(), joint, (0.5 * half_sweep1).tan()))
+}
+
+/// Approximate the cubic Bézier curve by lines and biarcs deviating at most
+/// `max_error`, returned as `(bulge, end)` pairs of the segments following
+/// the first control point. None if a piece still deviates more than
+/// `max_error` after `MAX_BIARC_DEPTH` subdivisions.
+fn bezier_to_arcs(
+    curve: &CubicBezier,
+    max_error: f64,
+    pos_equal_eps: f64,
+) -> Option<Vec<(f64, Vector2<f64>)>> {
+    let mut segments = Vec::new();
+    let within = |t0: f64, t1: f64, segs: &[(PlineVertex<f64>, PlineVertex<f64>)]| {
+        (1..=BIARC_CHECK_POINTS).all(|k| {
+            let t = t0 + (t1 - t0) * k as f64 / (BIARC_CHECK_POINTS + 1) as f64;
+            let p = curve.point(t);
+            segs.iter().any(|&(v1, v2)| {
+                (seg_closest_point(v1, v2, p, pos_equal_eps) - p).length() <= max_error
+            })
+        })
+    };
+    // depth first with the second half pushed first so segments come out in
+    // order
+    let mut stack = vec![(0.0, 1.0, 0)];
+    while let Some((t0, t1, depth)) = stack.pop() {
+        let (p0, p1) = (curve.point(t0), curve.point(t1));
+        let line = [(
+            PlineVertex::from_vector2(p0, 0.0),
+            PlineVertex::from_vector2(p1, 0.0),
+        )];
+        if within(t0, t1, &line) {
+            // pieces collapsing to a point add nothing
+            if !p0.fuzzy_eq_eps(p1, pos_equal_eps) {
+                segments.push((0.0, p1));
+            }
+            continue;
+        }
+        let arcs = curve
+            .direction(t0, t1)
+            .zip(curve.direction(t1, t0))
+            .and_then(|(d0, d1)| biarc(p0, d0, p1, -d1));
+        if let Some((b0, joint, b1)) = arcs {
+            let segs = [
+                (
+                    PlineVertex::from_vector2(p0, b0),
+                    PlineVertex::from_vector2(joint, 0.0),
+                ),
+                (
+                    PlineVertex::from_vector2(joint, b1),
+                    PlineVertex::from_vector2(p1, 0.0),
+                ),
+            ];
+            if within(t0, t1, &segs) {
+                segments.push((b0, joint));
+                segments.push((b1, p1));
+                continue;
+            }
+        }
+        if depth >= MAX_BIARC_DEPTH {
+            return None;
+        }
+        let mid = 0.5 * (t0 + t1);
+        stack.push((mid, t1, depth + 1));
+        stack.push((t0, mid, depth + 1));
+    }
+    Some(segments)
+}
+
+/// Append a cubic Bézier curve with the control points `p0`, `p1`, `p2` and
+/// `p3` to the end of the polyline, approximated by arcs.
+///
+/// The curve is approximated by biarcs (pairs of arcs joined with a common
+/// tangent) which keep the tangent continuous along the curve, subdividing it
+/// until every piece deviates at most `max_error` from the curve. Nearly
+/// straight pieces become line segments. This gives far fewer segments than
+/// flattening to lines, so the result offsets cleanly. If the polyline is not
+/// empty and its last vertex is not at `p0` a line segment to `p0` is added
+/// first, the last vertex of the polyline ends up at `p3`. The polyline is not
+/// modified if an error is returned.
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` is null.
+/// * 2 = `max_error` is not greater than 0.
+/// * 3 = a control point is not finite.
+/// * 4 = `max_error` cannot be met within 16 levels of subdivision (it is too
+///   small relative to the size of the curve).
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_append_bezier(
+    pline: *mut cavc_pline,
+    p0: cavc_point,
+    p1: cavc_point,
+    p2: cavc_point,
+    p3: cavc_point,
+    max_error: f64,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() {
+            return 1;
+        }
+        if max_error.is_nan() || max_error <= 0.0 {
+            return 2;
+        }
+        let points = [p0, p1, p2, p3].map(|p| Vector2::new(p.x, p.y));
+        if points.iter().any(|p| !p.x.is_finite() || !p.y.is_finite()) {
+            return 3;
+        }
+        let pos_equal_eps = PlineOffsetOptions::<f64>::new().pos_equal_eps;
+        let curve = CubicBezier(points);
+        let segments = match bezier_to_arcs(&curve, max_error, pos_equal_eps) {
+            Some(segments) => segments,
+            None => return 4,
+        };
+        evict(pline);
+        let pline = &mut (*pline).0;
+        let start = points[0];
+        match pline.last() {
+            Some(last) if last.pos().fuzzy_eq_eps(start, pos_equal_eps) => {}
+            _ => pline.add(start.x, start.y, 0.0),
+        }
+        for (bulge, end) in segments {
+            let last = pline.vertex_count() - 1;
+            let v = pline.at(last);
+            pline.set_vertex(last, v.with_bulge(bulge));
+            pline.add(end.x, end.y, 0.0);
+        }
+        0
+    })
+}
@@ -0,0 +1,759 @@
+//! G-code interchange of polylines.
+//!
+//! Arc segments map directly to G2 (clockwise) and G3 (counter clockwise)
+//! moves, so polylines are exchanged without linearizing arcs. Polyline
+//! coordinates are mapped to the two axes of the selected plane, `x` to the
+//! first and `y` to the second axis (X and Y for G17, Z and X for G18, Y and Z
+//! for G19), so a counter clockwise arc is always a G3 move.
+use std::f64::consts::TAU;
+use std::fmt::Write;
+use std::os::raw::c_char;
+
+use cavalier_contours::core::math::Vector2;
+use cavalier_contours::polyline::{
+    seg_arc_radius_and_center, PlineCreation, PlineOffsetOptions, PlineSource, PlineVertex,
+    Polyline,
+};
+use cavalier_contours_ffi::cavc_plinelist;
+
+use crate::error::set_last_error;
+use crate::util::{plinelist_into_raw, valid_eps, write_c_string};
+
+/// Largest number of decimal places written.
+const MAX_PRECISION: u32 = 12;
+
+/// Options for [cavc_plinelist_to_gcode].
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct cavc_gcode_o {
+    /// How arc moves are written: 0 = center offsets relative to the start
+    /// point (I, J, K words), 1 = signed radius (R word, negative for arcs
+    /// sweeping more than 180 degrees).
+    pub arc_format: i32,
+    /// Plane of the moves: 0 = G17 (XY), 1 = G18 (ZX), 2 = G19 (YZ).
+    pub plane: i32,
+    /// Feedrate written with the first feed move, no F word is written if it
+    /// is 0.
+    pub feedrate: f64,
+    /// Number of decimal places of coordinates, trailing zeros are dropped.
+    pub precision: u32,
+}
+
+impl Default for cavc_gcode_o {
+    fn default() -> Self {
+        Self {
+            arc_format: 0,
+            plane: 0,
+            feedrate: 0.0,
+            precision: 4,
+        }
+    }
+}
+
+/// Write the default [cavc_gcode_o] values to `options`: IJ arcs in the XY
+/// plane, no feedrate and 4 decimal places.
+///
+/// ## Specific Error Codes
+/// * 1 = `options` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_gcode_o_init(options: *mut cavc_gcode_o) -> i32 {
+    ffi_catch_unwind!({
+        if options.is_null() {
+            return 1;
+        }
+        *options = cavc_gcode_o::default();
+        0
+    })
+}
+
+/// G-code word letters of a plane.
+#[derive(Debug, Copy, Clone)]
+struct Plane {
+    /// Plane selection command.
+    command: &'static str,
+    /// Axis letters polyline `x` and `y` map to.
+    axes: [char; 2],
+    /// Arc center offset letters of the two axes.
+    offsets: [char; 2],
+}
+
+impl Plane {
+    fn from_i32(plane: i32) -> Option<Self> {
+        match plane {
+            0 => Some(Self {
+                command: "G17",
+                axes: ['X', 'Y'],
+                offsets: ['I', 'J'],
+            }),
+            1 => Some(Self {
+                command: "G18",
+                axes: ['Z', 'X'],
+                offsets: ['K', 'I'],
+            }),
+            2 => Some(Self {
+                command: "G19",
+                axes: ['Y', 'Z'],
+                offsets: ['J', 'K'],
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Append a word with `value` rounded to `precision` decimal places to `out`,
+/// returns false if the value is not finite.
+fn write_word(out: &mut String, letter: char, value: f64, precision: usize) -> bool {
+    if !value.is_finite() {
+        return false;
+    }
+    let mut text = format!("{:.*}", precision, value);
+    if text.contains('.') {
+        let trimmed = text.trim_end_matches('0').trim_end_matches('.').len();
+        text.truncate(trimmed);
+    }
+    if text == "-0" {
+        text.remove(0);
+    }
+    write!(out, " {}{}", letter, text).unwrap();
+    true
+}
+
+/// Writes the moves of polylines as G-code lines.
+struct GcodeWriter {
+    out: String,
+    plane: Plane,
+    use_radius: bool,
+    feedrate: f64,
+    precision: usize,
+    feedrate_written: bool,
+}
+
+impl GcodeWriter {
+    /// Append the end point words of a move.
+    fn write_point(&mut self, x: f64, y: f64) -> bool {
+        let [a1, a2] = self.plane.axes;
+        write_word(&mut self.out, a1, x, self.precision)
+            && write_word(&mut self.out, a2, y, self.precision)
+    }
+
+    /// Append the feedrate word if it was not written yet.
+    fn write_feedrate(&mut self) {
+        if self.feedrate > 0.0 && !self.feedrate_written {
+            write_word(&mut self.out, 'F', self.feedrate, self.precision);
+            self.feedrate_written = true;
+        }
+    }
+
+    /// Append a rapid move to the start and feed moves along all segments of
+    /// `pline`. Returns false if any vertex holds a non-finite value.
+    fn write_pline(&mut self, pline: &Polyline<f64>) -> bool {
+        let first = match pline.get(0) {
+            Some(v) => v,
+            None => return true,
+        };
+        self.out.push_str("G0");
+        if !self.write_point(first.x, first.y) {
+            return false;
+        }
+        self.out.push('\n');
+        for (v1, v2) in pline.iter_segments() {
+            if v1.x == v2.x && v1.y == v2.y {
+                continue;
+            }
+            if v1.bulge_is_zero() {
+                self.out.push_str("G1");
+                if !self.write_point(v2.x, v2.y) {
+                    return false;
+                }
+            } else {
+                let (radius, center) = seg_arc_radius_and_center(v1, v2);
+                let command = if v1.bulge_is_pos() { "G3" } else { "G2" };
+                self.out.push_str(command);
+                if !self.write_point(v2.x, v2.y) {
+                    return false;
+                }
+                let ok = if self.use_radius {
+                    let r = if v1.bulge.abs() > 1.0 {
+                        -radius
+                    } else {
+                        radius
+                    };
+                    write_word(&mut self.out, 'R', r, self.precision)
+                } else {
+                    let [o1, o2] = self.plane.offsets;
+                    write_word(&mut self.out, o1, center.x - v1.x, self.precision)
+                        && write_word(&mut self.out, o2, center.y - v1.y, self.precision)
+                };
+                if !ok {
+                    return false;
+                }
+            }
+            self.write_feedrate();
+            self.out.push('\n');
+        }
+        true
+    }
+}
+
+/// Write the polylines of the list as G-code moves.
+///
+/// The program starts with absolute positioning (G90) and the plane selection
+/// command, then every polyline is written as a rapid move (G0) to its first
+/// vertex followed by a feed move per segment: G1 for lines and G2/G3 for arcs
+/// (exactly, without linearizing). Closed polylines end with the move back to
+/// their first vertex, zero length segments are skipped. No units, spindle,
+/// tool or depth commands are written, those are left to the caller.
+/// `options` may be null in which case defaults are used (see
+/// [cavc_gcode_o_init]).
+///
+/// The nul terminated program text is written to `out` which has room for
+/// `out_cap` bytes. `out_len` receives the length of the text (not counting
+/// the nul terminator) even when `out` is too small, so the caller can
+/// allocate a large enough buffer and call again. `out` may be null if
+/// `out_cap` is 0.
+///
+/// ## Specific Error Codes
+/// * 1 = `plinelist` or `out_len` is null.
+/// * 2 = `out_cap` is too small to hold the text and its nul terminator.
+/// * 3 = a polyline contains a NaN or infinite value.
+/// * 4 = `options` has an invalid `arc_format` or `plane`, a negative or non
+///   finite `feedrate` or a `precision` greater than 12.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_plinelist_to_gcode(
+    plinelist: *const cavc_plinelist,
+    options: *const cavc_gcode_o,
+    out: *mut c_char,
+    out_cap: u32,
+    out_len: *mut u32,
+) -> i32 {
+    ffi_catch_unwind!({
+        if plinelist.is_null() || out_len.is_null() {
+            return 1;
+        }
+        let options = options.as_ref().copied().unwrap_or_default();
+        let plane = match Plane::from_i32(options.plane) {
+            Some(plane) => plane,
+            None => return 4,
+        };
+        let use_radius = match options.arc_format {
+            0 => false,
+            1 => true,
+            _ => return 4,
+        };
+        if !(options.feedrate >= 0.0 && options.feedrate.is_finite())
+            || options.precision > MAX_PRECISION
+        {
+            return 4;
+        }
+        let mut writer = GcodeWriter {
+            out: String::new(),
+            plane,
+            use_radius,
+            feedrate: options.feedrate,
+            precision: options.precision as usize,
+            feedrate_written: false,
+        };
+        writeln!(writer.out, "G90 {}", plane.command).unwrap();
+        for &pline in (*plinelist).0.iter() {
+            if !writer.write_pline(&(*pline).0) {
+                return 3;
+            }
+        }
+        *out_len = writer.out.len() as u32;
+        if !write_c_string(&writer.out, out, out_cap as usize) {
+            return 2;
+        }
+        0
+    })
+}
+
+/// Options for [cavc_plinelist_from_gcode].
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct cavc_gcode_import_o {
+    /// Moves shorter than this are skipped, an arc ending this close to its
+    /// start point is a full circle and a path ending this close to its start
+    /// point is closed (if `close_paths` is set).
+    pub pos_equal_eps: f64,
+    /// If not 0 paths ending at their start point become closed polylines.
+    pub close_paths: u8,
+}
+
+impl Default for cavc_gcode_import_o {
+    fn default() -> Self {
+        Self {
+            pos_equal_eps: PlineOffsetOptions::<f64>::new().pos_equal_eps,
+            close_paths: 0,
+        }
+    }
+}
+
+/// Write the default [cavc_gcode_import_o] values to `options`: the default
+/// position epsilon and all paths kept open.
+///
+/// ## Specific Error Codes
+/// * 1 = `options` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_gcode_import_o_init(options: *mut cavc_gcode_import_o) -> i32 {
+    ffi_catch_unwind!({
+        if options.is_null() {
+            return 1;
+        }
+        *options = cavc_gcode_import_o::default();
+        0
+    })
+}
+
+/// Error returned by [parse_gcode], `line` is 1 based.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct GcodeError {
+    pub(crate) line: usize,
+    pub(crate) message: &'static str,
+}
+
+/// Strip comments and whitespace from a line and convert it to upper case.
+fn clean_line(line: &str) -> Result<String, &'static str> {
+    let mut out = String::with_capacity(line.len());
+    let mut in_comment = false;
+    for c in line.chars() {
+        if in_comment {
+            in_comment = c != ')';
+            continue;
+        }
+        match c {
+            '(' => in_comment = true,
+            ';' => break,
+            c if c.is_whitespace() => {}
+            c => out.push(c.to_ascii_uppercase()),
+        }
+    }
+    if in_comment {
+        return Err("unterminated comment");
+    }
+    // block delete marker, the block is always executed
+    if out.starts_with('/') {
+        out.remove(0);
+    }
+    Ok(out)
+}
+
+/// Split a cleaned line into its letter and number words.
+fn parse_words(line: &str) -> Result<Vec<(u8, f64)>, &'static str> {
+    let bytes = line.as_bytes();
+    let mut words = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let letter = bytes[i];
+        if !letter.is_ascii_uppercase() {
+            return Err("expected a word letter");
+        }
+        i += 1;
+        let start = i;
+        while i < bytes.len() && matches!(bytes[i], b'0'..=b'9' | b'.' | b'+' | b'-') {
+            i += 1;
+        }
+        let value = line[start..i].parse().map_err(|_| "invalid number")?;
+        words.push((letter, value));
+    }
+    Ok(words)
+}
+
+/// Interprets G-code lines and accumulates the polylines of the feed moves.
+struct GcodeReader {
+    plines: Vec<Polyline<f64>>,
+    vertexes: Vec<PlineVertex<f64>>,
+    current: Vector2<f64>,
+    /// Active motion mode (0 - 3), none after G80 and canned cycles.
+    motion: Option<i32>,
+    absolute: bool,
+    absolute_arc_center: bool,
+    plane_xy: bool,
+    pos_equal_eps: f64,
+    close_paths: bool,
+}
+
+impl GcodeReader {
+    fn finish_path(&mut self) {
+        let mut vertexes = std::mem::take(&mut self.vertexes);
+        let mut is_closed = false;
+        if self.close_paths && vertexes.len() > 2 {
+            let first = vertexes[0].pos();
+            let last = vertexes[vertexes.len() - 1].pos();
+            if last.fuzzy_eq_eps(first, self.pos_equal_eps) {
+                vertexes.pop();
+                is_closed = true;
+            }
+        }
+        if vertexes.len() >= 2 {
+            self.plines
+                .push(Polyline::from_iter(vertexes.into_iter(), is_closed));
+        }
+    }
+
+    fn add_segment(&mut self, bulge: f64, end: Vector2<f64>) {
+        if self.vertexes.is_empty() {
+            self.vertexes
+                .push(PlineVertex::from_vector2(self.current, 0.0));
+        }
+        self.vertexes.last_mut().unwrap().bulge = bulge;
+        self.vertexes.push(PlineVertex::from_vector2(end, 0.0));
+        self.current = end;
+    }
+
+    /// Add an arc from the current point around `center` to `end`, a full
+    /// circle if `end` is at the current point.
+    fn arc_around(
+        &mut self,
+        center: Vector2<f64>,
+        end: Vector2<f64>,
+        ccw: bool,
+    ) -> Result<(), &'static str> {
+        let start = self.current;
+        let u = start - center;
+        let w = end - center;
+        if u.length() <= self.pos_equal_eps || w.length() <= self.pos_equal_eps {
+            return Err("arc center at its start or end point");
+        }
+        if end.fuzzy_eq_eps(start, self.pos_equal_eps) {
+            // two half circles, the second ends exactly at the start
+            let mid = center - u;
+            let bulge = if ccw { 1.0 } else { -1.0 };
+            self.add_segment(bulge, mid);
+            self.add_segment(bulge, start);
+            return Ok(());
+        }
+        let mut sweep = u.perp_dot(w).atan2(u.dot(w));
+        if ccw && sweep <= 0.0 {
+            sweep += TAU;
+        } else if !ccw && sweep >= 0.0 {
+            sweep -= TAU;
+        }
+        self.add_segment((sweep / 4.0).tan(), end);
+        Ok(())
+    }
+
+    /// Add an arc from the current point to `end` with the signed radius
+    /// `radius`, negative for arcs sweeping more than 180 degrees.
+    fn arc_radius(
+        &mut self,
+        radius: f64,
+        end: Vector2<f64>,
+        ccw: bool,
+    ) -> Result<(), &'static str> {
+        let start = self.current;
+        if radius == 0.0 {
+            return Err("arc with zero radius");
+        }
+        if end.fuzzy_eq_eps(start, self.pos_equal_eps) {
+            return Err("full circle given by radius");
+        }
+        // a chord longer than the diameter (rounding) is a half circle
+        let half_chord = 0.5 * (end - start).length();
+        let small_sweep = 2.0 * (half_chord / radius.abs()).min(1.0).asin();
+        let sweep = if radius < 0.0 {
+            TAU - small_sweep
+        } else {
+            small_sweep
+        };
+        let signed = if ccw { sweep } else { -sweep };
+        self.add_segment((signed / 4.0).tan(), end);
+        Ok(())
+    }
+
+    fn execute_line(&mut self, words: &[(u8, f64)]) -> Result<(), &'static str> {
+        let mut ignore_axes = false;
+        for &(_, value) in words.iter().filter(|(letter, _)| *letter == b'G') {
+            // codes with one decimal place such as G90.1 as integers
+            let code = (value * 10.0).round() as i32;
+            match code {
+                0 | 10 | 20 | 30 => self.motion = Some(code / 10),
+                170 => self.plane_xy = true,
+                180 | 190 => self.plane_xy = false,
+                900 => self.absolute = true,
+                910 => self.absolute = false,
+                901 => self.absolute_arc_center = true,
+                911 => self.absolute_arc_center = false,
+                // canned cycles
+                730 | 760 | 800..=890 => self.motion = None,
+                // non motion commands taking axis words
+                40 | 100 | 280 | 300 | 530 | 920..=923 => ignore_axes = true,
+                _ => {}
+            }
+        }
+        let word = |letter: u8| {
+            words
+                .iter()
+                .rev()
+                .find(|(l, _)| *l == letter)
+                .map(|&(_, value)| value)
+        };
+        let (x, y) = (word(b'X'), word(b'Y'));
+        let (i, j, r) = (word(b'I'), word(b'J'), word(b'R'));
+        let motion = match self.motion {
+            Some(motion) if !ignore_axes => motion,
+            _ => return Ok(()),
+        };
+        // arcs without end point words are full circles
+        let has_center = i.is_some() || j.is_some() || r.is_some();
+        if x.is_none() && y.is_none() && !(motion >= 2 && has_center) {
+            return Ok(());
+        }
+        let end = if self.absolute {
+            Vector2::new(x.unwrap_or(self.current.x), y.unwrap_or(self.current.y))
+        } else {
+            self.current + Vector2::new(x.unwrap_or(0.0), y.unwrap_or(0.0))
+        };
+        match motion {
+            0 => {
+                self.finish_path();
+                self.current = end;
+            }
+            1 => {
+                if !end.fuzzy_eq_eps(self.current, self.pos_equal_eps) {
+                    self.add_segment(0.0, end);
+                }
+            }
+            _ => {
+                if !self.plane_xy {
+                    return Err("arcs are only supported in the XY plane (G17)");
+                }
+                let ccw = motion == 3;
+                if i.is_some() || j.is_some() {
+                    let center = if self.absolute_arc_center {
+                        Vector2::new(i.unwrap_or(self.current.x), j.unwrap_or(self.current.y))
+                    } else {
+                        self.current + Vector2::new(i.unwrap_or(0.0), j.unwrap_or(0.0))
+                    };
+                    self.arc_around(center, end, ccw)?;
+                } else if let Some(r) = r {
+                    self.arc_radius(r, end, ccw)?;
+                } else {
+                    return Err("arc without center");
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parse G-code into polylines of the feed moves, one per path between rapid
+/// moves.
+pub(crate) fn parse_gcode(
+    text: &str,
+    options: &cavc_gcode_import_o,
+) -> Result<Vec<Polyline<f64>>, GcodeError> {
+    let mut reader = GcodeReader {
+        plines: Vec::new(),
+        vertexes: Vec::new(),
+        current: Vector2::zero(),
+        motion: None,
+        absolute: true,
+        absolute_arc_center: false,
+        plane_xy: true,
+        pos_equal_eps: options.pos_equal_eps,
+        close_paths: options.close_paths != 0,
+    };
+    for (index, line) in text.lines().enumerate() {
+        let error = |message| GcodeError {
+            line: index + 1,
+            message,
+        };
+        let line = clean_line(line).map_err(error)?;
+        // program start and end markers
+        if line == "%" {
+            continue;
+        }
+        let words = parse_words(&line).map_err(error)?;
+        reader.execute_line(&words).map_err(error)?;
+    }
+    reader.finish_path();
+    Ok(reader.plines)
+}
+
+/// Read the moves of a G-code program from the `len` bytes of text in
+/// `buffer` into open polylines.
+///
+/// G1 moves become line segments and G2/G3 moves become arc segments (exactly,
+/// with the center given by I and J offsets or an R radius, a negative radius
+/// selecting the arc larger than 180 degrees). Every rapid move (G0) ends the
+/// current polyline and the next feed move starts a new one at its end point.
+/// G90/G91 (absolute/incremental positions) and G90.1/G91.1 (absolute or
+/// incremental arc centers, incremental by default) are honored, the program
+/// starts at the origin in absolute mode with the motion mode unset. Z and all
+/// other axes are ignored, so moves only changing Z add nothing. Arc moves
+/// ending at their start point are full circles, and arc centers are taken
+/// from the start point so the end point is used as given even if its radius
+/// differs slightly. Axis words of G4, G10, G28, G30, G53 and G92 are
+/// ignored, as are canned cycles (G73, G76, G80 - G89), units (no inch to
+/// millimeter conversion is done) and every other word. Comments in
+/// parentheses and after `;` are skipped. `options` may be null in which
+/// case defaults are used (see [cavc_gcode_import_o_init]).
+///
+/// `result` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `result` is null or `buffer` is null while `len` is not 0.
+/// * 2 = the program is malformed or uses arcs outside of the XY plane, the
+///   error message holds the line number.
+/// * 3 = `options` has a negative or non finite `pos_equal_eps`.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_plinelist_from_gcode(
+    buffer: *const u8,
+    len: u32,
+    options: *const cavc_gcode_import_o,
+    result: *mut *mut cavc_plinelist,
+) -> i32 {
+    ffi_catch_unwind!({
+        if result.is_null() || (buffer.is_null() && len != 0) {
+            return 1;
+        }
+        let options = options.as_ref().copied().unwrap_or_default();
+        if !valid_eps(options.pos_equal_eps) {
+            return 3;
+        }
+        let bytes: &[u8] = if len == 0 {
+            &[]
+        } else {
+            std::slice::from_raw_parts(buffer, len as usize)
+        };
+        let text = String::from_utf8_lossy(bytes);
+        match parse_gcode(&text, &options) {
+            Ok(plines) => {
+                *result = plinelist_into_raw(plines);
+                0
+            }
+            Err(GcodeError { line, message }) => {
+                set_last_error(format!("invalid G-code on line {}: {}", line, message));
+                2
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use cavalier_contours::polyline::PlineSourceMut;
+
+    use super::*;
+
+    fn parse(text: &str) -> Result<Vec<Polyline<f64>>, GcodeError> {
+        parse_gcode(text, &cavc_gcode_import_o::default())
+    }
+
+    fn write(pline: &Polyline<f64>, use_radius: bool) -> String {
+        let mut writer = GcodeWriter {
+            out: String::new(),
+            plane: Plane::from_i32(0).unwrap(),
+            use_radius,
+            feedrate: 100.0,
+            precision: MAX_PRECISION as usize,
+            feedrate_written: false,
+        };
+        assert!(writer.write_pline(pline));
+        writer.out
+    }
+
+    #[test]
+    fn writes_trimmed_words() {
+        let mut out = String::new();
+        assert!(write_word(&mut out, 'X', 1.5, 4));
+        assert!(write_word(&mut out, 'Y', -0.00001, 4));
+        assert!(write_word(&mut out, 'Z', 2.0, 0));
+        assert!(!write_word(&mut out, 'I', f64::NAN, 4));
+        assert_eq!(out, " X1.5 Y0 Z2");
+    }
+
+    #[test]
+    fn round_trips_lines_and_arcs() {
+        let mut pline = Polyline::new_closed();
+        pline.add(0.0, 0.0, 0.0);
+        pline.add(10.0, 0.0, 0.5);
+        pline.add(10.0, 10.0, -1.5);
+        pline.add(0.0, 10.0, 0.0);
+        let options = cavc_gcode_import_o {
+            close_paths: 1,
+            ..Default::default()
+        };
+        for use_radius in [false, true] {
+            let text = write(&pline, use_radius);
+            assert_eq!(text.matches(" F100").count(), 1);
+            let plines = parse_gcode(&text, &options).unwrap();
+            assert_eq!(plines.len(), 1);
+            assert!(plines[0].is_closed());
+            assert_eq!(plines[0].vertex_count(), pline.vertex_count());
+            for (a, b) in plines[0].iter_vertexes().zip(pline.iter_vertexes()) {
+                assert!(a.fuzzy_eq_eps(b, 1e-9), "{:?} != {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn interprets_modes_and_comments() {
+        let text = "%\nG91 (incremental) G1 X1 Y0\nX0 Y1 ; comment\n/G0 X5\nG90 G1 X9 Y9\nM30\n%";
+        let plines = parse(text).unwrap();
+        assert_eq!(plines.len(), 2);
+        let positions = |p: &Polyline<f64>| -> Vec<(f64, f64)> {
+            p.iter_vertexes().map(|v| (v.x, v.y)).collect()
+        };
+        assert_eq!(
+            positions(&plines[0]),
+            vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0)]
+        );
+        assert_eq!(positions(&plines[1]), vec![(6.0, 1.0), (9.0, 9.0)]);
+        // axis words of non motion commands and canned cycles are ignored
+        let plines = parse("G1 X1\nG92 X5 Y5\nG81 X3 Y3\nG1 X2").unwrap();
+        assert_eq!(
+            positions(&plines[0]),
+            vec![(0.0, 0.0), (1.0, 0.0), (2.0, 0.0)]
+        );
+    }
+
+    #[test]
+    fn full_circles() {
+        let plines = parse("G0 X1 Y0\nG3 I-1 J0").unwrap();
+        assert_eq!(plines[0].vertex_count(), 3);
+        assert_eq!(plines[0].at(0).bulge, 1.0);
+        assert_eq!(plines[0].at(1).pos(), Vector2::new(-1.0, 0.0));
+        assert_eq!(plines[0].at(2).pos(), Vector2::new(1.0, 0.0));
+        // absolute arc centers
+        let plines = parse("G90.1\nG0 X1 Y0\nG2 X-1 Y0 I0 J0").unwrap();
+        assert!((plines[0].at(0).bulge + 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn degenerate_input() {
+        assert_eq!(parse("").unwrap().len(), 0);
+        assert_eq!(parse("G21\nM3 S1000\n(only comments)").unwrap().len(), 0);
+        // zero length moves and Z only moves add nothing
+        let plines = parse("G1 X0 Y0\nG1 Z-1\nG1 X1\nG1 X1").unwrap();
+        assert_eq!(plines[0].vertex_count(), 2);
+        // empty polylines write nothing
+        let mut writer = GcodeWriter {
+            out: String::new(),
+            plane: Plane::from_i32(1).unwrap(),
+            use_radius: false,
+            feedrate: 0.0,
+            precision: 4,
+            feedrate_written: false,
+        };
+        assert!(writer.write_pline(&Polyline::new()));
+        assert_eq!(writer.out, "");
+        assert!(Plane::from_i32(3).is_none());
+    }
+
+    #[test]
+    fn reports_errors_with_line_numbers() {
+        for (text, line) in [
+            ("G1 X1\n(open comment", 2),
+            ("G1 X1..2", 1),
+            ("G1 X1\n1 X2", 2),
+            ("G18\nG2 X1 I1", 2),
+            ("G1 X1\nG2 X2", 2),
+            ("G2 X1 R0", 1),
+            ("G0 X1\nG2 X1 R1", 2),
+            ("G3 X1 I0 J0", 1),
+        ] {
+            assert_eq!(parse(text).err().map(|e| e.line), Some(line), "{:?}", text);
+        }
+    }
+}
@@ -0,0 +1,158 @@
+//! GeoJSON export of polylines.
+use std::os::raw::c_char;
+
+use cavalier_contours::polyline::{PlineOrientation, PlineSource, Polyline};
+use cavalier_contours_ffi::cavc_plinelist;
+
+use crate::json::write_number;
+use crate::util::write_c_string;
+
+/// Append a GeoJSON position array of every vertex of `pline` (which must only
+/// contain lines) to `out`, repeating the first position at the end for rings.
+fn write_positions(out: &mut String, pline: &Polyline<f64>, reversed: bool) -> bool {
+    let mut points: Vec<_> = pline.iter_vertexes().map(|v| (v.x, v.y)).collect();
+    if reversed {
+        points.reverse();
+    }
+    if pline.is_closed() && !points.is_empty() {
+        points.push(points[0]);
+    }
+    out.push('[');
+    for (i, (x, y)) in points.into_iter().enumerate() {
+        if i != 0 {
+            out.push_str(", ");
+        }
+        out.push('[');
+        if !write_number(out, x) {
+            return false;
+        }
+        out.push_str(", ");
+        if !write_number(out, y) {
+            return false;
+        }
+        out.push(']');
+    }
+    out.push(']');
+    true
+}
+
+/// Index of the smallest counter clockwise polyline of `plines` containing the
+/// clockwise polyline `hole`.
+fn containing_outer(plines: &[Polyline<f64>], areas: &[f64], hole: usize) -> Option<usize> {
+    let point = plines[hole].at(0).pos();
+    (0..plines.len())
+        .filter(|&i| areas[i] > 0.0 && areas[i] > -areas[hole])
+        .filter(|&i| plines[i].winding_number(point) != 0)
+        .min_by(|&a, &b| areas[a].total_cmp(&areas[b]))
+}
+
+/// Convert a polyline list to a GeoJSON FeatureCollection.
+///
+/// Open polylines become LineString features. Closed counter clockwise
+/// polylines become Polygon features with the closed clockwise polylines they
+/// contain as holes, each hole is assigned to the smallest polyline that
+/// contains it. Clockwise polylines not contained in any counter clockwise
+/// polyline become polygons of their own. Rings are written with the
+/// orientation required by RFC 7946 (exterior rings counter clockwise, holes
+/// clockwise). Arc segments are approximated by lines deviating at most
+/// `arc_error` from the arcs. Features have empty properties.
+///
+/// The nul terminated string is written to `out` which has room for `out_cap`
+/// bytes. `out_len` receives the length of the string (not counting the nul
+/// terminator) even when `out` is too small, so the caller can allocate a
+/// large enough buffer and call again. `out` may be null if `out_cap` is 0.
+///
+/// ## Specific Error Codes
+/// * 1 = `plinelist` or `out_len` is null.
+/// * 2 = `out_cap` is too small to hold the string and its nul terminator.
+/// * 3 = a polyline contains a NaN or infinite value.
+/// * 4 = `arc_error` is not greater than 0.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_plinelist_to_geojson(
+    plinelist: *const cavc_plinelist,
+    arc_error: f64,
+    out: *mut c_char,
+    out_cap: u32,
+    out_len: *mut u32,
+) -> i32 {
+    ffi_catch_unwind!({
+        if plinelist.is_null() || out_len.is_null() {
+            return 1;
+        }
+        if arc_error.is_nan() || arc_error <= 0.0 {
+            return 4;
+        }
+        let plines: Vec<Polyline<f64>> = (*plinelist)
+            .0
+            .iter()
+            .map(|&p| {
+                let p = &(*p).0;
+                p.arcs_to_approx_lines(arc_error)
+                    .unwrap_or_else(|| p.clone())
+            })
+            .collect();
+        // signed areas, 0 for open polylines
+        let areas: Vec<f64> = plines
+            .iter()
+            .map(|p| {
+                if p.is_closed() && p.vertex_count() > 2 {
+                    p.area()
+                } else {
+                    0.0
+                }
+            })
+            .collect();
+        let mut holes: Vec<Vec<usize>> = vec![Vec::new(); plines.len()];
+        let mut is_hole = vec![false; plines.len()];
+        for i in 0..plines.len() {
+            if areas[i] < 0.0 {
+                if let Some(outer) = containing_outer(&plines, &areas, i) {
+                    holes[outer].push(i);
+                    is_hole[i] = true;
+                }
+            }
+        }
+
+        let mut json = String::from("{\"type\": \"FeatureCollection\", \"features\": [");
+        let mut first = true;
+        for (i, pline) in plines.iter().enumerate() {
+            if is_hole[i] || pline.vertex_count() == 0 {
+                continue;
+            }
+            if !first {
+                json.push_str(", ");
+            }
+            first = false;
+            json.push_str("{\"type\": \"Feature\", \"properties\": {}, \"geometry\": ");
+            let ok = if pline.is_closed() {
+                json.push_str("{\"type\": \"Polygon\", \"coordinates\": [");
+                let mut ok = write_positions(
+                    &mut json,
+                    pline,
+                    pline.orientation() == PlineOrientation::Clockwise,
+                );
+                for &h in &holes[i] {
+                    json.push_str(", ");
+                    ok = ok && write_positions(&mut json, &plines[h], false);
+                }
+                json.push_str("]}");
+                ok
+            } else {
+                json.push_str("{\"type\": \"LineString\", \"coordinates\": ");
+                let ok = write_positions(&mut json, pline, false);
+                json.push('}');
+                ok
+            };
+            if !ok {
+                return 3;
+            }
+            json.push('}');
+        }
+        json.push_str("]}");
+        *out_len = json.len() as u32;
+        if !write_c_string(&json, out, out_cap as usize) {
+            return 2;
+        }
+        0
+    })
+}
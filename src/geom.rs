@@ -0,0 +1,326 @@
+//! Segment geometry helpers.
+//!
+//! A segment is given by its start vertex `v1` (holding the bulge) and end
+//! vertex `v2`. Parameters `t` are fractions of the segment arc length, so
+//! `t = 0.5` is the midpoint of both lines and arcs.
+use cavalier_contours::core::math::Vector2;
+use cavalier_contours::polyline::{
+    seg_arc_radius_and_center, seg_closest_point, PlineSegIntr, PlineVertex,
+};
+
+/// Signed sweep angle of the segment, positive for counter clockwise arcs and
+/// 0 for lines.
+pub(crate) fn seg_sweep(v1: PlineVertex<f64>) -> f64 {
+    4.0 * v1.bulge.atan()
+}
+
+/// Point at parameter `t` on the segment.
+pub(crate) fn seg_point_at(v1: PlineVertex<f64>, v2: PlineVertex<f64>, t: f64) -> Vector2<f64> {
+    if v1.bulge_is_zero() {
+        return v1.pos() + (v2.pos() - v1.pos()).scale(t);
+    }
+    let (radius, center) = seg_arc_radius_and_center(v1, v2);
+    let start_angle = (v1.y - center.y).atan2(v1.x - center.x);
+    let angle = start_angle + t * seg_sweep(v1);
+    Vector2::new(
+        center.x + radius * angle.cos(),
+        center.y + radius * angle.sin(),
+    )
+}
+
+/// Unit tangent (direction of travel) at parameter `t` on the segment.
+///
+/// Zero length segments give a zero vector.
+pub(crate) fn seg_tangent_at(v1: PlineVertex<f64>, v2: PlineVertex<f64>, t: f64) -> Vector2<f64> {
+    let dir = if v1.bulge_is_zero() {
+        v2.pos() - v1.pos()
+    } else {
+        let (_, center) = seg_arc_radius_and_center(v1, v2);
+        let radial = seg_point_at(v1, v2, t) - center;
+        // perpendicular to the radius, rotated in the arc direction
+        if v1.bulge_is_neg() {
+            Vector2::new(radial.y, -radial.x)
+        } else {
+            Vector2::new(-radial.y, radial.x)
+        }
+    };
+    let len = dir.length();
+    if len > 0.0 {
+        dir.scale(1.0 / len)
+    } else {
+        Vector2::zero()
+    }
+}
+
+/// Signed turning angle at `v1` from the end direction of segment `v0, v1` to
+/// the start direction of segment `v1, v2`, positive for left turns and in
+/// `[-pi, pi]`.
+///
+/// 0 if either segment has zero length.
+pub(crate) fn seg_turn_angle(
+    v0: PlineVertex<f64>,
+    v1: PlineVertex<f64>,
+    v2: PlineVertex<f64>,
+) -> f64 {
+    let incoming = seg_tangent_at(v0, v1, 1.0);
+    let outgoing = seg_tangent_at(v1, v2, 0.0);
+    incoming.perp_dot(outgoing).atan2(incoming.dot(outgoing))
+}
+
+/// Point of the segment farthest in direction `dir` as `(point, t)`.
+///
+/// The maximum is at an end point or, for arcs sweeping across `dir`, at the
+/// point of the arc where its radius points along `dir`. Ties are resolved
+/// toward the start of the segment.
+pub(crate) fn seg_extreme_point(
+    v1: PlineVertex<f64>,
+    v2: PlineVertex<f64>,
+    dir: Vector2<f64>,
+) -> (Vector2<f64>, f64) {
+    let mut best = if v2.pos().dot(dir) > v1.pos().dot(dir) {
+        (v2.pos(), 1.0)
+    } else {
+        (v1.pos(), 0.0)
+    };
+    if !v1.bulge_is_zero() {
+        let (radius, center) = seg_arc_radius_and_center(v1, v2);
+        let sweep = seg_sweep(v1);
+        let start_angle = (v1.y - center.y).atan2(v1.x - center.x);
+        let angle = dir.y.atan2(dir.x);
+        // angle from the start in the arc direction
+        let rel = if sweep > 0.0 {
+            (angle - start_angle).rem_euclid(std::f64::consts::TAU)
+        } else {
+            (start_angle - angle).rem_euclid(std::f64::consts::TAU)
+        };
+        if rel <= sweep.abs() {
+            let len = dir.length();
+            let point = center + dir.scale(radius / len);
+            if point.dot(dir) > best.0.dot(dir) {
+                best = (point, rel / sweep.abs());
+            }
+        }
+    }
+    best
+}
+
+/// Signed curvature of the segment, `1 / radius` for counter clockwise arcs,
+/// `-1 / radius` for clockwise arcs and 0 for lines and zero length segments.
+pub(crate) fn seg_curvature(v1: PlineVertex<f64>, v2: PlineVertex<f64>) -> f64 {
+    if v1.bulge_is_zero() || v1.pos().fuzzy_eq(v2.pos()) {
+        return 0.0;
+    }
+    let (radius, _) = seg_arc_radius_and_center(v1, v2);
+    v1.bulge.signum() / radius
+}
+
+/// Part of the segment between parameters `t0` and `t1`, returned as the start
+/// vertex (holding the bulge of the part) and the end position.
+pub(crate) fn seg_sub(
+    v1: PlineVertex<f64>,
+    v2: PlineVertex<f64>,
+    t0: f64,
+    t1: f64,
+) -> (PlineVertex<f64>, Vector2<f64>) {
+    let start = if t0 <= 0.0 {
+        v1.pos()
+    } else {
+        seg_point_at(v1, v2, t0)
+    };
+    let end = if t1 >= 1.0 {
+        v2.pos()
+    } else {
+        seg_point_at(v1, v2, t1)
+    };
+    let bulge = if v1.bulge_is_zero() || (t0 <= 0.0 && t1 >= 1.0) {
+        v1.bulge
+    } else {
+        ((t1 - t0) * seg_sweep(v1) / 4.0).tan()
+    };
+    (PlineVertex::from_vector2(start, bulge), end)
+}
+
+/// Circle through three points as `(center, radius)`, or None if the points
+/// are collinear (or coincide).
+pub(crate) fn circle_from_points(
+    a: Vector2<f64>,
+    b: Vector2<f64>,
+    c: Vector2<f64>,
+) -> Option<(Vector2<f64>, f64)> {
+    let ab = b - a;
+    let ac = c - a;
+    let d = 2.0 * ab.perp_dot(ac);
+    if d == 0.0 || !d.is_finite() {
+        return None;
+    }
+    let ab_sq = ab.dot(ab);
+    let ac_sq = ac.dot(ac);
+    let offset = Vector2::new(
+        (ac.y * ab_sq - ab.y * ac_sq) / d,
+        (ab.x * ac_sq - ac.x * ab_sq) / d,
+    );
+    let center = a + offset;
+    if !center.x.is_finite() || !center.y.is_finite() {
+        return None;
+    }
+    Some((center, offset.length()))
+}
+
+/// Bulge of the arc from `start` to `end` around `center`, running counter
+/// clockwise if `is_ccw` is set.
+pub(crate) fn arc_bulge(
+    center: Vector2<f64>,
+    start: Vector2<f64>,
+    end: Vector2<f64>,
+    is_ccw: bool,
+) -> f64 {
+    let a0 = (start.y - center.y).atan2(start.x - center.x);
+    let a1 = (end.y - center.y).atan2(end.x - center.x);
+    let sweep = if is_ccw {
+        (a1 - a0).rem_euclid(std::f64::consts::TAU)
+    } else {
+        -(a0 - a1).rem_euclid(std::f64::consts::TAU)
+    };
+    (sweep / 4.0).tan()
+}
+
+/// Bulge of the arc from `start` through `mid` to `end`, or None if the points
+/// are collinear (or coincide).
+pub(crate) fn three_point_bulge(
+    start: Vector2<f64>,
+    mid: Vector2<f64>,
+    end: Vector2<f64>,
+) -> Option<f64> {
+    let (center, _) = circle_from_points(start, mid, end)?;
+    let is_ccw = (mid - start).perp_dot(end - start) > 0.0;
+    Some(arc_bulge(center, start, end, is_ccw))
+}
+
+/// Contribution of the segment to the winding number of `point` around the
+/// closed polyline it is part of.
+///
+/// Arcs contribute like their chord, adjusted for points between the chord
+/// and the arc.
+pub(crate) fn seg_winding_number(
+    v1: PlineVertex<f64>,
+    v2: PlineVertex<f64>,
+    point: Vector2<f64>,
+) -> i32 {
+    let is_left = (v2.pos() - v1.pos()).perp_dot(point - v1.pos());
+    // crossings of the ray from the point toward positive x
+    let mut winding = if v1.y <= point.y {
+        (v2.y > point.y && is_left > 0.0) as i32
+    } else {
+        -((v2.y <= point.y && is_left < 0.0) as i32)
+    };
+    if !v1.bulge_is_zero() {
+        // counter clockwise arcs lie to the right of their chord
+        let arc_side = if v1.bulge_is_pos() {
+            is_left < 0.0
+        } else {
+            is_left > 0.0
+        };
+        let (radius, center) = seg_arc_radius_and_center(v1, v2);
+        if arc_side && (point - center).length() < radius {
+            winding += if v1.bulge_is_pos() { 1 } else { -1 };
+        }
+    }
+    winding
+}
+
+/// Closest points between two segments as `(point on first, point on second,
+/// distance)`, for segments that do not intersect.
+///
+/// The closest points are either at an end point of one segment or where the
+/// connecting line is normal to both segments, which for arcs means it passes
+/// through the arc center, so only those candidates are checked.
+pub(crate) fn seg_seg_closest(
+    v1: PlineVertex<f64>,
+    v2: PlineVertex<f64>,
+    u1: PlineVertex<f64>,
+    u2: PlineVertex<f64>,
+    pos_equal_eps: f64,
+) -> (Vector2<f64>, Vector2<f64>, f64) {
+    let closest_on = |a1, a2, p| seg_closest_point(a1, a2, p, pos_equal_eps);
+    // points on the arc a1-a2 where the normal may also be normal to b1-b2
+    let arc_candidates = |a1: PlineVertex<f64>, a2, b1: PlineVertex<f64>, b2| {
+        let mut candidates = Vec::new();
+        if a1.bulge_is_zero() {
+            return candidates;
+        }
+        let (radius, center) = seg_arc_radius_and_center(a1, a2);
+        let toward = if b1.bulge_is_zero() {
+            closest_on(b1, b2, center)
+        } else {
+            seg_arc_radius_and_center(b1, b2).1
+        };
+        let dir = toward - center;
+        let len = dir.length();
+        if len > 0.0 {
+            let offset = dir.scale(radius / len);
+            candidates.push(closest_on(a1, a2, center + offset));
+            candidates.push(closest_on(a1, a2, center - offset));
+        }
+        candidates
+    };
+
+    let mut best = (v1.pos(), u1.pos(), f64::INFINITY);
+    let mut consider = |p: Vector2<f64>, q: Vector2<f64>| {
+        let dist = (p - q).length();
+        if dist < best.2 {
+            best = (p, q, dist);
+        }
+    };
+    let mut on_first = vec![v1.pos(), v2.pos()];
+    on_first.extend(arc_candidates(v1, v2, u1, u2));
+    for p in on_first {
+        consider(p, closest_on(u1, u2, p));
+    }
+    let mut on_second = vec![u1.pos(), u2.pos()];
+    on_second.extend(arc_candidates(u1, u2, v1, v2));
+    for q in on_second {
+        consider(closest_on(v1, v2, q), q);
+    }
+    best
+}
+
+/// Parameter of `point` (which lies on or close to the segment) on the
+/// segment, clamped to `[0, 1]`.
+pub(crate) fn seg_param_at_point(
+    v1: PlineVertex<f64>,
+    v2: PlineVertex<f64>,
+    point: Vector2<f64>,
+) -> f64 {
+    let t = if v1.bulge_is_zero() {
+        let dir = v2.pos() - v1.pos();
+        let len_sq = dir.dot(dir);
+        if len_sq == 0.0 {
+            return 0.0;
+        }
+        (point - v1.pos()).dot(dir) / len_sq
+    } else {
+        let (_, center) = seg_arc_radius_and_center(v1, v2);
+        let start_angle = (v1.y - center.y).atan2(v1.x - center.x);
+        let angle = (point.y - center.y).atan2(point.x - center.x);
+        let sweep = seg_sweep(v1);
+        if sweep > 0.0 {
+            (angle - start_angle).rem_euclid(std::f64::consts::TAU) / sweep
+        } else {
+            (start_angle - angle).rem_euclid(std::f64::consts::TAU) / -sweep
+        }
+    };
+    t.clamp(0.0, 1.0)
+}
+
+/// Points of a segment intersect, the end points for overlaps.
+pub(crate) fn intr_points(intr: PlineSegIntr<f64>) -> Vec<Vector2<f64>> {
+    match intr {
+        PlineSegIntr::NoIntersect => Vec::new(),
+        PlineSegIntr::TangentIntersect { point } | PlineSegIntr::OneIntersect { point } => {
+            vec![point]
+        }
+        PlineSegIntr::TwoIntersects { point1, point2 }
+        | PlineSegIntr::OverlappingLines { point1, point2 }
+        | PlineSegIntr::OverlappingArcs { point1, point2 } => vec![point1, point2],
+    }
+}
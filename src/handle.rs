@@ -0,0 +1,53 @@
+//! Lifetime of polyline handles.
+//!
+//! Side tables of this library (the user data tags of [crate::userdata] and
+//! the values cached by [crate::cache]) are keyed by handle address since `cavc_pline` and `cavc_plinelist` are
+//! defined by `cavalier_contours_ffi` and have no room for extra state.
+//! Handles created by this library start without any entries, and
+//! [cavc_pline_release] and [cavc_plinelist_release] remove the entries of a
+//! handle before freeing it, so a handle allocated later at the same address
+//! never sees stale state and the tables do not grow. Prefer them over
+//! `cavc_pline_f` and `cavc_plinelist_f`, which leave the entries behind.
+use cavalier_contours_ffi::{cavc_pline, cavc_pline_f, cavc_plinelist, cavc_plinelist_f};
+
+use crate::cache::evict;
+use crate::debug::{track_created, track_freed, KIND_PLINE};
+use crate::userdata::set_tag;
+
+/// Reset the side table entries of a polyline handle that was just created.
+pub(crate) fn pline_created(pline: *const cavc_pline) {
+    set_tag(pline, 0);
+    evict(pline);
+    track_created(KIND_PLINE, pline);
+}
+
+/// Remove the side table entries of a polyline handle about to be freed.
+pub(crate) fn pline_freed(pline: *const cavc_pline) {
+    set_tag(pline, 0);
+    evict(pline);
+    track_freed(pline);
+}
+
+/// Free a polyline and remove its user data tag and any other state this
+/// library keeps for it. Nothing happens if `pline` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_release(pline: *mut cavc_pline) {
+    if !pline.is_null() {
+        pline_freed(pline);
+        cavc_pline_f(pline);
+    }
+}
+
+/// Free a polyline list together with the polylines it still holds, removing
+/// the state this library keeps for all of them as by [cavc_pline_release].
+/// Nothing happens if `plinelist` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_plinelist_release(plinelist: *mut cavc_plinelist) {
+    if !plinelist.is_null() {
+        for &pline in (*plinelist).0.iter() {
+            pline_freed(pline);
+        }
+        track_freed(plinelist);
+        cavc_plinelist_f(plinelist);
+    }
+}
@@ -0,0 +1,232 @@
+//! Hatch fill lines.
+use cavalier_contours::core::math::Vector2;
+use cavalier_contours::polyline::{
+    seg_arc_radius_and_center, PlineCreation, PlineSource, PlineSourceMut, PlineVertex, Polyline,
+};
+use cavalier_contours_ffi::cavc_plinelist;
+
+use crate::geom::{seg_param_at_point, seg_sub};
+use crate::util::plinelist_into_raw;
+
+/// Piece of a segment that is monotonic in y, arcs are split at their top and
+/// bottom points so every piece crosses a horizontal line at most once.
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct Piece {
+    pub v1: PlineVertex<f64>,
+    pub v2: PlineVertex<f64>,
+}
+
+impl Piece {
+    /// x of the crossing with the horizontal line at `y`, which must be
+    /// between the piece end points.
+    pub(crate) fn x_at(&self, y: f64) -> f64 {
+        let (a, b) = (self.v1.pos(), self.v2.pos());
+        if self.v1.bulge_is_zero() {
+            return a.x + (y - a.y) * (b.x - a.x) / (b.y - a.y);
+        }
+        let (radius, center) = seg_arc_radius_and_center(self.v1, self.v2);
+        let dy = y - center.y;
+        let dx = (radius * radius - dy * dy).max(0.0).sqrt();
+        // y monotonic pieces lie on one side of the vertical through the
+        // center
+        if a.x + b.x >= 2.0 * center.x {
+            center.x + dx
+        } else {
+            center.x - dx
+        }
+    }
+}
+
+/// Split the segment into y monotonic pieces.
+pub(crate) fn monotonic_pieces(v1: PlineVertex<f64>, v2: PlineVertex<f64>, out: &mut Vec<Piece>) {
+    if v1.bulge_is_zero() {
+        out.push(Piece { v1, v2 });
+        return;
+    }
+    let (radius, center) = seg_arc_radius_and_center(v1, v2);
+    let mut params: Vec<f64> = [1.0, -1.0]
+        .iter()
+        .map(|s| seg_param_at_point(v1, v2, center + Vector2::new(0.0, s * radius)))
+        .filter(|&t| t > 0.0 && t < 1.0)
+        .collect();
+    params.sort_by(|a, b| a.total_cmp(b));
+    let mut t0 = 0.0;
+    for t1 in params.into_iter().chain(std::iter::once(1.0)) {
+        let (start, end) = seg_sub(v1, v2, t0, t1);
+        out.push(Piece {
+            v1: start,
+            v2: PlineVertex::from_vector2(end, 0.0),
+        });
+        t0 = t1;
+    }
+}
+
+/// Maximum number of hatch lines across the polylines in [hatch_lines].
+pub(crate) const MAX_HATCH_ROWS: f64 = 1_000_000.0;
+
+/// Hatch line segments filling the area of the closed polylines `plines`
+/// (even-odd rule) at `angle` radians from the x axis and `spacing` apart.
+///
+/// Returns `None` if the polylines span more than [MAX_HATCH_ROWS] hatch
+/// lines.
+pub(crate) fn hatch_lines(
+    plines: &[&Polyline<f64>],
+    angle: f64,
+    spacing: f64,
+) -> Option<Vec<(Vector2<f64>, Vector2<f64>)>> {
+    let (sin, cos) = angle.sin_cos();
+    // work in a frame rotated so the hatch lines are horizontal
+    let to_frame = |p: Vector2<f64>| Vector2::new(p.x * cos + p.y * sin, -p.x * sin + p.y * cos);
+    let from_frame = |p: Vector2<f64>| Vector2::new(p.x * cos - p.y * sin, p.x * sin + p.y * cos);
+    let mut pieces = Vec::new();
+    for pline in plines
+        .iter()
+        .filter(|p| p.is_closed() && p.vertex_count() > 1)
+    {
+        for (v1, v2) in pline.iter_segments() {
+            let r1 = PlineVertex::from_vector2(to_frame(v1.pos()), v1.bulge);
+            let r2 = PlineVertex::from_vector2(to_frame(v2.pos()), 0.0);
+            monotonic_pieces(r1, r2, &mut pieces);
+        }
+    }
+    let (min_y, max_y) = pieces.iter().fold((f64::MAX, f64::MIN), |(lo, hi), p| {
+        (lo.min(p.v1.y).min(p.v2.y), hi.max(p.v1.y).max(p.v2.y))
+    });
+    if ((max_y - min_y) / spacing).ceil() > MAX_HATCH_ROWS {
+        return None;
+    }
+
+    // crossings of every piece with the hatch lines `y = k * spacing`, a
+    // piece crosses a line if its end points are on different sides with
+    // points exactly on the line counted as below
+    let mut crossings: Vec<(i64, f64)> = Vec::new();
+    for piece in &pieces {
+        let (y1, y2) = (piece.v1.y, piece.v2.y);
+        let (lo, hi) = if y1 < y2 { (y1, y2) } else { (y2, y1) };
+        let first = (lo / spacing).ceil() as i64;
+        let mut k = first;
+        while (k as f64) * spacing < hi {
+            let y = k as f64 * spacing;
+            crossings.push((k, piece.x_at(y)));
+            k += 1;
+        }
+    }
+    crossings.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.total_cmp(&b.1)));
+
+    let mut lines = Vec::new();
+    let mut reverse = false;
+    for row in crossings.chunk_by(|a, b| a.0 == b.0) {
+        let y = row[0].0 as f64 * spacing;
+        let mut row_lines: Vec<_> = row
+            .chunks_exact(2)
+            .filter(|pair| pair[1].1 > pair[0].1)
+            .map(|pair| {
+                (
+                    from_frame(Vector2::new(pair[0].1, y)),
+                    from_frame(Vector2::new(pair[1].1, y)),
+                )
+            })
+            .collect();
+        // alternate the direction of every hatch line to shorten travel
+        if reverse {
+            row_lines.reverse();
+            for line in row_lines.iter_mut() {
+                *line = (line.1, line.0);
+            }
+        }
+        reverse = !reverse;
+        lines.extend(row_lines);
+    }
+    Some(lines)
+}
+
+/// Generate hatch lines filling the region bounded by the closed polylines of
+/// the list.
+///
+/// The region is formed with the even-odd rule, so holes (and islands in
+/// holes) need no special orientation. Hatch lines run at `angle` radians
+/// from the x axis, `spacing` apart and aligned to the origin so regions
+/// hatched separately line up. `result` receives one open two vertex
+/// polyline per hatch line segment, line by line with alternating direction
+/// so they can be followed with short moves between them. Arcs are accounted
+/// for exactly and open polylines are ignored.
+///
+/// `result` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `plinelist` or `result` is null.
+/// * 2 = `spacing` is not greater than 0 or `angle` is not finite.
+/// * 3 = the polylines span more than a million hatch lines at `spacing`.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_plinelist_hatch(
+    plinelist: *const cavc_plinelist,
+    angle: f64,
+    spacing: f64,
+    result: *mut *mut cavc_plinelist,
+) -> i32 {
+    ffi_catch_unwind!({
+        if plinelist.is_null() || result.is_null() {
+            return 1;
+        }
+        if !spacing.is_finite() || spacing <= 0.0 || !angle.is_finite() {
+            return 2;
+        }
+        let plines: Vec<&Polyline<f64>> = (*plinelist).0.iter().map(|&p| &(*p).0).collect();
+        let lines = match hatch_lines(&plines, angle, spacing) {
+            Some(lines) => lines,
+            None => return 3,
+        };
+        let lines = lines
+            .into_iter()
+            .map(|(a, b)| {
+                let mut line = Polyline::with_capacity(2, false);
+                line.add(a.x, a.y, 0.0);
+                line.add(b.x, b.y, 0.0);
+                line
+            })
+            .collect();
+        *result = plinelist_into_raw(lines);
+        0
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ptr::null_mut;
+
+    use super::*;
+    use crate::handle::cavc_plinelist_release;
+    use crate::test_util::{rect, square};
+
+    unsafe fn hatch(plines: Vec<Polyline<f64>>, spacing: f64) -> Result<Vec<Polyline<f64>>, i32> {
+        let plinelist = plinelist_into_raw(plines);
+        let mut result: *mut cavc_plinelist = null_mut();
+        let code = cavc_plinelist_hatch(plinelist, 0.0, spacing, &mut result);
+        cavc_plinelist_release(plinelist);
+        match code {
+            0 => {
+                let lines = (*result).0.iter().map(|&p| (*p).0.clone()).collect();
+                cavc_plinelist_release(result);
+                Ok(lines)
+            }
+            code => Err(code),
+        }
+    }
+
+    #[test]
+    fn hatch_fills_region() {
+        let lines = unsafe { hatch(vec![square(10.0)], 1.0) }.unwrap();
+        assert_eq!(lines.len(), 10);
+        assert!(lines.iter().all(|l| (l.path_length() - 10.0).abs() < 1e-9));
+        // a hole splits the rows crossing it
+        let lines = unsafe { hatch(vec![square(10.0), rect(2.5, 2.5, 7.5, 7.5)], 1.0) }.unwrap();
+        assert_eq!(lines.len(), 15);
+    }
+
+    #[test]
+    fn hatch_rejects_too_many_rows() {
+        let code = unsafe { hatch(vec![square(10.0)], 1e-6) }.err();
+        assert_eq!(code, Some(3));
+        assert!(unsafe { hatch(vec![square(10.0)], 1e-3) }.is_ok());
+    }
+}
@@ -0,0 +1,232 @@
+//! Containment hierarchy of closed polylines.
+use cavalier_contours::polyline::{PlineSource, Polyline};
+use cavalier_contours::static_aabb2d_index::StaticAABB2DIndexBuilder;
+use cavalier_contours_ffi::cavc_plinelist;
+
+use crate::util::{free_raw, into_raw};
+
+/// Opaque type holding the result of [cavc_plinelist_build_hierarchy].
+pub struct cavc_hierarchy {
+    /// Index of the parent of every polyline, -1 for roots.
+    parents: Vec<i32>,
+    /// Indexes of the children of every polyline.
+    children: Vec<Vec<u32>>,
+}
+
+/// Index of the parent of every polyline of `plines`, the closed polyline with
+/// the smallest area that contains it, -1 if there is none.
+pub(crate) fn parent_indexes(plines: &[&Polyline<f64>]) -> Vec<i32> {
+    let is_area = |p: &Polyline<f64>| p.is_closed() && p.vertex_count() > 1;
+    let areas: Vec<f64> = plines
+        .iter()
+        .map(|p| if is_area(p) { p.area().abs() } else { 0.0 })
+        .collect();
+    let boxes: Vec<_> = plines.iter().map(|p| p.extents()).collect();
+    let mut builder = StaticAABB2DIndexBuilder::new(plines.len());
+    for b in &boxes {
+        match b {
+            Some(b) => builder.add(b.min_x, b.min_y, b.max_x, b.max_y),
+            None => builder.add(0.0, 0.0, 0.0, 0.0),
+        };
+    }
+    let index = match builder.build() {
+        Ok(index) => index,
+        Err(_) => return vec![-1; plines.len()],
+    };
+
+    let mut parents = vec![-1; plines.len()];
+    for (i, pline) in plines.iter().enumerate() {
+        let b = match &boxes[i] {
+            Some(b) if areas[i] > 0.0 => b,
+            _ => continue,
+        };
+        let point = pline.at(0).pos();
+        // containers have a box containing the box of the polyline, so they
+        // are among the boxes overlapping it
+        parents[i] = index
+            .query(b.min_x, b.min_y, b.max_x, b.max_y)
+            .into_iter()
+            .filter(|&j| j != i && areas[j] > areas[i])
+            .filter(|&j| match &boxes[j] {
+                Some(c) => {
+                    c.min_x <= b.min_x
+                        && c.min_y <= b.min_y
+                        && c.max_x >= b.max_x
+                        && c.max_y >= b.max_y
+                }
+                None => false,
+            })
+            .filter(|&j| plines[j].winding_number(point) != 0)
+            .min_by(|&a, &b| areas[a].total_cmp(&areas[b]))
+            .map_or(-1, |j| j as i32);
+    }
+    parents
+}
+
+/// Build the nesting tree of the closed polylines of a list, e.g. to find
+/// which contour is a hole of which.
+///
+/// The parent of a closed polyline is the closed polyline with the smallest
+/// area that contains it, the polylines are assumed not to intersect each
+/// other (containment is decided by the first vertex). Orientation is not
+/// considered, a polyline at an odd depth is a hole of its parent and one at
+/// an even depth is an outer boundary. Open polylines and polylines without
+/// area never contain or are contained by others and are roots of their own.
+/// Candidate parents are found with a spatial index of the polyline extents.
+///
+/// `result` is only written to if the function returns 0 (success) and must be
+/// freed with [cavc_hierarchy_f].
+///
+/// ## Specific Error Codes
+/// * 1 = `plinelist` or `result` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_plinelist_build_hierarchy(
+    plinelist: *const cavc_plinelist,
+    result: *mut *mut cavc_hierarchy,
+) -> i32 {
+    ffi_catch_unwind!({
+        if plinelist.is_null() || result.is_null() {
+            return 1;
+        }
+        let plines: Vec<&Polyline<f64>> = (*plinelist).0.iter().map(|&p| &(*p).0).collect();
+        let parents = parent_indexes(&plines);
+        let mut children = vec![Vec::new(); parents.len()];
+        for (i, &parent) in parents.iter().enumerate() {
+            if parent >= 0 {
+                children[parent as usize].push(i as u32);
+            }
+        }
+        *result = into_raw(cavc_hierarchy { parents, children });
+        0
+    })
+}
+
+/// Free an existing [cavc_hierarchy] object. Nothing happens if `hierarchy` is
+/// null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_hierarchy_f(hierarchy: *mut cavc_hierarchy) {
+    free_raw(hierarchy);
+}
+
+/// Get the number of polylines of the hierarchy, the same as the length of the
+/// list it was built from.
+///
+/// ## Specific Error Codes
+/// * 1 = `hierarchy` or `count` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_hierarchy_get_count(
+    hierarchy: *const cavc_hierarchy,
+    count: *mut u32,
+) -> i32 {
+    ffi_catch_unwind!({
+        if hierarchy.is_null() || count.is_null() {
+            return 1;
+        }
+        *count = (*hierarchy).parents.len() as u32;
+        0
+    })
+}
+
+/// Write the parent index of every polyline to `parents` (-1 for roots), which
+/// must have room for [cavc_hierarchy_get_count] values.
+///
+/// ## Specific Error Codes
+/// * 1 = `hierarchy` or `parents` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_hierarchy_get_parents(
+    hierarchy: *const cavc_hierarchy,
+    parents: *mut i32,
+) -> i32 {
+    ffi_catch_unwind!({
+        if hierarchy.is_null() || parents.is_null() {
+            return 1;
+        }
+        let p = &(*hierarchy).parents;
+        std::ptr::copy_nonoverlapping(p.as_ptr(), parents, p.len());
+        0
+    })
+}
+
+/// Get the nesting depth of the polyline at `index`, 0 for roots.
+///
+/// ## Specific Error Codes
+/// * 1 = `hierarchy` or `depth` is null.
+/// * 2 = `index` is out of bounds.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_hierarchy_get_depth(
+    hierarchy: *const cavc_hierarchy,
+    index: u32,
+    depth: *mut u32,
+) -> i32 {
+    ffi_catch_unwind!({
+        if hierarchy.is_null() || depth.is_null() {
+            return 1;
+        }
+        let parents = &(*hierarchy).parents;
+        if index as usize >= parents.len() {
+            return 2;
+        }
+        let mut d = 0;
+        let mut current = parents[index as usize];
+        while current >= 0 {
+            d += 1;
+            current = parents[current as usize];
+        }
+        *depth = d;
+        0
+    })
+}
+
+/// Get the number of children of the polyline at `index`.
+///
+/// ## Specific Error Codes
+/// * 1 = `hierarchy` or `count` is null.
+/// * 2 = `index` is out of bounds.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_hierarchy_get_child_count(
+    hierarchy: *const cavc_hierarchy,
+    index: u32,
+    count: *mut u32,
+) -> i32 {
+    ffi_catch_unwind!({
+        if hierarchy.is_null() || count.is_null() {
+            return 1;
+        }
+        let all_children = &(*hierarchy).children;
+        match all_children.get(index as usize) {
+            Some(c) => {
+                *count = c.len() as u32;
+                0
+            }
+            None => 2,
+        }
+    })
+}
+
+/// Write the indexes of the children of the polyline at `index` to `children`
+/// in list order, which must have room for [cavc_hierarchy_get_child_count]
+/// values.
+///
+/// ## Specific Error Codes
+/// * 1 = `hierarchy` or `children` is null.
+/// * 2 = `index` is out of bounds.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_hierarchy_get_children(
+    hierarchy: *const cavc_hierarchy,
+    index: u32,
+    children: *mut u32,
+) -> i32 {
+    ffi_catch_unwind!({
+        if hierarchy.is_null() || children.is_null() {
+            return 1;
+        }
+        let all_children = &(*hierarchy).children;
+        match all_children.get(index as usize) {
+            Some(c) => {
+                std::ptr::copy_nonoverlapping(c.as_ptr(), children, c.len());
+                0
+            }
+            None => 2,
+        }
+    })
+}
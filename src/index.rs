@@ -0,0 +1,74 @@
+//! Spatial index queries.
+use cavalier_contours::polyline::PlineSource;
+use cavalier_contours_ffi::{cavc_aabbindex, cavc_pline};
+
+/// Create an approximate spatial index of the polyline segments.
+///
+/// Item `i` of the index is the bounding box of segment `i` (starting at
+/// vertex `i`), boxes of arc segments are approximate but always contain the
+/// whole arc. The index does not reference `pline`, but it has to be rebuilt
+/// if the polyline is modified.
+///
+/// `aabbindex` is only written to if the function returns 0 (success) and must
+/// be freed with `cavc_aabbindex_f`.
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `aabbindex` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_create_approx_aabb_index(
+    pline: *const cavc_pline,
+    aabbindex: *mut *mut cavc_aabbindex,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || aabbindex.is_null() {
+            return 1;
+        }
+        let index = (*pline).0.create_approx_aabb_index();
+        // freed by cavc_aabbindex_f of cavalier_contours_ffi, so it is not
+        // registered in the debug registry
+        *aabbindex = Box::into_raw(Box::new(cavc_aabbindex(index)));
+        0
+    })
+}
+
+/// Query the spatial index for all items whose boxes overlap the rectangle
+/// `(min_x, min_y)`-`(max_x, max_y)`.
+///
+/// For an index created from a polyline the items are segment indexes. The
+/// indexes are written to `out` which has room for `out_cap` values, while
+/// `out_count` receives the total number of overlapping items, so if it is
+/// larger than `out_cap` the caller can size the buffer and query again. `out`
+/// may be null if `out_cap` is 0.
+///
+/// ## Specific Error Codes
+/// * 1 = `aabbindex` or `out_count` is null.
+/// * 2 = `out_cap` is less than the number of overlapping items, the first
+///   `out_cap` of them are written.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_aabbindex_query(
+    aabbindex: *const cavc_aabbindex,
+    min_x: f64,
+    min_y: f64,
+    max_x: f64,
+    max_y: f64,
+    out: *mut u32,
+    out_cap: u32,
+    out_count: *mut u32,
+) -> i32 {
+    ffi_catch_unwind!({
+        if aabbindex.is_null() || out_count.is_null() {
+            return 1;
+        }
+        let hits = (*aabbindex).0.query(min_x, min_y, max_x, max_y);
+        *out_count = hits.len() as u32;
+        if !out.is_null() {
+            for (i, &hit) in hits.iter().take(out_cap as usize).enumerate() {
+                *out.add(i) = hit as u32;
+            }
+        }
+        if hits.len() > out_cap as usize {
+            return 2;
+        }
+        0
+    })
+}
@@ -0,0 +1,1067 @@
+//! Intersection queries.
+use cavalier_contours::core::math::Vector2;
+use cavalier_contours::polyline::{
+    seg_bounding_box, seg_intersect, FindIntersectsOptions, PlineCreation, PlineOffsetOptions,
+    PlineSegIntr, PlineSource, PlineSourceMut, PlineVertex, Polyline,
+};
+use cavalier_contours::static_aabb2d_index::StaticAABB2DIndexBuilder;
+use cavalier_contours_ffi::{
+    cavc_aabbindex, cavc_basic_intersect, cavc_intersects_result, cavc_overlapping_intersect,
+    cavc_pline, cavc_plinelist, cavc_point, cavc_vertex,
+};
+
+use crate::chain::append;
+use crate::geom::{intr_points, seg_param_at_point, seg_sub};
+use crate::options::cavc_options;
+use crate::path::{locate, path_length_at_point, seg_lengths, sub_path_from};
+use crate::util::{
+    free_raw, from_cavc_vertex, into_raw, parallel_map, pline_into_raw, plinelist_into_raw,
+    to_cavc_point, valid_eps,
+};
+
+/// Same as `cavc_pline_find_intersects` but accepts a precomputed spatial
+/// index of `pline1`.
+///
+/// `pline1_aabbindex` must be an index of `pline1` in its current state (e.g.
+/// from `cavc_pline_create_approx_aabb_index`). It may be null in which case
+/// the index is built internally, passing it avoids rebuilding it when
+/// intersecting one polyline against many others.
+///
+/// `result` is only written to if the function returns 0 (success) and must be
+/// freed with `cavc_intersects_result_f`.
+///
+/// ## Specific Error Codes
+/// * 1 = `pline1`, `pline2` or `result` is null.
+/// * 2 = `pos_equal_eps` is negative, NaN or infinite.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_find_intersects_with_index(
+    pline1: *const cavc_pline,
+    pline2: *const cavc_pline,
+    pline1_aabbindex: *const cavc_aabbindex,
+    pos_equal_eps: f64,
+    result: *mut *mut cavc_intersects_result,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline1.is_null() || pline2.is_null() || result.is_null() {
+            return 1;
+        }
+        if !valid_eps(pos_equal_eps) {
+            return 2;
+        }
+        let mut options = FindIntersectsOptions::new();
+        options.pos_equal_eps = pos_equal_eps;
+        options.pline1_aabb_index = pline1_aabbindex.as_ref().map(|i| &i.0);
+        *result = into_raw(find_intersects(&(*pline1).0, &(*pline2).0, &options));
+        0
+    })
+}
+
+/// Find the intersects between the polylines as an FFI result.
+fn find_intersects(
+    pline1: &Polyline<f64>,
+    pline2: &Polyline<f64>,
+    options: &FindIntersectsOptions<f64>,
+) -> cavc_intersects_result {
+    let intersects = pline1.find_intersects_opt(pline2, options);
+    let basic = intersects
+        .basic_intersects
+        .iter()
+        .map(|i| cavc_basic_intersect {
+            start_index1: i.start_index1 as u32,
+            start_index2: i.start_index2 as u32,
+            point: to_cavc_point(i.point),
+        })
+        .collect();
+    let overlapping = intersects
+        .overlapping_intersects
+        .iter()
+        .map(|i| cavc_overlapping_intersect {
+            start_index1: i.start_index1 as u32,
+            start_index2: i.start_index2 as u32,
+            point1: to_cavc_point(i.point1),
+            point2: to_cavc_point(i.point2),
+        })
+        .collect();
+    cavc_intersects_result { basic, overlapping }
+}
+
+/// Same as `cavc_pline_find_intersects` but can leave out intersects at
+/// shared vertexes.
+///
+/// If `exclude_vertex_touches` is not 0 basic intersects within
+/// `pos_equal_eps` of an end point of their segment on both polylines are
+/// dropped, e.g. where chained polylines meet at a common end point. This
+/// also drops crossings that happen exactly at a vertex of both polylines.
+/// Overlapping intersects are always kept. With `exclude_vertex_touches` set
+/// to 0 this is the same as `cavc_pline_find_intersects`.
+///
+/// `result` is only written to if the function returns 0 (success) and must be
+/// freed with `cavc_intersects_result_f`.
+///
+/// ## Specific Error Codes
+/// * 1 = `pline1`, `pline2` or `result` is null.
+/// * 2 = `pos_equal_eps` is negative, NaN or infinite.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_find_intersects_ex(
+    pline1: *const cavc_pline,
+    pline2: *const cavc_pline,
+    pos_equal_eps: f64,
+    exclude_vertex_touches: u8,
+    result: *mut *mut cavc_intersects_result,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline1.is_null() || pline2.is_null() || result.is_null() {
+            return 1;
+        }
+        if !valid_eps(pos_equal_eps) {
+            return 2;
+        }
+        let pline1 = &(*pline1).0;
+        let pline2 = &(*pline2).0;
+        let mut options = FindIntersectsOptions::new();
+        options.pos_equal_eps = pos_equal_eps;
+        let mut intersects = find_intersects(pline1, pline2, &options);
+        if exclude_vertex_touches != 0 {
+            let at_seg_end = |pline: &Polyline<f64>, i: u32, p: Vector2<f64>| {
+                let i = i as usize;
+                let v1 = pline.at(i).pos();
+                let v2 = pline.at(pline.next_wrapping_index(i)).pos();
+                p.fuzzy_eq_eps(v1, pos_equal_eps) || p.fuzzy_eq_eps(v2, pos_equal_eps)
+            };
+            intersects.basic.retain(|intr| {
+                let p = Vector2::new(intr.point.x, intr.point.y);
+                !(at_seg_end(pline1, intr.start_index1, p)
+                    && at_seg_end(pline2, intr.start_index2, p))
+            });
+        }
+        *result = into_raw(intersects);
+        0
+    })
+}
+
+/// Same as `cavc_pline_find_intersects` but uses the `pos_equal_eps` of the
+/// shared `options`, which may be null in which case defaults are used.
+///
+/// `result` is only written to if the function returns 0 (success) and must be
+/// freed with `cavc_intersects_result_f`.
+///
+/// ## Specific Error Codes
+/// * 1 = `pline1`, `pline2` or `result` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_find_intersects_opt(
+    pline1: *const cavc_pline,
+    pline2: *const cavc_pline,
+    options: *const cavc_options,
+    result: *mut *mut cavc_intersects_result,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline1.is_null() || pline2.is_null() || result.is_null() {
+            return 1;
+        }
+        let mut internal = FindIntersectsOptions::new();
+        internal.pos_equal_eps = cavc_options::or_default(options).pos_equal_eps;
+        *result = into_raw(find_intersects(&(*pline1).0, &(*pline2).0, &internal));
+        0
+    })
+}
+
+/// Call `visitor` with every self intersection of `pline` as
+/// `(segment index 1, segment index 2, intersection)` where index 1 is less
+/// than index 2. Intersections of adjacent segments at their shared vertex are
+/// not reported. Stops early if `visitor` returns false.
+pub(crate) fn visit_self_intersects(
+    pline: &Polyline<f64>,
+    pos_equal_eps: f64,
+    mut visitor: impl FnMut(usize, usize, PlineSegIntr<f64>) -> bool,
+) {
+    let seg_count = pline.segment_count();
+    if seg_count < 2 {
+        return;
+    }
+    let index = pline.create_approx_aabb_index();
+    let vertex_count = pline.vertex_count();
+    let seg = |i: usize| (pline.at(i), pline.at((i + 1) % vertex_count));
+    let at_shared_vertex = |i: usize, j: usize, p: Vector2<f64>| {
+        let (v1, v2) = seg(i);
+        let (u1, u2) = seg(j);
+        let shared = |a: PlineVertex<f64>, b: PlineVertex<f64>| {
+            a.pos().fuzzy_eq_eps(b.pos(), pos_equal_eps) && p.fuzzy_eq_eps(a.pos(), pos_equal_eps)
+        };
+        (j == i + 1 && shared(v2, u1)) || (i == 0 && j == seg_count - 1 && shared(v1, u2))
+    };
+
+    for i in 0..seg_count {
+        let (v1, v2) = seg(i);
+        let bb = seg_bounding_box(v1, v2);
+        let mut candidates = index.query(
+            bb.min_x - pos_equal_eps,
+            bb.min_y - pos_equal_eps,
+            bb.max_x + pos_equal_eps,
+            bb.max_y + pos_equal_eps,
+        );
+        candidates.sort_unstable();
+        for j in candidates.into_iter().filter(|&j| j > i) {
+            let (u1, u2) = seg(j);
+            let intr = match seg_intersect(v1, v2, u1, u2, pos_equal_eps) {
+                PlineSegIntr::NoIntersect => continue,
+                PlineSegIntr::TangentIntersect { point } | PlineSegIntr::OneIntersect { point } => {
+                    if at_shared_vertex(i, j, point) {
+                        continue;
+                    }
+                    PlineSegIntr::OneIntersect { point }
+                }
+                PlineSegIntr::TwoIntersects { point1, point2 } => {
+                    match (
+                        at_shared_vertex(i, j, point1),
+                        at_shared_vertex(i, j, point2),
+                    ) {
+                        (true, true) => continue,
+                        (true, false) => PlineSegIntr::OneIntersect { point: point2 },
+                        (false, true) => PlineSegIntr::OneIntersect { point: point1 },
+                        (false, false) => PlineSegIntr::TwoIntersects { point1, point2 },
+                    }
+                }
+                overlap => overlap,
+            };
+            if !visitor(i, j, intr) {
+                return;
+            }
+        }
+    }
+}
+
+/// Test if the polyline intersects itself.
+///
+/// Adjacent segments touching at their shared vertex do not count, segments
+/// folding back over each other do. `found` is set to 1 if a self
+/// intersection exists and 0 otherwise, the scan stops at the first one found.
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `found` is null.
+/// * 2 = `pos_equal_eps` is negative, NaN or infinite.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_scan_for_self_intersect(
+    pline: *const cavc_pline,
+    pos_equal_eps: f64,
+    found: *mut u8,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || found.is_null() {
+            return 1;
+        }
+        if !valid_eps(pos_equal_eps) {
+            return 2;
+        }
+        let mut any = false;
+        visit_self_intersects(&(*pline).0, pos_equal_eps, |_, _, _| {
+            any = true;
+            false
+        });
+        *found = any as u8;
+        0
+    })
+}
+
+/// Find all self intersections of the polyline.
+///
+/// Every intersection is reported once with `start_index1` less than
+/// `start_index2`. Segments lying on top of each other are reported as
+/// overlapping intersections. Adjacent segments touching at their shared
+/// vertex are not reported.
+///
+/// `result` is only written to if the function returns 0 (success) and must be
+/// freed with `cavc_intersects_result_f`.
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `result` is null.
+/// * 2 = `pos_equal_eps` is negative, NaN or infinite.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_find_self_intersects(
+    pline: *const cavc_pline,
+    pos_equal_eps: f64,
+    result: *mut *mut cavc_intersects_result,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || result.is_null() {
+            return 1;
+        }
+        if !valid_eps(pos_equal_eps) {
+            return 2;
+        }
+        let mut basic = Vec::new();
+        let mut overlapping = Vec::new();
+        visit_self_intersects(&(*pline).0, pos_equal_eps, |i, j, intr| {
+            let basic_intersect = |point| cavc_basic_intersect {
+                start_index1: i as u32,
+                start_index2: j as u32,
+                point: to_cavc_point(point),
+            };
+            match intr {
+                PlineSegIntr::NoIntersect => {}
+                PlineSegIntr::TangentIntersect { point } | PlineSegIntr::OneIntersect { point } => {
+                    basic.push(basic_intersect(point));
+                }
+                PlineSegIntr::TwoIntersects { point1, point2 } => {
+                    basic.push(basic_intersect(point1));
+                    basic.push(basic_intersect(point2));
+                }
+                PlineSegIntr::OverlappingLines { point1, point2 }
+                | PlineSegIntr::OverlappingArcs { point1, point2 } => {
+                    overlapping.push(cavc_overlapping_intersect {
+                        start_index1: i as u32,
+                        start_index2: j as u32,
+                        point1: to_cavc_point(point1),
+                        point2: to_cavc_point(point2),
+                    });
+                }
+            }
+            true
+        });
+        *result = into_raw(cavc_intersects_result { basic, overlapping });
+        0
+    })
+}
+
+/// Cut the closed `pline` at all of its self intersections and reassemble the
+/// pieces into loops which do not intersect themselves.
+///
+/// The path is walked from one intersection to the next, whenever it returns
+/// to an intersection already on the current walk the part since then is cut
+/// out as a loop. Loops thinner than `pos_equal_eps` (e.g. from segments
+/// folding back over each other) are dropped.
+fn split_self_intersections(pline: &Polyline<f64>, pos_equal_eps: f64) -> Vec<Polyline<f64>> {
+    let lengths = seg_lengths(pline);
+    let total: f64 = lengths.iter().sum();
+    // every visit of an intersection point as (path length, node)
+    let mut nodes: Vec<Vector2<f64>> = Vec::new();
+    let mut events: Vec<(f64, usize)> = Vec::new();
+    visit_self_intersects(pline, pos_equal_eps, |i, j, intr| {
+        for point in intr_points(intr) {
+            let node = match nodes
+                .iter()
+                .position(|n| n.fuzzy_eq_eps(point, pos_equal_eps))
+            {
+                Some(node) => node,
+                None => {
+                    nodes.push(point);
+                    nodes.len() - 1
+                }
+            };
+            for seg_index in [i, j] {
+                let s = path_length_at_point(pline, &lengths, seg_index, point, pos_equal_eps);
+                events.push((s, node));
+            }
+        }
+        true
+    });
+    events.sort_by(|a, b| a.0.total_cmp(&b.0));
+    events.dedup_by(|b, a| a.1 == b.1 && (b.0 - a.0).abs() <= pos_equal_eps);
+    if events.len() > 1 {
+        let (first, last) = (events[0], events[events.len() - 1]);
+        if first.1 == last.1 && total - last.0 + first.0 <= pos_equal_eps {
+            events.pop();
+        }
+    }
+    if events.len() < 2 {
+        return vec![pline.clone()];
+    }
+
+    let piece = |k: usize| {
+        let start = events[k].0;
+        let end = match events.get(k + 1) {
+            Some(e) => e.0,
+            None => events[0].0 + total,
+        };
+        sub_path_from(pline, &lengths, locate(&lengths, start), end - start)
+    };
+    let mut result = Vec::new();
+    let mut walk_nodes = vec![events[0].1];
+    let mut walk_pieces: Vec<Polyline<f64>> = Vec::new();
+    for k in 0..events.len() {
+        walk_pieces.push(piece(k));
+        let next = events[(k + 1) % events.len()].1;
+        let p = match walk_nodes.iter().position(|&n| n == next) {
+            Some(p) => p,
+            None => {
+                walk_nodes.push(next);
+                continue;
+            }
+        };
+        walk_nodes.truncate(p + 1);
+        let mut pieces = walk_pieces.drain(p..);
+        // always some, the walk has a piece for every node after the first
+        let mut chain = pieces.next().unwrap();
+        for piece in pieces {
+            append(&mut chain, &piece);
+        }
+        if chain.vertex_count() > 2 {
+            chain.remove_last();
+            chain.set_is_closed(true);
+            if chain.area().abs() > pos_equal_eps * chain.path_length() {
+                result.push(chain);
+            }
+        }
+    }
+    result
+}
+
+/// Split a closed polyline at all of its self intersections into loops which
+/// do not intersect themselves, the usual step to make a polyline valid
+/// before boolean operations.
+///
+/// The pieces between intersections are reassembled by following the path
+/// and cutting out a loop every time it returns to an intersection, so a
+/// figure eight becomes its two lobes. Every loop keeps the direction of its
+/// part of the path, loops may therefore have different orientations and may
+/// touch each other at the former intersection points. Loops thinner than
+/// `pos_equal_eps` (e.g. from segments folding back over each other) are
+/// dropped. A polyline without self intersections is returned as its only
+/// loop.
+///
+/// `result_plinelist` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `result_plinelist` is null.
+/// * 2 = `pline` is not closed.
+/// * 3 = `pos_equal_eps` is negative, NaN or infinite.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_split_self_intersections(
+    pline: *const cavc_pline,
+    pos_equal_eps: f64,
+    result_plinelist: *mut *mut cavc_plinelist,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || result_plinelist.is_null() {
+            return 1;
+        }
+        if !valid_eps(pos_equal_eps) {
+            return 3;
+        }
+        let pline = &(*pline).0;
+        if !pline.is_closed() {
+            return 2;
+        }
+        *result_plinelist = plinelist_into_raw(split_self_intersections(pline, pos_equal_eps));
+        0
+    })
+}
+
+/// Get a pointer to the basic intersects of the result and their count.
+///
+/// The pointer refers to the internal contiguous array, so the intersects can
+/// be read (e.g. wrapped as a numpy structured array) without copying them.
+/// It stays valid until `result` is freed. `data` may receive a dangling (but
+/// non-null) pointer if the count is 0.
+///
+/// ## Specific Error Codes
+/// * 1 = `result`, `data` or `count` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_intersects_result_basic_data(
+    result: *const cavc_intersects_result,
+    data: *mut *const cavc_basic_intersect,
+    count: *mut u32,
+) -> i32 {
+    ffi_catch_unwind!({
+        if result.is_null() || data.is_null() || count.is_null() {
+            return 1;
+        }
+        let basic = &(*result).basic;
+        *data = basic.as_ptr();
+        *count = basic.len() as u32;
+        0
+    })
+}
+
+/// Basic intersect between two polylines with the position of the point on
+/// both segments, see [cavc_intersects_result_basic_data_ex].
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct cavc_basic_intersect_ex {
+    /// Index of the segment of the first polyline.
+    pub start_index1: u32,
+    /// Index of the segment of the second polyline.
+    pub start_index2: u32,
+    /// Intersection point.
+    pub point: cavc_point,
+    /// Parameter of the point on the segment of the first polyline, the
+    /// fraction of its arc length from the start vertex.
+    pub t1: f64,
+    /// Parameter of the point on the segment of the second polyline.
+    pub t2: f64,
+    /// 0 if the segments cross at the point and 1 if they only touch
+    /// (tangent).
+    pub kind: u8,
+}
+
+/// Copy the basic intersects of the result into `out` extended with the
+/// parameters of the point on both segments and whether the segments cross
+/// or touch there.
+///
+/// The result only holds the segment indexes and points, so `pline1` and
+/// `pline2` must be the polylines the intersects were found with (in the same
+/// state). The segments are intersected again with `pos_equal_eps` to
+/// classify each point. `out` must have room for `out_cap` values;
+/// `out_count` receives the number of basic intersects even when `out` is too
+/// small, so the caller can size the buffer and call again. `out` may be null
+/// if `out_cap` is 0.
+///
+/// ## Specific Error Codes
+/// * 1 = `result`, `pline1`, `pline2` or `out_count` is null.
+/// * 2 = `out_cap` is less than the number of basic intersects.
+/// * 3 = a segment index of an intersect is not a segment of its polyline.
+/// * 4 = `pos_equal_eps` is negative, NaN or infinite.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_intersects_result_basic_data_ex(
+    result: *const cavc_intersects_result,
+    pline1: *const cavc_pline,
+    pline2: *const cavc_pline,
+    pos_equal_eps: f64,
+    out: *mut cavc_basic_intersect_ex,
+    out_cap: u32,
+    out_count: *mut u32,
+) -> i32 {
+    ffi_catch_unwind!({
+        if result.is_null() || pline1.is_null() || pline2.is_null() || out_count.is_null() {
+            return 1;
+        }
+        if !valid_eps(pos_equal_eps) {
+            return 4;
+        }
+        let basic = &(*result).basic;
+        *out_count = basic.len() as u32;
+        if basic.len() > out_cap as usize || (out.is_null() && !basic.is_empty()) {
+            return 2;
+        }
+        let pline1 = &(*pline1).0;
+        let pline2 = &(*pline2).0;
+        let seg = |pline: &Polyline<f64>, i: u32| {
+            let i = i as usize;
+            (i < pline.segment_count())
+                .then(|| (pline.at(i), pline.at(pline.next_wrapping_index(i))))
+        };
+        for (k, intr) in basic.iter().enumerate() {
+            let ((v1, v2), (u1, u2)) = match (
+                seg(pline1, intr.start_index1),
+                seg(pline2, intr.start_index2),
+            ) {
+                (Some(s1), Some(s2)) => (s1, s2),
+                _ => return 3,
+            };
+            let point = Vector2::new(intr.point.x, intr.point.y);
+            let is_tangent = matches!(
+                seg_intersect(v1, v2, u1, u2, pos_equal_eps),
+                PlineSegIntr::TangentIntersect { .. }
+            );
+            *out.add(k) = cavc_basic_intersect_ex {
+                start_index1: intr.start_index1,
+                start_index2: intr.start_index2,
+                point: intr.point,
+                t1: seg_param_at_point(v1, v2, point),
+                t2: seg_param_at_point(u1, u2, point),
+                kind: is_tangent as u8,
+            };
+        }
+        0
+    })
+}
+
+/// Get a pointer to the overlapping intersects of the result and their count,
+/// see [cavc_intersects_result_basic_data].
+///
+/// ## Specific Error Codes
+/// * 1 = `result`, `data` or `count` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_intersects_result_overlapping_data(
+    result: *const cavc_intersects_result,
+    data: *mut *const cavc_overlapping_intersect,
+    count: *mut u32,
+) -> i32 {
+    ffi_catch_unwind!({
+        if result.is_null() || data.is_null() || count.is_null() {
+            return 1;
+        }
+        let overlapping = &(*result).overlapping;
+        *data = overlapping.as_ptr();
+        *count = overlapping.len() as u32;
+        0
+    })
+}
+
+/// Get the geometry shared by the two polylines of the overlapping intersect
+/// at `index` as a new open polyline.
+///
+/// The result only holds the end points of the overlap, so `pline1` must be
+/// the first polyline the intersects were found with (in the same state). The
+/// overlap lies on segment `start_index1` of it and is returned as the part of
+/// that segment between the end points, running in the direction of
+/// `pline1`, arcs are cut exactly.
+///
+/// `out_pline` is only written to if the function returns 0 (success) and
+/// must be freed with `cavc_pline_f`.
+///
+/// ## Specific Error Codes
+/// * 1 = `result`, `pline1` or `out_pline` is null.
+/// * 2 = `index` is out of bounds.
+/// * 3 = `start_index1` of the intersect is not a segment of `pline1`.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_intersects_result_get_overlapping_pline(
+    result: *const cavc_intersects_result,
+    pline1: *const cavc_pline,
+    index: u32,
+    out_pline: *mut *mut cavc_pline,
+) -> i32 {
+    ffi_catch_unwind!({
+        if result.is_null() || pline1.is_null() || out_pline.is_null() {
+            return 1;
+        }
+        let overlap = match (*result).overlapping.get(index as usize) {
+            Some(o) => o,
+            None => return 2,
+        };
+        let pline1 = &(*pline1).0;
+        let seg_index = overlap.start_index1 as usize;
+        if seg_index >= pline1.segment_count() {
+            return 3;
+        }
+        let v1 = pline1.at(seg_index);
+        let v2 = pline1.at(pline1.next_wrapping_index(seg_index));
+        let param = |p: cavc_point| seg_param_at_point(v1, v2, Vector2::new(p.x, p.y));
+        let (t1, t2) = (param(overlap.point1), param(overlap.point2));
+        let (start, end) = seg_sub(v1, v2, t1.min(t2), t1.max(t2));
+        let mut shared = Polyline::with_capacity(2, false);
+        shared.add_vertex(start);
+        shared.add_vertex(PlineVertex::from_vector2(end, 0.0));
+        *out_pline = pline_into_raw(shared);
+        0
+    })
+}
+
+/// Intersect of a polyline with a standalone shape (line, ray, circle or
+/// segment).
+///
+/// `kind` is 0 where the shape crosses the segment, 1 where it only touches
+/// it (tangent) and 2 for an end point of a part where the shape and the
+/// segment overlap. `param` locates the point on the shape, its meaning is
+/// described by the function producing the intersects.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct cavc_shape_intersect {
+    /// Intersection point.
+    pub point: cavc_point,
+    /// Index of the polyline segment (its start vertex) the point lies on.
+    pub seg_index: u32,
+    /// Parameter of the point on the polyline segment, the fraction of its
+    /// arc length from the start vertex.
+    pub seg_param: f64,
+    /// Parameter of the point on the shape.
+    pub param: f64,
+    /// Classification of the intersect, see above.
+    pub kind: u8,
+}
+
+/// Opaque type holding a list of [cavc_shape_intersect].
+pub struct cavc_shape_intersects(Vec<cavc_shape_intersect>);
+
+/// Intersect every segment of `pline` with the segments `shape` forming a
+/// standalone shape. `param` maps an intersection point to its parameter on
+/// the shape. Points found on several shape segments (e.g. where they join)
+/// are only reported once per polyline segment. The result is ordered by
+/// segment index and parameter on the segment.
+fn shape_intersects(
+    pline: &Polyline<f64>,
+    shape: &[(PlineVertex<f64>, PlineVertex<f64>)],
+    pos_equal_eps: f64,
+    param: impl Fn(Vector2<f64>) -> f64,
+) -> Vec<cavc_shape_intersect> {
+    let mut result: Vec<cavc_shape_intersect> = Vec::new();
+    if pline.segment_count() == 0 {
+        return result;
+    }
+    let index = pline.create_approx_aabb_index();
+    for &(u1, u2) in shape {
+        let bb = seg_bounding_box(u1, u2);
+        let mut candidates = index.query(
+            bb.min_x - pos_equal_eps,
+            bb.min_y - pos_equal_eps,
+            bb.max_x + pos_equal_eps,
+            bb.max_y + pos_equal_eps,
+        );
+        candidates.sort_unstable();
+        for i in candidates {
+            let v1 = pline.at(i);
+            let v2 = pline.at(pline.next_wrapping_index(i));
+            let intr = seg_intersect(v1, v2, u1, u2, pos_equal_eps);
+            let kind = match intr {
+                PlineSegIntr::NoIntersect => continue,
+                PlineSegIntr::TangentIntersect { .. } => 1,
+                PlineSegIntr::OneIntersect { .. } | PlineSegIntr::TwoIntersects { .. } => 0,
+                PlineSegIntr::OverlappingLines { .. } | PlineSegIntr::OverlappingArcs { .. } => 2,
+            };
+            for point in intr_points(intr) {
+                let seen = result.iter().any(|r| {
+                    r.seg_index == i as u32
+                        && Vector2::new(r.point.x, r.point.y).fuzzy_eq_eps(point, pos_equal_eps)
+                });
+                if seen {
+                    continue;
+                }
+                result.push(cavc_shape_intersect {
+                    point: to_cavc_point(point),
+                    seg_index: i as u32,
+                    seg_param: seg_param_at_point(v1, v2, point),
+                    param: param(point),
+                    kind,
+                });
+            }
+        }
+    }
+    result.sort_by(|a, b| {
+        a.seg_index
+            .cmp(&b.seg_index)
+            .then(a.seg_param.total_cmp(&b.seg_param))
+    });
+    result
+}
+
+/// Find the intersects of the polyline with the line through `(px, py)` in
+/// the direction `(dx, dy)`, or with the ray starting at `(px, py)` if
+/// `is_ray` is non-zero.
+///
+/// The `param` of every intersect is `s` such that the point is
+/// `(px, py) + s * (dx, dy)`, intersects are ordered by it. A point where the
+/// line passes through a vertex is reported for both segments sharing it.
+///
+/// `result` is only written to if the function returns 0 (success) and must be
+/// freed with [cavc_shape_intersects_f].
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `result` is null.
+/// * 2 = `(dx, dy)` is zero or not finite.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_intersect_line(
+    pline: *const cavc_pline,
+    px: f64,
+    py: f64,
+    dx: f64,
+    dy: f64,
+    is_ray: u8,
+    result: *mut *mut cavc_shape_intersects,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || result.is_null() {
+            return 1;
+        }
+        let dir = Vector2::new(dx, dy);
+        let len_sq = dir.dot(dir);
+        if len_sq == 0.0 || !len_sq.is_finite() {
+            return 2;
+        }
+        let pline = &(*pline).0;
+        let origin = Vector2::new(px, py);
+        let pos_equal_eps = PlineOffsetOptions::<f64>::new().pos_equal_eps;
+        let mut intersects = Vec::new();
+        // clip the line to (slightly more than) the extents of the polyline
+        // so it can be intersected as a regular line segment
+        if let Some(extents) = pline.extents() {
+            let margin = 1.0 + pos_equal_eps;
+            let mut range = if is_ray != 0 {
+                (0.0, f64::INFINITY)
+            } else {
+                (f64::NEG_INFINITY, f64::INFINITY)
+            };
+            for (p, d, min, max) in [
+                (px, dx, extents.min_x - margin, extents.max_x + margin),
+                (py, dy, extents.min_y - margin, extents.max_y + margin),
+            ] {
+                if d == 0.0 {
+                    if p < min || p > max {
+                        range = (1.0, 0.0);
+                    }
+                    continue;
+                }
+                let (s0, s1) = ((min - p) / d, (max - p) / d);
+                range.0 = range.0.max(s0.min(s1));
+                range.1 = range.1.min(s0.max(s1));
+            }
+            if range.0 <= range.1 {
+                let start = PlineVertex::from_vector2(origin + dir.scale(range.0), 0.0);
+                let end = PlineVertex::from_vector2(origin + dir.scale(range.1), 0.0);
+                intersects =
+                    shape_intersects(pline, &[(start, end)], pos_equal_eps, |p: Vector2<f64>| {
+                        (p - origin).dot(dir) / len_sq
+                    });
+                intersects.sort_by(|a, b| a.param.total_cmp(&b.param));
+            }
+        }
+        *result = into_raw(cavc_shape_intersects(intersects));
+        0
+    })
+}
+
+/// Find the intersects of the polyline with the circle of `radius` around
+/// `(cx, cy)`.
+///
+/// The `param` of every intersect is the angle of the point around the
+/// center in radians, in the range [-pi, pi]. Segments touching the circle
+/// are reported with `kind` 1 and arcs lying on the circle with `kind` 2 at
+/// both ends of the overlap. Intersects are ordered by segment index and
+/// parameter on the segment, so the first one is the first contact along the
+/// polyline path.
+///
+/// `result` is only written to if the function returns 0 (success) and must be
+/// freed with [cavc_shape_intersects_f].
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `result` is null.
+/// * 2 = `radius` is not greater than 0.
+/// * 3 = `pos_equal_eps` is negative, NaN or infinite.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_intersect_circle(
+    pline: *const cavc_pline,
+    cx: f64,
+    cy: f64,
+    radius: f64,
+    pos_equal_eps: f64,
+    result: *mut *mut cavc_shape_intersects,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || result.is_null() {
+            return 1;
+        }
+        if !valid_eps(pos_equal_eps) {
+            return 3;
+        }
+        if radius.is_nan() || radius <= 0.0 {
+            return 2;
+        }
+        // the circle as two half circle arcs
+        let right = PlineVertex::new(cx + radius, cy, 1.0);
+        let left = PlineVertex::new(cx - radius, cy, 1.0);
+        let intersects = shape_intersects(
+            &(*pline).0,
+            &[(right, left), (left, right)],
+            pos_equal_eps,
+            |p: Vector2<f64>| (p.y - cy).atan2(p.x - cx),
+        );
+        *result = into_raw(cavc_shape_intersects(intersects));
+        0
+    })
+}
+
+/// Find the intersects of the polyline with the single segment from `v1` to
+/// `v2`, `v1` holding the bulge.
+///
+/// The `param` of every intersect is its parameter on the segment, the
+/// fraction of its arc length from `v1`. Intersects are ordered by polyline
+/// segment index and parameter on the polyline segment.
+///
+/// `result` is only written to if the function returns 0 (success) and must be
+/// freed with [cavc_shape_intersects_f].
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `result` is null.
+/// * 2 = `pos_equal_eps` is negative, NaN or infinite.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_intersect_segment(
+    pline: *const cavc_pline,
+    v1: cavc_vertex,
+    v2: cavc_vertex,
+    pos_equal_eps: f64,
+    result: *mut *mut cavc_shape_intersects,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || result.is_null() {
+            return 1;
+        }
+        if !valid_eps(pos_equal_eps) {
+            return 2;
+        }
+        let u1 = from_cavc_vertex(v1);
+        let u2 = PlineVertex::new(v2.x, v2.y, 0.0);
+        let intersects = shape_intersects(&(*pline).0, &[(u1, u2)], pos_equal_eps, |p| {
+            seg_param_at_point(u1, u2, p)
+        });
+        *result = into_raw(cavc_shape_intersects(intersects));
+        0
+    })
+}
+
+/// Free an existing [cavc_shape_intersects] object. Nothing happens if
+/// `intersects` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_shape_intersects_f(intersects: *mut cavc_shape_intersects) {
+    free_raw(intersects);
+}
+
+/// Get a pointer to the intersects and their count, see
+/// [cavc_intersects_result_basic_data].
+///
+/// ## Specific Error Codes
+/// * 1 = `intersects`, `data` or `count` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_shape_intersects_data(
+    intersects: *const cavc_shape_intersects,
+    data: *mut *const cavc_shape_intersect,
+    count: *mut u32,
+) -> i32 {
+    ffi_catch_unwind!({
+        if intersects.is_null() || data.is_null() || count.is_null() {
+            return 1;
+        }
+        let intersects = &(*intersects).0;
+        *data = intersects.as_ptr();
+        *count = intersects.len() as u32;
+        0
+    })
+}
+
+/// Opaque type holding the result of [cavc_plinelist_find_all_intersects],
+/// the list indexes of every intersecting pair with their intersects.
+pub struct cavc_plinelist_intersects(Vec<(u32, u32, cavc_intersects_result)>);
+
+/// Find the intersects of every pair of `plines` whose extents (grown by
+/// `pos_equal_eps`) overlap, keeping the pairs that intersect.
+pub(crate) fn find_all_intersects(
+    plines: &[&Polyline<f64>],
+    pos_equal_eps: f64,
+) -> Vec<(u32, u32, cavc_intersects_result)> {
+    let extents: Vec<_> = plines
+        .iter()
+        .enumerate()
+        .filter_map(|(i, p)| Some((i, p.extents()?)))
+        .collect();
+    let mut builder = StaticAABB2DIndexBuilder::new(extents.len());
+    for (_, e) in &extents {
+        builder.add(
+            e.min_x - pos_equal_eps,
+            e.min_y - pos_equal_eps,
+            e.max_x + pos_equal_eps,
+            e.max_y + pos_equal_eps,
+        );
+    }
+    let index = match builder.build() {
+        Ok(index) => index,
+        Err(_) => return Vec::new(),
+    };
+    let mut candidates = Vec::new();
+    for (k, (i, e)) in extents.iter().enumerate() {
+        for j in index.query(
+            e.min_x - pos_equal_eps,
+            e.min_y - pos_equal_eps,
+            e.max_x + pos_equal_eps,
+            e.max_y + pos_equal_eps,
+        ) {
+            if j > k {
+                candidates.push((*i, extents[j].0));
+            }
+        }
+    }
+    candidates.sort_unstable();
+
+    let mut options = FindIntersectsOptions::new();
+    options.pos_equal_eps = pos_equal_eps;
+    let results = parallel_map(candidates.len(), |c| {
+        let (i, j) = candidates[c];
+        find_intersects(plines[i], plines[j], &options)
+    });
+    candidates
+        .into_iter()
+        .zip(results)
+        .filter(|(_, r)| !r.basic.is_empty() || !r.overlapping.is_empty())
+        .map(|((i, j), r)| (i as u32, j as u32, r))
+        .collect()
+}
+
+/// Find every pair of intersecting polylines of a list and their intersects.
+///
+/// Candidate pairs are the polylines whose extents overlap (found with a
+/// spatial index), the candidates are then intersected concurrently on all
+/// available cores the same way as `cavc_pline_find_intersects`. Only pairs
+/// with at least one intersect are part of the result, ordered by the first
+/// and then the second list index, the first index always being the smaller
+/// one. Self intersections of the polylines are not reported.
+///
+/// `result` is only written to if the function returns 0 (success) and must be
+/// freed with [cavc_plinelist_intersects_f].
+///
+/// ## Specific Error Codes
+/// * 1 = `plinelist` or `result` is null.
+/// * 2 = `pos_equal_eps` is negative or not finite.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_plinelist_find_all_intersects(
+    plinelist: *const cavc_plinelist,
+    pos_equal_eps: f64,
+    result: *mut *mut cavc_plinelist_intersects,
+) -> i32 {
+    ffi_catch_unwind!({
+        if plinelist.is_null() || result.is_null() {
+            return 1;
+        }
+        if !valid_eps(pos_equal_eps) {
+            return 2;
+        }
+        let plines: Vec<&Polyline<f64>> = (*plinelist).0.iter().map(|&p| &(*p).0).collect();
+        let pairs = find_all_intersects(&plines, pos_equal_eps);
+        *result = into_raw(cavc_plinelist_intersects(pairs));
+        0
+    })
+}
+
+/// Free an existing [cavc_plinelist_intersects] object. Nothing happens if
+/// `intersects` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_plinelist_intersects_f(intersects: *mut cavc_plinelist_intersects) {
+    free_raw(intersects);
+}
+
+/// Get the number of intersecting pairs.
+///
+/// ## Specific Error Codes
+/// * 1 = `intersects` or `count` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_plinelist_intersects_get_count(
+    intersects: *const cavc_plinelist_intersects,
+    count: *mut u32,
+) -> i32 {
+    ffi_catch_unwind!({
+        if intersects.is_null() || count.is_null() {
+            return 1;
+        }
+        *count = (*intersects).0.len() as u32;
+        0
+    })
+}
+
+/// Get the intersecting pair at `position`: the list indexes of both
+/// polylines and their intersects.
+///
+/// `result` receives a pointer to the intersects of the pair, to be read with
+/// the `cavc_intersects_result_*` functions (polyline 1 being the one at
+/// `index1`). It is owned by `intersects` and stays valid until `intersects`
+/// is freed, it must not be freed with `cavc_intersects_result_f`.
+///
+/// ## Specific Error Codes
+/// * 1 = `intersects`, `index1`, `index2` or `result` is null.
+/// * 2 = `position` is out of bounds.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_plinelist_intersects_get_pair(
+    intersects: *const cavc_plinelist_intersects,
+    position: u32,
+    index1: *mut u32,
+    index2: *mut u32,
+    result: *mut *const cavc_intersects_result,
+) -> i32 {
+    ffi_catch_unwind!({
+        if intersects.is_null() || index1.is_null() || index2.is_null() || result.is_null() {
+            return 1;
+        }
+        let (i, j, pair) = match (*intersects).0.get(position as usize) {
+            Some(pair) => pair,
+            None => return 2,
+        };
+        *index1 = *i;
+        *index2 = *j;
+        *result = pair;
+        0
+    })
+}
@@ -0,0 +1,607 @@
+//! JSON serialization of polylines.
+//!
+//! A polyline is represented as
+//! `{"is_closed": bool, "vertices": [[x, y, bulge], ...]}` and a polyline list
+//! as a JSON array of polylines. Numbers are
+//! written using the shortest representation that parses back to the exact
+//! same `f64`, so a round trip through JSON is lossless.
+use std::ffi::CStr;
+use std::fmt::Write;
+use std::os::raw::c_char;
+
+use cavalier_contours::polyline::{PlineCreation, PlineSource, PlineSourceMut, Polyline};
+use cavalier_contours_ffi::{cavc_pline, cavc_plinelist};
+
+use crate::error::set_last_error;
+use crate::util::{pline_into_raw, plinelist_into_raw, write_c_string};
+
+/// Parsed JSON value.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    /// Look up `key` if this value is an object.
+    pub(crate) fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Object(members) => members.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+}
+
+/// Maximum nesting depth of arrays and objects accepted by [parse], deeper
+/// documents are rejected before they can overflow the stack.
+pub(crate) const MAX_DEPTH: usize = 256;
+
+/// Error returned by [parse].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ParseError {
+    /// The input is not valid JSON.
+    Invalid,
+    /// Arrays and objects are nested deeper than [MAX_DEPTH].
+    TooDeep,
+}
+
+/// Parse a complete JSON document.
+pub(crate) fn parse(input: &str) -> Result<Value, ParseError> {
+    let mut parser = Parser {
+        bytes: input.as_bytes(),
+        pos: 0,
+        depth: 0,
+    };
+    let value = parser.parse_value()?;
+    parser.skip_whitespace();
+    if parser.pos != parser.bytes.len() {
+        return Err(ParseError::Invalid);
+    }
+    Ok(value)
+}
+
+struct Parser<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    /// Number of arrays and objects currently being parsed.
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), ParseError> {
+        self.skip_whitespace();
+        if self.peek() != Some(byte) {
+            return Err(ParseError::Invalid);
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn expect_literal(&mut self, literal: &[u8]) -> Result<(), ParseError> {
+        if !self.bytes[self.pos..].starts_with(literal) {
+            return Err(ParseError::Invalid);
+        }
+        self.pos += literal.len();
+        Ok(())
+    }
+
+    fn parse_value(&mut self) -> Result<Value, ParseError> {
+        self.skip_whitespace();
+        match self.peek().ok_or(ParseError::Invalid)? {
+            b'n' => self.expect_literal(b"null").map(|_| Value::Null),
+            b't' => self.expect_literal(b"true").map(|_| Value::Bool(true)),
+            b'f' => self.expect_literal(b"false").map(|_| Value::Bool(false)),
+            b'"' => self.parse_string().map(Value::String),
+            open @ (b'[' | b'{') => {
+                if self.depth == MAX_DEPTH {
+                    return Err(ParseError::TooDeep);
+                }
+                self.depth += 1;
+                let value = if open == b'[' {
+                    self.parse_array()
+                } else {
+                    self.parse_object()
+                };
+                self.depth -= 1;
+                value
+            }
+            b'-' | b'0'..=b'9' => self.parse_number().map(Value::Number),
+            _ => Err(ParseError::Invalid),
+        }
+    }
+
+    fn parse_array(&mut self) -> Result<Value, ParseError> {
+        self.expect(b'[')?;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b']') {
+            self.pos += 1;
+            return Ok(Value::Array(items));
+        }
+        loop {
+            items.push(self.parse_value()?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b']') => {
+                    self.pos += 1;
+                    return Ok(Value::Array(items));
+                }
+                _ => return Err(ParseError::Invalid),
+            }
+        }
+    }
+
+    fn parse_object(&mut self) -> Result<Value, ParseError> {
+        self.expect(b'{')?;
+        let mut members = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b'}') {
+            self.pos += 1;
+            return Ok(Value::Object(members));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.parse_string()?;
+            self.expect(b':')?;
+            let value = self.parse_value()?;
+            members.push((key, value));
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b'}') => {
+                    self.pos += 1;
+                    return Ok(Value::Object(members));
+                }
+                _ => return Err(ParseError::Invalid),
+            }
+        }
+    }
+
+    fn parse_hex4(&mut self) -> Result<u32, ParseError> {
+        let digits = self
+            .bytes
+            .get(self.pos..self.pos + 4)
+            .ok_or(ParseError::Invalid)?;
+        let digits = std::str::from_utf8(digits).map_err(|_| ParseError::Invalid)?;
+        let code = u32::from_str_radix(digits, 16).map_err(|_| ParseError::Invalid)?;
+        self.pos += 4;
+        Ok(code)
+    }
+
+    fn parse_string(&mut self) -> Result<String, ParseError> {
+        if self.peek() != Some(b'"') {
+            return Err(ParseError::Invalid);
+        }
+        self.pos += 1;
+        let mut result = String::new();
+        loop {
+            let start = self.pos;
+            while !matches!(self.peek(), Some(b'"' | b'\\') | None) {
+                if self.bytes[self.pos] < 0x20 {
+                    return Err(ParseError::Invalid);
+                }
+                self.pos += 1;
+            }
+            // input came from a &str and we only split at ASCII bytes
+            result.push_str(std::str::from_utf8(&self.bytes[start..self.pos]).unwrap());
+            match self.peek().ok_or(ParseError::Invalid)? {
+                b'"' => {
+                    self.pos += 1;
+                    return Ok(result);
+                }
+                _ => {
+                    self.pos += 1;
+                    let escaped = self.peek().ok_or(ParseError::Invalid)?;
+                    self.pos += 1;
+                    let c = match escaped {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => {
+                            let mut code = self.parse_hex4()?;
+                            if (0xD800..0xDC00).contains(&code) {
+                                self.expect_literal(b"\\u")?;
+                                let low = self.parse_hex4()?;
+                                if !(0xDC00..0xE000).contains(&low) {
+                                    return Err(ParseError::Invalid);
+                                }
+                                code = 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00);
+                            }
+                            char::from_u32(code).ok_or(ParseError::Invalid)?
+                        }
+                        _ => return Err(ParseError::Invalid),
+                    };
+                    result.push(c);
+                }
+            }
+        }
+    }
+
+    fn parse_number(&mut self) -> Result<f64, ParseError> {
+        let start = self.pos;
+        if self.peek() == Some(b'-') {
+            self.pos += 1;
+        }
+        match self.peek() {
+            Some(b'0') => self.pos += 1,
+            Some(b'1'..=b'9') => self.skip_digits(),
+            _ => return Err(ParseError::Invalid),
+        }
+        if self.peek() == Some(b'.') {
+            self.pos += 1;
+            if !matches!(self.peek(), Some(b'0'..=b'9')) {
+                return Err(ParseError::Invalid);
+            }
+            self.skip_digits();
+        }
+        if let Some(b'e' | b'E') = self.peek() {
+            self.pos += 1;
+            if let Some(b'+' | b'-') = self.peek() {
+                self.pos += 1;
+            }
+            if !matches!(self.peek(), Some(b'0'..=b'9')) {
+                return Err(ParseError::Invalid);
+            }
+            self.skip_digits();
+        }
+        let text = std::str::from_utf8(&self.bytes[start..self.pos]).unwrap();
+        text.parse().map_err(|_| ParseError::Invalid)
+    }
+
+    fn skip_digits(&mut self) {
+        while let Some(b'0'..=b'9') = self.peek() {
+            self.pos += 1;
+        }
+    }
+}
+
+/// Append `value` to `out` using the shortest representation that round trips.
+///
+/// Returns false if `value` is not finite, since JSON cannot represent it.
+pub(crate) fn write_number(out: &mut String, value: f64) -> bool {
+    if !value.is_finite() {
+        return false;
+    }
+    // Debug formatting of f64 is the shortest round trip representation and
+    // is always valid JSON for finite values.
+    write!(out, "{:?}", value).unwrap();
+    true
+}
+
+/// Append the JSON representation of `pline` to `out`.
+///
+/// Returns false if any vertex holds a non-finite value.
+pub(crate) fn write_pline(out: &mut String, pline: &Polyline<f64>) -> bool {
+    out.push_str("{\"is_closed\": ");
+    out.push_str(if pline.is_closed() { "true" } else { "false" });
+    out.push_str(", \"vertices\": [");
+    for (i, v) in pline.iter_vertexes().enumerate() {
+        if i != 0 {
+            out.push_str(", ");
+        }
+        out.push('[');
+        for (j, component) in [v.x, v.y, v.bulge].into_iter().enumerate() {
+            if j != 0 {
+                out.push_str(", ");
+            }
+            if !write_number(out, component) {
+                return false;
+            }
+        }
+        out.push(']');
+    }
+    out.push_str("]}");
+    true
+}
+
+/// Convert a parsed JSON value into a polyline, `None` if it does not follow
+/// the polyline schema.
+pub(crate) fn read_pline(value: &Value) -> Option<Polyline<f64>> {
+    let is_closed = match value.get("is_closed")? {
+        Value::Bool(b) => *b,
+        _ => return None,
+    };
+    let vertices = match value.get("vertices")? {
+        Value::Array(items) => items,
+        _ => return None,
+    };
+    let mut pline = Polyline::with_capacity(vertices.len(), is_closed);
+    for v in vertices {
+        match v {
+            Value::Array(components) => match components.as_slice() {
+                [Value::Number(x), Value::Number(y), Value::Number(bulge)] => {
+                    pline.add(*x, *y, *bulge);
+                }
+                _ => return None,
+            },
+            _ => return None,
+        }
+    }
+    Some(pline)
+}
+
+/// Serialize a polyline to JSON.
+///
+/// The nul terminated JSON string is written to `out` which has room for
+/// `out_cap` bytes. `out_len` receives the length of the JSON string (not
+/// counting the nul terminator) even when `out` is too small, so the caller
+/// can allocate a large enough buffer and call again. `out` may be null if
+/// `out_cap` is 0.
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `out_len` is null.
+/// * 2 = `out_cap` is too small to hold the string and its nul terminator.
+/// * 3 = `pline` contains a NaN or infinite value which JSON cannot represent.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_to_json(
+    pline: *const cavc_pline,
+    out: *mut c_char,
+    out_cap: u32,
+    out_len: *mut u32,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || out_len.is_null() {
+            return 1;
+        }
+        let mut json = String::new();
+        if !write_pline(&mut json, &(*pline).0) {
+            return 3;
+        }
+        *out_len = json.len() as u32;
+        if !write_c_string(&json, out, out_cap as usize) {
+            return 2;
+        }
+        0
+    })
+}
+
+/// Create a polyline from its JSON representation.
+///
+/// `json` must be a nul terminated UTF-8 string. `result` is only written to
+/// if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `json` or `result` is null.
+/// * 2 = `json` is not valid UTF-8 or not valid JSON.
+/// * 3 = `json` is valid JSON but does not describe a polyline.
+/// * 4 = arrays and objects of `json` are nested deeper than 256 levels.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_from_json(
+    json: *const c_char,
+    result: *mut *mut cavc_pline,
+) -> i32 {
+    ffi_catch_unwind!({
+        if json.is_null() || result.is_null() {
+            return 1;
+        }
+        let value = match CStr::from_ptr(json).to_str().map(parse) {
+            Ok(Ok(value)) => value,
+            Ok(Err(ParseError::TooDeep)) => {
+                set_last_error("JSON is nested too deeply");
+                return 4;
+            }
+            Ok(Err(ParseError::Invalid)) => {
+                set_last_error("invalid JSON");
+                return 2;
+            }
+            Err(_) => {
+                set_last_error("JSON string is not valid UTF-8");
+                return 2;
+            }
+        };
+        match read_pline(&value) {
+            Some(pline) => {
+                *result = pline_into_raw(pline);
+                0
+            }
+            None => {
+                set_last_error("JSON does not describe a polyline");
+                3
+            }
+        }
+    })
+}
+
+/// Serialize a polyline list to JSON, see [cavc_pline_to_json] for how the
+/// string is returned.
+///
+/// ## Specific Error Codes
+/// * 1 = `plinelist` or `out_len` is null.
+/// * 2 = `out_cap` is too small to hold the string and its nul terminator.
+/// * 3 = a polyline contains a NaN or infinite value which JSON cannot
+///   represent.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_plinelist_to_json(
+    plinelist: *const cavc_plinelist,
+    out: *mut c_char,
+    out_cap: u32,
+    out_len: *mut u32,
+) -> i32 {
+    ffi_catch_unwind!({
+        if plinelist.is_null() || out_len.is_null() {
+            return 1;
+        }
+        let plines = &(*plinelist).0;
+        let mut json = String::from("[");
+        for (i, &pline) in plines.iter().enumerate() {
+            if i != 0 {
+                json.push_str(", ");
+            }
+            if !write_pline(&mut json, &(*pline).0) {
+                return 3;
+            }
+        }
+        json.push(']');
+        *out_len = json.len() as u32;
+        if !write_c_string(&json, out, out_cap as usize) {
+            return 2;
+        }
+        0
+    })
+}
+
+/// Create a polyline list from its JSON representation.
+///
+/// `json` must be a nul terminated UTF-8 string. `result` is only written to
+/// if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `json` or `result` is null.
+/// * 2 = `json` is not valid UTF-8 or not valid JSON.
+/// * 3 = `json` is valid JSON but does not describe a list of polylines.
+/// * 4 = arrays and objects of `json` are nested deeper than 256 levels.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_plinelist_from_json(
+    json: *const c_char,
+    result: *mut *mut cavc_plinelist,
+) -> i32 {
+    ffi_catch_unwind!({
+        if json.is_null() || result.is_null() {
+            return 1;
+        }
+        let value = match CStr::from_ptr(json).to_str().map(parse) {
+            Ok(Ok(value)) => value,
+            Ok(Err(ParseError::TooDeep)) => {
+                set_last_error("JSON is nested too deeply");
+                return 4;
+            }
+            Ok(Err(ParseError::Invalid)) => {
+                set_last_error("invalid JSON");
+                return 2;
+            }
+            Err(_) => {
+                set_last_error("JSON string is not valid UTF-8");
+                return 2;
+            }
+        };
+        let plines = match value {
+            Value::Array(items) => items.iter().map(read_pline).collect::<Option<Vec<_>>>(),
+            _ => None,
+        };
+        match plines {
+            Some(plines) => {
+                *result = plinelist_into_raw(plines);
+                0
+            }
+            None => {
+                set_last_error("JSON does not describe a list of polylines");
+                3
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_nested_values() {
+        let value = parse(r#"{"a": [1, -2.5e3, true, null, "x\u00e9"], "b": {}}"#).unwrap();
+        assert_eq!(
+            value.get("a"),
+            Some(&Value::Array(vec![
+                Value::Number(1.0),
+                Value::Number(-2500.0),
+                Value::Bool(true),
+                Value::Null,
+                Value::String("x\u{e9}".to_string()),
+            ]))
+        );
+        assert_eq!(value.get("b"), Some(&Value::Object(Vec::new())));
+    }
+
+    #[test]
+    fn nesting_up_to_max_depth() {
+        let ok = "[".repeat(MAX_DEPTH) + &"]".repeat(MAX_DEPTH);
+        assert!(parse(&ok).is_ok());
+        let deep = "[".repeat(MAX_DEPTH + 1) + &"]".repeat(MAX_DEPTH + 1);
+        assert_eq!(parse(&deep), Err(ParseError::TooDeep));
+        // far deeper than the stack could handle without the limit
+        let huge = "[{\"a\":".repeat(100_000);
+        assert_eq!(parse(&huge), Err(ParseError::TooDeep));
+    }
+
+    #[test]
+    fn rejects_truncated_input() {
+        for input in [
+            "", "[", "[1,", "{\"a\"", "{\"a\":", "\"abc", "tru", "-", "1.",
+        ] {
+            assert_eq!(parse(input), Err(ParseError::Invalid), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        for input in [
+            "[1,]", "{1: 2}", "[1 2]", "01", "1e", "\"\\x\"", "[] []", "nul",
+        ] {
+            assert_eq!(parse(input), Err(ParseError::Invalid), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn pline_round_trip() {
+        let mut pline = Polyline::new_closed();
+        pline.add(0.1, -2.0, 0.5);
+        pline.add(1e300, 3.0, 0.0);
+        let mut json = String::new();
+        assert!(write_pline(&mut json, &pline));
+        let read = read_pline(&parse(&json).unwrap()).unwrap();
+        assert!(read.is_closed());
+        assert_eq!(read.vertex_count(), 2);
+        assert_eq!(read.at(0).bulge, 0.5);
+        assert_eq!(read.at(1).x, 1e300);
+    }
+
+    #[test]
+    fn pline_degenerate_input() {
+        let mut json = String::new();
+        assert!(write_pline(&mut json, &Polyline::new()));
+        assert_eq!(json, r#"{"is_closed": false, "vertices": []}"#);
+        let read = read_pline(&parse(&json).unwrap()).unwrap();
+        assert_eq!((read.is_closed(), read.vertex_count()), (false, 0));
+        // zero length segments are kept
+        let json = r#"{"is_closed": true, "vertices": [[1, 1, 0], [1, 1, 0]]}"#;
+        assert_eq!(read_pline(&parse(json).unwrap()).unwrap().vertex_count(), 2);
+        // non-finite values have no JSON representation
+        let mut pline = Polyline::new();
+        pline.add(f64::NAN, 0.0, 0.0);
+        assert!(!write_pline(&mut String::new(), &pline));
+    }
+
+    #[test]
+    fn rejects_pline_schema_violations() {
+        for input in [
+            r#"[]"#,
+            r#"{"vertices": []}"#,
+            r#"{"is_closed": 1, "vertices": []}"#,
+            r#"{"is_closed": true, "vertices": {}}"#,
+            r#"{"is_closed": true, "vertices": [[1, 2]]}"#,
+            r#"{"is_closed": true, "vertices": [[1, 2, "0"]]}"#,
+            r#"{"is_closed": true, "vertices": [1, 2, 0]}"#,
+        ] {
+            assert!(read_pline(&parse(input).unwrap()).is_none(), "{}", input);
+        }
+    }
+}
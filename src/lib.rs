@@ -1 +1,88 @@
+//! C FFI used by the Python bindings.
+//!
+//! Everything from `cavalier_contours_ffi` is re-exported as is; the modules
+//! below add functions on top of it following the same conventions: every
+//! function returns an `i32` error code where 0 is success, -1 means a panic
+//! was caught, -2 means the operation was cancelled by its callback (see
+//! [progress]) and positive values are function specific. The reason of the
+//! last failure on a thread can be retrieved with
+//! [error::cavc_last_error_message], call [error::cavc_init] first to get the
+//! cause of panics reported there. Counts, lengths and capacities of arrays,
+//! strings and byte data are `u32` as in `cavalier_contours_ffi`, only the
+//! memory usage reported by [memory] is a `usize`.
+#![allow(non_camel_case_types)]
+#![allow(clippy::missing_safety_doc)]
+
 pub use cavalier_contours_ffi::*;
+
+/// Catch a panic in the body and return -1 instead of unwinding into C,
+/// otherwise return whatever the body returned. Non-zero codes are recorded
+/// in the thread local error slot (see [error]).
+macro_rules! ffi_catch_unwind {
+    ($body: expr) => {{
+        $crate::error::begin_call();
+        let code = match std::panic::catch_unwind(move || $body) {
+            Ok(r) => r,
+            Err(_) => -1,
+        };
+        $crate::error::end_call(code);
+        code
+    }};
+}
+
+mod geom;
+mod predicates;
+#[cfg(test)]
+mod test_util;
+mod util;
+
+pub mod binary;
+pub mod boolean;
+pub mod buffer;
+pub mod builder;
+pub mod cache;
+pub mod chain;
+pub mod cleanup;
+pub mod clip;
+pub mod compare;
+pub mod corners;
+pub mod debug;
+pub mod dxf;
+pub mod edit;
+pub mod error;
+pub mod f32;
+pub mod fit;
+pub mod gcode;
+pub mod geojson;
+pub mod handle;
+pub mod hatch;
+pub mod hierarchy;
+pub mod index;
+pub mod intersect;
+pub mod json;
+pub mod measure;
+pub mod medial;
+pub mod memory;
+pub mod minkowski;
+pub mod offset;
+pub mod options;
+pub mod path;
+pub mod plinelist;
+pub mod pocket;
+pub mod primitive;
+pub mod progress;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod raster;
+pub mod segment;
+pub mod shape;
+pub mod simplify;
+pub mod svg;
+pub mod tabs;
+pub mod thicken;
+pub mod transform;
+pub mod triangulate;
+pub mod userdata;
+pub mod validate;
+pub mod version;
+pub mod wkt;
@@ -0,0 +1,1472 @@
+//! Measurements derived from polyline geometry.
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::f64::consts::{PI, SQRT_2, TAU};
+
+use cavalier_contours::core::math::Vector2;
+use cavalier_contours::polyline::{
+    seg_arc_radius_and_center, seg_bounding_box, seg_closest_point, seg_fast_approx_bounding_box,
+    seg_length, FindIntersectsOptions, PlineCreation, PlineOffsetOptions, PlineSource, PlineVertex,
+    Polyline,
+};
+use cavalier_contours::static_aabb2d_index::{Control, StaticAABB2DIndex};
+use cavalier_contours_ffi::{cavc_pline, cavc_point};
+
+use crate::geom::{
+    seg_extreme_point, seg_param_at_point, seg_point_at, seg_seg_closest, seg_sweep,
+    seg_tangent_at, seg_winding_number,
+};
+use crate::util::{to_cavc_point, valid_eps};
+
+/// Compute the area of the band between a closed polyline and its parallel
+/// offset.
+///
+/// The offset may split into multiple loops, the areas of all of them are
+/// accounted for. The result is positive when the band lies inside of the
+/// original loop (the offset shrinks it) and negative when it lies outside,
+/// regardless of the orientation of `pline`.
+///
+/// `area` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `area` is null.
+/// * 2 = `pline` is not closed.
+/// * 3 = `pos_equal_eps` is negative, NaN or infinite.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_offset_band_area(
+    pline: *const cavc_pline,
+    offset: f64,
+    pos_equal_eps: f64,
+    area: *mut f64,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || area.is_null() {
+            return 1;
+        }
+        let pline = &(*pline).0;
+        if !pline.is_closed() {
+            return 2;
+        }
+        if !valid_eps(pos_equal_eps) {
+            return 3;
+        }
+
+        let mut options = PlineOffsetOptions::new();
+        options.pos_equal_eps = pos_equal_eps;
+        let original_area = pline.area();
+        let offset_area: f64 = pline
+            .parallel_offset_opt(offset, &options)
+            .iter()
+            .map(|p| p.area())
+            .sum();
+
+        let band_area = (original_area - offset_area).abs();
+        let shrinks = offset_area.abs() < original_area.abs();
+        *area = if shrinks { band_area } else { -band_area };
+        0
+    })
+}
+
+/// Write the arc length of every segment of the polyline into `out`.
+///
+/// Segment `i` starts at vertex `i`, for closed polylines the last entry is
+/// the closing segment from the last vertex back to the first. `out` must
+/// have room for `out_cap` values; `out_count` receives the number of
+/// segments even when `out` is too small, so the caller can size the buffer
+/// and call again. `out` may be null if `out_cap` is 0.
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `out_count` is null.
+/// * 2 = `out_cap` is less than the number of segments.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_segment_lengths(
+    pline: *const cavc_pline,
+    out: *mut f64,
+    out_cap: u32,
+    out_count: *mut u32,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || out_count.is_null() {
+            return 1;
+        }
+        let pline = &(*pline).0;
+        let count = pline.segment_count();
+        *out_count = count as u32;
+        if count > out_cap as usize || (out.is_null() && count != 0) {
+            return 2;
+        }
+        for (i, (v1, v2)) in pline.iter_segments().enumerate() {
+            *out.add(i) = seg_length(v1, v2);
+        }
+        0
+    })
+}
+
+/// Write the axis aligned bounding box of every segment of the polyline into
+/// `out_buffer` as `[min_x, min_y, max_x, max_y]` per segment.
+///
+/// Arc segments are bounded exactly, including the extremes of the arc
+/// between its end points. Segment `i` starts at vertex `i`, for closed
+/// polylines the last box is the one of the closing segment. `out_buffer`
+/// must have room for `out_cap` boxes (`4 * out_cap` values); `out_count`
+/// receives the number of segments even when the buffer is too small, so the
+/// caller can size the buffer and call again. `out_buffer` may be null if
+/// `out_cap` is 0.
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `out_count` is null.
+/// * 2 = `out_cap` is less than the number of segments.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_segment_bounding_boxes(
+    pline: *const cavc_pline,
+    out_buffer: *mut f64,
+    out_cap: u32,
+    out_count: *mut u32,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || out_count.is_null() {
+            return 1;
+        }
+        let pline = &(*pline).0;
+        let count = pline.segment_count();
+        *out_count = count as u32;
+        if count > out_cap as usize || (out_buffer.is_null() && count != 0) {
+            return 2;
+        }
+        for (i, (v1, v2)) in pline.iter_segments().enumerate() {
+            let bb = seg_bounding_box(v1, v2);
+            for (k, value) in [bb.min_x, bb.min_y, bb.max_x, bb.max_y]
+                .into_iter()
+                .enumerate()
+            {
+                *out_buffer.add(4 * i + k) = value;
+            }
+        }
+        0
+    })
+}
+
+/// Compute the signed distance from a point to a closed polyline, negative
+/// inside and positive outside.
+///
+/// The magnitude is the exact distance to the closest point on the polyline,
+/// arcs are measured as true arcs. Points within `pos_equal_eps` of the
+/// polyline get a distance of 0.
+///
+/// `sdist` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `sdist` is null.
+/// * 2 = `pline` is not closed.
+/// * 3 = `pline` has no vertexes.
+/// * 4 = `pos_equal_eps` is negative, NaN or infinite.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_signed_distance(
+    pline: *const cavc_pline,
+    x: f64,
+    y: f64,
+    pos_equal_eps: f64,
+    sdist: *mut f64,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || sdist.is_null() {
+            return 1;
+        }
+        if !valid_eps(pos_equal_eps) {
+            return 4;
+        }
+        let pline = &(*pline).0;
+        if !pline.is_closed() {
+            return 2;
+        }
+        let point = Vector2::new(x, y);
+        let distance = match pline.closest_point(point, pos_equal_eps) {
+            Some(closest) => closest.distance,
+            None => return 3,
+        };
+        *sdist = if distance <= pos_equal_eps {
+            0.0
+        } else if pline.winding_number(point) != 0 {
+            -distance
+        } else {
+            distance
+        };
+        0
+    })
+}
+
+/// Compute the signed area of a closed polyline, arc segments are accounted
+/// for exactly.
+///
+/// The area is positive for counter clockwise and negative for clockwise
+/// polylines.
+///
+/// `area` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `area` is null.
+/// * 2 = `pline` is not closed.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_area(pline: *const cavc_pline, area: *mut f64) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || area.is_null() {
+            return 1;
+        }
+        let pline = &(*pline).0;
+        if !pline.is_closed() {
+            return 2;
+        }
+        *area = pline.area();
+        0
+    })
+}
+
+/// Signed area integrals of the region enclosed by a closed polyline, taken
+/// relative to an origin.
+#[derive(Debug, Default, Copy, Clone)]
+struct AreaIntegrals {
+    /// Integral of 1.
+    area: f64,
+    /// Integral of x.
+    mx: f64,
+    /// Integral of y.
+    my: f64,
+    /// Integral of x².
+    sxx: f64,
+    /// Integral of y².
+    syy: f64,
+    /// Integral of x * y.
+    sxy: f64,
+}
+
+/// Compute the [AreaIntegrals] of a closed polyline relative to `origin`.
+///
+/// Every segment contributes the triangle spanned with `origin` plus, for
+/// arcs, the circular segment between the chord and the arc. The integrals
+/// are positive for counter clockwise polylines.
+fn area_integrals(pline: &Polyline<f64>, origin: Vector2<f64>) -> AreaIntegrals {
+    let mut r = AreaIntegrals::default();
+    for (v1, v2) in pline.iter_segments() {
+        let p1 = v1.pos() - origin;
+        let p2 = v2.pos() - origin;
+        let cross = p1.perp_dot(p2);
+        r.area += cross / 2.0;
+        r.mx += cross * (p1.x + p2.x) / 6.0;
+        r.my += cross * (p1.y + p2.y) / 6.0;
+        r.sxx += cross * (p1.x * p1.x + p1.x * p2.x + p2.x * p2.x) / 12.0;
+        r.syy += cross * (p1.y * p1.y + p1.y * p2.y + p2.y * p2.y) / 12.0;
+        r.sxy += cross * (2.0 * p1.x * p1.y + p1.x * p2.y + p2.x * p1.y + 2.0 * p2.x * p2.y) / 24.0;
+        if v1.bulge_is_zero() || v1.pos().fuzzy_eq(v2.pos()) {
+            continue;
+        }
+        let (radius, center) = seg_arc_radius_and_center(v1, v2);
+        let sweep = seg_sweep(v1);
+        let sign = sweep.signum();
+        let half = sweep.abs() / 2.0;
+        let (sin, cos) = half.sin_cos();
+        // u points from the arc center toward the arc midpoint, v is normal
+        // to it, the circular segment is symmetric about u
+        let mid = seg_point_at(v1, v2, 0.5) - center;
+        let u = mid.scale(1.0 / mid.length());
+        let c = center - origin;
+        let r2 = radius * radius;
+        let r4 = r2 * r2;
+        // integrals about the arc center as the sector minus the triangle
+        // spanned by the center and the chord
+        let area = r2 * (2.0 * half - (2.0 * half).sin()) / 2.0;
+        let mu = 2.0 / 3.0 * radius * r2 * sin.powi(3);
+        let suu = r4 / 8.0 * (2.0 * half + (2.0 * half).sin()) - r4 * sin * cos.powi(3) / 2.0;
+        let svv = r4 / 8.0 * (2.0 * half - (2.0 * half).sin()) - r4 * sin.powi(3) * cos / 6.0;
+        // rotate into x/y and shift from the arc center to the origin
+        let (mx, my) = (mu * u.x, mu * u.y);
+        let sxx = u.x * u.x * suu + u.y * u.y * svv;
+        let syy = u.y * u.y * suu + u.x * u.x * svv;
+        let sxy = u.x * u.y * (suu - svv);
+        r.area += sign * area;
+        r.mx += sign * (mx + area * c.x);
+        r.my += sign * (my + area * c.y);
+        r.sxx += sign * (sxx + 2.0 * c.x * mx + area * c.x * c.x);
+        r.syy += sign * (syy + 2.0 * c.y * my + area * c.y * c.y);
+        r.sxy += sign * (sxy + c.x * my + c.y * mx + area * c.x * c.y);
+    }
+    r
+}
+
+/// Compute the area centroid of a closed polyline, arc segments are accounted
+/// for exactly.
+///
+/// The centroid does not depend on the orientation of the polyline.
+///
+/// The out parameters are only written to if the function returns 0
+/// (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline`, `out_x` or `out_y` is null.
+/// * 2 = `pline` is not closed.
+/// * 3 = `pline` has no area.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_centroid(
+    pline: *const cavc_pline,
+    out_x: *mut f64,
+    out_y: *mut f64,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || out_x.is_null() || out_y.is_null() {
+            return 1;
+        }
+        let pline = &(*pline).0;
+        if !pline.is_closed() {
+            return 2;
+        }
+        let origin = match pline.get(0) {
+            Some(v) => v.pos(),
+            None => return 3,
+        };
+        let integrals = area_integrals(pline, origin);
+        let area = integrals.area;
+        if area == 0.0 || !area.is_finite() {
+            return 3;
+        }
+        *out_x = origin.x + integrals.mx / area;
+        *out_y = origin.y + integrals.my / area;
+        0
+    })
+}
+
+/// Compute the second moments of area of a closed polyline about its
+/// centroid, arc segments are accounted for exactly.
+///
+/// `out_ixx` receives the moment about the horizontal axis through the
+/// centroid (the integral of y²), `out_iyy` the moment about the vertical
+/// axis (the integral of x²) and `out_ixy` the product of inertia (the
+/// integral of x * y), all relative to the centroid. The moments do not
+/// depend on the orientation of the polyline.
+///
+/// The out parameters are only written to if the function returns 0
+/// (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or any of the out parameters is null.
+/// * 2 = `pline` is not closed.
+/// * 3 = `pline` has no area.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_area_moments(
+    pline: *const cavc_pline,
+    out_ixx: *mut f64,
+    out_iyy: *mut f64,
+    out_ixy: *mut f64,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || out_ixx.is_null() || out_iyy.is_null() || out_ixy.is_null() {
+            return 1;
+        }
+        let pline = &(*pline).0;
+        if !pline.is_closed() {
+            return 2;
+        }
+        let origin = match pline.get(0) {
+            Some(v) => v.pos(),
+            None => return 3,
+        };
+        let i = area_integrals(pline, origin);
+        if i.area == 0.0 || !i.area.is_finite() {
+            return 3;
+        }
+        // parallel axis theorem, the sign flips with the orientation
+        let cx = i.mx / i.area;
+        let cy = i.my / i.area;
+        let sign = i.area.signum();
+        *out_ixx = sign * (i.syy - i.area * cy * cy);
+        *out_iyy = sign * (i.sxx - i.area * cx * cx);
+        *out_ixy = sign * (i.sxy - i.area * cx * cy);
+        0
+    })
+}
+
+/// Compute the axis aligned bounding box of the polyline.
+///
+/// Arc segments are accounted for exactly, the box touches the arc extremes
+/// rather than just the vertex positions.
+///
+/// The out parameters are only written to if the function returns 0
+/// (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or any of the out parameters is null.
+/// * 2 = `pline` has no vertexes.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_extents(
+    pline: *const cavc_pline,
+    min_x: *mut f64,
+    min_y: *mut f64,
+    max_x: *mut f64,
+    max_y: *mut f64,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null()
+            || min_x.is_null()
+            || min_y.is_null()
+            || max_x.is_null()
+            || max_y.is_null()
+        {
+            return 1;
+        }
+        match (*pline).0.extents() {
+            Some(extents) => {
+                *min_x = extents.min_x;
+                *min_y = extents.min_y;
+                *max_x = extents.max_x;
+                *max_y = extents.max_y;
+                0
+            }
+            None => 2,
+        }
+    })
+}
+
+/// Compute the winding number of a closed polyline around the point `(x, y)`.
+///
+/// The winding number counts how many times the polyline winds around the
+/// point, positive for counter clockwise and negative for clockwise turns, so
+/// both the non-zero and even-odd fill rules can be applied to it. Points
+/// exactly on the polyline may get either of the neighboring values.
+///
+/// `out_i32` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `out_i32` is null.
+/// * 2 = `pline` is not closed.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_winding_number(
+    pline: *const cavc_pline,
+    x: f64,
+    y: f64,
+    out_i32: *mut i32,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || out_i32.is_null() {
+            return 1;
+        }
+        let pline = &(*pline).0;
+        if !pline.is_closed() {
+            return 2;
+        }
+        *out_i32 = pline.winding_number(Vector2::new(x, y));
+        0
+    })
+}
+
+/// Angles below this are treated as no turn when testing convexity.
+const CONVEX_ANGLE_EPS: f64 = 1e-9;
+
+/// Test if the closed polyline bounds a convex region, see
+/// [cavc_pline_is_convex].
+fn is_convex(pline: &Polyline<f64>, pos_equal_eps: f64) -> bool {
+    let segs: Vec<_> = pline
+        .iter_segments()
+        .filter(|(v1, v2)| !v1.pos().fuzzy_eq_eps(v2.pos(), pos_equal_eps))
+        .collect();
+    if segs.is_empty() {
+        return false;
+    }
+    // signed turns along the boundary: at every vertex between the tangents of
+    // the segments meeting there and along every arc by its sweep
+    let mut turns = Vec::with_capacity(2 * segs.len());
+    for (k, &(v1, v2)) in segs.iter().enumerate() {
+        let (u1, u2) = segs[(k + 1) % segs.len()];
+        let t_in = seg_tangent_at(v1, v2, 1.0);
+        let t_out = seg_tangent_at(u1, u2, 0.0);
+        let corner = t_in.perp_dot(t_out).atan2(t_in.dot(t_out));
+        if corner.abs() >= PI - CONVEX_ANGLE_EPS {
+            // the boundary doubles back on itself
+            return false;
+        }
+        turns.push(seg_sweep(v1));
+        turns.push(corner);
+    }
+    let has_left = turns.iter().any(|&t| t > CONVEX_ANGLE_EPS);
+    let has_right = turns.iter().any(|&t| t < -CONVEX_ANGLE_EPS);
+    let total: f64 = turns.iter().sum();
+    // turning consistently by exactly one full turn, more turns wind around
+    // several times and intersect
+    !(has_left && has_right) && (total.abs() - TAU).abs() < 1e-6
+}
+
+/// Test if a closed polyline bounds a convex region.
+///
+/// Arc segments are accounted for: the boundary is convex if it turns in the
+/// same direction everywhere (at the vertexes and along the arcs) and by
+/// exactly one full turn. So an arc bulging inward, an arc bulging outward so
+/// far that the boundary turns back at its end points or a polyline winding
+/// around more than once is not convex even if its vertexes form a convex
+/// polygon. Collinear vertexes and segments shorter than the default
+/// `pos_equal_eps` are ignored. Either orientation can be convex. A polyline
+/// without area (e.g. doubling back on itself) is not convex. `out_bool` is
+/// set to 1 if the polyline is convex and 0 otherwise.
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `out_bool` is null.
+/// * 2 = `pline` is not closed.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_is_convex(pline: *const cavc_pline, out_bool: *mut u8) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || out_bool.is_null() {
+            return 1;
+        }
+        let pline = &(*pline).0;
+        if !pline.is_closed() {
+            return 2;
+        }
+        let pos_equal_eps = PlineOffsetOptions::<f64>::new().pos_equal_eps;
+        *out_bool = is_convex(pline, pos_equal_eps) as u8;
+        0
+    })
+}
+
+/// Sweep of the arc pieces sampled for the convex hull of arc segments.
+const HULL_ARC_STEP: f64 = PI / 64.0;
+
+/// Convex hull of `points` in counter clockwise order without collinear
+/// points (Andrew's monotone chain).
+fn convex_hull(mut points: Vec<Vector2<f64>>) -> Vec<Vector2<f64>> {
+    points.sort_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+    points.dedup_by(|a, b| a.x == b.x && a.y == b.y);
+    if points.len() < 3 {
+        return points;
+    }
+    let cross = |o: Vector2<f64>, a: Vector2<f64>, b: Vector2<f64>| {
+        (a.x - o.x) * (b.y - o.y) - (a.y - o.y) * (b.x - o.x)
+    };
+    let mut hull: Vec<Vector2<f64>> = Vec::with_capacity(2 * points.len());
+    // lower hull forward, then upper hull backward
+    for pass in [points.clone(), points.into_iter().rev().collect()] {
+        let base = hull.len();
+        for p in pass {
+            while hull.len() >= base + 2
+                && cross(hull[hull.len() - 2], hull[hull.len() - 1], p) <= 0.0
+            {
+                hull.pop();
+            }
+            hull.push(p);
+        }
+        // the last point starts the other half
+        hull.pop();
+    }
+    hull
+}
+
+/// Direction (radians) of the side of the minimum area rectangle enclosing
+/// the convex `hull`, found with rotating calipers since one side of that
+/// rectangle lies on a hull edge.
+fn min_area_rect_angle(hull: &[Vector2<f64>]) -> f64 {
+    let m = hull.len();
+    if m < 3 {
+        return match hull {
+            [a, b] => (b.y - a.y).atan2(b.x - a.x),
+            _ => 0.0,
+        };
+    }
+    let dot = |a: Vector2<f64>, b: Vector2<f64>| a.x * b.x + a.y * b.y;
+    // pointers to the points furthest along the edge, across it and against it
+    let (mut far, mut top, mut near) = (1, 1, 1);
+    let mut best = (f64::INFINITY, 0.0);
+    for i in 0..m {
+        let origin = hull[i];
+        let u = (hull[(i + 1) % m] - origin).normalize();
+        let n = Vector2::new(-u.y, u.x);
+        let along = |k: usize| dot(hull[k % m] - origin, u);
+        let across = |k: usize| dot(hull[k % m] - origin, n);
+        // every pointer only moves forward as the edge rotates
+        while along(far + 1) > along(far) {
+            far += 1;
+        }
+        top = top.max(far);
+        while across(top + 1) > across(top) {
+            top += 1;
+        }
+        near = near.max(top);
+        while along(near + 1) < along(near) {
+            near += 1;
+        }
+        let area = (along(far) - along(near)) * across(top);
+        if area < best.0 {
+            best = (area, u.y.atan2(u.x));
+        }
+    }
+    best.1
+}
+
+/// Compute the minimum area oriented bounding box of the polyline.
+///
+/// The box direction is found with rotating calipers on the convex hull of
+/// the vertexes and of points along the arc segments (every pi / 64 of
+/// sweep), then the box is fitted to the polyline in that direction with
+/// arcs accounted for exactly, so it always encloses the whole polyline and
+/// its area is within a tiny fraction of the true minimum. `out_center`
+/// receives the center of the box, `out_extents` its width (along the
+/// direction) as x and its height as y, and `out_angle` the direction of the
+/// width side in radians in [0, pi / 2), measured counter clockwise from the
+/// x axis. Works for open and closed polylines, a polyline with all vertexes
+/// on a line gives a height of 0.
+///
+/// The out parameters are only written to if the function returns 0
+/// (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or any of the out parameters is null.
+/// * 2 = `pline` has no vertexes.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_min_oriented_bbox(
+    pline: *const cavc_pline,
+    out_center: *mut cavc_point,
+    out_extents: *mut cavc_point,
+    out_angle: *mut f64,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || out_center.is_null() || out_extents.is_null() || out_angle.is_null() {
+            return 1;
+        }
+        let pline = &(*pline).0;
+        if pline.vertex_count() == 0 {
+            return 2;
+        }
+        let mut points: Vec<Vector2<f64>> = pline.iter_vertexes().map(|v| v.pos()).collect();
+        for (v1, v2) in pline.iter_segments() {
+            if v1.bulge_is_zero() {
+                continue;
+            }
+            let count = (seg_sweep(v1).abs() / HULL_ARC_STEP).ceil() as usize;
+            points.extend((1..count).map(|k| seg_point_at(v1, v2, k as f64 / count as f64)));
+        }
+        let angle = min_area_rect_angle(&convex_hull(points)).rem_euclid(PI / 2.0);
+        // fit the box exactly in the frame rotated by -angle
+        let (sin, cos) = angle.sin_cos();
+        let rotated = Polyline::from_iter(
+            pline
+                .iter_vertexes()
+                .map(|v| PlineVertex::new(v.x * cos + v.y * sin, v.y * cos - v.x * sin, v.bulge)),
+            pline.is_closed(),
+        );
+        // always some since the polyline has vertexes
+        let extents = rotated.extents().unwrap();
+        let cx = 0.5 * (extents.min_x + extents.max_x);
+        let cy = 0.5 * (extents.min_y + extents.max_y);
+        *out_center = to_cavc_point(Vector2::new(cx * cos - cy * sin, cx * sin + cy * cos));
+        *out_extents = to_cavc_point(Vector2::new(
+            extents.max_x - extents.min_x,
+            extents.max_y - extents.min_y,
+        ));
+        *out_angle = angle;
+        0
+    })
+}
+
+/// Square cell of the search for the maximum inscribed circle.
+#[derive(Debug, Copy, Clone)]
+struct Cell {
+    center: Vector2<f64>,
+    half_size: f64,
+    /// Distance from the center to the polyline, negative outside.
+    distance: f64,
+}
+
+impl Cell {
+    fn new(
+        pline: &Polyline<f64>,
+        index: &StaticAABB2DIndex<f64>,
+        max_x: f64,
+        center: Vector2<f64>,
+        half_size: f64,
+        pos_equal_eps: f64,
+    ) -> Self {
+        // always some since the polyline has vertexes
+        let (_, _, distance) = closest_point_indexed(pline, index, center, pos_equal_eps).unwrap();
+        let distance = if winding_number_indexed(pline, index, max_x, center) != 0 {
+            distance
+        } else {
+            -distance
+        };
+        Self {
+            center,
+            half_size,
+            distance,
+        }
+    }
+
+    /// Upper bound of the distance of any point in the cell.
+    fn potential(&self) -> f64 {
+        self.distance + self.half_size * SQRT_2
+    }
+}
+
+impl PartialEq for Cell {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Cell {}
+
+impl PartialOrd for Cell {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Cell {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.potential().total_cmp(&other.potential())
+    }
+}
+
+/// Center and radius of the maximum inscribed circle of the closed `pline`
+/// within `tolerance` (pole of inaccessibility), found by refining a single
+/// cell covering the extents most promising cells first.
+pub(crate) fn max_inscribed_circle(pline: &Polyline<f64>, tolerance: f64) -> (Vector2<f64>, f64) {
+    let pos_equal_eps = PlineOffsetOptions::<f64>::new().pos_equal_eps;
+    // always some since the polyline has vertexes
+    let extents = pline.extents().unwrap();
+    let width = extents.max_x - extents.min_x;
+    let height = extents.max_y - extents.min_y;
+    let box_center = Vector2::new(
+        0.5 * (extents.min_x + extents.max_x),
+        0.5 * (extents.min_y + extents.max_y),
+    );
+    if width.min(height) <= 0.0 {
+        return (box_center, 0.0);
+    }
+    let index = pline.create_approx_aabb_index();
+    let cell = |center: Vector2<f64>, half_size: f64| {
+        Cell::new(
+            pline,
+            &index,
+            extents.max_x,
+            center,
+            half_size,
+            pos_equal_eps,
+        )
+    };
+    // a single cell, a grid of cells the size of the smaller side would have
+    // as many cells as the aspect ratio of thin shapes
+    let mut queue = BinaryHeap::new();
+    queue.push(cell(box_center, 0.5 * width.max(height)));
+    let mut best = cell(box_center, 0.0);
+    while let Some(c) = queue.pop() {
+        if c.distance > best.distance {
+            best = c;
+        }
+        // cells are popped by decreasing potential, none can improve enough
+        if c.potential() - best.distance <= tolerance {
+            break;
+        }
+        let h = 0.5 * c.half_size;
+        for (dx, dy) in [(-h, -h), (h, -h), (-h, h), (h, h)] {
+            queue.push(cell(c.center + Vector2::new(dx, dy), h));
+        }
+    }
+    (best.center, best.distance.max(0.0))
+}
+
+/// Find the largest circle inside a closed polyline (the pole of
+/// inaccessibility), e.g. the largest tool that fits a pocket or where to
+/// place a label.
+///
+/// The area is searched with a grid of cells refined around the most
+/// promising ones, evaluating the exact distance to the polyline (arcs
+/// included), until the radius found is within `tolerance` of the maximum.
+/// The inside is given by the non-zero winding rule, so the orientation does
+/// not matter. A polyline without area gives the center of its extents with
+/// a radius of 0.
+///
+/// The out parameters are only written to if the function returns 0
+/// (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or any of the out parameters is null.
+/// * 2 = `pline` is not closed or has less than 2 vertexes.
+/// * 3 = `tolerance` is not greater than 0 or not finite.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_max_inscribed_circle(
+    pline: *const cavc_pline,
+    tolerance: f64,
+    out_cx: *mut f64,
+    out_cy: *mut f64,
+    out_r: *mut f64,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || out_cx.is_null() || out_cy.is_null() || out_r.is_null() {
+            return 1;
+        }
+        let pline = &(*pline).0;
+        if !pline.is_closed() || pline.vertex_count() < 2 {
+            return 2;
+        }
+        if !tolerance.is_finite() || tolerance <= 0.0 {
+            return 3;
+        }
+        let (center, radius) = max_inscribed_circle(pline, tolerance);
+        *out_cx = center.x;
+        *out_cy = center.y;
+        *out_r = radius;
+        0
+    })
+}
+
+/// Find the point of the polyline farthest in direction `(dir_x, dir_y)`
+/// (the support point used by GJK style collision checks).
+///
+/// Arc segments are accounted for exactly, the point may lie inside an arc
+/// rather than at a vertex. `out_seg_index` receives the index of the segment
+/// the point lies on (0 for a polyline with a single vertex). If several
+/// points are equally far the first along the polyline is returned. The
+/// direction does not need to be normalized.
+///
+/// The out parameters are only written to if the function returns 0
+/// (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or any of the out parameters is null.
+/// * 2 = `pline` has no vertexes.
+/// * 3 = the direction is zero or not finite.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_extreme_point(
+    pline: *const cavc_pline,
+    dir_x: f64,
+    dir_y: f64,
+    out_x: *mut f64,
+    out_y: *mut f64,
+    out_seg_index: *mut u32,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || out_x.is_null() || out_y.is_null() || out_seg_index.is_null() {
+            return 1;
+        }
+        let pline = &(*pline).0;
+        let first = match pline.get(0) {
+            Some(v) => v,
+            None => return 2,
+        };
+        let dir = Vector2::new(dir_x, dir_y);
+        if !dir_x.is_finite() || !dir_y.is_finite() || dir.length() == 0.0 {
+            return 3;
+        }
+        let mut best = (first.pos(), 0);
+        for (i, (v1, v2)) in pline.iter_segments().enumerate() {
+            let (point, _) = seg_extreme_point(v1, v2, dir);
+            if point.dot(dir) > best.0.dot(dir) {
+                best = (point, i);
+            }
+        }
+        *out_x = best.0.x;
+        *out_y = best.0.y;
+        *out_seg_index = best.1 as u32;
+        0
+    })
+}
+
+/// Test whether each of `count` points lies inside a closed polyline.
+///
+/// `points_buffer` holds the points interleaved as `[x0, y0, x1, y1, ...]`
+/// and `results_buffer` receives one value per point, 1 if the point is
+/// inside (has a non-zero winding number) and 0 if it is outside. Points
+/// exactly on the polyline may be classified either way. A spatial index of
+/// the segments is built once, so only the segments near every point are
+/// visited. The buffers may be null if `count` is 0.
+///
+/// `results_buffer` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` is null or a buffer is null while `count` is not 0.
+/// * 2 = `pline` is not closed.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_contains_points(
+    pline: *const cavc_pline,
+    points_buffer: *const f64,
+    count: u32,
+    results_buffer: *mut u8,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || ((points_buffer.is_null() || results_buffer.is_null()) && count != 0)
+        {
+            return 1;
+        }
+        let pline = &(*pline).0;
+        if !pline.is_closed() {
+            return 2;
+        }
+        if count == 0 {
+            return 0;
+        }
+        let points = std::slice::from_raw_parts(points_buffer, 2 * count as usize);
+        let results = std::slice::from_raw_parts_mut(results_buffer, count as usize);
+        let extents = match pline.extents() {
+            Some(extents) if pline.vertex_count() > 1 => extents,
+            _ => {
+                results.fill(0);
+                return 0;
+            }
+        };
+        let index = pline.create_approx_aabb_index();
+        for (p, result) in points.chunks_exact(2).zip(results.iter_mut()) {
+            let point = Vector2::new(p[0], p[1]);
+            if !(extents.min_x..=extents.max_x).contains(&point.x)
+                || !(extents.min_y..=extents.max_y).contains(&point.y)
+            {
+                *result = 0;
+                continue;
+            }
+            *result = (winding_number_indexed(pline, &index, extents.max_x, point) != 0) as u8;
+        }
+        0
+    })
+}
+
+/// Winding number of the closed `pline` around `point` using the spatial
+/// index of its segments, `max_x` is the maximum x of the polyline extents.
+pub(crate) fn winding_number_indexed(
+    pline: &Polyline<f64>,
+    index: &StaticAABB2DIndex<f64>,
+    max_x: f64,
+    point: Vector2<f64>,
+) -> i32 {
+    if point.x > max_x {
+        return 0;
+    }
+    // only segments overlapping the ray toward positive x contribute
+    let mut winding = 0;
+    index.visit_query(point.x, point.y, max_x, point.y, &mut |i| {
+        let next = pline.next_wrapping_index(i);
+        winding += seg_winding_number(pline.at(i), pline.at(next), point);
+    });
+    winding
+}
+
+/// Closest point on the polyline to `point` as `(segment index, point,
+/// distance)` using the spatial index of its segments, None if the polyline
+/// has no vertexes.
+pub(crate) fn closest_point_indexed(
+    pline: &Polyline<f64>,
+    index: &StaticAABB2DIndex<f64>,
+    point: Vector2<f64>,
+    pos_equal_eps: f64,
+) -> Option<(usize, Vector2<f64>, f64)> {
+    if pline.vertex_count() < 2 {
+        let v = pline.get(0)?;
+        return Some((0, v.pos(), (v.pos() - point).length()));
+    }
+    let mut closest: Option<(usize, Vector2<f64>, f64)> = None;
+    // boxes are visited by increasing distance, no segment in a box further
+    // away than the closest point found so far can be closer
+    index.visit_neighbors(point.x, point.y, &mut |i, box_dist_sq| {
+        if let Some((_, _, dist)) = closest {
+            if box_dist_sq > dist * dist {
+                return Control::Break;
+            }
+        }
+        let next = pline.next_wrapping_index(i);
+        let p = seg_closest_point(pline.at(i), pline.at(next), point, pos_equal_eps);
+        let dist = (p - point).length();
+        if !matches!(closest, Some((_, _, d)) if d <= dist) {
+            closest = Some((i, p, dist));
+        }
+        Control::Continue
+    });
+    closest
+}
+
+/// Closest point on a polyline to a query point.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct cavc_closest_point {
+    /// Closest point on the polyline.
+    pub point: cavc_point,
+    /// Index of the segment (its start vertex) the closest point lies on.
+    pub seg_index: u32,
+    /// Distance from the query point to the closest point.
+    pub distance: f64,
+}
+
+/// Find the closest point on the polyline for each of `count` points.
+///
+/// `points_buffer` holds the points interleaved as `[x0, y0, x1, y1, ...]`
+/// and `out_buffer` receives one [cavc_closest_point] per point. A spatial
+/// index of the segments is built once and only the segments near every
+/// point are visited. The buffers may be null if `count` is 0.
+///
+/// `out_buffer` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` is null or a buffer is null while `count` is not 0.
+/// * 2 = `pline` has no vertexes.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_closest_points(
+    pline: *const cavc_pline,
+    points_buffer: *const f64,
+    count: u32,
+    out_buffer: *mut cavc_closest_point,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || ((points_buffer.is_null() || out_buffer.is_null()) && count != 0) {
+            return 1;
+        }
+        let pline = &(*pline).0;
+        if pline.vertex_count() == 0 {
+            return 2;
+        }
+        if count == 0 {
+            return 0;
+        }
+        let points = std::slice::from_raw_parts(points_buffer, 2 * count as usize);
+        let out = std::slice::from_raw_parts_mut(out_buffer, count as usize);
+        let pos_equal_eps = PlineOffsetOptions::<f64>::new().pos_equal_eps;
+        let index = pline.create_approx_aabb_index();
+        for (p, result) in points.chunks_exact(2).zip(out.iter_mut()) {
+            let point = Vector2::new(p[0], p[1]);
+            // always some since the polyline has vertexes
+            let (seg_index, closest, distance) =
+                closest_point_indexed(pline, &index, point, pos_equal_eps).unwrap();
+            *result = cavc_closest_point {
+                point: to_cavc_point(closest),
+                seg_index: seg_index as u32,
+                distance,
+            };
+        }
+        0
+    })
+}
+
+/// Closest point on a polyline to a query point with its position along the
+/// polyline, see [cavc_pline_closest_point_ex].
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct cavc_closest_point_ex {
+    /// Closest point on the polyline.
+    pub point: cavc_point,
+    /// Index of the segment (its start vertex) the closest point lies on.
+    pub seg_index: u32,
+    /// Distance from the query point to the closest point.
+    pub distance: f64,
+    /// Parameter of the point on the segment, the fraction of its arc length
+    /// from the start vertex.
+    pub t: f64,
+    /// Path length from the start of the polyline to the closest point.
+    pub path_length: f64,
+    /// 1 if the closest point is a vertex of the polyline (where the segment
+    /// it lies on is ambiguous, e.g. the query point is outside a corner) and
+    /// 0 otherwise.
+    pub at_vertex: u8,
+}
+
+/// Find the closest point on the polyline to `(x, y)` together with its
+/// parameter on the segment, its path length and whether it is a vertex.
+///
+/// The closest point is found the same way as by
+/// [cavc_pline_closest_points]. It is a vertex if it is within
+/// `pos_equal_eps` of an end point of its segment, in which case `t` is 0 or
+/// 1. The closest point of a polyline with a single vertex is that vertex.
+///
+/// `result` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `result` is null.
+/// * 2 = `pline` has no vertexes.
+/// * 3 = `pos_equal_eps` is negative, NaN or infinite.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_closest_point_ex(
+    pline: *const cavc_pline,
+    x: f64,
+    y: f64,
+    pos_equal_eps: f64,
+    result: *mut cavc_closest_point_ex,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || result.is_null() {
+            return 1;
+        }
+        if !valid_eps(pos_equal_eps) {
+            return 3;
+        }
+        let pline = &(*pline).0;
+        let point = Vector2::new(x, y);
+        if pline.vertex_count() < 2 {
+            let v = match pline.get(0) {
+                Some(v) => v,
+                None => return 2,
+            };
+            *result = cavc_closest_point_ex {
+                point: to_cavc_point(v.pos()),
+                seg_index: 0,
+                distance: (v.pos() - point).length(),
+                t: 0.0,
+                path_length: 0.0,
+                at_vertex: 1,
+            };
+            return 0;
+        }
+        let index = pline.create_approx_aabb_index();
+        // always some since the polyline has vertexes
+        let (seg_index, closest, distance) =
+            closest_point_indexed(pline, &index, point, pos_equal_eps).unwrap();
+        let v1 = pline.at(seg_index);
+        let v2 = pline.at(pline.next_wrapping_index(seg_index));
+        let mut t = seg_param_at_point(v1, v2, closest);
+        let mut at_vertex = true;
+        if closest.fuzzy_eq_eps(v1.pos(), pos_equal_eps) {
+            t = 0.0;
+        } else if closest.fuzzy_eq_eps(v2.pos(), pos_equal_eps) {
+            t = 1.0;
+        } else {
+            at_vertex = false;
+        }
+        let before: f64 = pline
+            .iter_segments()
+            .take(seg_index)
+            .map(|(u1, u2)| seg_length(u1, u2))
+            .sum();
+        *result = cavc_closest_point_ex {
+            point: to_cavc_point(closest),
+            seg_index: seg_index as u32,
+            distance,
+            t,
+            path_length: before + t * seg_length(v1, v2),
+            at_vertex: at_vertex as u8,
+        };
+        0
+    })
+}
+
+/// Minimum distance between two polylines as `(point on pline1, point on
+/// pline2, distance)`, None if either has no vertexes.
+pub(crate) fn pline_distance(
+    pline1: &Polyline<f64>,
+    pline2: &Polyline<f64>,
+    pos_equal_eps: f64,
+) -> Option<(Vector2<f64>, Vector2<f64>, f64)> {
+    if pline1.vertex_count() == 0 || pline2.vertex_count() == 0 {
+        return None;
+    }
+    let index1 = pline1.create_approx_aabb_index();
+    let index2 = pline2.create_approx_aabb_index();
+    if pline1.vertex_count() == 1 {
+        let p = pline1.at(0).pos();
+        let (_, q, dist) = closest_point_indexed(pline2, &index2, p, pos_equal_eps)?;
+        return Some((p, q, dist));
+    }
+    if pline2.vertex_count() == 1 {
+        let q = pline2.at(0).pos();
+        let (_, p, dist) = closest_point_indexed(pline1, &index1, q, pos_equal_eps)?;
+        return Some((p, q, dist));
+    }
+
+    let mut options = FindIntersectsOptions::new();
+    options.pos_equal_eps = pos_equal_eps;
+    options.pline1_aabb_index = Some(&index1);
+    let intersects = pline1.find_intersects_opt(pline2, &options);
+    if let Some(i) = intersects.basic_intersects.first() {
+        return Some((i.point, i.point, 0.0));
+    }
+    if let Some(i) = intersects.overlapping_intersects.first() {
+        return Some((i.point1, i.point1, 0.0));
+    }
+
+    // start from the distance of one vertex, then only segment pairs with
+    // boxes closer than the best distance so far need to be checked
+    let start = pline1.at(0).pos();
+    let (_, q, dist) = closest_point_indexed(pline2, &index2, start, pos_equal_eps)?;
+    let mut best = (start, q, dist);
+    for (v1, v2) in pline1.iter_segments() {
+        let b = seg_fast_approx_bounding_box(v1, v2);
+        let d = best.2;
+        index2.visit_query(
+            b.min_x - d,
+            b.min_y - d,
+            b.max_x + d,
+            b.max_y + d,
+            &mut |j| {
+                let next = pline2.next_wrapping_index(j);
+                let (p, q, dist) =
+                    seg_seg_closest(v1, v2, pline2.at(j), pline2.at(next), pos_equal_eps);
+                if dist < best.2 {
+                    best = (p, q, dist);
+                }
+            },
+        );
+    }
+    Some(best)
+}
+
+/// Compute the minimum distance between two polylines with the closest pair of
+/// points.
+///
+/// The distance is 0 if the polylines intersect, in which case both points
+/// are an intersection point. Arcs are measured as true arcs. Spatial indexes
+/// of both polylines are used to find intersects and to skip segment pairs
+/// further apart than the closest pair found so far.
+///
+/// The out parameters are only written to if the function returns 0
+/// (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline1`, `pline2` or any of the out parameters is null.
+/// * 2 = `pline1` or `pline2` has no vertexes.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_distance_to(
+    pline1: *const cavc_pline,
+    pline2: *const cavc_pline,
+    out_distance: *mut f64,
+    out_p1: *mut cavc_point,
+    out_p2: *mut cavc_point,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline1.is_null()
+            || pline2.is_null()
+            || out_distance.is_null()
+            || out_p1.is_null()
+            || out_p2.is_null()
+        {
+            return 1;
+        }
+        let pos_equal_eps = PlineOffsetOptions::<f64>::new().pos_equal_eps;
+        match pline_distance(&(*pline1).0, &(*pline2).0, pos_equal_eps) {
+            Some((p1, p2, distance)) => {
+                *out_distance = distance;
+                *out_p1 = to_cavc_point(p1);
+                *out_p2 = to_cavc_point(p2);
+                0
+            }
+            None => 2,
+        }
+    })
+}
+
+/// Maximum number of points sampled along a polyline by [max_deviation].
+const MAX_DEVIATION_SAMPLES: f64 = 10_000_000.0;
+
+/// Number of points [max_deviation] samples along `pline` besides its first
+/// vertex.
+fn deviation_samples(pline: &Polyline<f64>, tolerance: f64) -> f64 {
+    pline
+        .iter_segments()
+        .map(|(v1, v2)| (seg_length(v1, v2) / tolerance).ceil().max(1.0))
+        .sum()
+}
+
+/// Largest distance from a point of `pline` to `reference`, sampling `pline`
+/// at most `tolerance` apart. None if either polyline has no vertexes.
+fn max_deviation(pline: &Polyline<f64>, reference: &Polyline<f64>, tolerance: f64) -> Option<f64> {
+    let pos_equal_eps = PlineOffsetOptions::<f64>::new().pos_equal_eps;
+    let index = reference.create_approx_aabb_index();
+    let distance = |p| closest_point_indexed(reference, &index, p, pos_equal_eps).map(|c| c.2);
+    let mut max = distance(pline.get(0)?.pos())?;
+    for (v1, v2) in pline.iter_segments() {
+        let steps = (seg_length(v1, v2) / tolerance).ceil().max(1.0) as usize;
+        for k in 1..=steps {
+            let p = seg_point_at(v1, v2, k as f64 / steps as f64);
+            max = max.max(distance(p)?);
+        }
+    }
+    Some(max)
+}
+
+/// Compute the largest distance from any point of `pline` to the closest point
+/// of `reference`, e.g. how far a simplified polyline deviates from its
+/// original.
+///
+/// `pline` is sampled at points at most `tolerance` apart along its path
+/// (always including the vertexes), so the result may underestimate the true
+/// deviation by at most `tolerance / 2`. Distances to `reference` are exact,
+/// arcs are measured as true arcs. The deviation is not symmetric, see
+/// [cavc_pline_hausdorff_distance].
+///
+/// `out` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline`, `reference` or `out` is null.
+/// * 2 = `pline` or `reference` has no vertexes.
+/// * 3 = `tolerance` is not greater than 0.
+/// * 4 = sampling `pline` at `tolerance` takes more than ten million points.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_max_deviation(
+    pline: *const cavc_pline,
+    reference: *const cavc_pline,
+    tolerance: f64,
+    out: *mut f64,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || reference.is_null() || out.is_null() {
+            return 1;
+        }
+        if tolerance.is_nan() || tolerance <= 0.0 {
+            return 3;
+        }
+        if deviation_samples(&(*pline).0, tolerance) > MAX_DEVIATION_SAMPLES {
+            return 4;
+        }
+        match max_deviation(&(*pline).0, &(*reference).0, tolerance) {
+            Some(deviation) => {
+                *out = deviation;
+                0
+            }
+            None => 2,
+        }
+    })
+}
+
+/// Compute the Hausdorff distance between two polylines, the larger of the
+/// deviations of each polyline from the other.
+///
+/// Both polylines are sampled as described by [cavc_pline_max_deviation], so
+/// the result may underestimate the true distance by at most
+/// `tolerance / 2`.
+///
+/// `out` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline1`, `pline2` or `out` is null.
+/// * 2 = `pline1` or `pline2` has no vertexes.
+/// * 3 = `tolerance` is not greater than 0.
+/// * 4 = sampling `pline1` or `pline2` at `tolerance` takes more than ten
+///   million points.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_hausdorff_distance(
+    pline1: *const cavc_pline,
+    pline2: *const cavc_pline,
+    tolerance: f64,
+    out: *mut f64,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline1.is_null() || pline2.is_null() || out.is_null() {
+            return 1;
+        }
+        if tolerance.is_nan() || tolerance <= 0.0 {
+            return 3;
+        }
+        let pline1 = &(*pline1).0;
+        let pline2 = &(*pline2).0;
+        if [pline1, pline2]
+            .iter()
+            .any(|p| deviation_samples(p, tolerance) > MAX_DEVIATION_SAMPLES)
+        {
+            return 4;
+        }
+        match (
+            max_deviation(pline1, pline2, tolerance),
+            max_deviation(pline2, pline1, tolerance),
+        ) {
+            (Some(d1), Some(d2)) => {
+                *out = d1.max(d2);
+                0
+            }
+            _ => 2,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ptr::null;
+
+    use cavalier_contours::polyline::PlineSourceMut;
+
+    use super::*;
+    use crate::test_util::{cw, pline, rect};
+
+    #[test]
+    fn inscribed_circle_of_rectangles() {
+        let (center, radius) = max_inscribed_circle(&rect(0.0, 0.0, 10.0, 4.0), 1e-6);
+        assert!(radius <= 2.0 && radius >= 2.0 - 1e-6, "{}", radius);
+        assert!((center.y - 2.0).abs() <= 1e-6 && (2.0..=8.0).contains(&center.x));
+        // the orientation does not matter
+        let (_, radius) = max_inscribed_circle(&cw(rect(0.0, 0.0, 10.0, 4.0)), 1e-6);
+        assert!(radius >= 2.0 - 1e-6);
+        // thin shapes are searched with a single cell
+        let (center, radius) = max_inscribed_circle(&rect(0.0, 0.0, 1000.0, 1.0), 1e-3);
+        assert!(radius >= 0.5 - 1e-3 && (center.y - 0.5).abs() <= 1e-3);
+    }
+
+    #[test]
+    fn inscribed_circle_with_arcs() {
+        let circle = pline(&[(0.0, 0.0, 1.0), (2.0, 0.0, 1.0)], true);
+        let (center, radius) = max_inscribed_circle(&circle, 1e-6);
+        assert!(radius <= 1.0 + 1e-12 && radius >= 1.0 - 1e-6);
+        assert!(center.fuzzy_eq_eps(Vector2::new(1.0, 0.0), 1e-3));
+    }
+
+    #[test]
+    fn inscribed_circle_of_l_shape() {
+        // the vertical arm of width 4 holds the largest circle
+        let l_shape = pline(
+            &[
+                (0.0, 0.0, 0.0),
+                (20.0, 0.0, 0.0),
+                (20.0, 2.0, 0.0),
+                (4.0, 2.0, 0.0),
+                (4.0, 20.0, 0.0),
+                (0.0, 20.0, 0.0),
+            ],
+            true,
+        );
+        let (center, radius) = max_inscribed_circle(&l_shape, 1e-6);
+        assert!(radius <= 2.0 + 1e-12 && radius >= 2.0 - 1e-6, "{}", radius);
+        assert!((center.x - 2.0).abs() <= 1e-3);
+    }
+
+    #[test]
+    fn inscribed_circle_degenerate_input() {
+        // no area gives the center of the extents
+        let flat = pline(&[(0.0, 0.0, 0.0), (2.0, 0.0, 0.0)], true);
+        let (center, radius) = max_inscribed_circle(&flat, 1e-6);
+        assert_eq!((center, radius), (Vector2::new(1.0, 0.0), 0.0));
+        let point = pline(&[(1.0, 1.0, 0.0), (1.0, 1.0, 0.0)], true);
+        assert_eq!(max_inscribed_circle(&point, 1e-6).1, 0.0);
+        // tiny tolerances only refine around the center
+        let (center, radius) = max_inscribed_circle(&rect(0.0, 0.0, 10.0, 10.0), 1e-12);
+        assert_eq!((center, radius), (Vector2::new(5.0, 5.0), 5.0));
+    }
+
+    #[test]
+    fn inscribed_circle_rejects_invalid_input() {
+        let (mut cx, mut cy, mut r) = (0.0, 0.0, 0.0);
+        let mut call = |pline: &Polyline<f64>, tolerance: f64| unsafe {
+            let input = cavc_pline(pline.clone());
+            cavc_pline_max_inscribed_circle(&input, tolerance, &mut cx, &mut cy, &mut r)
+        };
+        for tolerance in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert_eq!(call(&rect(0.0, 0.0, 1.0, 1.0), tolerance), 3);
+        }
+        let mut open = rect(0.0, 0.0, 1.0, 1.0);
+        open.set_is_closed(false);
+        assert_eq!(call(&open, 0.1), 2);
+        assert_eq!(call(&pline(&[(0.0, 0.0, 0.0)], true), 0.1), 2);
+        assert_eq!(call(&Polyline::new(), 0.1), 2);
+        let code =
+            unsafe { cavc_pline_max_inscribed_circle(null(), 0.1, &mut cx, &mut cy, &mut r) };
+        assert_eq!(code, 1);
+        assert_eq!((cx, cy, r), (0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn deviation_rejects_too_many_samples() {
+        let square = cavc_pline(rect(0.0, 0.0, 10.0, 10.0));
+        let inner = cavc_pline(rect(1.0, 1.0, 9.0, 9.0));
+        let mut out = 0.0;
+        let code = unsafe { cavc_pline_max_deviation(&inner, &square, 0.5, &mut out) };
+        assert_eq!(code, 0);
+        assert!((out - 1.0).abs() < 1e-9, "{}", out);
+        let code = unsafe { cavc_pline_hausdorff_distance(&square, &inner, 0.5, &mut out) };
+        assert_eq!(code, 0);
+        assert!((out - std::f64::consts::SQRT_2).abs() < 1e-9, "{}", out);
+
+        // only the sampled polyline counts, the reference may be long
+        let large = cavc_pline(rect(0.0, 0.0, 1000.0, 1000.0));
+        let small = cavc_pline(rect(1.0, 1.0, 2.0, 2.0));
+        let code = unsafe { cavc_pline_max_deviation(&small, &large, 1e-4, &mut out) };
+        assert_eq!(code, 0);
+        out = -1.0;
+        let code = unsafe { cavc_pline_max_deviation(&large, &small, 1e-4, &mut out) };
+        assert_eq!(code, 4);
+        let code = unsafe { cavc_pline_hausdorff_distance(&small, &large, 1e-4, &mut out) };
+        assert_eq!(code, 4);
+        assert_eq!(out, -1.0);
+    }
+}
@@ -0,0 +1,490 @@
+//! Approximate medial axis of regions.
+//!
+//! The boundary of the region is sampled densely and the medial axis is
+//! approximated by the edges of the Voronoi diagram of the samples that lie
+//! inside of the region, dropping the edges separating samples which are
+//! neighbors along the boundary (those only mark the boundary itself).
+use cavalier_contours::core::math::Vector2;
+use cavalier_contours::polyline::{PlineCreation, PlineSource, PlineSourceMut, Polyline};
+use cavalier_contours_ffi::cavc_plinelist;
+
+use crate::chain::chain_plines;
+use crate::measure::winding_number_indexed;
+use crate::path::{sample_at_steps, seg_lengths};
+use crate::predicates::{incircle, orient2d};
+use crate::shape::cavc_shape;
+use crate::util::{hilbert_key, plinelist_into_raw, SplitMix64};
+
+/// Largest number of boundary samples.
+const MAX_MEDIAL_SAMPLES: f64 = 1_000_000.0;
+
+/// Boundary sample, `pos` is the path length of the sample along its
+/// polyline.
+#[derive(Debug, Copy, Clone)]
+struct Sample {
+    point: Vector2<f64>,
+    contour: usize,
+    pos: f64,
+}
+
+/// Delaunay triangle in counter clockwise order, `adj[i]` is the triangle
+/// across the edge opposite of `v[i]`.
+#[derive(Debug, Copy, Clone)]
+struct Triangle {
+    v: [usize; 3],
+    adj: [Option<usize>; 3],
+}
+
+impl Triangle {
+    /// Edge opposite of `v[i]`, in counter clockwise order.
+    fn edge(&self, i: usize) -> (usize, usize) {
+        (self.v[(i + 1) % 3], self.v[(i + 2) % 3])
+    }
+
+    /// Index of the edge from `a` to `b`.
+    fn edge_index(&self, a: usize, b: usize) -> Option<usize> {
+        (0..3).find(|&i| self.edge(i) == (a, b))
+    }
+}
+
+/// Circumcenter of the triangle, None if it is too flat to have one.
+fn circumcenter(a: Vector2<f64>, b: Vector2<f64>, c: Vector2<f64>) -> Option<Vector2<f64>> {
+    let ab = b - a;
+    let ac = c - a;
+    let d = 2.0 * ab.perp_dot(ac);
+    let ab_sq = ab.dot(ab);
+    let ac_sq = ac.dot(ac);
+    let offset = Vector2::new(
+        (ac.y * ab_sq - ab.y * ac_sq) / d,
+        (ab.x * ac_sq - ac.x * ab_sq) / d,
+    );
+    if d == 0.0 || !offset.x.is_finite() || !offset.y.is_finite() {
+        return None;
+    }
+    Some(a + offset)
+}
+
+/// Triangle containing `p` (possibly on its boundary) found by walking from
+/// `start` across the edges `p` lies beyond, None if `p` is outside of the
+/// triangulation.
+fn locate(
+    points: &[Vector2<f64>],
+    triangles: &[Triangle],
+    start: usize,
+    p: Vector2<f64>,
+) -> Option<usize> {
+    let beyond = |t: &Triangle, i: usize| {
+        let (a, b) = t.edge(i);
+        orient2d(points[a], points[b], p) < 0.0
+    };
+    // with exact predicates the walk cannot cycle in a Delaunay
+    // triangulation, the bound only guards against inconsistent input
+    let mut t = start;
+    for _ in 0..triangles.len() {
+        match (0..3).find(|&i| beyond(&triangles[t], i)) {
+            Some(i) => t = triangles[t].adj[i]?,
+            None => return Some(t),
+        }
+    }
+    triangles
+        .iter()
+        .position(|tri| (0..3).all(|i| !beyond(tri, i)))
+}
+
+/// Biased randomized insertion order of `points`: shuffled, then split into
+/// rounds doubling in size, each sorted along a Hilbert curve. Random order
+/// keeps the cavities small (boundary order builds fans of thin triangles
+/// which every new point invalidates) while the sorting keeps the walks
+/// between consecutive points short. The shuffle is seeded so results are
+/// reproducible.
+fn insertion_order(points: &[Vector2<f64>]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..points.len()).collect();
+    let mut rng = SplitMix64(0);
+    for i in (1..order.len()).rev() {
+        order.swap(i, (rng.next_u64() % (i as u64 + 1)) as usize);
+    }
+    let (mut min, mut max) = (points[0], points[0]);
+    for p in points {
+        min = Vector2::new(min.x.min(p.x), min.y.min(p.y));
+        max = Vector2::new(max.x.max(p.x), max.y.max(p.y));
+    }
+    let size = (max.x - min.x).max(max.y - min.y);
+    let key = |i: usize| hilbert_key(points[i], min, size);
+    let mut end = order.len();
+    while end > 0 {
+        let start = if end > 64 { end / 2 } else { 0 };
+        order[start..end].sort_by_cached_key(|&i| key(i));
+        end = start;
+    }
+    order
+}
+
+/// Delaunay triangulation of `points` by incremental (Bowyer-Watson)
+/// insertion with exact predicates, in the order of [insertion_order] with
+/// every point located by walking from the triangle created last. The last
+/// three points must form a counter clockwise triangle containing all the
+/// others, triangles using them are part of the result. Points coinciding
+/// with an earlier one are skipped.
+fn delaunay(points: &[Vector2<f64>]) -> Vec<Triangle> {
+    let n = points.len() - 3;
+    let mut triangles = vec![Triangle {
+        v: [n, n + 1, n + 2],
+        adj: [None; 3],
+    }];
+    let mut last = 0;
+    let mut in_cavity = Vec::new();
+    let mut cavity = Vec::new();
+    let mut boundary = Vec::new();
+    let mut created = Vec::new();
+    for i in insertion_order(&points[..n]) {
+        let p = points[i];
+        let start = match locate(points, &triangles, last, p) {
+            Some(t) => t,
+            None => continue,
+        };
+        if triangles[start]
+            .v
+            .iter()
+            .any(|&v| points[v].x == p.x && points[v].y == p.y)
+        {
+            continue;
+        }
+        // triangles whose circumcircle contains the point form a star shaped
+        // cavity around it, replaced by a fan over the cavity boundary
+        in_cavity.resize(triangles.len(), false);
+        in_cavity[start] = true;
+        cavity.clear();
+        cavity.push(start);
+        let mut k = 0;
+        while k < cavity.len() {
+            for neighbor in triangles[cavity[k]].adj.into_iter().flatten() {
+                let [a, b, c] = triangles[neighbor].v;
+                if !in_cavity[neighbor] && incircle(points[a], points[b], points[c], p) > 0.0 {
+                    in_cavity[neighbor] = true;
+                    cavity.push(neighbor);
+                }
+            }
+            k += 1;
+        }
+        boundary.clear();
+        for &t in &cavity {
+            for e in 0..3 {
+                let outer = triangles[t].adj[e];
+                if !outer.map_or(false, |o| in_cavity[o]) {
+                    let (a, b) = triangles[t].edge(e);
+                    boundary.push((a, b, outer));
+                }
+            }
+        }
+        for &t in &cavity {
+            in_cavity[t] = false;
+        }
+
+        // the fan reuses the slots of the cavity, it has two more triangles
+        created.clear();
+        for (k, &(a, b, outer)) in boundary.iter().enumerate() {
+            let tri = Triangle {
+                v: [a, b, i],
+                adj: [None, None, outer],
+            };
+            let t = match cavity.get(k) {
+                Some(&t) => {
+                    triangles[t] = tri;
+                    t
+                }
+                None => {
+                    triangles.push(tri);
+                    triangles.len() - 1
+                }
+            };
+            if let Some(o) = outer {
+                if let Some(e) = triangles[o].edge_index(b, a) {
+                    triangles[o].adj[e] = Some(t);
+                }
+            }
+            created.push(t);
+        }
+        for (k, &(a, b, _)) in boundary.iter().enumerate() {
+            let next = boundary.iter().position(|&(start, _, _)| start == b);
+            let prev = boundary.iter().position(|&(_, end, _)| end == a);
+            let t = created[k];
+            triangles[t].adj[0] = next.map(|k| created[k]);
+            triangles[t].adj[1] = prev.map(|k| created[k]);
+        }
+        last = created[created.len() - 1];
+    }
+    triangles
+}
+
+/// Approximate medial axis of the region bounded by the closed polylines
+/// `plines` (non-zero winding rule), sampling the boundary at most
+/// `tolerance` apart. None if that takes more than `MAX_MEDIAL_SAMPLES`
+/// samples.
+fn medial_axis(plines: &[&Polyline<f64>], tolerance: f64) -> Option<Vec<Polyline<f64>>> {
+    let contours: Vec<(&Polyline<f64>, Vec<f64>, f64)> = plines
+        .iter()
+        .filter(|p| p.is_closed() && p.vertex_count() > 1)
+        .filter_map(|&pline| {
+            let lengths = seg_lengths(pline);
+            let total: f64 = lengths.iter().sum();
+            (total > 0.0).then_some((pline, lengths, total))
+        })
+        .collect();
+    let sample_count: f64 = contours
+        .iter()
+        .map(|(_, _, total)| (total / tolerance).ceil().max(3.0))
+        .sum();
+    if sample_count > MAX_MEDIAL_SAMPLES {
+        return None;
+    }
+    let mut samples = Vec::with_capacity(sample_count as usize);
+    let mut coords = Vec::new();
+    for (contour, (pline, lengths, total)) in contours.iter().enumerate() {
+        let count = ((total / tolerance).ceil() as usize).max(3);
+        let step = total / count as f64;
+        coords.clear();
+        sample_at_steps(pline, lengths, step, count, false, &mut coords);
+        samples.extend(coords.chunks_exact(2).enumerate().map(|(k, c)| Sample {
+            point: Vector2::new(c[0], c[1]),
+            contour,
+            pos: k as f64 * step,
+        }));
+    }
+    if samples.len() < 3 {
+        return Some(Vec::new());
+    }
+
+    // work relative to the center of the samples for precision and enclose
+    // them in a large triangle
+    let (mut min, mut max) = (samples[0].point, samples[0].point);
+    for s in &samples {
+        min = Vector2::new(min.x.min(s.point.x), min.y.min(s.point.y));
+        max = Vector2::new(max.x.max(s.point.x), max.y.max(s.point.y));
+    }
+    let mid = (min + max).scale(0.5);
+    let size = (max.x - min.x).max(max.y - min.y).max(tolerance);
+    let mut points: Vec<Vector2<f64>> = samples.iter().map(|s| s.point - mid).collect();
+    points.extend([
+        Vector2::new(-20.0 * size, -20.0 * size),
+        Vector2::new(20.0 * size, -20.0 * size),
+        Vector2::new(0.0, 20.0 * size),
+    ]);
+    let triangles = delaunay(&points);
+
+    // Voronoi vertexes, only those inside of the region are kept
+    let indexed: Vec<_> = contours
+        .iter()
+        .filter_map(|(pline, _, _)| {
+            let max_x = pline.extents()?.max_x;
+            Some((*pline, pline.create_approx_aabb_index(), max_x))
+        })
+        .collect();
+    let is_inside = |p: Vector2<f64>| {
+        indexed
+            .iter()
+            .map(|(pline, index, max_x)| winding_number_indexed(pline, index, *max_x, p))
+            .sum::<i32>()
+            != 0
+    };
+    let centers: Vec<Option<Vector2<f64>>> = triangles
+        .iter()
+        .map(|t| {
+            if t.v.iter().any(|&v| v >= samples.len()) {
+                return None;
+            }
+            let [a, b, c] = t.v.map(|v| points[v]);
+            let center = circumcenter(a, b, c)? + mid;
+            is_inside(center).then_some(center)
+        })
+        .collect();
+
+    // samples this close along the boundary are neighbors, the Voronoi edge
+    // between them leads to the boundary rather than along the medial axis
+    let neighbor_dist = 2.0 * tolerance;
+    let mut pieces = Vec::new();
+    for (t1, tri) in triangles.iter().enumerate() {
+        for e in 0..3 {
+            let t2 = match tri.adj[e] {
+                Some(t2) if t1 < t2 => t2,
+                _ => continue,
+            };
+            let (c1, c2) = match (centers[t1], centers[t2]) {
+                (Some(c1), Some(c2)) => (c1, c2),
+                _ => continue,
+            };
+            let (a, b) = tri.edge(e);
+            let (sa, sb) = (samples[a], samples[b]);
+            if sa.contour == sb.contour {
+                let d = (sa.pos - sb.pos).abs();
+                if d.min(contours[sa.contour].2 - d) <= neighbor_dist {
+                    continue;
+                }
+            }
+            if (c2 - c1).length() == 0.0 {
+                continue;
+            }
+            let mut piece = Polyline::with_capacity(2, false);
+            piece.add(c1.x, c1.y, 0.0);
+            piece.add(c2.x, c2.y, 0.0);
+            pieces.push(piece);
+        }
+    }
+    let pieces: Vec<&Polyline<f64>> = pieces.iter().collect();
+    Some(chain_plines(&pieces, tolerance * 1e-6))
+}
+
+/// Compute an approximate medial axis (centerline) of the region of the
+/// shape, e.g. for V-carving or single stroke engraving.
+///
+/// The boundary is sampled at most `tolerance` apart and the medial axis is
+/// approximated by the edges of the Voronoi diagram of the samples lying
+/// inside of the region, so its accuracy is about `tolerance`. Branches toward
+/// convex corners end about `tolerance` away from the corner and features
+/// narrower than `2 * tolerance` may be lost. Counter clockwise polylines of
+/// the shape add to and clockwise polylines subtract from the region. The
+/// edges are chained into open polylines, split at branching points, and
+/// loops around holes become closed polylines. The samples are triangulated
+/// with exact predicates in a randomized, spatially sorted order, which takes
+/// close to linear time in their number.
+///
+/// `result_plinelist` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `shape` or `result_plinelist` is null.
+/// * 2 = `tolerance` is not greater than 0 or not finite.
+/// * 3 = the boundary needs more than 1000000 samples at `tolerance`.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_shape_medial_axis(
+    shape: *const cavc_shape,
+    tolerance: f64,
+    result_plinelist: *mut *mut cavc_plinelist,
+) -> i32 {
+    ffi_catch_unwind!({
+        if shape.is_null() || result_plinelist.is_null() {
+            return 1;
+        }
+        if !tolerance.is_finite() || tolerance <= 0.0 {
+            return 2;
+        }
+        let shape = &(*shape).0;
+        let plines: Vec<&Polyline<f64>> = shape
+            .ccw_plines
+            .iter()
+            .chain(&shape.cw_plines)
+            .map(|p| &p.polyline)
+            .collect();
+        let axis = match medial_axis(&plines, tolerance) {
+            Some(axis) => axis,
+            None => return 3,
+        };
+        *result_plinelist = plinelist_into_raw(axis);
+        0
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::rect;
+
+    /// `points` followed by a triangle enclosing them as [delaunay] expects.
+    fn with_super_triangle(mut points: Vec<Vector2<f64>>) -> Vec<Vector2<f64>> {
+        points.extend([
+            Vector2::new(-1e3, -1e3),
+            Vector2::new(1e3, -1e3),
+            Vector2::new(0.0, 1e3),
+        ]);
+        points
+    }
+
+    /// Check that `triangles` form a Delaunay triangulation of `points` with
+    /// consistent adjacency, returning the number of triangles.
+    fn check_delaunay(points: &[Vector2<f64>], triangles: &[Triangle]) -> usize {
+        for (t, tri) in triangles.iter().enumerate() {
+            let [a, b, c] = tri.v.map(|v| points[v]);
+            assert!(orient2d(a, b, c) > 0.0);
+            for (k, p) in points.iter().enumerate() {
+                if !tri.v.contains(&k) {
+                    assert!(incircle(a, b, c, *p) <= 0.0, "{} in circle of {}", k, t);
+                }
+            }
+            for e in 0..3 {
+                if let Some(o) = tri.adj[e] {
+                    let (a, b) = tri.edge(e);
+                    let back = triangles[o].edge_index(b, a).unwrap();
+                    assert_eq!(triangles[o].adj[back], Some(t));
+                }
+            }
+        }
+        triangles.len()
+    }
+
+    #[test]
+    fn triangulates_random_points() {
+        let mut rng = SplitMix64(7);
+        let points: Vec<_> = (0..300)
+            .map(|_| Vector2::new(rng.next_f64() * 10.0, rng.next_f64() * 10.0))
+            .collect();
+        let points = with_super_triangle(points);
+        let count = check_delaunay(&points, &delaunay(&points));
+        // every point is inside of the super triangle
+        assert_eq!(count, 2 * 300 + 1);
+    }
+
+    #[test]
+    fn triangulates_degenerate_points() {
+        // cocircular grid points, collinear rows and duplicates
+        let mut points = Vec::new();
+        for i in 0..12 {
+            for j in 0..12 {
+                points.push(Vector2::new(i as f64, j as f64));
+            }
+        }
+        points.extend_from_within(0..20);
+        let points = with_super_triangle(points);
+        let count = check_delaunay(&points, &delaunay(&points));
+        assert_eq!(count, 2 * 144 + 1);
+    }
+
+    #[test]
+    fn rectangle_axis_runs_through_the_middle() {
+        let pline = rect(0.0, 0.0, 10.0, 2.0);
+        let axis = medial_axis(&[&pline], 0.05).unwrap();
+        assert!(!axis.is_empty());
+        let mut middle = 0;
+        for v in axis.iter().flat_map(|p| p.iter_vertexes()) {
+            assert!(v.x > 0.0 && v.x < 10.0 && v.y > 0.0 && v.y < 2.0);
+            if v.x > 2.0 && v.x < 8.0 {
+                assert!((v.y - 1.0).abs() < 0.05, "{:?}", v);
+                middle += 1;
+            }
+        }
+        assert!(middle > 0);
+    }
+
+    #[test]
+    fn axis_avoids_holes() {
+        let outer = rect(0.0, 0.0, 10.0, 10.0);
+        let mut hole = rect(3.0, 3.0, 7.0, 7.0);
+        hole.invert_direction_mut();
+        let axis = medial_axis(&[&outer, &hole], 0.1).unwrap();
+        assert!(!axis.is_empty());
+        for v in axis.iter().flat_map(|p| p.iter_vertexes()) {
+            let in_hole = v.x > 3.0 && v.x < 7.0 && v.y > 3.0 && v.y < 7.0;
+            assert!(!in_hole && v.x > 0.0 && v.x < 10.0 && v.y > 0.0 && v.y < 10.0);
+        }
+    }
+
+    #[test]
+    fn degenerate_input() {
+        assert!(medial_axis(&[], 0.1).unwrap().is_empty());
+        let mut point = Polyline::with_capacity(2, true);
+        point.add(1.0, 1.0, 0.0);
+        point.add(1.0, 1.0, 0.0);
+        let mut open = rect(0.0, 0.0, 1.0, 1.0);
+        open.set_is_closed(false);
+        assert!(medial_axis(&[&point, &open], 0.1).unwrap().is_empty());
+        // a tiny tolerance needs too many samples
+        assert!(medial_axis(&[&rect(0.0, 0.0, 10.0, 10.0)], 1e-6).is_none());
+    }
+}
@@ -0,0 +1,67 @@
+//! Introspection of the memory held by handles.
+//!
+//! Sizes are in bytes and count the allocations owned by a handle: the handle
+//! itself, its vertex storage and values cached for it (see [crate::cache]).
+//! Vertex storage is counted by the number of vertexes, capacity reserved
+//! beyond that is not visible through the polyline API.
+use std::mem::size_of;
+
+use cavalier_contours::polyline::{PlineSource, PlineVertex};
+use cavalier_contours_ffi::{cavc_pline, cavc_plinelist};
+
+use crate::cache::cached_heap_bytes;
+
+/// Heap bytes owned by the polyline handle `pline`.
+unsafe fn pline_heap_bytes(pline: *const cavc_pline) -> usize {
+    size_of::<cavc_pline>()
+        + (*pline).0.vertex_count() * size_of::<PlineVertex<f64>>()
+        + cached_heap_bytes(pline)
+}
+
+/// Get the number of heap bytes owned by the polyline handle, including its
+/// vertexes and any values cached for it by the `_cached` functions.
+///
+/// `out_bytes` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `out_bytes` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_memory_usage(
+    pline: *const cavc_pline,
+    out_bytes: *mut usize,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || out_bytes.is_null() {
+            return 1;
+        }
+        *out_bytes = pline_heap_bytes(pline);
+        0
+    })
+}
+
+/// Get the number of heap bytes owned by the polyline list handle, including
+/// all of its polylines (see [cavc_pline_memory_usage]).
+///
+/// `out_bytes` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `plinelist` or `out_bytes` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_plinelist_memory_usage(
+    plinelist: *const cavc_plinelist,
+    out_bytes: *mut usize,
+) -> i32 {
+    ffi_catch_unwind!({
+        if plinelist.is_null() || out_bytes.is_null() {
+            return 1;
+        }
+        let plines = &(*plinelist).0;
+        let mut bytes =
+            size_of::<cavc_plinelist>() + plines.capacity() * size_of::<*mut cavc_pline>();
+        for &pline in plines.iter() {
+            bytes += pline_heap_bytes(pline);
+        }
+        *out_bytes = bytes;
+        0
+    })
+}
@@ -0,0 +1,144 @@
+//! Minkowski sums of closed polylines.
+use cavalier_contours::core::math::Vector2;
+use cavalier_contours::polyline::{PlineCreation, PlineSource, PlineSourceMut, Polyline};
+use cavalier_contours_ffi::{cavc_pline, cavc_plinelist};
+
+use crate::boolean::{to_ccw, union_all};
+use crate::util::{hilbert_key, plinelist_into_raw};
+
+/// Copy of `pline` translated by `by`.
+fn translated(pline: &Polyline<f64>, by: Vector2<f64>) -> Polyline<f64> {
+    let mut pline = pline.clone();
+    pline.translate_mut(by.x, by.y);
+    pline
+}
+
+/// Parallelogram swept by segment `a1`-`a2` moving along `b1`-`b2`, None if
+/// the segments are parallel.
+fn edge_sum(
+    a1: Vector2<f64>,
+    a2: Vector2<f64>,
+    b1: Vector2<f64>,
+    b2: Vector2<f64>,
+    pos_equal_eps: f64,
+) -> Option<Polyline<f64>> {
+    let da = a2 - a1;
+    let db = b2 - b1;
+    if da.perp_dot(db).abs() <= pos_equal_eps * (da.length() + db.length()) {
+        return None;
+    }
+    let mut quad = Polyline::with_capacity(4, true);
+    for p in [a1 + b1, a2 + b1, a2 + b2, a1 + b2] {
+        quad.add(p.x, p.y, 0.0);
+    }
+    Some(to_ccw(&quad))
+}
+
+/// Minkowski sum of two closed polylines made of lines only, as the
+/// outer boundaries and holes of the result.
+///
+/// Uses the identity `A + B = (A + b) ∪ (a + B) ∪ (∂A + ∂B)` for any points
+/// `a` in `A` and `b` in `B`, where the boundary sum is the union of the
+/// parallelograms swept by every pair of segments.
+pub(crate) fn minkowski_sum(
+    a: &Polyline<f64>,
+    b: &Polyline<f64>,
+    pos_equal_eps: f64,
+) -> (Vec<Polyline<f64>>, Vec<Polyline<f64>>) {
+    let a_pts: Vec<_> = a.iter_vertexes().map(|v| v.pos()).collect();
+    let b_pts: Vec<_> = b.iter_vertexes().map(|v| v.pos()).collect();
+    let mut parts = vec![translated(a, b_pts[0]), translated(b, a_pts[0])];
+    for (i, &a1) in a_pts.iter().enumerate() {
+        let a2 = a_pts[(i + 1) % a_pts.len()];
+        for (j, &b1) in b_pts.iter().enumerate() {
+            let b2 = b_pts[(j + 1) % b_pts.len()];
+            parts.extend(edge_sum(a1, a2, b1, b2, pos_equal_eps));
+        }
+    }
+    // sorted along a Hilbert curve so every half of the divide and conquer
+    // union is a compact patch, most merges then only touch neighbors
+    let centers: Vec<Vector2<f64>> = parts
+        .iter()
+        .map(|p| {
+            let sum = p
+                .iter_vertexes()
+                .fold(Vector2::new(0.0, 0.0), |sum, v| sum + v.pos());
+            sum.scale(1.0 / p.vertex_count() as f64)
+        })
+        .collect();
+    let (mut min, mut max) = (centers[0], centers[0]);
+    for c in &centers {
+        min = Vector2::new(min.x.min(c.x), min.y.min(c.y));
+        max = Vector2::new(max.x.max(c.x), max.y.max(c.y));
+    }
+    let size = (max.x - min.x).max(max.y - min.y);
+    let mut keyed: Vec<(u64, Polyline<f64>)> = centers
+        .iter()
+        .map(|&c| hilbert_key(c, min, size))
+        .zip(parts)
+        .collect();
+    keyed.sort_by_key(|(key, _)| *key);
+    let regions = union_all(keyed.into_iter().map(|(_, p)| p), pos_equal_eps);
+    let mut outers = Vec::with_capacity(regions.len());
+    let mut holes = Vec::new();
+    for region in regions {
+        outers.push(region.outer);
+        holes.extend(region.holes);
+    }
+    (outers, holes)
+}
+
+/// Compute the Minkowski sum of two closed polylines.
+///
+/// Arc segments are approximated by lines deviating at most `tolerance` from
+/// the arcs before the sum is computed, so the result is made of lines only.
+/// `result_plinelist` receives the outer boundaries of the sum (counter
+/// clockwise) followed by its holes (clockwise), the sum of two connected
+/// polylines is connected so there is exactly one outer boundary unless an
+/// island lies inside a hole.
+///
+/// The computation unions a parallelogram for every pair of segments in one
+/// batch, spatially sorted so the divide and conquer union mostly merges
+/// neighbors, the cost still grows with the product of the vertex counts.
+/// The Minkowski difference `A - B` used for no fit polygons is the sum of `A`
+/// with `B` mirrored through the origin.
+///
+/// `result_plinelist` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline_a`, `pline_b` or `result_plinelist` is null.
+/// * 2 = `pline_a` or `pline_b` is not closed or has less than 3 vertexes.
+/// * 3 = `tolerance` is not greater than 0.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_minkowski_sum(
+    pline_a: *const cavc_pline,
+    pline_b: *const cavc_pline,
+    tolerance: f64,
+    result_plinelist: *mut *mut cavc_plinelist,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline_a.is_null() || pline_b.is_null() || result_plinelist.is_null() {
+            return 1;
+        }
+        let a = &(*pline_a).0;
+        let b = &(*pline_b).0;
+        if [a, b]
+            .iter()
+            .any(|p| !p.is_closed() || p.vertex_count() < 3)
+        {
+            return 2;
+        }
+        if tolerance.is_nan() || tolerance <= 0.0 {
+            return 3;
+        }
+        let linearize = |p: &Polyline<f64>| {
+            p.arcs_to_approx_lines(tolerance)
+                .unwrap_or_else(|| p.clone())
+        };
+        let pos_equal_eps = 1e-5_f64.min(0.1 * tolerance);
+        let (mut outers, holes) = minkowski_sum(&linearize(a), &linearize(b), pos_equal_eps);
+        outers.extend(holes);
+        *result_plinelist = plinelist_into_raw(outers);
+        0
+    })
+}
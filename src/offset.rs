@@ -0,0 +1,805 @@
+//! Parallel offset variants.
+use std::os::raw::c_void;
+
+use cavalier_contours::core::math::Vector2;
+use cavalier_contours::polyline::internal::pline_offset::{
+    create_raw_offset_polyline, slices_from_dual_raw_offsets, slices_from_raw_offset,
+    stitch_offset_slices_together,
+};
+use cavalier_contours::polyline::{
+    seg_arc_radius_and_center, PlineOffsetOptions, PlineSource, PlineVertex, Polyline,
+};
+use cavalier_contours_ffi::{
+    cavc_pline, cavc_pline_parallel_offset_o, cavc_plinelist, cavc_point, cavc_vertex,
+};
+
+use crate::boolean::{list_regions, region_and, region_plines, to_ccw, Region, FILL_ORIENTATION};
+use crate::buffer::cavc_buffer;
+use crate::chain::append;
+use crate::clip::clip_path_to_region;
+use crate::options::cavc_options;
+use crate::progress::{cavc_progress_fn, Cancelled, Progress, CAVC_CANCELLED};
+use crate::shape::cavc_shape;
+use crate::userdata::{get_tag, set_tag, tag_plinelist};
+use crate::util::{
+    free_raw, into_raw, parallel_map, plinelist_into_raw, to_cavc_point, to_cavc_vertex, valid_eps,
+};
+
+/// Callback polled by cancellable operations between the steps of the
+/// computation, returning non-zero requests cancellation. It is always invoked
+/// on the calling thread.
+pub type cavc_cancel_fn = Option<extern "C" fn(user_data: *mut c_void) -> u8>;
+
+/// Convert FFI offset options into internal ones, null means defaults.
+///
+/// The spatial index of the options is not carried over.
+pub(crate) unsafe fn offset_options(
+    options: *const cavc_pline_parallel_offset_o,
+) -> PlineOffsetOptions<'static, f64> {
+    let mut result = PlineOffsetOptions::new();
+    if let Some(options) = options.as_ref() {
+        result.pos_equal_eps = options.pos_equal_eps;
+        result.slice_join_eps = options.slice_join_eps;
+        result.offset_dist_eps = options.offset_dist_eps;
+        result.handle_self_intersects = options.handle_self_intersects != 0;
+    }
+    result
+}
+
+/// Whether all epsilons of the offset options are valid, see [valid_eps].
+pub(crate) fn valid_offset_eps(options: &PlineOffsetOptions<f64>) -> bool {
+    [
+        options.pos_equal_eps,
+        options.slice_join_eps,
+        options.offset_dist_eps,
+    ]
+    .into_iter()
+    .all(valid_eps)
+}
+
+/// Parallel offset of `pline` computed step by step the same way as
+/// `parallel_offset_opt` does (raw offset, slices, stitching), calling `check`
+/// with the fraction done before every step so the computation can be
+/// stopped between them.
+pub(crate) fn parallel_offset_steps<E>(
+    pline: &Polyline<f64>,
+    offset: f64,
+    options: &PlineOffsetOptions<f64>,
+    check: &mut dyn FnMut(f64) -> Result<(), E>,
+) -> Result<Vec<Polyline<f64>>, E> {
+    check(0.0)?;
+    if pline.vertex_count() < 2 {
+        return Ok(Vec::new());
+    }
+    let index = pline.create_approx_aabb_index();
+    let raw_offset: Polyline<f64> =
+        create_raw_offset_polyline(pline, offset, options.pos_equal_eps);
+    if raw_offset.vertex_count() == 0 {
+        return Ok(Vec::new());
+    }
+    check(0.25)?;
+    let result = if pline.is_closed() && !options.handle_self_intersects {
+        let slices = slices_from_raw_offset(pline, &raw_offset, &index, offset, options);
+        check(0.75)?;
+        stitch_offset_slices_together(
+            &raw_offset,
+            &slices,
+            true,
+            raw_offset.vertex_count() - 1,
+            options,
+        )
+    } else {
+        let dual_raw_offset: Polyline<f64> =
+            create_raw_offset_polyline(pline, -offset, options.pos_equal_eps);
+        check(0.4)?;
+        let slices = slices_from_dual_raw_offsets(
+            pline,
+            &raw_offset,
+            &dual_raw_offset,
+            &index,
+            offset,
+            options,
+        );
+        check(0.75)?;
+        stitch_offset_slices_together(
+            &raw_offset,
+            &slices,
+            pline.is_closed(),
+            raw_offset.vertex_count(),
+            options,
+        )
+    };
+    Ok(result)
+}
+
+/// Cancellation callback of [cavc_pline_parallel_offset_cancellable] and its
+/// user data, passed to [cancel_progress] as its user data.
+struct CancelAdapter {
+    should_cancel: cavc_cancel_fn,
+    user_data: *mut c_void,
+}
+
+/// [cavc_progress_fn] polling the [CancelAdapter] pointed to by `user_data`.
+extern "C" fn cancel_progress(_progress: f64, user_data: *mut c_void) -> i32 {
+    let adapter = unsafe { &*(user_data as *const CancelAdapter) };
+    match adapter.should_cancel {
+        Some(should_cancel) => should_cancel(adapter.user_data) as i32,
+        None => 0,
+    }
+}
+
+/// Same as `cavc_pline_parallel_offset` but can be cancelled.
+///
+/// Thin wrapper over [cavc_pline_parallel_offset_with_progress] for callers
+/// that only need cancellation: `should_cancel` is invoked with `user_data`
+/// where that function reports progress, once it returns non-zero the
+/// function returns [CAVC_CANCELLED]. If `should_cancel` is null this behaves
+/// exactly like the non-cancellable variant.
+///
+/// `result` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `result` is null.
+/// * 2 = a tolerance of `options` is negative, NaN or infinite.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_parallel_offset_cancellable(
+    pline: *const cavc_pline,
+    offset: f64,
+    options: *const cavc_pline_parallel_offset_o,
+    should_cancel: cavc_cancel_fn,
+    user_data: *mut c_void,
+    result: *mut *mut cavc_plinelist,
+) -> i32 {
+    ffi_catch_unwind!({
+        let mut adapter = CancelAdapter {
+            should_cancel,
+            user_data,
+        };
+        let adapter = &mut adapter as *mut CancelAdapter as *mut c_void;
+        cavc_pline_parallel_offset_with_progress(
+            pline,
+            offset,
+            options,
+            Some(cancel_progress),
+            adapter,
+            result,
+        )
+    })
+}
+
+/// Same as `cavc_pline_parallel_offset` but reports its progress to
+/// `progress` and can be cancelled by it.
+///
+/// The offset is computed on the calling thread in the steps of
+/// `cavalier_contours` (raw offset, its dual for open or self intersecting
+/// polylines, slicing at intersects, stitching the slices) and `progress` is
+/// invoked with `user_data` before every step (at most every few
+/// milliseconds) with the fraction done, and with 1 once the offset is done.
+/// Once it returns non-zero the offset stops before the next step and the
+/// function returns [CAVC_CANCELLED], a single step is not interrupted. If
+/// `progress` is null this behaves exactly like the non-cancellable variant.
+/// `options` may be null in which case default options are used, the
+/// `aabb_index` of the options is ignored.
+///
+/// `result` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `result` is null.
+/// * 2 = a tolerance of `options` is negative, NaN or infinite.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_parallel_offset_with_progress(
+    pline: *const cavc_pline,
+    offset: f64,
+    options: *const cavc_pline_parallel_offset_o,
+    progress: cavc_progress_fn,
+    user_data: *mut c_void,
+    result: *mut *mut cavc_plinelist,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || result.is_null() {
+            return 1;
+        }
+        let options = offset_options(options);
+        if !valid_offset_eps(&options) {
+            return 2;
+        }
+        let mut progress = Progress::new(progress, user_data);
+        let mut check = |fraction| progress.report(fraction);
+        match parallel_offset_steps(&(*pline).0, offset, &options, &mut check) {
+            Ok(offsets) => {
+                progress.finish();
+                *result = plinelist_into_raw(offsets);
+                tag_plinelist(*result, get_tag(pline));
+                0
+            }
+            Err(Cancelled) => CAVC_CANCELLED,
+        }
+    })
+}
+
+/// Raw offset of a single polyline segment before any trimming.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct cavc_raw_offset_seg {
+    /// Start vertex of the offset segment, holding its bulge.
+    pub v1: cavc_vertex,
+    /// End vertex of the offset segment (bulge is always 0).
+    pub v2: cavc_vertex,
+    /// End position of the original segment this was offset from.
+    pub orig_v2_pos: cavc_point,
+    /// 1 if the segment is an arc that collapsed (offset is larger than its
+    /// radius toward its center), in which case `v1` to `v2` is a line through
+    /// the side of the arc center opposite to the original arc, otherwise 0.
+    pub collapsed_arc: u8,
+}
+
+/// Opaque type holding the result of [cavc_pline_raw_offset_segments].
+pub struct cavc_raw_offset_segs(Vec<cavc_raw_offset_seg>);
+
+/// Offset a single segment, positive `offset` is to the left of the segment
+/// direction.
+fn raw_offset_seg(v1: PlineVertex<f64>, v2: PlineVertex<f64>, offset: f64) -> cavc_raw_offset_seg {
+    if v1.bulge_is_zero() {
+        let dir = v2.pos() - v1.pos();
+        let shift = if dir.length() > 0.0 {
+            dir.unit_perp().scale(offset)
+        } else {
+            Vector2::zero()
+        };
+        return cavc_raw_offset_seg {
+            v1: to_cavc_vertex(PlineVertex::from_vector2(v1.pos() + shift, 0.0)),
+            v2: to_cavc_vertex(PlineVertex::from_vector2(v2.pos() + shift, 0.0)),
+            orig_v2_pos: to_cavc_point(v2.pos()),
+            collapsed_arc: 0,
+        };
+    }
+
+    let (radius, center) = seg_arc_radius_and_center(v1, v2);
+    // left of a counter clockwise arc is toward its center
+    let offset_radius = if v1.bulge_is_neg() {
+        radius + offset
+    } else {
+        radius - offset
+    };
+    let collapsed = offset_radius <= 0.0;
+    let offset_point = |p: Vector2<f64>| center + (p - center).normalize().scale(offset_radius);
+    let bulge = if collapsed { 0.0 } else { v1.bulge };
+    cavc_raw_offset_seg {
+        v1: to_cavc_vertex(PlineVertex::from_vector2(offset_point(v1.pos()), bulge)),
+        v2: to_cavc_vertex(PlineVertex::from_vector2(offset_point(v2.pos()), 0.0)),
+        orig_v2_pos: to_cavc_point(v2.pos()),
+        collapsed_arc: collapsed as u8,
+    }
+}
+
+/// Compute the raw offset segments of a polyline.
+///
+/// These are the segments of the polyline individually offset by `offset`
+/// before they are joined, trimmed and any self intersections are resolved,
+/// mainly useful for visualizing how the offset is constructed. Segment `i`
+/// of the result is the offset of segment `i` of `pline`.
+///
+/// `result` is only written to if the function returns 0 (success) and must be
+/// freed with [cavc_raw_offset_segs_f].
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `result` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_raw_offset_segments(
+    pline: *const cavc_pline,
+    offset: f64,
+    result: *mut *mut cavc_raw_offset_segs,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || result.is_null() {
+            return 1;
+        }
+        let segs = (*pline)
+            .0
+            .iter_segments()
+            .map(|(v1, v2)| raw_offset_seg(v1, v2, offset))
+            .collect();
+        *result = into_raw(cavc_raw_offset_segs(segs));
+        0
+    })
+}
+
+/// Free an existing [cavc_raw_offset_segs] object. Nothing happens if `segs` is
+/// null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_raw_offset_segs_f(segs: *mut cavc_raw_offset_segs) {
+    free_raw(segs);
+}
+
+/// Get the number of raw offset segments.
+///
+/// ## Specific Error Codes
+/// * 1 = `segs` or `count` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_raw_offset_segs_get_count(
+    segs: *const cavc_raw_offset_segs,
+    count: *mut u32,
+) -> i32 {
+    ffi_catch_unwind!({
+        if segs.is_null() || count.is_null() {
+            return 1;
+        }
+        *count = (*segs).0.len() as u32;
+        0
+    })
+}
+
+/// Get the raw offset segment at `index`.
+///
+/// ## Specific Error Codes
+/// * 1 = `segs` or `seg` is null.
+/// * 2 = `index` is out of bounds.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_raw_offset_segs_get(
+    segs: *const cavc_raw_offset_segs,
+    index: u32,
+    seg: *mut cavc_raw_offset_seg,
+) -> i32 {
+    ffi_catch_unwind!({
+        if segs.is_null() || seg.is_null() {
+            return 1;
+        }
+        let segs = &(*segs).0;
+        match segs.get(index as usize) {
+            Some(s) => {
+                *seg = *s;
+                0
+            }
+            None => 2,
+        }
+    })
+}
+
+/// Parallel offset a polyline by `distance` to both sides in a single call.
+///
+/// `pos_result` receives the offsets by `distance` and `neg_result` the
+/// offsets by `-distance`, the same as calling `cavc_pline_parallel_offset`
+/// twice. The spatial index of the polyline is built once and shared by both
+/// offsets. `options` may be null in which case default options are used, its
+/// `aabb_index` is ignored.
+///
+/// `pos_result` and `neg_result` are only written to if the function returns 0
+/// (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline`, `pos_result` or `neg_result` is null.
+/// * 2 = a tolerance of `options` is negative, NaN or infinite.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_parallel_offset_both(
+    pline: *const cavc_pline,
+    distance: f64,
+    options: *const cavc_pline_parallel_offset_o,
+    pos_result: *mut *mut cavc_plinelist,
+    neg_result: *mut *mut cavc_plinelist,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || pos_result.is_null() || neg_result.is_null() {
+            return 1;
+        }
+        let tag = get_tag(pline);
+        let pline = &(*pline).0;
+        let index = (pline.segment_count() > 0).then(|| pline.create_approx_aabb_index());
+        let mut options = offset_options(options);
+        if !valid_offset_eps(&options) {
+            return 2;
+        }
+        options.aabb_index = index.as_ref();
+        let pos = pline.parallel_offset_opt(distance, &options);
+        let neg = pline.parallel_offset_opt(-distance, &options);
+        *pos_result = plinelist_into_raw(pos);
+        *neg_result = plinelist_into_raw(neg);
+        tag_plinelist(*pos_result, tag);
+        tag_plinelist(*neg_result, tag);
+        0
+    })
+}
+
+/// Parallel offset a polyline honoring every field of `options`, including
+/// its `aabb_index`.
+///
+/// Unlike the other offset variants a non-null `aabb_index` is used rather
+/// than ignored. It must be an index of `pline` in its current state (e.g. from
+/// `cavc_pline_create_approx_aabb_index`), passing it avoids rebuilding it
+/// when offsetting the same polyline repeatedly. `options` may be null in
+/// which case default options are used.
+///
+/// `result` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `result` is null.
+/// * 2 = a tolerance is negative, NaN or infinite.
+/// * 3 = `aabb_index` does not hold one box per segment of `pline`.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_parallel_offset_full(
+    pline: *const cavc_pline,
+    distance: f64,
+    options: *const cavc_pline_parallel_offset_o,
+    result: *mut *mut cavc_plinelist,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || result.is_null() {
+            return 1;
+        }
+        let tag = get_tag(pline);
+        let pline = &(*pline).0;
+        let mut internal = offset_options(options);
+        if !valid_offset_eps(&internal) {
+            return 2;
+        }
+        if let Some(index) = options.as_ref().and_then(|o| o.aabb_index.as_ref()) {
+            if index.0.count() != pline.segment_count() {
+                return 3;
+            }
+            internal.aabb_index = Some(&index.0);
+        }
+        *result = plinelist_into_raw(pline.parallel_offset_opt(distance, &internal));
+        tag_plinelist(*result, tag);
+        0
+    })
+}
+
+/// Parallel offset a polyline using the tolerances of the shared `options`,
+/// which may be null in which case defaults are used.
+///
+/// Otherwise the same as `cavc_pline_parallel_offset`, `handle_self_intersects`
+/// is non-zero if the polyline may intersect itself.
+///
+/// `result` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `result` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_parallel_offset_opt(
+    pline: *const cavc_pline,
+    offset: f64,
+    handle_self_intersects: u8,
+    options: *const cavc_options,
+    result: *mut *mut cavc_plinelist,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || result.is_null() {
+            return 1;
+        }
+        let shared = cavc_options::or_default(options);
+        let mut internal = PlineOffsetOptions::new();
+        internal.pos_equal_eps = shared.pos_equal_eps;
+        internal.slice_join_eps = shared.slice_join_eps;
+        internal.offset_dist_eps = shared.offset_dist_eps;
+        internal.handle_self_intersects = handle_self_intersects != 0;
+        *result = plinelist_into_raw((*pline).0.parallel_offset_opt(offset, &internal));
+        tag_plinelist(*result, get_tag(pline));
+        0
+    })
+}
+
+/// Compute `count` successive parallel offsets of a polyline, each offset by
+/// `step` from the previous one, as used for pocketing tool paths.
+///
+/// Every offset is computed from the polylines of the previous one rather
+/// than from `pline`, so each step only works on the (usually smaller)
+/// previous result. Stops early once an offset is empty (e.g. the pocket is
+/// fully covered). `result_plinelist` receives the polylines of all offsets
+/// in order, the polylines of the first offset first. Default offset options
+/// are used.
+///
+/// `result_plinelist` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `result_plinelist` is null.
+/// * 2 = `step` is 0, NaN or infinite.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_offset_multi(
+    pline: *const cavc_pline,
+    step: f64,
+    count: u32,
+    result_plinelist: *mut *mut cavc_plinelist,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || result_plinelist.is_null() {
+            return 1;
+        }
+        if !step.is_finite() || step == 0.0 {
+            return 2;
+        }
+        let options = PlineOffsetOptions::new();
+        let mut offsets = Vec::new();
+        let mut level = vec![(*pline).0.clone()];
+        for _ in 0..count {
+            level = level
+                .iter()
+                .flat_map(|p| p.parallel_offset_opt(step, &options))
+                .collect();
+            if level.is_empty() {
+                break;
+            }
+            offsets.extend(level.iter().cloned());
+        }
+        *result_plinelist = plinelist_into_raw(offsets);
+        tag_plinelist(*result_plinelist, get_tag(pline));
+        0
+    })
+}
+
+/// Parallel offset every polyline of the list by `offset`, offsetting the
+/// polylines concurrently on all available cores.
+///
+/// The offsets of all polylines are combined into `result` in the order of
+/// the input, the offsets of the first polyline first. Every polyline is
+/// offset on its own, offsets of different polylines are neither joined nor
+/// trimmed against each other. If `result_source_indexes` is not null it
+/// receives the index of the input polyline every polyline of `result` is an
+/// offset of. `options` may be null in which case default options are used,
+/// the `aabb_index` of the options is ignored.
+///
+/// `result` and `result_source_indexes` are only written to if the function
+/// returns 0 (success), the latter must be freed with
+/// [crate::buffer::cavc_buffer_f].
+///
+/// ## Specific Error Codes
+/// * 1 = `plinelist` or `result` is null.
+/// * 2 = a tolerance of `options` is negative, NaN or infinite.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_plinelist_parallel_offset(
+    plinelist: *const cavc_plinelist,
+    offset: f64,
+    options: *const cavc_pline_parallel_offset_o,
+    result: *mut *mut cavc_plinelist,
+    result_source_indexes: *mut *mut cavc_buffer,
+) -> i32 {
+    ffi_catch_unwind!({
+        if plinelist.is_null() || result.is_null() {
+            return 1;
+        }
+        let options = offset_options(options);
+        if !valid_offset_eps(&options) {
+            return 2;
+        }
+        let plines: Vec<&Polyline<f64>> = (*plinelist).0.iter().map(|&p| &(*p).0).collect();
+        let offsets = parallel_map(plines.len(), |i| {
+            plines[i].parallel_offset_opt(offset, &options)
+        });
+        let mut combined = Vec::new();
+        let mut source_indexes = Vec::new();
+        for (i, pline_offsets) in offsets.into_iter().enumerate() {
+            source_indexes.extend(std::iter::repeat(i as u32).take(pline_offsets.len()));
+            combined.extend(pline_offsets);
+        }
+        *result = plinelist_into_raw(combined);
+        for (&offset_pline, &i) in (*(*result)).0.iter().zip(&source_indexes) {
+            set_tag(offset_pline, get_tag((*plinelist).0[i as usize]));
+        }
+        if !result_source_indexes.is_null() {
+            *result_source_indexes = into_raw(cavc_buffer::from(source_indexes));
+        }
+        0
+    })
+}
+
+/// Pieces of the closed polyline `pline` inside the area bounded by
+/// `boundaries` as paths, see [clip_path_to_region]. The piece running
+/// through the start of `pline` is not split there and `pline` is returned
+/// as is if it lies entirely inside.
+fn clip_closed_path(
+    pline: &Polyline<f64>,
+    boundaries: &[&Polyline<f64>],
+    pos_equal_eps: f64,
+) -> Vec<Polyline<f64>> {
+    let mut pieces = clip_path_to_region(pline, boundaries, true, pos_equal_eps);
+    let start = pline.at(0).pos();
+    let at_start = |p: &Polyline<f64>, i: usize| p.at(i).pos().fuzzy_eq_eps(start, pos_equal_eps);
+    let wraps = match (pieces.first(), pieces.last()) {
+        (Some(first), Some(last)) => at_start(first, 0) && at_start(last, last.vertex_count() - 1),
+        _ => false,
+    };
+    if !wraps {
+        return pieces;
+    }
+    if pieces.len() == 1 {
+        return vec![pline.clone()];
+    }
+    let first = pieces.remove(0);
+    let mut wrapped = pieces.pop().unwrap();
+    append(&mut wrapped, &first);
+    pieces.insert(0, wrapped);
+    pieces
+}
+
+/// Parallel offset a polyline and clip the offset to the area of a shape, as
+/// used for pocketing inside a stock boundary.
+///
+/// The area of `boundary` is bounded by its counter clockwise polylines with
+/// its clockwise polylines as holes. Open offset polylines (offsets of open
+/// polylines) are split where they cross the boundary and give the open
+/// pieces inside of it, parts running along the boundary count as inside.
+/// Closed offset polylines are clipped depending on `clip_as_paths`:
+/// * 0 = as areas, giving the regions of their area inside the boundary, each
+///   as its outer boundary (counter clockwise) followed by its holes
+///   (clockwise).
+/// * non-zero = as paths like open offsets (e.g. for tool paths), giving the
+///   open pieces inside the boundary in path order. A piece running through
+///   the start of the offset is not split there and an offset entirely inside
+///   the boundary stays closed.
+///
+/// `options` may be null in which case default options are used, the
+/// `aabb_index` of the options is ignored.
+///
+/// `result` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline`, `boundary` or `result` is null.
+/// * 2 = a tolerance of `options` is negative, NaN or infinite.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_offset_clipped(
+    pline: *const cavc_pline,
+    offset: f64,
+    boundary: *const cavc_shape,
+    clip_as_paths: u8,
+    options: *const cavc_pline_parallel_offset_o,
+    result: *mut *mut cavc_plinelist,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || boundary.is_null() || result.is_null() {
+            return 1;
+        }
+        let options = offset_options(options);
+        if !valid_offset_eps(&options) {
+            return 2;
+        }
+        let eps = options.pos_equal_eps;
+        let shape = &(*boundary).0;
+        let boundaries: Vec<&Polyline<f64>> = shape
+            .ccw_plines
+            .iter()
+            .chain(shape.cw_plines.iter())
+            .map(|p| &p.polyline)
+            .collect();
+        let regions = list_regions(&boundaries, FILL_ORIENTATION, eps);
+        let mut plines = Vec::new();
+        for offset_pline in (*pline).0.parallel_offset_opt(offset, &options) {
+            if offset_pline.is_closed() && clip_as_paths != 0 {
+                plines.extend(clip_closed_path(&offset_pline, &boundaries, eps));
+            } else if offset_pline.is_closed() && offset_pline.vertex_count() > 1 {
+                let region = Region {
+                    outer: to_ccw(&offset_pline),
+                    holes: Vec::new(),
+                };
+                let clipped = regions.iter().flat_map(|r| region_and(&region, r, eps));
+                plines.extend(region_plines(clipped.collect()));
+            } else {
+                plines.extend(clip_path_to_region(&offset_pline, &boundaries, true, eps));
+            }
+        }
+        *result = plinelist_into_raw(plines);
+        tag_plinelist(*result, get_tag(pline));
+        0
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ptr::{null, null_mut};
+
+    use super::*;
+    use crate::handle::{cavc_pline_release, cavc_plinelist_release};
+    use crate::test_util::{pline, rect};
+    use crate::util::pline_into_raw;
+
+    fn assert_same_offsets(actual: &[Polyline<f64>], expected: &[Polyline<f64>]) {
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(expected) {
+            assert_eq!(a.is_closed(), e.is_closed());
+            assert_eq!(a.vertex_count(), e.vertex_count());
+            for (va, ve) in a.iter_vertexes().zip(e.iter_vertexes()) {
+                assert!((va.x - ve.x).abs() < 1e-9, "{:?} != {:?}", va, ve);
+                assert!((va.y - ve.y).abs() < 1e-9, "{:?} != {:?}", va, ve);
+                assert!((va.bulge - ve.bulge).abs() < 1e-9, "{:?} != {:?}", va, ve);
+            }
+        }
+    }
+
+    fn test_plines() -> Vec<(Polyline<f64>, bool)> {
+        vec![
+            (rect(0.0, 0.0, 10.0, 5.0), false),
+            (
+                pline(
+                    &[(0.0, 0.0, 0.0), (10.0, 0.0, 0.5), (10.0, 10.0, 0.0)],
+                    false,
+                ),
+                false,
+            ),
+            // a bow tie crossing itself in the middle
+            (
+                pline(
+                    &[
+                        (0.0, 0.0, 0.0),
+                        (10.0, 10.0, 0.0),
+                        (10.0, 0.0, 0.0),
+                        (0.0, 10.0, 0.0),
+                    ],
+                    true,
+                ),
+                true,
+            ),
+        ]
+    }
+
+    extern "C" fn always_cancel(_user_data: *mut c_void) -> u8 {
+        1
+    }
+
+    #[test]
+    fn steps_match_parallel_offset() {
+        for (pline, handle_self_intersects) in test_plines() {
+            let mut options = PlineOffsetOptions::new();
+            options.handle_self_intersects = handle_self_intersects;
+            for offset in [-2.0, -0.5, 0.5, 2.0] {
+                let steps =
+                    parallel_offset_steps::<()>(&pline, offset, &options, &mut |_| Ok(())).unwrap();
+                assert_same_offsets(&steps, &pline.parallel_offset_opt(offset, &options));
+            }
+        }
+    }
+
+    #[test]
+    fn cancellable_offset() {
+        for (pline, handle_self_intersects) in test_plines() {
+            let options = cavc_pline_parallel_offset_o {
+                aabb_index: null(),
+                pos_equal_eps: 1e-5,
+                slice_join_eps: 1e-4,
+                offset_dist_eps: 1e-4,
+                handle_self_intersects: handle_self_intersects as u8,
+            };
+            unsafe {
+                let expected = pline.parallel_offset_opt(1.0, &offset_options(&options));
+                let handle = pline_into_raw(pline);
+                let mut result: *mut cavc_plinelist = null_mut();
+                let code = cavc_pline_parallel_offset_cancellable(
+                    handle,
+                    1.0,
+                    &options,
+                    None,
+                    null_mut(),
+                    &mut result,
+                );
+                assert_eq!(code, 0);
+                let offsets: Vec<_> = (*result).0.iter().map(|&p| (*p).0.clone()).collect();
+                assert_same_offsets(&offsets, &expected);
+                cavc_plinelist_release(result);
+
+                result = null_mut();
+                let code = cavc_pline_parallel_offset_cancellable(
+                    handle,
+                    1.0,
+                    &options,
+                    Some(always_cancel),
+                    null_mut(),
+                    &mut result,
+                );
+                assert_eq!(code, CAVC_CANCELLED);
+                assert!(result.is_null());
+
+                let invalid = cavc_pline_parallel_offset_o {
+                    pos_equal_eps: f64::NAN,
+                    ..options
+                };
+                let code = cavc_pline_parallel_offset_cancellable(
+                    handle,
+                    1.0,
+                    &invalid,
+                    None,
+                    null_mut(),
+                    &mut result,
+                );
+                assert_eq!(code, 2);
+                assert!(result.is_null());
+                cavc_pline_release(handle);
+            }
+        }
+    }
+}
@@ -0,0 +1,131 @@
+//! Shared tolerance options.
+//!
+//! A [cavc_options] handle holds the epsilons used by intersect, offset and
+//! boolean operations so an application can keep one consistent set of
+//! tolerances and pass it to the `_opt` functions. Every epsilon an operation
+//! does not use is ignored by it. The tolerances are validated when they are
+//! set (see [cavc_options_set]), so the `_opt` functions do not check them
+//! again.
+use cavalier_contours::polyline::PlineOffsetOptions;
+
+use crate::util::{free_raw, into_raw, valid_eps};
+
+/// Opaque type holding shared tolerances, see the module documentation.
+#[derive(Debug, Copy, Clone)]
+pub struct cavc_options {
+    /// Fuzzy comparison epsilon used for determining if two positions are
+    /// equal.
+    pub(crate) pos_equal_eps: f64,
+    /// Fuzzy comparison epsilon used for determining if two positions are
+    /// equal when stitching offset slices together.
+    pub(crate) slice_join_eps: f64,
+    /// Fuzzy comparison epsilon used when testing distance of offset slices
+    /// to the original polyline for validity.
+    pub(crate) offset_dist_eps: f64,
+}
+
+impl Default for cavc_options {
+    fn default() -> Self {
+        let defaults = PlineOffsetOptions::<f64>::new();
+        Self {
+            pos_equal_eps: defaults.pos_equal_eps,
+            slice_join_eps: defaults.slice_join_eps,
+            offset_dist_eps: defaults.offset_dist_eps,
+        }
+    }
+}
+
+impl cavc_options {
+    /// The options behind `options`, or defaults if it is null.
+    pub(crate) unsafe fn or_default(options: *const cavc_options) -> cavc_options {
+        options.as_ref().copied().unwrap_or_default()
+    }
+}
+
+/// Create a new options handle holding the default tolerances.
+///
+/// `options` is only written to if the function returns 0 (success) and must
+/// be freed with [cavc_options_f].
+///
+/// ## Specific Error Codes
+/// * 1 = `options` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_options_create(options: *mut *mut cavc_options) -> i32 {
+    ffi_catch_unwind!({
+        if options.is_null() {
+            return 1;
+        }
+        *options = into_raw(cavc_options::default());
+        0
+    })
+}
+
+/// Free an existing [cavc_options] object. Nothing happens if `options` is
+/// null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_options_f(options: *mut cavc_options) {
+    free_raw(options);
+}
+
+/// Set all tolerances of the options.
+///
+/// Nothing is modified if an error is returned.
+///
+/// ## Specific Error Codes
+/// * 1 = `options` is null.
+/// * 2 = a tolerance is negative, NaN or infinite.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_options_set(
+    options: *mut cavc_options,
+    pos_equal_eps: f64,
+    slice_join_eps: f64,
+    offset_dist_eps: f64,
+) -> i32 {
+    ffi_catch_unwind!({
+        if options.is_null() {
+            return 1;
+        }
+        if [pos_equal_eps, slice_join_eps, offset_dist_eps]
+            .iter()
+            .any(|&eps| !valid_eps(eps))
+        {
+            return 2;
+        }
+        *options = cavc_options {
+            pos_equal_eps,
+            slice_join_eps,
+            offset_dist_eps,
+        };
+        0
+    })
+}
+
+/// Get all tolerances of the options.
+///
+/// The out parameters are only written to if the function returns 0
+/// (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `options` or any of the out parameters is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_options_get(
+    options: *const cavc_options,
+    pos_equal_eps: *mut f64,
+    slice_join_eps: *mut f64,
+    offset_dist_eps: *mut f64,
+) -> i32 {
+    ffi_catch_unwind!({
+        if options.is_null()
+            || pos_equal_eps.is_null()
+            || slice_join_eps.is_null()
+            || offset_dist_eps.is_null()
+        {
+            return 1;
+        }
+        let options = &*options;
+        *pos_equal_eps = options.pos_equal_eps;
+        *slice_join_eps = options.slice_join_eps;
+        *offset_dist_eps = options.offset_dist_eps;
+        0
+    })
+}
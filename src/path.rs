@@ -0,0 +1,1088 @@
+//! Queries and edits by path length (arc length measured along the polyline
+//! from its first vertex).
+use cavalier_contours::core::math::Vector2;
+use cavalier_contours::polyline::{
+    seg_length, seg_split_at_point, PlineCreation, PlineSource, PlineSourceMut, PlineVertex,
+    Polyline,
+};
+use cavalier_contours_ffi::{cavc_pline, cavc_plinelist, cavc_point};
+
+use crate::buffer::cavc_buffer;
+use crate::geom::{seg_curvature, seg_point_at, seg_sub, seg_tangent_at};
+use crate::measure::closest_point_indexed;
+use crate::util::{into_raw, pline_into_raw, plinelist_into_raw, to_cavc_point, valid_eps};
+
+/// Position on a polyline, `t` is the parameter on segment `seg_index` (see
+/// [crate::geom]).
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct PathPos {
+    pub seg_index: usize,
+    pub t: f64,
+}
+
+/// Lengths of all segments of the polyline, in segment order.
+pub(crate) fn seg_lengths(pline: &Polyline<f64>) -> Vec<f64> {
+    pline
+        .iter_segments()
+        .map(|(v1, v2)| seg_length(v1, v2))
+        .collect()
+}
+
+/// Find the position at path length `s`, clamped to the polyline.
+///
+/// Path lengths falling on a vertex resolve to the end of the segment before
+/// it. The polyline must have at least one segment.
+pub(crate) fn locate(lengths: &[f64], s: f64) -> PathPos {
+    debug_assert!(!lengths.is_empty());
+    let mut acc = 0.0;
+    for (i, &len) in lengths.iter().enumerate() {
+        if s <= acc + len {
+            let t = if len > 0.0 {
+                ((s - acc) / len).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            return PathPos { seg_index: i, t };
+        }
+        acc += len;
+    }
+    PathPos {
+        seg_index: lengths.len() - 1,
+        t: 1.0,
+    }
+}
+
+/// Point and unit tangent at path length `s`, clamped to the polyline.
+///
+/// On a zero length segment the tangent is taken from the nearest segment of
+/// non-zero length after the position (or before it at the end of an open
+/// polyline), it is a zero vector only if the whole polyline has zero length.
+pub(crate) fn point_and_tangent_at(
+    pline: &Polyline<f64>,
+    lengths: &[f64],
+    s: f64,
+) -> (Vector2<f64>, Vector2<f64>) {
+    let pos = locate(lengths, s);
+    let seg = |i: usize| (pline.at(i), pline.at(pline.next_wrapping_index(i)));
+    let (v1, v2) = seg(pos.seg_index);
+    let point = seg_point_at(v1, v2, pos.t);
+    if lengths[pos.seg_index] > 0.0 {
+        return (point, seg_tangent_at(v1, v2, pos.t));
+    }
+    let count = lengths.len();
+    let after = (pos.seg_index + 1..count).chain(0..if pline.is_closed() { count } else { 0 });
+    let before = (0..pos.seg_index).rev();
+    let tangent = after
+        .map(|i| (i, 0.0))
+        .chain(before.map(|i| (i, 1.0)))
+        .find(|&(i, _)| lengths[i] > 0.0)
+        .map(|(i, t)| {
+            let (v1, v2) = seg(i);
+            seg_tangent_at(v1, v2, t)
+        })
+        .unwrap_or_else(Vector2::zero);
+    (point, tangent)
+}
+
+/// Path length at `point` lying on segment `seg_index`.
+pub(crate) fn path_length_at_point(
+    pline: &Polyline<f64>,
+    lengths: &[f64],
+    seg_index: usize,
+    point: Vector2<f64>,
+    pos_equal_eps: f64,
+) -> f64 {
+    let v1 = pline.at(seg_index);
+    let v2 = pline.at(pline.next_wrapping_index(seg_index));
+    let split = seg_split_at_point(v1, v2, point, pos_equal_eps);
+    let before: f64 = lengths[..seg_index].iter().sum();
+    before + seg_length(split.updated_start, split.split_vertex)
+}
+
+/// Open polyline following `pline` for `length` starting at path length
+/// `start`.
+///
+/// Closed polylines are followed across their closing segment and around
+/// again as needed, open polylines stop at their last vertex. The polyline must
+/// have at least one segment.
+pub(crate) fn sub_path(pline: &Polyline<f64>, start: f64, length: f64) -> Polyline<f64> {
+    let lengths = seg_lengths(pline);
+    let pos = locate(&lengths, start);
+    sub_path_from(pline, &lengths, pos, length)
+}
+
+/// Same as [sub_path] starting at `pos` with the segment `lengths` already
+/// computed.
+pub(crate) fn sub_path_from(
+    pline: &Polyline<f64>,
+    lengths: &[f64],
+    pos: PathPos,
+    length: f64,
+) -> Polyline<f64> {
+    let seg_count = lengths.len();
+    let mut result = Polyline::new();
+    let mut remaining = length.max(0.0);
+    let mut i = pos.seg_index;
+    let mut offset = pos.t * lengths[i];
+    // guard against looping forever on a closed polyline of zero length
+    let mut zero_steps = 0;
+    loop {
+        let len = lengths[i];
+        let v1 = pline.at(i);
+        let v2 = pline.at(pline.next_wrapping_index(i));
+        let avail = len - offset;
+        let is_last = !pline.is_closed() && i + 1 == seg_count;
+        if remaining <= avail || is_last || zero_steps > seg_count {
+            let end = if len > 0.0 {
+                ((offset + remaining) / len).min(1.0)
+            } else {
+                1.0
+            };
+            let t0 = if len > 0.0 { offset / len } else { 0.0 };
+            let (start_v, end_pos) = seg_sub(v1, v2, t0, end);
+            result.add_vertex(start_v);
+            result.add_vertex(PlineVertex::from_vector2(end_pos, 0.0));
+            break;
+        }
+        if avail > 0.0 {
+            let (start_v, _) = seg_sub(v1, v2, offset / len, 1.0);
+            result.add_vertex(start_v);
+            remaining -= avail;
+            zero_steps = 0;
+        } else {
+            zero_steps += 1;
+        }
+        offset = 0.0;
+        i = pline.next_wrapping_index(i);
+    }
+    result
+}
+
+/// Extract the part of the polyline between path lengths `start_length` and
+/// `end_length` as a new open polyline.
+///
+/// For open polylines the positions are clamped to the polyline and
+/// `start_length` must not be greater than `end_length`. For closed polylines
+/// the positions wrap around the path length, if `end_length` comes before
+/// `start_length` the slice runs across the closing segment, and if both are
+/// the same position the whole loop starting there is returned. Arc segments
+/// are cut exactly. `pline` is left unchanged.
+///
+/// `result` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `result` is null.
+/// * 2 = `pline` has less than 2 vertexes.
+/// * 3 = `start_length` or `end_length` is not finite, or `start_length` is
+///   greater than `end_length` for an open polyline.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_slice(
+    pline: *const cavc_pline,
+    start_length: f64,
+    end_length: f64,
+    result: *mut *mut cavc_pline,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || result.is_null() {
+            return 1;
+        }
+        let pline = &(*pline).0;
+        if pline.vertex_count() < 2 {
+            return 2;
+        }
+        if !start_length.is_finite() || !end_length.is_finite() {
+            return 3;
+        }
+        let total = pline.path_length();
+        let slice = if pline.is_closed() {
+            let (start, end) = if total > 0.0 {
+                (start_length.rem_euclid(total), end_length.rem_euclid(total))
+            } else {
+                (0.0, 0.0)
+            };
+            let length = if end > start {
+                end - start
+            } else {
+                end - start + total
+            };
+            sub_path(pline, start, length)
+        } else {
+            if start_length > end_length {
+                return 3;
+            }
+            let start = start_length.clamp(0.0, total);
+            let end = end_length.clamp(0.0, total);
+            sub_path(pline, start, end - start)
+        };
+        *result = pline_into_raw(slice);
+        0
+    })
+}
+
+/// Extract the segments `start_seg` to `end_seg` (both included) of the
+/// polyline as a new open polyline.
+///
+/// Segment `i` starts at vertex `i`, as the segment indexes of intersects.
+/// For closed polylines the range wraps around across the closing segment if
+/// `end_seg` is less than `start_seg`, `start_seg` equal to `end_seg` is the
+/// single segment. `pline` is left unchanged.
+///
+/// `result` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `result` is null.
+/// * 2 = `start_seg` or `end_seg` is not less than the number of segments.
+/// * 3 = `start_seg` is greater than `end_seg` for an open polyline.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_extract_range(
+    pline: *const cavc_pline,
+    start_seg: u32,
+    end_seg: u32,
+    result: *mut *mut cavc_pline,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || result.is_null() {
+            return 1;
+        }
+        let pline = &(*pline).0;
+        let (start, end) = (start_seg as usize, end_seg as usize);
+        let seg_count = pline.segment_count();
+        if start >= seg_count || end >= seg_count {
+            return 2;
+        }
+        if start > end && !pline.is_closed() {
+            return 3;
+        }
+        let n = pline.vertex_count();
+        let count = (end + n - start) % n + 1;
+        let mut range = Polyline::with_capacity(count + 1, false);
+        for i in 0..count {
+            range.add_vertex(pline.at((start + i) % n));
+        }
+        let last = pline.at((end + 1) % n);
+        range.add_vertex(last.with_bulge(0.0));
+        *result = pline_into_raw(range);
+        0
+    })
+}
+
+/// Split the polyline at the point closest to `(x, y)`.
+///
+/// For an open polyline `result1` receives the part from the start to the split
+/// point and `result2` the part from the split point to the end. If the split
+/// point is within `pos_equal_eps` of the start (or end) there is nothing
+/// before (or after) it and `result1` (or `result2`) is set to null.
+///
+/// A closed polyline is not cut into two pieces, instead `result1` receives a
+/// copy of it rotated to start at the split point and `result2` is set to null.
+///
+/// `result1` and `result2` are only written to if the function returns 0
+/// (success). `pline` is left unchanged.
+///
+/// ## Specific Error Codes
+/// * 1 = `pline`, `result1` or `result2` is null.
+/// * 2 = `pline` has less than 2 vertexes.
+/// * 3 = `pos_equal_eps` is negative, NaN or infinite.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_split_at_point(
+    pline: *const cavc_pline,
+    x: f64,
+    y: f64,
+    pos_equal_eps: f64,
+    result1: *mut *mut cavc_pline,
+    result2: *mut *mut cavc_pline,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || result1.is_null() || result2.is_null() {
+            return 1;
+        }
+        if !valid_eps(pos_equal_eps) {
+            return 3;
+        }
+        let pline = &(*pline).0;
+        if pline.vertex_count() < 2 {
+            return 2;
+        }
+        let closest = match pline.closest_point(Vector2::new(x, y), pos_equal_eps) {
+            Some(c) => c,
+            None => return 2,
+        };
+        let lengths = seg_lengths(pline);
+        let total: f64 = lengths.iter().sum();
+        let at = path_length_at_point(
+            pline,
+            &lengths,
+            closest.seg_start_index,
+            closest.seg_point,
+            pos_equal_eps,
+        );
+        if pline.is_closed() {
+            let mut rotated = sub_path(pline, at, total);
+            // the open loop ends where it starts
+            rotated.remove_last();
+            rotated.set_is_closed(true);
+            *result1 = pline_into_raw(rotated);
+            *result2 = std::ptr::null_mut();
+        } else {
+            *result1 = if at > pos_equal_eps {
+                pline_into_raw(sub_path(pline, 0.0, at))
+            } else {
+                std::ptr::null_mut()
+            };
+            *result2 = if total - at > pos_equal_eps {
+                pline_into_raw(sub_path(pline, at, total - at))
+            } else {
+                std::ptr::null_mut()
+            };
+        }
+        0
+    })
+}
+
+/// Compute the distance along the polyline between the points on it closest
+/// to `(x1, y1)` and `(x2, y2)`.
+///
+/// Both points are projected onto the polyline as by
+/// `cavc_pline_closest_point`. For an open polyline the result is the path
+/// length between the projections, for a closed polyline it is the shorter of
+/// the two ways around. The result is never negative and does not depend on
+/// the order of the points.
+///
+/// `out_length` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `out_length` is null.
+/// * 2 = `pline` has less than 2 vertexes.
+/// * 3 = `pos_equal_eps` is negative or not finite.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_path_length_between(
+    pline: *const cavc_pline,
+    x1: f64,
+    y1: f64,
+    x2: f64,
+    y2: f64,
+    pos_equal_eps: f64,
+    out_length: *mut f64,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || out_length.is_null() {
+            return 1;
+        }
+        let pline = &(*pline).0;
+        if pline.vertex_count() < 2 {
+            return 2;
+        }
+        if !valid_eps(pos_equal_eps) {
+            return 3;
+        }
+        let lengths = seg_lengths(pline);
+        let total: f64 = lengths.iter().sum();
+        let project = |x: f64, y: f64| {
+            pline
+                .closest_point(Vector2::new(x, y), pos_equal_eps)
+                .map(|c| {
+                    path_length_at_point(
+                        pline,
+                        &lengths,
+                        c.seg_start_index,
+                        c.seg_point,
+                        pos_equal_eps,
+                    )
+                })
+        };
+        let (s1, s2) = match (project(x1, y1), project(x2, y2)) {
+            (Some(s1), Some(s2)) => (s1, s2),
+            _ => return 2,
+        };
+        let between = (s2 - s1).abs();
+        *out_length = if pline.is_closed() {
+            between.min(total - between).max(0.0)
+        } else {
+            between
+        };
+        0
+    })
+}
+
+/// Compute the unit tangent (direction of travel) and unit normal at path
+/// length `s` along the polyline.
+///
+/// The normal is the tangent rotated 90 degrees counter clockwise (pointing to
+/// the left of the direction of travel). Both are evaluated exactly on arc
+/// segments. `s` is clamped to the polyline for open polylines and wraps around
+/// for closed polylines. At a vertex joining two segments the direction at
+/// the end of the segment arriving there is used.
+///
+/// The outputs are only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or any of the outputs is null.
+/// * 2 = `pline` has less than 2 vertexes.
+/// * 3 = `s` is not finite.
+/// * 4 = `pline` has zero length so there is no direction.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_direction_at_path_length(
+    pline: *const cavc_pline,
+    s: f64,
+    tangent_x: *mut f64,
+    tangent_y: *mut f64,
+    normal_x: *mut f64,
+    normal_y: *mut f64,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null()
+            || tangent_x.is_null()
+            || tangent_y.is_null()
+            || normal_x.is_null()
+            || normal_y.is_null()
+        {
+            return 1;
+        }
+        let pline = &(*pline).0;
+        if pline.vertex_count() < 2 {
+            return 2;
+        }
+        if !s.is_finite() {
+            return 3;
+        }
+        let lengths = seg_lengths(pline);
+        let total: f64 = lengths.iter().sum();
+        if total <= 0.0 {
+            return 4;
+        }
+        let s = if pline.is_closed() {
+            s.rem_euclid(total)
+        } else {
+            s.clamp(0.0, total)
+        };
+        let (_, tangent) = point_and_tangent_at(pline, &lengths, s);
+        *tangent_x = tangent.x;
+        *tangent_y = tangent.y;
+        *normal_x = -tangent.y;
+        *normal_y = tangent.x;
+        0
+    })
+}
+
+/// Wrap or clamp path length `s` onto the polyline of length `total`, see
+/// [cavc_pline_curvature_at_path_length].
+fn wrap_length(pline: &Polyline<f64>, total: f64, s: f64) -> f64 {
+    if pline.is_closed() && total > 0.0 {
+        s.rem_euclid(total)
+    } else {
+        s.clamp(0.0, total)
+    }
+}
+
+/// Compute the signed curvature at path length `s` along the polyline.
+///
+/// The curvature is `1 / radius` on counter clockwise (left turning) arcs,
+/// `-1 / radius` on clockwise arcs and 0 on lines, so it is exact and
+/// piecewise constant. `s` is clamped to the polyline for open polylines and
+/// wraps around for closed polylines. At a vertex joining two segments the
+/// curvature of the segment arriving there is used.
+///
+/// `out_curvature` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `out_curvature` is null.
+/// * 2 = `pline` has less than 2 vertexes.
+/// * 3 = `s` is not finite.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_curvature_at_path_length(
+    pline: *const cavc_pline,
+    s: f64,
+    out_curvature: *mut f64,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || out_curvature.is_null() {
+            return 1;
+        }
+        let pline = &(*pline).0;
+        if pline.vertex_count() < 2 {
+            return 2;
+        }
+        if !s.is_finite() {
+            return 3;
+        }
+        let lengths = seg_lengths(pline);
+        let total: f64 = lengths.iter().sum();
+        let pos = locate(&lengths, wrap_length(pline, total, s));
+        let next = pline.next_wrapping_index(pos.seg_index);
+        *out_curvature = seg_curvature(pline.at(pos.seg_index), pline.at(next));
+        0
+    })
+}
+
+/// Compute the signed curvature at each of `count` path lengths along the
+/// polyline, see [cavc_pline_curvature_at_path_length].
+///
+/// `lengths_buffer` holds the path lengths and `out_buffer` receives one
+/// curvature per path length, the path lengths do not need to be sorted. The
+/// buffers may be null if `count` is 0.
+///
+/// `out_buffer` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` is null or a buffer is null while `count` is not 0.
+/// * 2 = `pline` has less than 2 vertexes.
+/// * 3 = a path length is not finite.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_curvatures_at_path_lengths(
+    pline: *const cavc_pline,
+    lengths_buffer: *const f64,
+    count: u32,
+    out_buffer: *mut f64,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || ((lengths_buffer.is_null() || out_buffer.is_null()) && count != 0) {
+            return 1;
+        }
+        let pline = &(*pline).0;
+        if pline.vertex_count() < 2 {
+            return 2;
+        }
+        if count == 0 {
+            return 0;
+        }
+        let stations = std::slice::from_raw_parts(lengths_buffer, count as usize);
+        if stations.iter().any(|s| !s.is_finite()) {
+            return 3;
+        }
+        let out = std::slice::from_raw_parts_mut(out_buffer, count as usize);
+        let lengths = seg_lengths(pline);
+        let ends = seg_ends(&lengths);
+        let total = ends[ends.len() - 1];
+        let curvatures: Vec<f64> = pline
+            .iter_segments()
+            .map(|(v1, v2)| seg_curvature(v1, v2))
+            .collect();
+        for (&s, curvature) in stations.iter().zip(out.iter_mut()) {
+            let pos = locate_sorted(&lengths, &ends, wrap_length(pline, total, s));
+            *curvature = curvatures[pos.seg_index];
+        }
+        0
+    })
+}
+
+/// Convert path length `s` into the position on the polyline given by segment
+/// index and parameter.
+///
+/// The parameter `t` is the fraction of the segment length from its start
+/// vertex, for lines and arcs alike, so `(index, t)` stays a valid position
+/// if other segments of the polyline are changed. `s` is clamped to the
+/// polyline for open polylines and wraps around for closed polylines. A path
+/// length falling on a vertex resolves to the end (`t = 1`) of the segment
+/// arriving there, except at the very start.
+///
+/// `index` and `t` are only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline`, `index` or `t` is null.
+/// * 2 = `pline` has less than 2 vertexes.
+/// * 3 = `s` is not finite.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_length_to_param(
+    pline: *const cavc_pline,
+    s: f64,
+    index: *mut u32,
+    t: *mut f64,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || index.is_null() || t.is_null() {
+            return 1;
+        }
+        let pline = &(*pline).0;
+        if pline.vertex_count() < 2 {
+            return 2;
+        }
+        if !s.is_finite() {
+            return 3;
+        }
+        let lengths = seg_lengths(pline);
+        let total: f64 = lengths.iter().sum();
+        let s = if pline.is_closed() && total > 0.0 {
+            s.rem_euclid(total)
+        } else {
+            s.clamp(0.0, total)
+        };
+        let pos = locate(&lengths, s);
+        *index = pos.seg_index as u32;
+        *t = pos.t;
+        0
+    })
+}
+
+/// Convert the position given by segment `index` and parameter `t` (see
+/// [cavc_pline_length_to_param]) into the path length from the start of the
+/// polyline.
+///
+/// `s` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `s` is null.
+/// * 2 = `index` is not less than the number of segments.
+/// * 3 = `t` is not between 0 and 1.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_param_to_length(
+    pline: *const cavc_pline,
+    index: u32,
+    t: f64,
+    s: *mut f64,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || s.is_null() {
+            return 1;
+        }
+        let pline = &(*pline).0;
+        let index = index as usize;
+        if index >= pline.segment_count() {
+            return 2;
+        }
+        if !(0.0..=1.0).contains(&t) {
+            return 3;
+        }
+        let lengths = seg_lengths(pline);
+        *s = lengths[..index].iter().sum::<f64>() + t * lengths[index];
+        0
+    })
+}
+
+/// Extended `cavc_pline_find_point_at_path_length` also giving the unit
+/// tangent (direction of travel) at the point and the path length remaining to
+/// the end of the polyline.
+///
+/// Negative `s` is measured back from the end, so `-1.0` is the point 1 before
+/// the end. The tangent follows the same rules as
+/// [cavc_pline_direction_at_path_length] and is a zero vector if the polyline
+/// has zero length. `tangent` and `remaining` may be null if not needed.
+///
+/// The outputs are only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `point` is null.
+/// * 2 = `pline` has less than 2 vertexes.
+/// * 3 = `s` is not finite or its magnitude is greater than the path length.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_find_point_at_path_length_ex(
+    pline: *const cavc_pline,
+    s: f64,
+    point: *mut cavc_point,
+    tangent: *mut cavc_point,
+    remaining: *mut f64,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || point.is_null() {
+            return 1;
+        }
+        let pline = &(*pline).0;
+        if pline.vertex_count() < 2 {
+            return 2;
+        }
+        let lengths = seg_lengths(pline);
+        let total: f64 = lengths.iter().sum();
+        if !s.is_finite() || s.abs() > total {
+            return 3;
+        }
+        let s = if s < 0.0 { total + s } else { s };
+        let (p, dir) = point_and_tangent_at(pline, &lengths, s);
+        *point = to_cavc_point(p);
+        if !tangent.is_null() {
+            *tangent = to_cavc_point(dir);
+        }
+        if !remaining.is_null() {
+            *remaining = total - s;
+        }
+        0
+    })
+}
+
+/// Maximum number of pattern repetitions along the polyline in
+/// [cavc_pline_dash].
+const MAX_DASHES: f64 = 1_000_000.0;
+
+/// Split the polyline into dashes following an on/off `pattern` of path
+/// lengths.
+///
+/// `pattern` holds `pattern_len` lengths alternating between dashes (on) and
+/// gaps (off), starting with a dash. An odd number of lengths is repeated to
+/// make it even, like SVG `stroke-dasharray`. The pattern starts `phase` into
+/// it at the start of the polyline (negative values shift it the other way).
+/// `result_plinelist` receives the dashes as open polylines in path order,
+/// dashes of length 0 are kept as two coincident vertexes (dots). Arc segments
+/// are cut exactly. For closed polylines a dash running across the start is
+/// returned as one piece.
+///
+/// `result_plinelist` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `result_plinelist` is null, or `pattern` is null while
+///   `pattern_len` is not 0.
+/// * 2 = `pline` has less than 2 vertexes.
+/// * 3 = `pattern` is empty, has a negative, NaN or infinite length or all
+///   lengths are 0.
+/// * 4 = `phase` is not finite.
+/// * 5 = the pattern is too short for the path length, it would be repeated
+///   more than 1000000 times.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_dash(
+    pline: *const cavc_pline,
+    pattern: *const f64,
+    pattern_len: u32,
+    phase: f64,
+    result_plinelist: *mut *mut cavc_plinelist,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || result_plinelist.is_null() || (pattern.is_null() && pattern_len != 0)
+        {
+            return 1;
+        }
+        let pline = &(*pline).0;
+        if pline.vertex_count() < 2 {
+            return 2;
+        }
+        let mut dashes: Vec<f64> = if pattern_len == 0 {
+            Vec::new()
+        } else {
+            std::slice::from_raw_parts(pattern, pattern_len as usize).to_vec()
+        };
+        if dashes.is_empty() || dashes.iter().any(|d| !d.is_finite() || *d < 0.0) {
+            return 3;
+        }
+        if dashes.len() % 2 == 1 {
+            dashes.extend_from_within(..);
+        }
+        let period: f64 = dashes.iter().sum();
+        if period <= 0.0 {
+            return 3;
+        }
+        if !phase.is_finite() {
+            return 4;
+        }
+
+        // find where in the pattern the polyline starts
+        let mut k = 0;
+        let mut rem = dashes[0];
+        let mut skip = phase.rem_euclid(period);
+        while skip >= rem {
+            skip -= rem;
+            k = (k + 1) % dashes.len();
+            rem = dashes[k];
+        }
+        rem -= skip;
+
+        let lengths = seg_lengths(pline);
+        let total: f64 = lengths.iter().sum();
+        if period < total / MAX_DASHES {
+            return 5;
+        }
+        // (start, length) of the dashes
+        let mut pieces: Vec<(f64, f64)> = Vec::new();
+        let mut s = 0.0;
+        while s < total {
+            let is_gap = k % 2 == 1;
+            if !is_gap {
+                pieces.push((s, rem.min(total - s)));
+            }
+            s += rem;
+            k = (k + 1) % dashes.len();
+            rem = dashes[k];
+        }
+        if pline.is_closed() && pieces.len() > 1 {
+            let (first_start, first_len) = pieces[0];
+            let (last_start, last_len) = pieces[pieces.len() - 1];
+            if first_start == 0.0 && last_start + last_len >= total {
+                pieces.remove(0);
+                let last = pieces.len() - 1;
+                pieces[last] = (last_start, last_len + first_len);
+            }
+        }
+
+        let ends = seg_ends(&lengths);
+        let pos_at = |s: f64| locate_sorted(&lengths, &ends, s);
+        let plines = pieces
+            .into_iter()
+            .map(|(start, length)| sub_path_from(pline, &lengths, pos_at(start), length))
+            .collect();
+        *result_plinelist = plinelist_into_raw(plines);
+        0
+    })
+}
+
+/// Path length at the end of every segment, the running sum of `lengths`.
+pub(crate) fn seg_ends(lengths: &[f64]) -> Vec<f64> {
+    let mut acc = 0.0;
+    lengths
+        .iter()
+        .map(|len| {
+            acc += len;
+            acc
+        })
+        .collect()
+}
+
+/// Same as [locate] using a binary search over the segment `ends` (see
+/// [seg_ends]).
+pub(crate) fn locate_sorted(lengths: &[f64], ends: &[f64], s: f64) -> PathPos {
+    let i = ends.partition_point(|&end| end < s).min(lengths.len() - 1);
+    let len = lengths[i];
+    let t = if len > 0.0 {
+        ((s - (ends[i] - len)) / len).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    PathPos { seg_index: i, t }
+}
+
+/// Split the polyline into `piece_count` open polylines of equal path length,
+/// e.g. to place tabs or machine a contour piece by piece.
+///
+/// The pieces follow the polyline in order starting at its first vertex,
+/// pieces of closed polylines run across the closing segment as needed. Arc
+/// segments are cut exactly. `pline` is left unchanged.
+///
+/// `result_plinelist` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `result_plinelist` is null.
+/// * 2 = `pline` has less than 2 vertexes.
+/// * 3 = `piece_count` is 0.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_split_even(
+    pline: *const cavc_pline,
+    piece_count: u32,
+    result_plinelist: *mut *mut cavc_plinelist,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || result_plinelist.is_null() {
+            return 1;
+        }
+        let pline = &(*pline).0;
+        if pline.vertex_count() < 2 {
+            return 2;
+        }
+        if piece_count == 0 {
+            return 3;
+        }
+        let lengths = seg_lengths(pline);
+        let ends = seg_ends(&lengths);
+        let total = ends[ends.len() - 1];
+        let step = total / piece_count as f64;
+        let pos_at = |s: f64| locate_sorted(&lengths, &ends, s);
+        let plines = (0..piece_count)
+            .map(|k| sub_path_from(pline, &lengths, pos_at(k as f64 * step), step))
+            .collect();
+        *result_plinelist = plinelist_into_raw(plines);
+        0
+    })
+}
+
+/// Break `pline` at the sorted path lengths `cuts`, all strictly inside the
+/// path (and distinct around the loop for closed polylines).
+fn break_at_lengths(pline: &Polyline<f64>, lengths: &[f64], cuts: &[f64]) -> Vec<Polyline<f64>> {
+    let ends = seg_ends(lengths);
+    let total = ends[ends.len() - 1];
+    let piece = |start: f64, end: f64| {
+        let pos = locate_sorted(lengths, &ends, start);
+        sub_path_from(pline, lengths, pos, end - start)
+    };
+    if pline.is_closed() {
+        // the last piece runs across the closing segment back to the first cut
+        let first = cuts[0];
+        cuts.iter()
+            .enumerate()
+            .map(|(i, &start)| piece(start, cuts.get(i + 1).copied().unwrap_or(first + total)))
+            .collect()
+    } else {
+        let bounds: Vec<f64> = std::iter::once(0.0)
+            .chain(cuts.iter().copied())
+            .chain(std::iter::once(total))
+            .collect();
+        bounds.windows(2).map(|w| piece(w[0], w[1])).collect()
+    }
+}
+
+/// Break the polyline at the points closest to each of `count` points.
+///
+/// `points_buffer` holds the points interleaved as `[x0, y0, x1, y1, ...]`.
+/// All points are projected onto the original polyline and it is cut at all
+/// of them in one pass, so the result does not depend on the order of the
+/// points and arc segments are cut exactly. Projections within
+/// `pos_equal_eps` of each other are merged, as are projections within
+/// `pos_equal_eps` of the ends of an open polyline.
+///
+/// The pieces are open polylines in path order. For an open polyline they
+/// run from the start to the end, for a closed polyline they start at the
+/// first break point (by path length) and the last piece runs across the
+/// closing segment back to it, so a single break point gives one piece
+/// running around the whole loop. Without any break points the list holds a
+/// copy of `pline`. `pline` is left unchanged. `points_buffer` may be null if
+/// `count` is 0.
+///
+/// `result_plinelist` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `result_plinelist` is null, or `points_buffer` is null
+///   while `count` is not 0.
+/// * 2 = `pline` has less than 2 vertexes.
+/// * 3 = `pos_equal_eps` is negative, NaN or infinite.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_break_at_points(
+    pline: *const cavc_pline,
+    points_buffer: *const f64,
+    count: u32,
+    pos_equal_eps: f64,
+    result_plinelist: *mut *mut cavc_plinelist,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || result_plinelist.is_null() || (points_buffer.is_null() && count != 0)
+        {
+            return 1;
+        }
+        if !valid_eps(pos_equal_eps) {
+            return 3;
+        }
+        let pline = &(*pline).0;
+        if pline.vertex_count() < 2 {
+            return 2;
+        }
+        let points: &[f64] = if count == 0 {
+            &[]
+        } else {
+            std::slice::from_raw_parts(points_buffer, 2 * count as usize)
+        };
+        let lengths = seg_lengths(pline);
+        let total: f64 = lengths.iter().sum();
+        let index = pline.create_approx_aabb_index();
+        let mut cuts: Vec<f64> = points
+            .chunks_exact(2)
+            .map(|p| {
+                // always some since the polyline has vertexes
+                let (seg_index, closest, _) =
+                    closest_point_indexed(pline, &index, Vector2::new(p[0], p[1]), pos_equal_eps)
+                        .unwrap();
+                path_length_at_point(pline, &lengths, seg_index, closest, pos_equal_eps)
+            })
+            .filter(|&s| pline.is_closed() || (s > pos_equal_eps && total - s > pos_equal_eps))
+            .collect();
+        cuts.sort_by(|a, b| a.total_cmp(b));
+        cuts.dedup_by(|b, a| *b - *a <= pos_equal_eps);
+        if pline.is_closed()
+            && cuts.len() > 1
+            && cuts[0] + total - cuts[cuts.len() - 1] <= pos_equal_eps
+        {
+            cuts.pop();
+        }
+        let plines = if cuts.is_empty() {
+            vec![pline.clone()]
+        } else {
+            break_at_lengths(pline, &lengths, &cuts)
+        };
+        *result_plinelist = plinelist_into_raw(plines);
+        0
+    })
+}
+
+/// Sample the polyline at `count` path lengths `0, step, 2 * step, ...`,
+/// appending x, y (and tangent x, y if `include_tangents` is set) per sample to
+/// `out`.
+pub(crate) fn sample_at_steps(
+    pline: &Polyline<f64>,
+    lengths: &[f64],
+    step: f64,
+    count: usize,
+    include_tangents: bool,
+    out: &mut Vec<f64>,
+) {
+    let mut i = 0;
+    let mut acc = 0.0;
+    for k in 0..count {
+        let s = k as f64 * step;
+        // samples are increasing so the segment is found by walking forward
+        while i + 1 < lengths.len() && s > acc + lengths[i] {
+            acc += lengths[i];
+            i += 1;
+        }
+        let len = lengths[i];
+        let v1 = pline.at(i);
+        let v2 = pline.at(pline.next_wrapping_index(i));
+        let t = if len > 0.0 {
+            ((s - acc) / len).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        let point = seg_point_at(v1, v2, t);
+        out.extend([point.x, point.y]);
+        if include_tangents {
+            let tangent = if len > 0.0 {
+                seg_tangent_at(v1, v2, t)
+            } else {
+                point_and_tangent_at(pline, lengths, s).1
+            };
+            out.extend([tangent.x, tangent.y]);
+        }
+    }
+}
+
+/// Maximum number of points sampled by [cavc_pline_sample_even].
+const MAX_SAMPLES: f64 = 10_000_000.0;
+
+/// Sample points spaced evenly by path length along the polyline.
+///
+/// The spacing is adjusted to the nearest value that divides the path length
+/// into a whole number of intervals. Open polylines are sampled from the first
+/// to the last vertex (both included), closed polylines get one point per
+/// interval starting at the first vertex (the end is not repeated).
+///
+/// Points are written to the buffer as interleaved `[x, y]` values, or as
+/// `[x, y, tangent_x, tangent_y]` if `include_tangents` is not 0, where the
+/// tangent is the unit direction of travel evaluated exactly on arcs.
+///
+/// `result` is only written to if the function returns 0 (success) and must be
+/// freed with [crate::buffer::cavc_buffer_f].
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `result` is null.
+/// * 2 = `pline` has less than 2 vertexes.
+/// * 3 = `spacing` is not greater than 0 or not finite.
+/// * 4 = `spacing` is too small for the path length, more than 10000000 points
+///   would be sampled.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_sample_even(
+    pline: *const cavc_pline,
+    spacing: f64,
+    include_tangents: u8,
+    result: *mut *mut cavc_buffer,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || result.is_null() {
+            return 1;
+        }
+        let pline = &(*pline).0;
+        if pline.vertex_count() < 2 {
+            return 2;
+        }
+        if !spacing.is_finite() || spacing <= 0.0 {
+            return 3;
+        }
+        let lengths = seg_lengths(pline);
+        let total: f64 = lengths.iter().sum();
+        let intervals = (total / spacing).round();
+        if intervals.is_nan() || intervals >= MAX_SAMPLES {
+            return 4;
+        }
+        let intervals = (intervals as usize).max(1);
+        let step = total / intervals as f64;
+        let count = if pline.is_closed() {
+            intervals
+        } else {
+            intervals + 1
+        };
+        let include_tangents = include_tangents != 0;
+        let stride = if include_tangents { 4 } else { 2 };
+        let mut values = Vec::with_capacity(stride * count);
+        sample_at_steps(pline, &lengths, step, count, include_tangents, &mut values);
+        *result = into_raw(cavc_buffer::from(values));
+        0
+    })
+}
@@ -0,0 +1,355 @@
+//! Sorting, filtering and spatial queries of polyline lists.
+use cavalier_contours::core::math::Vector2;
+use cavalier_contours::polyline::{PlineSource, Polyline};
+use cavalier_contours::static_aabb2d_index::{StaticAABB2DIndex, StaticAABB2DIndexBuilder};
+use cavalier_contours_ffi::cavc_plinelist;
+
+use crate::hierarchy::parent_indexes;
+use crate::userdata::{get_tag, set_tag};
+use crate::util::{free_raw, into_raw, plinelist_into_raw};
+
+/// Absolute area of the polyline, 0 for open polylines.
+fn abs_area(pline: &Polyline<f64>) -> f64 {
+    if pline.is_closed() {
+        pline.area().abs()
+    } else {
+        0.0
+    }
+}
+
+/// Copy the polylines of the list for which `keep` is true into a new list,
+/// preserving their order.
+unsafe fn filter(
+    plinelist: *const cavc_plinelist,
+    keep: impl Fn(&Polyline<f64>) -> bool,
+) -> *mut cavc_plinelist {
+    let kept: Vec<_> = (*plinelist)
+        .0
+        .iter()
+        .copied()
+        .filter(|&p| keep(&(*p).0))
+        .collect();
+    let result = plinelist_into_raw(kept.iter().map(|&p| (*p).0.clone()).collect());
+    for (&source, &copy) in kept.iter().zip((*result).0.iter()) {
+        set_tag(copy, get_tag(source));
+    }
+    result
+}
+
+/// Sort the polylines of the list in place by their absolute area, ascending
+/// or descending if `descending` is non-zero.
+///
+/// Open polylines have an area of 0. The sort is stable, polylines of equal
+/// area keep their relative order.
+///
+/// ## Specific Error Codes
+/// * 1 = `plinelist` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_plinelist_sort_by_area(
+    plinelist: *mut cavc_plinelist,
+    descending: u8,
+) -> i32 {
+    ffi_catch_unwind!({
+        if plinelist.is_null() {
+            return 1;
+        }
+        let list = &mut (*plinelist).0;
+        let mut keyed: Vec<_> = list.iter().map(|&p| (abs_area(&(*p).0), p)).collect();
+        if descending != 0 {
+            keyed.sort_by(|a, b| b.0.total_cmp(&a.0));
+        } else {
+            keyed.sort_by(|a, b| a.0.total_cmp(&b.0));
+        }
+        *list = keyed.into_iter().map(|(_, p)| p).collect();
+        0
+    })
+}
+
+/// Copy the polylines of the list with an absolute area of at least
+/// `min_abs_area` into a new list, e.g. to drop tiny loops left over by
+/// boolean or offset operations.
+///
+/// Open polylines have an area of 0. The order of the polylines is kept and
+/// `plinelist` is left unchanged.
+///
+/// `result` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `plinelist` or `result` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_plinelist_filter_area(
+    plinelist: *const cavc_plinelist,
+    min_abs_area: f64,
+    result: *mut *mut cavc_plinelist,
+) -> i32 {
+    ffi_catch_unwind!({
+        if plinelist.is_null() || result.is_null() {
+            return 1;
+        }
+        *result = filter(plinelist, |p| abs_area(p) >= min_abs_area);
+        0
+    })
+}
+
+/// Copy the polylines of the list with a path length of at least
+/// `min_length` into a new list.
+///
+/// The order of the polylines is kept and `plinelist` is left unchanged.
+///
+/// `result` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `plinelist` or `result` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_plinelist_filter_length(
+    plinelist: *const cavc_plinelist,
+    min_length: f64,
+    result: *mut *mut cavc_plinelist,
+) -> i32 {
+    ffi_catch_unwind!({
+        if plinelist.is_null() || result.is_null() {
+            return 1;
+        }
+        *result = filter(plinelist, |p| p.path_length() >= min_length);
+        0
+    })
+}
+
+/// Greedy cut order of `plines` starting at `start`, see
+/// [cavc_plinelist_sort_cut_order].
+fn cut_order(plines: &[&Polyline<f64>], start: Vector2<f64>, inner_first: bool) -> Vec<u32> {
+    let count = plines.len();
+    // without inner_first every polyline is treated as a root
+    let parents = if inner_first {
+        parent_indexes(plines)
+    } else {
+        vec![-1; count]
+    };
+    // number of children of every polyline not cut yet
+    let mut pending_children = vec![0usize; count];
+    for &parent in parents.iter().filter(|&&p| p >= 0) {
+        pending_children[parent as usize] += 1;
+    }
+    let endpoints = |p: &Polyline<f64>| match p.vertex_count() {
+        0 => None,
+        _ if p.is_closed() => Some((p.at(0).pos(), p.at(0).pos())),
+        n => Some((p.at(0).pos(), p.at(n - 1).pos())),
+    };
+
+    let mut order = Vec::with_capacity(count);
+    let mut done = vec![false; count];
+    let mut position = start;
+    while order.len() < count {
+        // always some, a polyline whose children are all cut exists since
+        // the hierarchy is a forest
+        let next = (0..count)
+            .filter(|&i| !done[i] && pending_children[i] == 0)
+            .min_by(|&a, &b| {
+                let dist = |i: usize| {
+                    endpoints(plines[i]).map_or(f64::INFINITY, |(s, _)| (s - position).length())
+                };
+                dist(a).total_cmp(&dist(b))
+            })
+            .unwrap();
+        done[next] = true;
+        order.push(next as u32);
+        if parents[next] >= 0 {
+            pending_children[parents[next] as usize] -= 1;
+        }
+        if let Some((_, end)) = endpoints(plines[next]) {
+            position = end;
+        }
+    }
+    order
+}
+
+/// Compute an order to cut the polylines of the list in, e.g. for laser or
+/// plasma cutting.
+///
+/// Starting at `(start_x, start_y)` the polyline whose first vertex is
+/// closest to the current position is cut next, the position then moves to
+/// its end (the first vertex again for closed polylines). If `inner_first` is
+/// non-zero a polyline is only cut after all polylines nested inside of it
+/// (see `cavc_plinelist_build_hierarchy`), so holes come before their parents
+/// and parts are cut out last. Polylines without vertexes are placed last.
+///
+/// `result_order` must have room for one index per polyline of the list and
+/// receives the indexes of the polylines in cut order, it may be null if the
+/// list is empty. `plinelist` is left unchanged.
+///
+/// ## Specific Error Codes
+/// * 1 = `plinelist` is null or `result_order` is null while the list is not
+///   empty.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_plinelist_sort_cut_order(
+    plinelist: *const cavc_plinelist,
+    start_x: f64,
+    start_y: f64,
+    inner_first: u8,
+    result_order: *mut u32,
+) -> i32 {
+    ffi_catch_unwind!({
+        if plinelist.is_null() {
+            return 1;
+        }
+        let plines: Vec<&Polyline<f64>> = (*plinelist).0.iter().map(|&p| &(*p).0).collect();
+        if plines.is_empty() {
+            return 0;
+        }
+        if result_order.is_null() {
+            return 1;
+        }
+        let order = cut_order(&plines, Vector2::new(start_x, start_y), inner_first != 0);
+        std::ptr::copy_nonoverlapping(order.as_ptr(), result_order, order.len());
+        0
+    })
+}
+
+/// Opaque type holding a spatial index of the extents of the polylines of a
+/// list, see [cavc_plinelist_create_index].
+pub struct cavc_plinelist_index {
+    /// Index of the extents of the polylines with vertexes, None if there
+    /// are none.
+    index: Option<StaticAABB2DIndex<f64>>,
+    /// Index in the list of every item of the index.
+    items: Vec<u32>,
+}
+
+impl cavc_plinelist_index {
+    /// List indexes of the polylines whose extents overlap the rectangle, in
+    /// ascending order.
+    fn query(&self, min_x: f64, min_y: f64, max_x: f64, max_y: f64) -> Vec<u32> {
+        let mut hits: Vec<u32> = match &self.index {
+            Some(index) => index
+                .query(min_x, min_y, max_x, max_y)
+                .into_iter()
+                .map(|i| self.items[i])
+                .collect(),
+            None => Vec::new(),
+        };
+        hits.sort_unstable();
+        hits
+    }
+}
+
+/// Write `hits` to `out` which has room for `out_cap` values as described by
+/// [cavc_plinelist_query_rect].
+unsafe fn write_hits(hits: &[u32], out: *mut u32, out_cap: u32, out_count: *mut u32) -> i32 {
+    *out_count = hits.len() as u32;
+    if !out.is_null() {
+        for (i, &hit) in hits.iter().take(out_cap as usize).enumerate() {
+            *out.add(i) = hit;
+        }
+    }
+    if hits.len() > out_cap as usize {
+        return 2;
+    }
+    0
+}
+
+/// Create a spatial index of the extents of the polylines of a list, e.g. to
+/// quickly find the polylines under a cursor among thousands of them.
+///
+/// Item `i` of the index is polyline `i` of the list, arcs are accounted for
+/// exactly. Polylines without vertexes are never found. The index does not
+/// reference `plinelist`, but it has to be rebuilt if the list or its
+/// polylines are modified.
+///
+/// `result` is only written to if the function returns 0 (success) and must be
+/// freed with [cavc_plinelist_index_f].
+///
+/// ## Specific Error Codes
+/// * 1 = `plinelist` or `result` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_plinelist_create_index(
+    plinelist: *const cavc_plinelist,
+    result: *mut *mut cavc_plinelist_index,
+) -> i32 {
+    ffi_catch_unwind!({
+        if plinelist.is_null() || result.is_null() {
+            return 1;
+        }
+        let extents: Vec<_> = (*plinelist)
+            .0
+            .iter()
+            .enumerate()
+            .filter_map(|(i, &p)| Some((i as u32, (*p).0.extents()?)))
+            .collect();
+        let mut builder = StaticAABB2DIndexBuilder::new(extents.len());
+        for (_, e) in &extents {
+            builder.add(e.min_x, e.min_y, e.max_x, e.max_y);
+        }
+        *result = into_raw(cavc_plinelist_index {
+            index: builder.build().ok(),
+            items: extents.into_iter().map(|(i, _)| i).collect(),
+        });
+        0
+    })
+}
+
+/// Free an existing [cavc_plinelist_index] object. Nothing happens if `index`
+/// is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_plinelist_index_f(index: *mut cavc_plinelist_index) {
+    free_raw(index);
+}
+
+/// Query the index for all polylines whose extents contain the point
+/// `(x, y)`.
+///
+/// Only the extents are tested, the caller may refine the hits with exact
+/// tests such as `cavc_pline_winding_number`. The list indexes are written to
+/// `out` in ascending order as described by [cavc_plinelist_query_rect].
+///
+/// ## Specific Error Codes
+/// * 1 = `index` or `out_count` is null.
+/// * 2 = `out_cap` is less than the number of hits, the first `out_cap` of
+///   them are written.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_plinelist_query_point(
+    index: *const cavc_plinelist_index,
+    x: f64,
+    y: f64,
+    out: *mut u32,
+    out_cap: u32,
+    out_count: *mut u32,
+) -> i32 {
+    ffi_catch_unwind!({
+        if index.is_null() || out_count.is_null() {
+            return 1;
+        }
+        write_hits(&(*index).query(x, y, x, y), out, out_cap, out_count)
+    })
+}
+
+/// Query the index for all polylines whose extents overlap the rectangle
+/// `(min_x, min_y)`-`(max_x, max_y)`.
+///
+/// The list indexes are written to `out` in ascending order, it has room for
+/// `out_cap` values, while `out_count` receives the total number of hits, so
+/// if it is larger than `out_cap` the caller can size the buffer and query
+/// again. `out` may be null if `out_cap` is 0.
+///
+/// ## Specific Error Codes
+/// * 1 = `index` or `out_count` is null.
+/// * 2 = `out_cap` is less than the number of hits, the first `out_cap` of
+///   them are written.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_plinelist_query_rect(
+    index: *const cavc_plinelist_index,
+    min_x: f64,
+    min_y: f64,
+    max_x: f64,
+    max_y: f64,
+    out: *mut u32,
+    out_cap: u32,
+    out_count: *mut u32,
+) -> i32 {
+    ffi_catch_unwind!({
+        if index.is_null() || out_count.is_null() {
+            return 1;
+        }
+        let hits = (*index).query(min_x, min_y, max_x, max_y);
+        write_hits(&hits, out, out_cap, out_count)
+    })
+}
@@ -0,0 +1,349 @@
+//! Pocketing toolpaths.
+//!
+//! Toolpaths are returned as a polyline list alternating between cutting
+//! passes (at even indexes) and connecting moves (at odd indexes). Connecting
+//! moves are open two vertex lines from the end of a pass to the start of the
+//! next pass, they are straight and not checked against the region boundary
+//! so the caller decides whether to cut along them or retract. The shape given
+//! is the region the tool center may reach, offsetting the pocket boundary by
+//! the tool radius is left to the caller.
+use cavalier_contours::core::math::Vector2;
+use cavalier_contours::polyline::{PlineCreation, PlineSource, PlineSourceMut, Polyline};
+use cavalier_contours::shape_algorithms::{Shape, ShapeOffsetOptions};
+use cavalier_contours_ffi::cavc_plinelist;
+
+use crate::hatch::hatch_lines;
+use crate::shape::cavc_shape;
+use crate::util::plinelist_into_raw;
+
+/// Distance from `point` to where `pass` would be entered and the vertex index
+/// to enter at (reversing open passes if the index is not 0).
+fn entry(pass: &Polyline<f64>, point: Vector2<f64>) -> (f64, usize) {
+    let dist = |i: usize| (pass.at(i).pos() - point).length();
+    if pass.is_closed() {
+        (0..pass.vertex_count())
+            .map(|i| (dist(i), i))
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .unwrap_or((f64::INFINITY, 0))
+    } else {
+        let last = pass.vertex_count() - 1;
+        let (to_start, to_end) = (dist(0), dist(last));
+        if to_end < to_start {
+            (to_end, last)
+        } else {
+            (to_start, 0)
+        }
+    }
+}
+
+/// Reorder `pass` to start at vertex `start` as chosen by [entry].
+fn enter_at(pass: Polyline<f64>, start: usize) -> Polyline<f64> {
+    if start == 0 {
+        return pass;
+    }
+    if !pass.is_closed() {
+        let mut pass = pass;
+        pass.invert_direction_mut();
+        return pass;
+    }
+    let n = pass.vertex_count();
+    let mut rotated = Polyline::with_capacity(n, true);
+    for i in 0..n {
+        rotated.add_vertex(pass.at((start + i) % n));
+    }
+    rotated
+}
+
+/// Order the passes group by group, within a group always continuing with the
+/// pass that can be entered closest to the current position, and insert the
+/// connecting moves between them.
+fn link_passes(groups: Vec<Vec<Polyline<f64>>>) -> Vec<Polyline<f64>> {
+    let mut result = Vec::new();
+    let mut position: Option<Vector2<f64>> = None;
+    for mut remaining in groups {
+        remaining.retain(|p| p.vertex_count() > 1);
+        while !remaining.is_empty() {
+            let (at, start) = match position {
+                Some(point) => remaining
+                    .iter()
+                    .enumerate()
+                    .map(|(i, p)| (i, entry(p, point)))
+                    .min_by(|a, b| a.1 .0.total_cmp(&b.1 .0))
+                    .map(|(i, (_, start))| (i, start))
+                    .unwrap(),
+                None => (0, 0),
+            };
+            let pass = enter_at(remaining.swap_remove(at), start);
+            let first = pass.at(0).pos();
+            if let Some(point) = position {
+                let mut link = Polyline::with_capacity(2, false);
+                link.add(point.x, point.y, 0.0);
+                link.add(first.x, first.y, 0.0);
+                result.push(link);
+            }
+            position = Some(if pass.is_closed() {
+                first
+            } else {
+                pass.at(pass.vertex_count() - 1).pos()
+            });
+            result.push(pass);
+        }
+    }
+    result
+}
+
+/// Maximum number of offset levels of a contour parallel pocket.
+const MAX_POCKET_LEVELS: usize = 100_000;
+
+/// Signed area enclosed by the polylines of a level (holes are clockwise).
+fn level_area(level: &[Polyline<f64>]) -> f64 {
+    level.iter().map(|p| p.area()).sum()
+}
+
+/// Offset passes of `shape` every `stepover`, innermost level first.
+///
+/// Returns `None` if more than [MAX_POCKET_LEVELS] levels would be needed or
+/// an offset does not strictly decrease the enclosed area or encloses a
+/// negative area (holes without outer boundary grow forever).
+fn contour_levels(shape: &Shape<f64>, stepover: f64) -> Option<Vec<Vec<Polyline<f64>>>> {
+    let plines = |s: &Shape<f64>| -> Vec<Polyline<f64>> {
+        let all = s.ccw_plines.iter().chain(&s.cw_plines);
+        all.map(|p| p.polyline.clone()).collect()
+    };
+    let mut levels = vec![plines(shape)];
+    let mut area = level_area(&levels[0]);
+    for k in 1.. {
+        // offset from the original shape to not accumulate errors
+        let level = plines(&shape.parallel_offset(k as f64 * stepover, ShapeOffsetOptions::new()));
+        if level.is_empty() {
+            break;
+        }
+        let next_area = level_area(&level);
+        if next_area >= area || next_area < 0.0 || levels.len() == MAX_POCKET_LEVELS {
+            return None;
+        }
+        area = next_area;
+        levels.push(level);
+    }
+    levels.reverse();
+    Some(levels)
+}
+
+/// Contour parallel pocketing toolpath of the shape.
+///
+/// Passes follow the shape boundary (outer boundaries and holes) and its
+/// inward offsets every `stepover` until nothing is left, cut from the
+/// innermost offset out so the last passes finish the boundary. Passes of the
+/// same level are ordered by proximity and closed passes start at the vertex
+/// closest to the end of the previous pass. See the module documentation for
+/// the layout of `result`.
+///
+/// `result` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `shape` or `result` is null.
+/// * 2 = `stepover` is not greater than 0 or infinite.
+/// * 3 = `stepover` is too small for the shape, more than 100000 passes would
+///   be needed or the offsets stopped shrinking (e.g. a shape of only
+///   clockwise polylines).
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pocket_contour_parallel(
+    shape: *const cavc_shape,
+    stepover: f64,
+    result: *mut *mut cavc_plinelist,
+) -> i32 {
+    ffi_catch_unwind!({
+        if shape.is_null() || result.is_null() {
+            return 1;
+        }
+        if !stepover.is_finite() || stepover <= 0.0 {
+            return 2;
+        }
+        let levels = match contour_levels(&(*shape).0, stepover) {
+            Some(levels) => levels,
+            None => return 3,
+        };
+        *result = plinelist_into_raw(link_passes(levels));
+        0
+    })
+}
+
+/// Zigzag pocketing toolpath of the shape.
+///
+/// Passes are the hatch lines of the shape region at `angle` radians from the
+/// x axis and `stepover` apart (see `cavc_plinelist_hatch`), every pass
+/// continuing with the closest end of the remaining lines so consecutive rows
+/// are cut in alternating directions. No boundary finishing pass is added,
+/// combine with [cavc_pocket_contour_parallel] output if one is needed. See
+/// the module documentation for the layout of `result`.
+///
+/// `result` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `shape` or `result` is null.
+/// * 2 = `stepover` is not greater than 0 or `angle` is not finite.
+/// * 3 = the shape spans more than a million passes at `stepover`.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pocket_zigzag(
+    shape: *const cavc_shape,
+    angle: f64,
+    stepover: f64,
+    result: *mut *mut cavc_plinelist,
+) -> i32 {
+    ffi_catch_unwind!({
+        if shape.is_null() || result.is_null() {
+            return 1;
+        }
+        if !stepover.is_finite() || stepover <= 0.0 || !angle.is_finite() {
+            return 2;
+        }
+        let shape = &(*shape).0;
+        let plines: Vec<&Polyline<f64>> = shape
+            .ccw_plines
+            .iter()
+            .chain(&shape.cw_plines)
+            .map(|p| &p.polyline)
+            .collect();
+        let passes = match hatch_lines(&plines, angle, stepover) {
+            Some(passes) => passes,
+            None => return 3,
+        };
+        let passes = passes
+            .into_iter()
+            .map(|(a, b)| {
+                let mut line = Polyline::with_capacity(2, false);
+                line.add(a.x, a.y, 0.0);
+                line.add(b.x, b.y, 0.0);
+                line
+            })
+            .collect();
+        *result = plinelist_into_raw(link_passes(vec![passes]));
+        0
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ptr::null_mut;
+
+    use cavalier_contours_ffi::cavc_plinelist_f;
+
+    use super::*;
+    use crate::test_util::{cw, pline, rect};
+
+    /// Passes of a toolpath, checking that the connecting moves join them.
+    fn passes(toolpath: &[Polyline<f64>]) -> Vec<&Polyline<f64>> {
+        assert_eq!(toolpath.len() % 2, 1);
+        for k in (1..toolpath.len()).step_by(2) {
+            let (prev, link, next) = (&toolpath[k - 1], &toolpath[k], &toolpath[k + 1]);
+            let end = if prev.is_closed() {
+                prev.at(0)
+            } else {
+                prev.at(prev.vertex_count() - 1)
+            };
+            assert_eq!(link.vertex_count(), 2);
+            assert_eq!(link.at(0).pos(), end.pos());
+            assert_eq!(link.at(1).pos(), next.at(0).pos());
+        }
+        toolpath.iter().step_by(2).collect()
+    }
+
+    unsafe fn run(
+        pocket: impl FnOnce(*const cavc_shape, *mut *mut cavc_plinelist) -> i32,
+        plines: Vec<Polyline<f64>>,
+    ) -> Result<Vec<Polyline<f64>>, i32> {
+        let shape = cavc_shape(Shape::from_plines(plines));
+        let mut result: *mut cavc_plinelist = null_mut();
+        match pocket(&shape as *const _, &mut result as *mut _) {
+            0 => {
+                let plines = (*result).0.iter().map(|&p| (*p).0.clone()).collect();
+                cavc_plinelist_f(result);
+                Ok(plines)
+            }
+            code => Err(code),
+        }
+    }
+
+    #[test]
+    fn contour_levels_innermost_first() {
+        let shape = Shape::from_plines([rect(0.0, 0.0, 10.0, 10.0)]);
+        let levels = contour_levels(&shape, 1.0).unwrap();
+        assert!((4..=6).contains(&levels.len()), "{}", levels.len());
+        let areas: Vec<f64> = levels.iter().map(|l| level_area(l)).collect();
+        assert!(areas.windows(2).all(|w| w[0] < w[1]), "{:?}", areas);
+        assert!((areas[areas.len() - 1] - 100.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn contour_levels_with_holes() {
+        let shape = Shape::from_plines([rect(0.0, 0.0, 20.0, 10.0), cw(rect(8.0, 3.0, 12.0, 7.0))]);
+        let levels = contour_levels(&shape, 1.0).unwrap();
+        // the boundary level holds the outer boundary and the hole
+        assert_eq!(levels[levels.len() - 1].len(), 2);
+        assert!((level_area(&levels[levels.len() - 1]) - 184.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn contour_levels_reject_growing_offsets() {
+        let shape = Shape::from_plines([cw(rect(0.0, 0.0, 10.0, 10.0))]);
+        assert!(contour_levels(&shape, 1.0).is_none());
+    }
+
+    #[test]
+    fn links_passes_by_proximity() {
+        let groups = vec![
+            vec![pline(&[(0.0, 0.0, 0.0), (1.0, 0.0, 0.0)], false)],
+            vec![
+                rect(5.0, 5.0, 6.0, 6.0),
+                // entered from its end, so it is reversed
+                pline(&[(3.0, 0.0, 0.0), (1.0, 0.5, 0.0)], false),
+            ],
+        ];
+        let toolpath = link_passes(groups);
+        let passes = passes(&toolpath);
+        assert_eq!(passes.len(), 3);
+        assert_eq!(passes[1].at(0).pos(), Vector2::new(1.0, 0.5));
+        assert_eq!(passes[1].at(1).pos(), Vector2::new(3.0, 0.0));
+        // closed passes start at the vertex closest to the previous end
+        assert_eq!(passes[2].at(0).pos(), Vector2::new(5.0, 5.0));
+        assert_eq!(passes[2].vertex_count(), 4);
+        // single vertex passes are dropped
+        assert!(link_passes(vec![vec![Polyline::new()]]).is_empty());
+    }
+
+    #[test]
+    fn zigzag_alternates_directions() {
+        let pocket = |s, r| unsafe { cavc_pocket_zigzag(s, 0.0, 1.0, r) };
+        let toolpath = unsafe { run(pocket, vec![rect(0.0, 0.5, 10.0, 6.0)]) }.unwrap();
+        let passes = passes(&toolpath);
+        assert_eq!(passes.len(), 5);
+        for pair in passes.windows(2) {
+            let (dx0, dx1) = (
+                pair[0].at(1).x - pair[0].at(0).x,
+                pair[1].at(1).x - pair[1].at(0).x,
+            );
+            assert!(dx0 * dx1 < 0.0);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_parameters() {
+        let square = || vec![rect(0.0, 0.0, 10.0, 10.0)];
+        for stepover in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            let pocket = |s, r| unsafe { cavc_pocket_contour_parallel(s, stepover, r) };
+            assert_eq!(unsafe { run(pocket, square()) }.err(), Some(2));
+            let pocket = |s, r| unsafe { cavc_pocket_zigzag(s, 0.0, stepover, r) };
+            assert_eq!(unsafe { run(pocket, square()) }.err(), Some(2));
+        }
+        let pocket = |s, r| unsafe { cavc_pocket_zigzag(s, f64::NAN, 1.0, r) };
+        assert_eq!(unsafe { run(pocket, square()) }.err(), Some(2));
+        let pocket = |s, r| unsafe { cavc_pocket_contour_parallel(s, 1.0, r) };
+        let clockwise = vec![cw(rect(0.0, 0.0, 10.0, 10.0))];
+        assert_eq!(unsafe { run(pocket, clockwise) }.err(), Some(3));
+        let pocket = |_, r| unsafe { cavc_pocket_contour_parallel(std::ptr::null(), 1.0, r) };
+        assert_eq!(unsafe { run(pocket, square()) }.err(), Some(1));
+        // an empty shape gives an empty toolpath
+        let pocket = |s, r| unsafe { cavc_pocket_contour_parallel(s, 1.0, r) };
+        assert_eq!(unsafe { run(pocket, Vec::new()) }.unwrap().len(), 0);
+    }
+}
@@ -0,0 +1,184 @@
+//! Robust geometric predicates.
+//!
+//! The determinants are first evaluated in floating point and accepted if
+//! they are larger than a bound on their rounding error (Shewchuk's stage A
+//! filter), otherwise they are recomputed exactly with floating point
+//! expansions (sums of non-overlapping doubles). Only the sign of the results
+//! is meaningful.
+use cavalier_contours::core::math::Vector2;
+
+const EPSILON: f64 = f64::EPSILON * 0.5;
+const ORIENT_ERROR_BOUND: f64 = (3.0 + 16.0 * EPSILON) * EPSILON;
+const INCIRCLE_ERROR_BOUND: f64 = (10.0 + 96.0 * EPSILON) * EPSILON;
+
+/// Exact sum of two doubles as `(sum, error)`.
+fn two_sum(a: f64, b: f64) -> (f64, f64) {
+    let x = a + b;
+    let b_virtual = x - a;
+    let a_virtual = x - b_virtual;
+    (x, (a - a_virtual) + (b - b_virtual))
+}
+
+/// Exact product of two doubles as `(product, error)`.
+fn two_product(a: f64, b: f64) -> (f64, f64) {
+    let x = a * b;
+    (x, a.mul_add(b, -x))
+}
+
+/// Exact value as a sum of non-overlapping components of increasing
+/// magnitude (zeros may appear anywhere).
+#[derive(Debug, Clone)]
+struct Expansion(Vec<f64>);
+
+impl Expansion {
+    /// Exact difference of two doubles.
+    fn diff(a: f64, b: f64) -> Self {
+        let (x, y) = two_sum(a, -b);
+        Self(vec![y, x])
+    }
+
+    /// Add `b` to the expansion, dropping zero components to keep it short.
+    fn grow(&mut self, b: f64) {
+        let mut q = b;
+        let mut len = 0;
+        for k in 0..self.0.len() {
+            let (sum, error) = two_sum(q, self.0[k]);
+            q = sum;
+            if error != 0.0 {
+                self.0[len] = error;
+                len += 1;
+            }
+        }
+        self.0.truncate(len);
+        if q != 0.0 || self.0.is_empty() {
+            self.0.push(q);
+        }
+    }
+
+    fn add(mut self, other: &Self) -> Self {
+        for &f in &other.0 {
+            self.grow(f);
+        }
+        self
+    }
+
+    fn sub(self, other: &Self) -> Self {
+        self.add(&other.neg())
+    }
+
+    fn neg(&self) -> Self {
+        Self(self.0.iter().map(|e| -e).collect())
+    }
+
+    fn mul(&self, other: &Self) -> Self {
+        let mut result = Self(Vec::new());
+        for &f in &other.0 {
+            for &e in &self.0 {
+                let (x, y) = two_product(e, f);
+                result.grow(y);
+                result.grow(x);
+            }
+        }
+        result
+    }
+
+    /// Sign of the value, the sign of its largest non-zero component.
+    fn sign(&self) -> f64 {
+        match self.0.iter().rev().find(|e| **e != 0.0) {
+            Some(e) => e.signum(),
+            None => 0.0,
+        }
+    }
+}
+
+/// Positive if `a`, `b` and `c` are in counter clockwise order, negative if
+/// they are in clockwise order and 0 if they are collinear.
+pub(crate) fn orient2d(a: Vector2<f64>, b: Vector2<f64>, c: Vector2<f64>) -> f64 {
+    let left = (a.x - c.x) * (b.y - c.y);
+    let right = (a.y - c.y) * (b.x - c.x);
+    let det = left - right;
+    if det.abs() > ORIENT_ERROR_BOUND * (left.abs() + right.abs()) {
+        return det;
+    }
+    let left = Expansion::diff(a.x, c.x).mul(&Expansion::diff(b.y, c.y));
+    let right = Expansion::diff(a.y, c.y).mul(&Expansion::diff(b.x, c.x));
+    left.sub(&right).sign()
+}
+
+/// Positive if `d` lies inside the circle through the counter clockwise
+/// triangle `a`, `b`, `c`, negative if it lies outside and 0 if it is on the
+/// circle. The sign is reversed for clockwise triangles.
+pub(crate) fn incircle(a: Vector2<f64>, b: Vector2<f64>, c: Vector2<f64>, d: Vector2<f64>) -> f64 {
+    let (adx, ady) = (a.x - d.x, a.y - d.y);
+    let (bdx, bdy) = (b.x - d.x, b.y - d.y);
+    let (cdx, cdy) = (c.x - d.x, c.y - d.y);
+    let (bdxcdy, cdxbdy) = (bdx * cdy, cdx * bdy);
+    let (cdxady, adxcdy) = (cdx * ady, adx * cdy);
+    let (adxbdy, bdxady) = (adx * bdy, bdx * ady);
+    let alift = adx * adx + ady * ady;
+    let blift = bdx * bdx + bdy * bdy;
+    let clift = cdx * cdx + cdy * cdy;
+    let det = alift * (bdxcdy - cdxbdy) + blift * (cdxady - adxcdy) + clift * (adxbdy - bdxady);
+    let permanent = (bdxcdy.abs() + cdxbdy.abs()) * alift
+        + (cdxady.abs() + adxcdy.abs()) * blift
+        + (adxbdy.abs() + bdxady.abs()) * clift;
+    if det.abs() > INCIRCLE_ERROR_BOUND * permanent {
+        return det;
+    }
+    let (adx, ady) = (Expansion::diff(a.x, d.x), Expansion::diff(a.y, d.y));
+    let (bdx, bdy) = (Expansion::diff(b.x, d.x), Expansion::diff(b.y, d.y));
+    let (cdx, cdy) = (Expansion::diff(c.x, d.x), Expansion::diff(c.y, d.y));
+    let lift = |x: &Expansion, y: &Expansion| x.mul(x).add(&y.mul(y));
+    let cross = |x1: &Expansion, y1: &Expansion, x2: &Expansion, y2: &Expansion| {
+        x1.mul(y2).sub(&x2.mul(y1))
+    };
+    let a_term = lift(&adx, &ady).mul(&cross(&bdx, &bdy, &cdx, &cdy));
+    let b_term = lift(&bdx, &bdy).mul(&cross(&cdx, &cdy, &adx, &ady));
+    let c_term = lift(&cdx, &cdy).mul(&cross(&adx, &ady, &bdx, &bdy));
+    a_term.add(&b_term).add(&c_term).sign()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v(x: f64, y: f64) -> Vector2<f64> {
+        Vector2::new(x, y)
+    }
+
+    #[test]
+    fn orient2d_signs() {
+        assert!(orient2d(v(0.0, 0.0), v(1.0, 0.0), v(0.0, 1.0)) > 0.0);
+        assert!(orient2d(v(0.0, 0.0), v(0.0, 1.0), v(1.0, 0.0)) < 0.0);
+        assert_eq!(orient2d(v(0.0, 0.0), v(1.0, 1.0), v(2.0, 2.0)), 0.0);
+    }
+
+    #[test]
+    fn orient2d_nearly_collinear() {
+        // points a few ulps off the line where the naive determinant rounds
+        // to inconsistent signs
+        let (b, c) = (v(12.0, 12.0), v(24.0, 24.0));
+        assert_eq!(orient2d(v(0.5, 0.5), b, c), 0.0);
+        for k in 1..64 {
+            let p = v(0.5 + k as f64 * f64::EPSILON, 0.5);
+            assert!(orient2d(p, b, c) < 0.0);
+            assert!(orient2d(b, p, c) > 0.0);
+        }
+    }
+
+    #[test]
+    fn incircle_signs() {
+        let (a, b, c) = (v(0.0, 0.0), v(1.0, 0.0), v(0.0, 1.0));
+        assert!(incircle(a, b, c, v(0.5, 0.5)) > 0.0);
+        assert!(incircle(a, b, c, v(2.0, 2.0)) < 0.0);
+        assert_eq!(incircle(a, b, c, v(1.0, 1.0)), 0.0);
+    }
+
+    #[test]
+    fn incircle_cocircular_exact() {
+        // points on the circle of radius 5 around the origin
+        let (a, b, c) = (v(5.0, 0.0), v(3.0, 4.0), v(-4.0, 3.0));
+        assert_eq!(incircle(a, b, c, v(0.0, -5.0)), 0.0);
+        assert_eq!(incircle(a, b, c, v(-3.0, -4.0)), 0.0);
+    }
+}
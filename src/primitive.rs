@@ -0,0 +1,291 @@
+//! Construction of closed polylines for common shapes.
+//!
+//! All shapes are counter clockwise, exact circles and arcs are represented by
+//! bulge segments.
+use std::f64::consts::{FRAC_PI_8, TAU};
+
+use cavalier_contours::core::math::Vector2;
+use cavalier_contours::polyline::{
+    seg_closest_point, PlineCreation, PlineOffsetOptions, PlineSource, PlineSourceMut, PlineVertex,
+    Polyline,
+};
+use cavalier_contours_ffi::cavc_pline;
+
+use crate::fit::biarc;
+use crate::util::pline_into_raw;
+
+/// Largest number of biarcs an ellipse is approximated with.
+const MAX_ELLIPSE_BIARCS: usize = 4096;
+
+/// Number of interior ellipse points of every biarc checked against it.
+const ELLIPSE_CHECK_POINTS: usize = 8;
+
+/// Append a vertex to the closed `pline` unless it is at the position of the
+/// last one, in which case only its bulge is updated.
+fn push_vertex(pline: &mut Polyline<f64>, x: f64, y: f64, bulge: f64) {
+    let count = pline.vertex_count();
+    if count > 0 {
+        let last = pline.at(count - 1);
+        if last.x == x && last.y == y {
+            pline.set_vertex(count - 1, last.with_bulge(bulge));
+            return;
+        }
+    }
+    pline.add(x, y, bulge);
+}
+
+/// Create a closed counter clockwise circle of two half circle arcs.
+///
+/// The vertexes are at the left and right ends of the circle.
+///
+/// `result` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `result` is null.
+/// * 2 = `radius` is not greater than 0, or a value is not finite.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_create_circle(
+    cx: f64,
+    cy: f64,
+    radius: f64,
+    result: *mut *mut cavc_pline,
+) -> i32 {
+    ffi_catch_unwind!({
+        if result.is_null() {
+            return 1;
+        }
+        if !cx.is_finite() || !cy.is_finite() || !radius.is_finite() || radius <= 0.0 {
+            return 2;
+        }
+        let mut pline = Polyline::with_capacity(2, true);
+        pline.add(cx - radius, cy, 1.0);
+        pline.add(cx + radius, cy, 1.0);
+        *result = pline_into_raw(pline);
+        0
+    })
+}
+
+/// Create a closed counter clockwise axis aligned rectangle with the lower
+/// left corner at `(x, y)`, optionally with rounded corners.
+///
+/// With a `corner_radius` of 0 the rectangle has 4 vertexes starting at the
+/// lower left corner. Otherwise every corner is replaced by a quarter circle
+/// arc of the radius and the polyline starts at the lower end of the bottom
+/// edge. Straight edges of zero length (the radius is half the width or
+/// height) are left out, so the maximum radius gives a stadium or a circle.
+///
+/// `result` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `result` is null.
+/// * 2 = `width` or `height` is not greater than 0, or a value is not finite.
+/// * 3 = `corner_radius` is negative or greater than half the width or height.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_create_rect(
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+    corner_radius: f64,
+    result: *mut *mut cavc_pline,
+) -> i32 {
+    ffi_catch_unwind!({
+        if result.is_null() {
+            return 1;
+        }
+        if [x, y, width, height].iter().any(|v| !v.is_finite()) || width <= 0.0 || height <= 0.0 {
+            return 2;
+        }
+        if corner_radius.is_nan() || corner_radius < 0.0 || 2.0 * corner_radius > width.min(height)
+        {
+            return 3;
+        }
+        let (x2, y2) = (x + width, y + height);
+        let mut pline = Polyline::with_capacity(8, true);
+        if corner_radius == 0.0 {
+            pline.add(x, y, 0.0);
+            pline.add(x2, y, 0.0);
+            pline.add(x2, y2, 0.0);
+            pline.add(x, y2, 0.0);
+        } else {
+            let r = corner_radius;
+            let b = FRAC_PI_8.tan();
+            push_vertex(&mut pline, x + r, y, 0.0);
+            push_vertex(&mut pline, x2 - r, y, b);
+            push_vertex(&mut pline, x2, y + r, 0.0);
+            push_vertex(&mut pline, x2, y2 - r, b);
+            push_vertex(&mut pline, x2 - r, y2, 0.0);
+            push_vertex(&mut pline, x + r, y2, b);
+            push_vertex(&mut pline, x, y2 - r, 0.0);
+            push_vertex(&mut pline, x, y + r, b);
+            // a zero length left edge ends at the first vertex
+            let first = pline.at(0);
+            let last = pline.at(pline.vertex_count() - 1);
+            if first.x == last.x && first.y == last.y {
+                pline.remove_last();
+            }
+        }
+        *result = pline_into_raw(pline);
+        0
+    })
+}
+
+/// Create a closed counter clockwise regular polygon with `side_count` sides
+/// inscribed in the circle of `radius` around `(cx, cy)`.
+///
+/// The first vertex is at the angle `rotation` (in radians, counter clockwise
+/// from the positive x axis).
+///
+/// `result` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `result` is null.
+/// * 2 = `radius` is not greater than 0, or a value is not finite.
+/// * 3 = `side_count` is less than 3.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_create_regular_polygon(
+    cx: f64,
+    cy: f64,
+    radius: f64,
+    side_count: u32,
+    rotation: f64,
+    result: *mut *mut cavc_pline,
+) -> i32 {
+    ffi_catch_unwind!({
+        if result.is_null() {
+            return 1;
+        }
+        if [cx, cy, radius, rotation].iter().any(|v| !v.is_finite()) || radius <= 0.0 {
+            return 2;
+        }
+        if side_count < 3 {
+            return 3;
+        }
+        let n = side_count as usize;
+        let mut pline = Polyline::with_capacity(n, true);
+        for k in 0..n {
+            let (sin, cos) = (rotation + TAU * k as f64 / n as f64).sin_cos();
+            pline.add(cx + radius * cos, cy + radius * sin, 0.0);
+        }
+        *result = pline_into_raw(pline);
+        0
+    })
+}
+
+/// Approximate the axis aligned ellipse by `count` biarcs, each joining
+/// evenly spaced ellipse points (in its angle parameter) with the tangents of
+/// the ellipse there, so the tangent is continuous along the whole outline.
+///
+/// Returns the vertexes and the largest distance of the checked ellipse
+/// points of every piece to its biarc, infinite if a piece has no biarc.
+fn ellipse_arcs(
+    center: Vector2<f64>,
+    rx: f64,
+    ry: f64,
+    count: usize,
+) -> (Vec<PlineVertex<f64>>, f64) {
+    let point = |t: f64| {
+        let (sin, cos) = t.sin_cos();
+        Vector2::new(center.x + rx * cos, center.y + ry * sin)
+    };
+    let tangent = |t: f64| {
+        let (sin, cos) = t.sin_cos();
+        let d = Vector2::new(-rx * sin, ry * cos);
+        d.scale(1.0 / d.length())
+    };
+    let pos_equal_eps = PlineOffsetOptions::<f64>::new().pos_equal_eps;
+    let step = TAU / count as f64;
+    let mut vertexes = Vec::with_capacity(2 * count);
+    let mut max_error = 0.0f64;
+    for k in 0..count {
+        let (t0, t1) = (k as f64 * step, (k + 1) as f64 * step);
+        let (start, end) = (point(t0), point(t1));
+        let (b0, joint, b1) = match biarc(start, tangent(t0), end, tangent(t1)) {
+            Some(arcs) => arcs,
+            None => return (vertexes, f64::INFINITY),
+        };
+        let segs = [
+            (
+                PlineVertex::from_vector2(start, b0),
+                PlineVertex::from_vector2(joint, 0.0),
+            ),
+            (
+                PlineVertex::from_vector2(joint, b1),
+                PlineVertex::from_vector2(end, 0.0),
+            ),
+        ];
+        for i in 1..=ELLIPSE_CHECK_POINTS {
+            let p = point(t0 + step * i as f64 / (ELLIPSE_CHECK_POINTS + 1) as f64);
+            let deviation = segs
+                .iter()
+                .map(|&(v1, v2)| (seg_closest_point(v1, v2, p, pos_equal_eps) - p).length())
+                .fold(f64::INFINITY, f64::min);
+            max_error = max_error.max(deviation);
+        }
+        vertexes.push(segs[0].0);
+        vertexes.push(segs[1].0);
+    }
+    (vertexes, max_error)
+}
+
+/// Create a closed counter clockwise axis aligned ellipse with radii `rx` and
+/// `ry` around `(cx, cy)`, approximated by arcs.
+///
+/// An ellipse has no exact bulge representation, so it is approximated by
+/// biarcs (pairs of arcs joined with a common tangent) between evenly spaced
+/// points of the ellipse (in its angle parameter) matching the ellipse tangent
+/// there, so the outline has no corners and offsets cleanly. The number of
+/// biarcs is doubled (starting at 4, at most 4096) until the distance to the
+/// ellipse is at most `arc_error`. The biarc ends lie exactly on the ellipse
+/// and include its 4 extreme points, the first vertex being the right one. If
+/// the radii are equal an exact circle of two arcs is returned.
+///
+/// `result` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `result` is null.
+/// * 2 = `rx` or `ry` is not greater than 0, or a value is not finite.
+/// * 3 = `arc_error` is not greater than 0.
+/// * 4 = `arc_error` cannot be met with 4096 biarcs (it is too small relative
+///   to the radii).
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_create_ellipse(
+    cx: f64,
+    cy: f64,
+    rx: f64,
+    ry: f64,
+    arc_error: f64,
+    result: *mut *mut cavc_pline,
+) -> i32 {
+    ffi_catch_unwind!({
+        if result.is_null() {
+            return 1;
+        }
+        if [cx, cy, rx, ry].iter().any(|v| !v.is_finite()) || rx <= 0.0 || ry <= 0.0 {
+            return 2;
+        }
+        if arc_error.is_nan() || arc_error <= 0.0 {
+            return 3;
+        }
+        let pline = if rx == ry {
+            let mut pline = Polyline::with_capacity(2, true);
+            pline.add(cx + rx, cy, 1.0);
+            pline.add(cx - rx, cy, 1.0);
+            pline
+        } else {
+            let center = Vector2::new(cx, cy);
+            let mut count = 4;
+            let (mut vertexes, mut error) = ellipse_arcs(center, rx, ry, count);
+            while error > arc_error {
+                if count >= MAX_ELLIPSE_BIARCS {
+                    return 4;
+                }
+                count *= 2;
+                (vertexes, error) = ellipse_arcs(center, rx, ry, count);
+            }
+            Polyline::from_iter(vertexes.into_iter(), true)
+        };
+        *result = pline_into_raw(pline);
+        0
+    })
+}
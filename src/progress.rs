@@ -0,0 +1,76 @@
+//! Progress reporting and cancellation of long running operations.
+//!
+//! Operations run on the calling thread and report their progress between
+//! the steps of their computation (e.g. every merge of a union, every ear
+//! clipped by a triangulation or every stage of an offset). A cancelled
+//! operation stops at the next step, leaves no work running and returns
+//! [CAVC_CANCELLED].
+use std::os::raw::c_void;
+use std::time::{Duration, Instant};
+
+use crate::error::set_last_error;
+
+/// Callback reporting the progress of a long running operation as a fraction
+/// between 0 and 1, returning non-zero cancels the operation. It is always
+/// invoked on the calling thread.
+pub type cavc_progress_fn = Option<extern "C" fn(progress: f64, user_data: *mut c_void) -> i32>;
+
+/// Error code returned by every operation cancelled by its callback.
+pub const CAVC_CANCELLED: i32 = -2;
+
+/// Minimum time between two progress reports.
+const REPORT_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Marker of an operation cancelled by its progress callback.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) struct Cancelled;
+
+/// Reporter of the progress of an operation to a [cavc_progress_fn].
+pub(crate) struct Progress {
+    callback: cavc_progress_fn,
+    user_data: *mut c_void,
+    last_report: Option<Instant>,
+}
+
+impl Progress {
+    pub(crate) fn new(callback: cavc_progress_fn, user_data: *mut c_void) -> Self {
+        Self {
+            callback,
+            user_data,
+            last_report: None,
+        }
+    }
+
+    /// Report `fraction` of the operation as done.
+    ///
+    /// Reports closer than [REPORT_INTERVAL] to the previous one are skipped
+    /// (apart from the first), so this may be called for every step however
+    /// small. Records the reason of the failure if the operation is cancelled.
+    pub(crate) fn report(&mut self, fraction: f64) -> Result<(), Cancelled> {
+        let callback = match self.callback {
+            Some(callback) => callback,
+            None => return Ok(()),
+        };
+        let now = Instant::now();
+        if self
+            .last_report
+            .is_some_and(|last| now.duration_since(last) < REPORT_INTERVAL)
+        {
+            return Ok(());
+        }
+        self.last_report = Some(now);
+        if callback(fraction.clamp(0.0, 1.0), self.user_data) != 0 {
+            set_last_error("operation cancelled");
+            return Err(Cancelled);
+        }
+        Ok(())
+    }
+
+    /// Report the operation as done, it can no longer be cancelled so the
+    /// return value of the callback is ignored.
+    pub(crate) fn finish(&mut self) {
+        if let Some(callback) = self.callback {
+            callback(1.0, self.user_data);
+        }
+    }
+}
@@ -0,0 +1,401 @@
+//! Native CPython extension module, enabled by the `python` feature.
+//!
+//! Exposes polylines and shapes as Python classes built on the same internals
+//! as the C FFI, so no raw pointers cross into Python and every failure is
+//! raised as an exception (panics included). Vertexes are exchanged as numpy
+//! arrays of shape `(n, 3)` with one `x, y, bulge` row per vertex, polylines
+//! also convert with `numpy.asarray`. The module is imported as
+//! `py_cavalier_contours.native` from wheels built with
+//! `maturin build --features python --bindings pyo3` after setting the module
+//! name to `py_cavalier_contours._native` (see the CI workflow).
+use cavalier_contours::core::math::Vector2;
+use cavalier_contours::polyline::{
+    PlineBooleanOptions, PlineCreation, PlineOffsetOptions, PlineOrientation, PlineSource,
+    PlineSourceMut, PlineVertex, Polyline,
+};
+use cavalier_contours::shape_algorithms::{Shape, ShapeOffsetOptions};
+use numpy::ndarray::Array2;
+use numpy::{IntoPyArray, PyArray2, PyReadonlyArray2};
+use pyo3::exceptions::{PyIndexError, PyValueError};
+use pyo3::prelude::*;
+
+use crate::boolean::{boolean_op_from_i32, union_all};
+use crate::measure::max_inscribed_circle;
+
+fn default_eps() -> f64 {
+    PlineOffsetOptions::<f64>::new().pos_equal_eps
+}
+
+fn default_slice_join_eps() -> f64 {
+    PlineOffsetOptions::<f64>::new().slice_join_eps
+}
+
+fn default_offset_dist_eps() -> f64 {
+    PlineOffsetOptions::<f64>::new().offset_dist_eps
+}
+
+/// Check that an epsilon is finite and not negative.
+fn check_eps(name: &str, value: f64) -> PyResult<()> {
+    if !value.is_finite() || value < 0.0 {
+        return Err(PyValueError::new_err(format!(
+            "{name} must be finite and not negative"
+        )));
+    }
+    Ok(())
+}
+
+/// Polyline made of line and arc segments.
+#[pyclass(name = "Polyline")]
+#[derive(Debug, Clone)]
+pub struct PyPolyline(pub Polyline<f64>);
+
+fn wrap_plines(plines: impl IntoIterator<Item = Polyline<f64>>) -> Vec<PyPolyline> {
+    plines.into_iter().map(PyPolyline).collect()
+}
+
+impl PyPolyline {
+    /// Index of a vertex from a Python index, negative ones counting from the
+    /// end.
+    fn vertex_index(&self, index: isize) -> PyResult<usize> {
+        let count = self.0.vertex_count() as isize;
+        let i = if index < 0 { index + count } else { index };
+        if !(0..count).contains(&i) {
+            return Err(PyIndexError::new_err("vertex index out of range"));
+        }
+        Ok(i as usize)
+    }
+}
+
+#[pymethods]
+impl PyPolyline {
+    /// Create a polyline from an `(n, 3)` array of `x, y, bulge` rows.
+    #[new]
+    #[pyo3(signature = (vertexes, is_closed = false))]
+    fn new(vertexes: PyReadonlyArray2<f64>, is_closed: bool) -> PyResult<Self> {
+        let vertexes = vertexes.as_array();
+        if vertexes.ncols() != 3 {
+            return Err(PyValueError::new_err("vertexes must have shape (n, 3)"));
+        }
+        let mut pline = Polyline::with_capacity(vertexes.nrows(), is_closed);
+        for row in vertexes.rows() {
+            pline.add(row[0], row[1], row[2]);
+        }
+        Ok(Self(pline))
+    }
+
+    #[getter]
+    fn is_closed(&self) -> bool {
+        self.0.is_closed()
+    }
+
+    #[setter]
+    fn set_is_closed(&mut self, is_closed: bool) {
+        self.0.set_is_closed(is_closed);
+    }
+
+    fn __len__(&self) -> usize {
+        self.0.vertex_count()
+    }
+
+    /// Vertex at `index` as an `(x, y, bulge)` tuple.
+    fn __getitem__(&self, index: isize) -> PyResult<(f64, f64, f64)> {
+        let v = self.0.at(self.vertex_index(index)?);
+        Ok((v.x, v.y, v.bulge))
+    }
+
+    fn __setitem__(&mut self, index: isize, vertex: (f64, f64, f64)) -> PyResult<()> {
+        let i = self.vertex_index(index)?;
+        self.0.set(i, vertex.0, vertex.1, vertex.2);
+        Ok(())
+    }
+
+    fn __copy__(&self) -> Self {
+        self.clone()
+    }
+
+    /// Vertexes as an `(n, 3)` array, so `numpy.asarray` accepts polylines.
+    #[pyo3(signature = (dtype = None))]
+    fn __array__<'py>(&self, py: Python<'py>, dtype: Option<&'py PyAny>) -> PyResult<&'py PyAny> {
+        let array: &PyAny = self.vertex_data(py);
+        match dtype {
+            Some(dtype) => array.call_method1("astype", (dtype,)),
+            None => Ok(array),
+        }
+    }
+
+    #[pyo3(signature = (x, y, bulge = 0.0))]
+    fn add(&mut self, x: f64, y: f64, bulge: f64) {
+        self.0.add(x, y, bulge);
+    }
+
+    /// Insert a vertex before `index` (which may be the vertex count to
+    /// append).
+    #[pyo3(signature = (index, x, y, bulge = 0.0))]
+    fn insert(&mut self, index: usize, x: f64, y: f64, bulge: f64) -> PyResult<()> {
+        if index > self.0.vertex_count() {
+            return Err(PyIndexError::new_err("vertex index out of range"));
+        }
+        self.0.insert_vertex(index, PlineVertex::new(x, y, bulge));
+        Ok(())
+    }
+
+    /// Remove the vertex at `index`, returning it as an `(x, y, bulge)` tuple.
+    fn remove(&mut self, index: isize) -> PyResult<(f64, f64, f64)> {
+        let v = self.0.remove(self.vertex_index(index)?);
+        Ok((v.x, v.y, v.bulge))
+    }
+
+    fn clear(&mut self) {
+        self.0.clear();
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Polyline(vertex_count={}, is_closed={})",
+            self.0.vertex_count(),
+            if self.0.is_closed() { "True" } else { "False" }
+        )
+    }
+
+    /// Vertexes as a new `(n, 3)` array of `x, y, bulge` rows.
+    fn vertex_data<'py>(&self, py: Python<'py>) -> &'py PyArray2<f64> {
+        let values = self
+            .0
+            .iter_vertexes()
+            .flat_map(|v| [v.x, v.y, v.bulge])
+            .collect();
+        // the length always matches the shape
+        Array2::from_shape_vec((self.0.vertex_count(), 3), values)
+            .unwrap()
+            .into_pyarray(py)
+    }
+
+    /// Signed area, positive for counter clockwise polylines, 0 if open.
+    fn area(&self) -> f64 {
+        self.0.area()
+    }
+
+    fn path_length(&self) -> f64 {
+        self.0.path_length()
+    }
+
+    fn winding_number(&self, x: f64, y: f64) -> i32 {
+        self.0.winding_number(Vector2::new(x, y))
+    }
+
+    /// `(min_x, min_y, max_x, max_y)` with arcs accounted for exactly, None
+    /// without vertexes.
+    fn extents(&self) -> Option<(f64, f64, f64, f64)> {
+        self.0
+            .extents()
+            .map(|e| (e.min_x, e.min_y, e.max_x, e.max_y))
+    }
+
+    fn is_clockwise(&self) -> bool {
+        self.0.orientation() == PlineOrientation::Clockwise
+    }
+
+    fn invert_direction(&mut self) {
+        self.0.invert_direction_mut();
+    }
+
+    /// Scale all vertexes by `factor` about the origin.
+    fn scale(&mut self, factor: f64) {
+        self.0.scale_mut(factor);
+    }
+
+    fn translate(&mut self, x: f64, y: f64) {
+        self.0.translate_mut(x, y);
+    }
+
+    /// Copy without consecutive vertexes at the same position.
+    #[pyo3(signature = (pos_equal_eps = default_eps()))]
+    fn remove_repeat_pos(&self, pos_equal_eps: f64) -> PyResult<PyPolyline> {
+        check_eps("pos_equal_eps", pos_equal_eps)?;
+        let result = self.0.remove_repeat_pos(pos_equal_eps);
+        Ok(PyPolyline(result.unwrap_or_else(|| self.0.clone())))
+    }
+
+    /// Copy without repeated positions and with collinear lines and arcs on
+    /// the same circle merged.
+    #[pyo3(signature = (pos_equal_eps = default_eps()))]
+    fn remove_redundant(&self, pos_equal_eps: f64) -> PyResult<PyPolyline> {
+        check_eps("pos_equal_eps", pos_equal_eps)?;
+        let result = self.0.remove_redundant(pos_equal_eps);
+        Ok(PyPolyline(result.unwrap_or_else(|| self.0.clone())))
+    }
+
+    /// Copy with arcs replaced by lines deviating at most `error_distance`.
+    fn arcs_to_approx_lines(&self, error_distance: f64) -> PyResult<PyPolyline> {
+        if !error_distance.is_finite() || error_distance <= 0.0 {
+            return Err(PyValueError::new_err(
+                "error_distance must be greater than 0",
+            ));
+        }
+        let result = self.0.arcs_to_approx_lines(error_distance);
+        Ok(PyPolyline(result.unwrap_or_else(|| self.0.clone())))
+    }
+
+    /// Closest point to `(x, y)` as `(x, y, seg_index, distance)`.
+    #[pyo3(signature = (x, y, pos_equal_eps = default_eps()))]
+    fn closest_point(
+        &self,
+        x: f64,
+        y: f64,
+        pos_equal_eps: f64,
+    ) -> PyResult<(f64, f64, usize, f64)> {
+        let point = Vector2::new(x, y);
+        match self.0.closest_point(point, pos_equal_eps) {
+            Some(c) => Ok((c.seg_point.x, c.seg_point.y, c.seg_start_index, c.distance)),
+            None => Err(PyValueError::new_err("polyline has no vertexes")),
+        }
+    }
+
+    /// Parallel offset, positive offsets go to the left of the direction of
+    /// travel.
+    #[pyo3(signature = (
+        offset,
+        handle_self_intersects = false,
+        pos_equal_eps = default_eps(),
+        slice_join_eps = default_slice_join_eps(),
+        offset_dist_eps = default_offset_dist_eps(),
+    ))]
+    fn parallel_offset(
+        &self,
+        offset: f64,
+        handle_self_intersects: bool,
+        pos_equal_eps: f64,
+        slice_join_eps: f64,
+        offset_dist_eps: f64,
+    ) -> PyResult<Vec<PyPolyline>> {
+        check_eps("pos_equal_eps", pos_equal_eps)?;
+        check_eps("slice_join_eps", slice_join_eps)?;
+        check_eps("offset_dist_eps", offset_dist_eps)?;
+        if !offset.is_finite() {
+            return Err(PyValueError::new_err("offset must be finite"));
+        }
+        let mut options = PlineOffsetOptions::new();
+        options.handle_self_intersects = handle_self_intersects;
+        options.pos_equal_eps = pos_equal_eps;
+        options.slice_join_eps = slice_join_eps;
+        options.offset_dist_eps = offset_dist_eps;
+        Ok(wrap_plines(self.0.parallel_offset_opt(offset, &options)))
+    }
+
+    /// Boolean operation with `other` (0 = or, 1 = and, 2 = not, 3 = xor) as
+    /// `(positive, negative)` lists of polylines, the negative ones are holes.
+    #[pyo3(signature = (other, operation, pos_equal_eps = default_eps()))]
+    fn boolean(
+        &self,
+        other: &PyPolyline,
+        operation: i32,
+        pos_equal_eps: f64,
+    ) -> PyResult<(Vec<PyPolyline>, Vec<PyPolyline>)> {
+        let op = boolean_op_from_i32(operation)
+            .ok_or_else(|| PyValueError::new_err("operation must be 0, 1, 2 or 3"))?;
+        if !self.0.is_closed() || !other.0.is_closed() {
+            return Err(PyValueError::new_err("both polylines must be closed"));
+        }
+        check_eps("pos_equal_eps", pos_equal_eps)?;
+        let mut options = PlineBooleanOptions::new();
+        options.pos_equal_eps = pos_equal_eps;
+        let result = self.0.boolean_opt(&other.0, op, &options);
+        Ok((
+            wrap_plines(result.pos_plines.into_iter().map(|p| p.pline)),
+            wrap_plines(result.neg_plines.into_iter().map(|p| p.pline)),
+        ))
+    }
+
+    /// Largest circle inside the closed polyline as `(x, y, radius)`.
+    fn max_inscribed_circle(&self, tolerance: f64) -> PyResult<(f64, f64, f64)> {
+        if !self.0.is_closed() || self.0.vertex_count() < 2 {
+            return Err(PyValueError::new_err(
+                "polyline must be closed with at least 2 vertexes",
+            ));
+        }
+        if !tolerance.is_finite() || tolerance <= 0.0 {
+            return Err(PyValueError::new_err("tolerance must be greater than 0"));
+        }
+        let (center, radius) = max_inscribed_circle(&self.0, tolerance);
+        Ok((center.x, center.y, radius))
+    }
+}
+
+/// Set of closed polylines forming a region with holes.
+#[pyclass(name = "Shape")]
+pub struct PyShape(pub Shape<f64>);
+
+#[pymethods]
+impl PyShape {
+    /// Create a shape from closed polylines, counter clockwise ones are outer
+    /// boundaries and clockwise ones holes.
+    #[new]
+    fn new(polylines: Vec<PyRef<PyPolyline>>) -> PyResult<Self> {
+        if polylines.iter().any(|p| !p.0.is_closed()) {
+            return Err(PyValueError::new_err("all polylines must be closed"));
+        }
+        Ok(Self(Shape::from_plines(
+            polylines.iter().map(|p| p.0.clone()),
+        )))
+    }
+
+    /// Parallel offset, positive offsets shrink the shape.
+    #[pyo3(signature = (
+        offset,
+        pos_equal_eps = default_eps(),
+        slice_join_eps = default_slice_join_eps(),
+        offset_dist_eps = default_offset_dist_eps(),
+    ))]
+    fn parallel_offset(
+        &self,
+        offset: f64,
+        pos_equal_eps: f64,
+        slice_join_eps: f64,
+        offset_dist_eps: f64,
+    ) -> PyResult<PyShape> {
+        check_eps("pos_equal_eps", pos_equal_eps)?;
+        check_eps("slice_join_eps", slice_join_eps)?;
+        check_eps("offset_dist_eps", offset_dist_eps)?;
+        if !offset.is_finite() {
+            return Err(PyValueError::new_err("offset must be finite"));
+        }
+        let mut options = ShapeOffsetOptions::new();
+        options.pos_equal_eps = pos_equal_eps;
+        options.slice_join_eps = slice_join_eps;
+        options.offset_dist_eps = offset_dist_eps;
+        Ok(PyShape(self.0.parallel_offset(offset, options)))
+    }
+
+    #[getter]
+    fn ccw_polylines(&self) -> Vec<PyPolyline> {
+        wrap_plines(self.0.ccw_plines.iter().map(|p| p.polyline.clone()))
+    }
+
+    #[getter]
+    fn cw_polylines(&self) -> Vec<PyPolyline> {
+        wrap_plines(self.0.cw_plines.iter().map(|p| p.polyline.clone()))
+    }
+}
+
+/// Union of closed polylines of any orientation as a list of outer
+/// boundaries (counter clockwise) each followed by its holes (clockwise).
+#[pyfunction]
+#[pyo3(signature = (polylines, pos_equal_eps = default_eps()))]
+fn union(polylines: Vec<PyRef<PyPolyline>>, pos_equal_eps: f64) -> PyResult<Vec<PyPolyline>> {
+    if polylines.iter().any(|p| !p.0.is_closed()) {
+        return Err(PyValueError::new_err("all polylines must be closed"));
+    }
+    check_eps("pos_equal_eps", pos_equal_eps)?;
+    let regions = union_all(polylines.iter().map(|p| p.0.clone()), pos_equal_eps);
+    Ok(wrap_plines(regions.into_iter().flat_map(|region| {
+        std::iter::once(region.outer).chain(region.holes)
+    })))
+}
+
+#[pymodule]
+fn _native(_py: Python, m: &PyModule) -> PyResult<()> {
+    m.add_class::<PyPolyline>()?;
+    m.add_class::<PyShape>()?;
+    m.add_function(wrap_pyfunction!(union, m)?)?;
+    Ok(())
+}
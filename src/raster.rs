@@ -0,0 +1,240 @@
+//! Rasterization of regions and distances to regular grids.
+use cavalier_contours::core::math::Vector2;
+use cavalier_contours::polyline::{PlineOffsetOptions, PlineSource, Polyline};
+use cavalier_contours_ffi::cavc_pline;
+
+use crate::geom::seg_winding_number;
+use crate::hatch::{monotonic_pieces, Piece};
+use crate::measure::closest_point_indexed;
+use crate::shape::cavc_shape;
+
+/// Number of samples per pixel along each axis when anti-aliasing.
+const AA_SAMPLES: usize = 4;
+
+/// Grid of square pixels, pixel `(col, row)` covers x from
+/// `origin_x + col * pixel_size` and y from `origin_y + row * pixel_size`.
+#[derive(Debug, Copy, Clone)]
+struct Grid {
+    origin_x: f64,
+    origin_y: f64,
+    pixel_size: f64,
+    width: usize,
+    height: usize,
+}
+
+/// Fill `out` (one value per pixel, row by row) with the coverage of the
+/// region bounded by the closed polylines `plines` (non-zero winding rule),
+/// from 0 for outside to 255 for inside.
+///
+/// Every pixel is sampled at `samples` x `samples` evenly spaced points.
+fn rasterize(plines: &[&Polyline<f64>], grid: Grid, samples: usize, out: &mut [u8]) {
+    let n = samples;
+    let sub_size = grid.pixel_size / n as f64;
+    // sample row `r` lies at y = origin_y + (r + 0.5) * sub_size
+    let sample_rows = grid.height * n;
+    let sample_cols = grid.width * n;
+    let mut pieces = Vec::new();
+    for pline in plines
+        .iter()
+        .filter(|p| p.is_closed() && p.vertex_count() > 1)
+    {
+        for (v1, v2) in pline.iter_segments() {
+            monotonic_pieces(v1, v2, &mut pieces);
+        }
+    }
+
+    // pieces crossing every sample row, a piece crosses a row if its end
+    // points are on different sides with points exactly on it counted as below
+    let mut rows: Vec<Vec<usize>> = vec![Vec::new(); sample_rows];
+    let row_of = |y: f64| ((y - grid.origin_y) / sub_size - 0.5).ceil();
+    for (i, piece) in pieces.iter().enumerate() {
+        let (y1, y2) = (piece.v1.y, piece.v2.y);
+        let (lo, hi) = if y1 < y2 { (y1, y2) } else { (y2, y1) };
+        let first = row_of(lo).max(0.0);
+        let end = row_of(hi).min(sample_rows as f64);
+        if first < end {
+            for rows_at in &mut rows[first as usize..end as usize] {
+                rows_at.push(i);
+            }
+        }
+    }
+
+    let max = (n * n) as u32;
+    let mut counts = vec![0u32; grid.width];
+    let mut crossings: Vec<(f64, i32)> = Vec::new();
+    for (row, out_row) in out.chunks_exact_mut(grid.width).enumerate() {
+        counts.fill(0);
+        for sample_row in row * n..(row + 1) * n {
+            let y = grid.origin_y + (sample_row as f64 + 0.5) * sub_size;
+            crossings.clear();
+            crossings.extend(rows[sample_row].iter().map(|&i| {
+                let piece: &Piece = &pieces[i];
+                let dir = if piece.v2.y > piece.v1.y { 1 } else { -1 };
+                (piece.x_at(y), dir)
+            }));
+            crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+            let mut winding = 0;
+            for pair in crossings.windows(2) {
+                winding += pair[0].1;
+                if winding == 0 {
+                    continue;
+                }
+                // sample columns with x in [pair[0].0, pair[1].0)
+                let col_of = |x: f64| ((x - grid.origin_x) / sub_size - 0.5).ceil();
+                let first = col_of(pair[0].0).max(0.0) as usize;
+                let end = col_of(pair[1].0).clamp(0.0, sample_cols as f64) as usize;
+                for col in first..end {
+                    counts[col / n] += 1;
+                }
+            }
+        }
+        for (value, &count) in out_row.iter_mut().zip(&counts) {
+            *value = ((count * 255 + max / 2) / max) as u8;
+        }
+    }
+}
+
+/// Rasterize the region of the shape into a grayscale bitmap.
+///
+/// `out_u8_buffer` must have room for `width * height` values and receives
+/// them row by row, pixel `(col, row)` at index `row * width + col` covers x
+/// from `origin_x + col * pixel_size` and y from `origin_y + row * pixel_size`
+/// (so row 0 is the bottom row). Counter clockwise polylines of the shape add
+/// to and clockwise polylines subtract from the region. If `antialias` is 0
+/// every pixel is sampled at its center and set to 0 or 255, otherwise it is
+/// sampled at 4 x 4 points and receives the covered fraction scaled to 0 -
+/// 255. Arcs are accounted for exactly. The buffer may be null if `width` or
+/// `height` is 0.
+///
+/// `out_u8_buffer` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `shape` is null or `out_u8_buffer` is null while the bitmap is not
+///   empty.
+/// * 2 = `pixel_size` is not greater than 0, or the origin is not finite.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_shape_rasterize(
+    shape: *const cavc_shape,
+    origin_x: f64,
+    origin_y: f64,
+    pixel_size: f64,
+    width: u32,
+    height: u32,
+    antialias: u8,
+    out_u8_buffer: *mut u8,
+) -> i32 {
+    ffi_catch_unwind!({
+        let len = width as usize * height as usize;
+        if shape.is_null() || (out_u8_buffer.is_null() && len != 0) {
+            return 1;
+        }
+        if !pixel_size.is_finite()
+            || pixel_size <= 0.0
+            || !origin_x.is_finite()
+            || !origin_y.is_finite()
+        {
+            return 2;
+        }
+        if len == 0 {
+            return 0;
+        }
+        let shape = &(*shape).0;
+        let plines: Vec<&Polyline<f64>> = shape
+            .ccw_plines
+            .iter()
+            .chain(&shape.cw_plines)
+            .map(|p| &p.polyline)
+            .collect();
+        let grid = Grid {
+            origin_x,
+            origin_y,
+            pixel_size,
+            width: width as usize,
+            height: height as usize,
+        };
+        let samples = if antialias != 0 { AA_SAMPLES } else { 1 };
+        let out = std::slice::from_raw_parts_mut(out_u8_buffer, len);
+        rasterize(&plines, grid, samples, out);
+        0
+    })
+}
+
+/// Compute the signed distance to the closed polyline at the center of every
+/// pixel of a grid, negative inside and positive outside.
+///
+/// `out_f64_buffer` must have room for `width * height` values and receives
+/// them row by row, pixel `(col, row)` at index `row * width + col` covers x
+/// from `origin_x + col * pixel_size` and y from `origin_y + row * pixel_size`
+/// (so row 0 is the bottom row). Distances are exact, arcs are measured as
+/// true arcs, and a spatial index of the segments is built once so only the
+/// segments near every pixel are visited. The buffer may be null if `width`
+/// or `height` is 0.
+///
+/// `out_f64_buffer` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` is null or `out_f64_buffer` is null while the grid is not
+///   empty.
+/// * 2 = `pline` is not closed.
+/// * 3 = `pline` has no vertexes.
+/// * 4 = `pixel_size` is not greater than 0, or the origin is not finite.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_distance_field(
+    pline: *const cavc_pline,
+    origin_x: f64,
+    origin_y: f64,
+    pixel_size: f64,
+    width: u32,
+    height: u32,
+    out_f64_buffer: *mut f64,
+) -> i32 {
+    ffi_catch_unwind!({
+        let len = width as usize * height as usize;
+        if pline.is_null() || (out_f64_buffer.is_null() && len != 0) {
+            return 1;
+        }
+        let pline = &(*pline).0;
+        if !pline.is_closed() {
+            return 2;
+        }
+        if pline.vertex_count() == 0 {
+            return 3;
+        }
+        if !pixel_size.is_finite()
+            || pixel_size <= 0.0
+            || !origin_x.is_finite()
+            || !origin_y.is_finite()
+        {
+            return 4;
+        }
+        if len == 0 {
+            return 0;
+        }
+        let out = std::slice::from_raw_parts_mut(out_f64_buffer, len);
+        let pos_equal_eps = PlineOffsetOptions::<f64>::new().pos_equal_eps;
+        let index = pline.create_approx_aabb_index();
+        // always some since the polyline has vertexes
+        let max_x = pline.extents().unwrap().max_x;
+        for (i, value) in out.iter_mut().enumerate() {
+            let col = i % width as usize;
+            let row = i / width as usize;
+            let point = Vector2::new(
+                origin_x + (col as f64 + 0.5) * pixel_size,
+                origin_y + (row as f64 + 0.5) * pixel_size,
+            );
+            // always some since the polyline has vertexes
+            let (_, _, distance) =
+                closest_point_indexed(pline, &index, point, pos_equal_eps).unwrap();
+            // only segments overlapping the ray toward positive x contribute
+            let mut winding = 0;
+            if pline.vertex_count() > 1 && point.x <= max_x {
+                index.visit_query(point.x, point.y, max_x, point.y, &mut |seg| {
+                    let next = pline.next_wrapping_index(seg);
+                    winding += seg_winding_number(pline.at(seg), pline.at(next), point);
+                });
+            }
+            *value = if winding != 0 { -distance } else { distance };
+        }
+        0
+    })
+}
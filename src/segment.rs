@@ -0,0 +1,309 @@
+//! Per segment geometry queries.
+use std::os::raw::c_void;
+
+use cavalier_contours::core::math::Vector2;
+use cavalier_contours::polyline::{seg_arc_radius_and_center, seg_length, PlineSource};
+use cavalier_contours_ffi::{cavc_pline, cavc_point, cavc_vertex};
+
+use crate::geom::{seg_point_at, seg_sweep, three_point_bulge};
+use crate::util::{from_cavc_vertex, to_cavc_point, to_cavc_vertex};
+
+/// Callback invoked by [cavc_pline_visit_segments] with the segment index, its
+/// start and end vertexes (the start holding the bulge) and the user data.
+/// Returning non-zero stops the visit. The vertex pointers are only valid
+/// during the call.
+pub type cavc_segment_visitor_fn = Option<
+    extern "C" fn(
+        index: u32,
+        start: *const cavc_vertex,
+        end: *const cavc_vertex,
+        user_data: *mut c_void,
+    ) -> u8,
+>;
+
+/// Geometry of a single polyline segment.
+///
+/// `seg_type` is 0 for a line and 1 for an arc. For lines only `start` and
+/// `end` are meaningful and the remaining fields are 0. For arcs `end_angle` is
+/// `start_angle` plus the signed sweep angle (radians), so it may lie outside
+/// of [-pi, pi], and `is_ccw` is 1 if the arc runs counter clockwise.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct cavc_segment {
+    pub seg_type: u8,
+    pub start: cavc_point,
+    pub end: cavc_point,
+    pub center: cavc_point,
+    pub radius: f64,
+    pub start_angle: f64,
+    pub end_angle: f64,
+    pub is_ccw: u8,
+}
+
+/// Get the geometry of segment `index` of the polyline (the segment starting
+/// at vertex `index`), converting the bulge to arc parameters.
+///
+/// `out` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `out` is null.
+/// * 2 = `index` is not less than the number of segments.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_get_segment(
+    pline: *const cavc_pline,
+    index: u32,
+    out: *mut cavc_segment,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || out.is_null() {
+            return 1;
+        }
+        let pline = &(*pline).0;
+        let index = index as usize;
+        if index >= pline.segment_count() {
+            return 2;
+        }
+        let v1 = pline.at(index);
+        let v2 = pline.at(pline.next_wrapping_index(index));
+        let zero = to_cavc_point(Vector2::zero());
+        let mut seg = cavc_segment {
+            seg_type: 0,
+            start: to_cavc_point(v1.pos()),
+            end: to_cavc_point(v2.pos()),
+            center: zero,
+            radius: 0.0,
+            start_angle: 0.0,
+            end_angle: 0.0,
+            is_ccw: 0,
+        };
+        if !v1.bulge_is_zero() {
+            let (radius, center) = seg_arc_radius_and_center(v1, v2);
+            let start_angle = (v1.y - center.y).atan2(v1.x - center.x);
+            seg.seg_type = 1;
+            seg.center = to_cavc_point(center);
+            seg.radius = radius;
+            seg.start_angle = start_angle;
+            seg.end_angle = start_angle + seg_sweep(v1);
+            seg.is_ccw = v1.bulge_is_pos() as u8;
+        }
+        *out = seg;
+        0
+    })
+}
+
+/// Invoke `visitor` once per segment of the polyline in order, passing
+/// `user_data` through.
+///
+/// This streams the segments to the caller without allocating a result. The
+/// visit stops early (still returning 0) when `visitor` returns non-zero, the
+/// polyline must not be modified from within `visitor`.
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `visitor` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_visit_segments(
+    pline: *const cavc_pline,
+    visitor: cavc_segment_visitor_fn,
+    user_data: *mut c_void,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() {
+            return 1;
+        }
+        let visitor = match visitor {
+            Some(visitor) => visitor,
+            None => return 1,
+        };
+        for (i, (v1, v2)) in (*pline).0.iter_segments().enumerate() {
+            let (start, end) = (to_cavc_vertex(v1), to_cavc_vertex(v2));
+            if visitor(i as u32, &start, &end, user_data) != 0 {
+                break;
+            }
+        }
+        0
+    })
+}
+
+/// Compute the bulge of the arc segment from `(x1, y1)` through `(x2, y2)` to
+/// `(x3, y3)`.
+///
+/// `bulge` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `bulge` is null.
+/// * 2 = the points are collinear or coincide, so no arc passes through them.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_seg_arc_from_three_points(
+    x1: f64,
+    y1: f64,
+    x2: f64,
+    y2: f64,
+    x3: f64,
+    y3: f64,
+    bulge: *mut f64,
+) -> i32 {
+    ffi_catch_unwind!({
+        if bulge.is_null() {
+            return 1;
+        }
+        let (start, mid, end) = (
+            Vector2::new(x1, y1),
+            Vector2::new(x2, y2),
+            Vector2::new(x3, y3),
+        );
+        match three_point_bulge(start, mid, end) {
+            Some(b) => {
+                *bulge = b;
+                0
+            }
+            None => 2,
+        }
+    })
+}
+
+/// Convert a bulge into the signed sweep angle (radians) of its arc, positive
+/// for counter clockwise arcs.
+///
+/// `sweep` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `sweep` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_seg_bulge_to_sweep(bulge: f64, sweep: *mut f64) -> i32 {
+    ffi_catch_unwind!({
+        if sweep.is_null() {
+            return 1;
+        }
+        *sweep = 4.0 * bulge.atan();
+        0
+    })
+}
+
+/// Convert a signed sweep angle (radians) into the bulge of its arc.
+///
+/// `bulge` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `bulge` is null.
+/// * 2 = `sweep` is NaN or not strictly between -2 pi and 2 pi (a full circle
+///   is not a single segment).
+#[no_mangle]
+pub unsafe extern "C" fn cavc_seg_sweep_to_bulge(sweep: f64, bulge: *mut f64) -> i32 {
+    ffi_catch_unwind!({
+        if bulge.is_null() {
+            return 1;
+        }
+        if sweep.is_nan() || sweep.abs() >= std::f64::consts::TAU {
+            return 2;
+        }
+        *bulge = (sweep / 4.0).tan();
+        0
+    })
+}
+
+/// Compute the center and radius of the arc segment from `v1` to `v2` (`v1`
+/// holding the bulge).
+///
+/// `center` and `radius` are only written to if the function returns 0
+/// (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `center` or `radius` is null.
+/// * 2 = the segment is a line.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_seg_arc_center_and_radius(
+    v1: cavc_vertex,
+    v2: cavc_vertex,
+    center: *mut cavc_point,
+    radius: *mut f64,
+) -> i32 {
+    ffi_catch_unwind!({
+        if center.is_null() || radius.is_null() {
+            return 1;
+        }
+        let (v1, v2) = (from_cavc_vertex(v1), from_cavc_vertex(v2));
+        if v1.bulge_is_zero() {
+            return 2;
+        }
+        let (r, c) = seg_arc_radius_and_center(v1, v2);
+        *center = to_cavc_point(c);
+        *radius = r;
+        0
+    })
+}
+
+/// Compute the midpoint (halfway along the path) of the segment from `v1` to
+/// `v2` (`v1` holding the bulge).
+///
+/// `point` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `point` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_seg_midpoint(
+    v1: cavc_vertex,
+    v2: cavc_vertex,
+    point: *mut cavc_point,
+) -> i32 {
+    ffi_catch_unwind!({
+        if point.is_null() {
+            return 1;
+        }
+        let midpoint = seg_point_at(from_cavc_vertex(v1), from_cavc_vertex(v2), 0.5);
+        *point = to_cavc_point(midpoint);
+        0
+    })
+}
+
+/// Compute the point at parameter `t` on the segment from `v1` to `v2` (`v1`
+/// holding the bulge).
+///
+/// `t` is the fraction of the arc length from `v1`, so points are evaluated
+/// exactly on arcs and `t = 0.5` gives the same point as [cavc_seg_midpoint].
+///
+/// `point` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `point` is null.
+/// * 2 = `t` is not within [0, 1].
+#[no_mangle]
+pub unsafe extern "C" fn cavc_seg_point_at(
+    v1: cavc_vertex,
+    v2: cavc_vertex,
+    t: f64,
+    point: *mut cavc_point,
+) -> i32 {
+    ffi_catch_unwind!({
+        if point.is_null() {
+            return 1;
+        }
+        if !(0.0..=1.0).contains(&t) {
+            return 2;
+        }
+        let p = seg_point_at(from_cavc_vertex(v1), from_cavc_vertex(v2), t);
+        *point = to_cavc_point(p);
+        0
+    })
+}
+
+/// Compute the path length of the segment from `v1` to `v2` (`v1` holding the
+/// bulge).
+///
+/// `length` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `length` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_seg_length(
+    v1: cavc_vertex,
+    v2: cavc_vertex,
+    length: *mut f64,
+) -> i32 {
+    ffi_catch_unwind!({
+        if length.is_null() {
+            return 1;
+        }
+        *length = seg_length(from_cavc_vertex(v1), from_cavc_vertex(v2));
+        0
+    })
+}
@@ -0,0 +1,461 @@
+//! FFI for [Shape], a set of closed polylines forming a region with holes.
+use cavalier_contours::polyline::{PlineSource, Polyline};
+use cavalier_contours::shape_algorithms::{IndexedPolyline, Shape, ShapeOffsetOptions};
+use cavalier_contours_ffi::{cavc_pline, cavc_plinelist};
+
+use crate::hierarchy::parent_indexes;
+use crate::util::{free_raw, into_raw, pline_into_raw, plinelist_into_raw, valid_eps};
+
+/// Opaque type that wraps a [Shape].
+pub struct cavc_shape(pub Shape<f64>);
+
+/// Shape parallel offset options.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct cavc_shape_offset_o {
+    /// Fuzzy comparison epsilon used for determining if two positions are
+    /// equal.
+    pub pos_equal_eps: f64,
+    /// Fuzzy comparison epsilon used when testing distance of slices to
+    /// original polylines for validity.
+    pub offset_dist_eps: f64,
+    /// Fuzzy comparison epsilon used for determining if two positions are
+    /// equal when stitching offset slices together.
+    pub slice_join_eps: f64,
+}
+
+impl cavc_shape_offset_o {
+    /// Whether all epsilons are valid, see [valid_eps].
+    fn is_valid(&self) -> bool {
+        [
+            self.pos_equal_eps,
+            self.offset_dist_eps,
+            self.slice_join_eps,
+        ]
+        .into_iter()
+        .all(valid_eps)
+    }
+
+    fn to_internal(self) -> ShapeOffsetOptions<f64> {
+        let mut options = ShapeOffsetOptions::new();
+        options.pos_equal_eps = self.pos_equal_eps;
+        options.offset_dist_eps = self.offset_dist_eps;
+        options.slice_join_eps = self.slice_join_eps;
+        options
+    }
+}
+
+impl Default for cavc_shape_offset_o {
+    fn default() -> Self {
+        let defaults = ShapeOffsetOptions::new();
+        Self {
+            pos_equal_eps: defaults.pos_equal_eps,
+            offset_dist_eps: defaults.offset_dist_eps,
+            slice_join_eps: defaults.slice_join_eps,
+        }
+    }
+}
+
+/// Write the default [cavc_shape_offset_o] values to `options`.
+///
+/// ## Specific Error Codes
+/// * 1 = `options` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_shape_offset_o_init(options: *mut cavc_shape_offset_o) -> i32 {
+    ffi_catch_unwind!({
+        if options.is_null() {
+            return 1;
+        }
+        *options = cavc_shape_offset_o::default();
+        0
+    })
+}
+
+/// Create a shape from a list of closed polylines.
+///
+/// Counter clockwise polylines form the outer boundaries and clockwise
+/// polylines the holes of the shape. The polylines are copied, `plinelist` is
+/// left unchanged.
+///
+/// `shape` is only written to if the function returns 0 (success) and must be
+/// freed with [cavc_shape_f].
+///
+/// ## Specific Error Codes
+/// * 1 = `plinelist` or `shape` is null.
+/// * 2 = `plinelist` contains an open polyline.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_shape_create_from_plinelist(
+    plinelist: *const cavc_plinelist,
+    shape: *mut *mut cavc_shape,
+) -> i32 {
+    ffi_catch_unwind!({
+        if plinelist.is_null() || shape.is_null() {
+            return 1;
+        }
+        let plines = &(*plinelist).0;
+        if plines.iter().any(|&p| !(*p).0.is_closed()) {
+            return 2;
+        }
+        let s = Shape::from_plines(plines.iter().map(|&p| (*p).0.clone()));
+        *shape = into_raw(cavc_shape(s));
+        0
+    })
+}
+
+/// Free an existing [cavc_shape] object. Nothing happens if `shape` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_shape_f(shape: *mut cavc_shape) {
+    free_raw(shape);
+}
+
+/// Parallel offset a shape, holes are offset in the opposite direction of the
+/// outer boundaries and all resulting intersections are resolved.
+///
+/// Positive `offset` shrinks the shape. `options` may be null in which case
+/// defaults are used.
+///
+/// `result` is only written to if the function returns 0 (success) and must be
+/// freed with [cavc_shape_f].
+///
+/// ## Specific Error Codes
+/// * 1 = `shape` or `result` is null.
+/// * 2 = a tolerance of `options` is negative, NaN or infinite.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_shape_parallel_offset(
+    shape: *const cavc_shape,
+    offset: f64,
+    options: *const cavc_shape_offset_o,
+    result: *mut *mut cavc_shape,
+) -> i32 {
+    ffi_catch_unwind!({
+        if shape.is_null() || result.is_null() {
+            return 1;
+        }
+        let options = options.as_ref().copied().unwrap_or_default();
+        if !options.is_valid() {
+            return 2;
+        }
+        let offset_shape = (*shape).0.parallel_offset(offset, options.to_internal());
+        *result = into_raw(cavc_shape(offset_shape));
+        0
+    })
+}
+
+/// Outer boundary of a shape with the holes lying in it.
+struct ShapeGroup {
+    outer: Polyline<f64>,
+    holes: Vec<Polyline<f64>>,
+}
+
+/// Opaque type holding the result of [cavc_shape_parallel_offset_grouped].
+pub struct cavc_shape_groups(Vec<ShapeGroup>);
+
+/// Group every clockwise polyline (hole) of `shape` with the smallest counter
+/// clockwise polyline (outer boundary) containing it.
+///
+/// Returns `None` if a hole is not inside any outer boundary.
+fn group_holes(shape: Shape<f64>) -> Option<Vec<ShapeGroup>> {
+    let outer_count = shape.ccw_plines.len();
+    let parents = {
+        let plines: Vec<&Polyline<f64>> = shape
+            .ccw_plines
+            .iter()
+            .chain(shape.cw_plines.iter())
+            .map(|p| &p.polyline)
+            .collect();
+        parent_indexes(&plines)
+    };
+    let mut groups: Vec<ShapeGroup> = shape
+        .ccw_plines
+        .into_iter()
+        .map(|p| ShapeGroup {
+            outer: p.polyline,
+            holes: Vec::new(),
+        })
+        .collect();
+    for (hole, &parent) in shape.cw_plines.into_iter().zip(&parents[outer_count..]) {
+        // a hole directly inside another hole (e.g. touching or overlapping
+        // after the offset) belongs to the outer boundary around both
+        let mut parent = parent;
+        while parent >= outer_count as i32 {
+            parent = parents[parent as usize];
+        }
+        if parent < 0 {
+            return None;
+        }
+        groups[parent as usize].holes.push(hole.polyline);
+    }
+    Some(groups)
+}
+
+/// Same as [cavc_shape_parallel_offset] but the result is grouped into the
+/// outer boundaries of the offset shape each with its holes.
+///
+/// Every clockwise polyline (hole) of the offset is assigned to the smallest
+/// counter clockwise polyline (outer boundary) containing it, so nested
+/// islands form groups of their own. The groups are in the order of the outer
+/// boundaries of the offset shape. `options` may be null in which case
+/// defaults are used.
+///
+/// `result` is only written to if the function returns 0 (success) and must be
+/// freed with [cavc_shape_groups_f].
+///
+/// ## Specific Error Codes
+/// * 1 = `shape` or `result` is null.
+/// * 2 = a hole of the offset is not inside any outer boundary, so it can not
+///   be grouped.
+/// * 3 = a tolerance of `options` is negative, NaN or infinite.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_shape_parallel_offset_grouped(
+    shape: *const cavc_shape,
+    offset: f64,
+    options: *const cavc_shape_offset_o,
+    result: *mut *mut cavc_shape_groups,
+) -> i32 {
+    ffi_catch_unwind!({
+        if shape.is_null() || result.is_null() {
+            return 1;
+        }
+        let options = options.as_ref().copied().unwrap_or_default();
+        if !options.is_valid() {
+            return 3;
+        }
+        let offset_shape = (*shape).0.parallel_offset(offset, options.to_internal());
+        let groups = match group_holes(offset_shape) {
+            Some(groups) => groups,
+            None => return 2,
+        };
+        *result = into_raw(cavc_shape_groups(groups));
+        0
+    })
+}
+
+/// Free an existing [cavc_shape_groups] object. Nothing happens if `groups` is
+/// null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_shape_groups_f(groups: *mut cavc_shape_groups) {
+    free_raw(groups);
+}
+
+/// Get the number of groups (outer boundaries).
+///
+/// ## Specific Error Codes
+/// * 1 = `groups` or `count` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_shape_groups_get_count(
+    groups: *const cavc_shape_groups,
+    count: *mut u32,
+) -> i32 {
+    ffi_catch_unwind!({
+        if groups.is_null() || count.is_null() {
+            return 1;
+        }
+        *count = (*groups).0.len() as u32;
+        0
+    })
+}
+
+/// Get a copy of the outer boundary and the holes of the group at `index`.
+///
+/// `outer` receives the counter clockwise outer boundary and `holes` a list of
+/// the clockwise holes inside it (possibly empty). Either may be null if not
+/// needed.
+///
+/// The outputs are only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `groups` is null.
+/// * 2 = `index` is out of bounds.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_shape_groups_get_group(
+    groups: *const cavc_shape_groups,
+    index: u32,
+    outer: *mut *mut cavc_pline,
+    holes: *mut *mut cavc_plinelist,
+) -> i32 {
+    ffi_catch_unwind!({
+        if groups.is_null() {
+            return 1;
+        }
+        let group = match (*groups).0.get(index as usize) {
+            Some(group) => group,
+            None => return 2,
+        };
+        if !outer.is_null() {
+            *outer = pline_into_raw(group.outer.clone());
+        }
+        if !holes.is_null() {
+            *holes = plinelist_into_raw(group.holes.clone());
+        }
+        0
+    })
+}
+
+fn clone_plines(plines: &[IndexedPolyline<f64>]) -> Vec<Polyline<f64>> {
+    plines.iter().map(|p| p.polyline.clone()).collect()
+}
+
+/// Get a copy of the counter clockwise polylines (outer boundaries) of the
+/// shape as a new list.
+///
+/// `plinelist` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `shape` or `plinelist` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_shape_get_ccw_plines(
+    shape: *const cavc_shape,
+    plinelist: *mut *mut cavc_plinelist,
+) -> i32 {
+    ffi_catch_unwind!({
+        if shape.is_null() || plinelist.is_null() {
+            return 1;
+        }
+        *plinelist = plinelist_into_raw(clone_plines(&(*shape).0.ccw_plines));
+        0
+    })
+}
+
+/// Get a copy of the clockwise polylines (holes) of the shape as a new list.
+///
+/// `plinelist` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `shape` or `plinelist` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_shape_get_cw_plines(
+    shape: *const cavc_shape,
+    plinelist: *mut *mut cavc_plinelist,
+) -> i32 {
+    ffi_catch_unwind!({
+        if shape.is_null() || plinelist.is_null() {
+            return 1;
+        }
+        *plinelist = plinelist_into_raw(clone_plines(&(*shape).0.cw_plines));
+        0
+    })
+}
+
+/// Get the number of counter clockwise polylines (outer boundaries) of the
+/// shape.
+///
+/// ## Specific Error Codes
+/// * 1 = `shape` or `count` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_shape_get_ccw_count(
+    shape: *const cavc_shape,
+    count: *mut u32,
+) -> i32 {
+    ffi_catch_unwind!({
+        if shape.is_null() || count.is_null() {
+            return 1;
+        }
+        *count = (*shape).0.ccw_plines.len() as u32;
+        0
+    })
+}
+
+/// Get the number of clockwise polylines (holes) of the shape.
+///
+/// ## Specific Error Codes
+/// * 1 = `shape` or `count` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_shape_get_cw_count(shape: *const cavc_shape, count: *mut u32) -> i32 {
+    ffi_catch_unwind!({
+        if shape.is_null() || count.is_null() {
+            return 1;
+        }
+        *count = (*shape).0.cw_plines.len() as u32;
+        0
+    })
+}
+
+/// Get a copy of a single polyline of the shape, `which` selects the counter
+/// clockwise polylines (0) or the clockwise polylines (1) and `index` the
+/// polyline among them.
+///
+/// `out_pline` is only written to if the function returns 0 (success) and must
+/// be freed with `cavc_pline_f`.
+///
+/// ## Specific Error Codes
+/// * 1 = `shape` or `out_pline` is null.
+/// * 2 = `which` is not 0 or 1.
+/// * 3 = `index` is out of bounds.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_shape_get_pline(
+    shape: *const cavc_shape,
+    which: u32,
+    index: u32,
+    out_pline: *mut *mut cavc_pline,
+) -> i32 {
+    ffi_catch_unwind!({
+        if shape.is_null() || out_pline.is_null() {
+            return 1;
+        }
+        let plines = match which {
+            0 => &(*shape).0.ccw_plines,
+            1 => &(*shape).0.cw_plines,
+            _ => return 2,
+        };
+        match plines.get(index as usize) {
+            Some(p) => {
+                *out_pline = pline_into_raw(p.polyline.clone());
+                0
+            }
+            None => 3,
+        }
+    })
+}
+
+/// Compute the axis aligned bounding box of all polylines of the shape.
+///
+/// Arc segments are accounted for exactly, see `cavc_pline_extents`.
+///
+/// The out parameters are only written to if the function returns 0
+/// (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `shape` or any of the out parameters is null.
+/// * 2 = the shape has no polylines with vertexes.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_shape_extents(
+    shape: *const cavc_shape,
+    min_x: *mut f64,
+    min_y: *mut f64,
+    max_x: *mut f64,
+    max_y: *mut f64,
+) -> i32 {
+    ffi_catch_unwind!({
+        if shape.is_null()
+            || min_x.is_null()
+            || min_y.is_null()
+            || max_x.is_null()
+            || max_y.is_null()
+        {
+            return 1;
+        }
+        let shape = &(*shape).0;
+        let extents = shape
+            .ccw_plines
+            .iter()
+            .chain(shape.cw_plines.iter())
+            .filter_map(|p| p.polyline.extents())
+            .reduce(|mut a, b| {
+                a.min_x = a.min_x.min(b.min_x);
+                a.min_y = a.min_y.min(b.min_y);
+                a.max_x = a.max_x.max(b.max_x);
+                a.max_y = a.max_y.max(b.max_y);
+                a
+            });
+        match extents {
+            Some(extents) => {
+                *min_x = extents.min_x;
+                *min_y = extents.min_y;
+                *max_x = extents.max_x;
+                *max_y = extents.max_y;
+                0
+            }
+            None => 2,
+        }
+    })
+}
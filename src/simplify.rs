@@ -0,0 +1,250 @@
+//! Lossy simplification of polylines.
+use std::f64::consts::TAU;
+
+use cavalier_contours::core::math::Vector2;
+use cavalier_contours::polyline::{
+    seg_arc_radius_and_center, PlineCreation, PlineSource, PlineSourceMut, PlineVertex, Polyline,
+};
+use cavalier_contours_ffi::cavc_pline;
+
+use crate::geom::seg_sweep;
+use crate::util::pline_into_raw;
+
+/// Maximum distance between an arc segment and its chord.
+fn sagitta(v1: PlineVertex<f64>, v2: PlineVertex<f64>) -> f64 {
+    0.5 * (v2.pos() - v1.pos()).length() * v1.bulge.abs()
+}
+
+/// True if a single arc from vertex `start` to vertex `end` (wrapping) with
+/// `bulge` stays within `tolerance` of the original arc segments in between.
+fn merged_arc_fits(
+    pline: &Polyline<f64>,
+    start: usize,
+    end: usize,
+    bulge: f64,
+    tolerance: f64,
+) -> bool {
+    let n = pline.vertex_count();
+    let v1 = pline.at(start).with_bulge(bulge);
+    let v2 = pline.at(end % n);
+    if v1.pos() == v2.pos() {
+        return false;
+    }
+    let (radius, center) = seg_arc_radius_and_center(v1, v2);
+    (start..end).all(|i| {
+        let u1 = pline.at(i);
+        let u2 = pline.at((i + 1) % n);
+        if u1.pos() == u2.pos() {
+            return false;
+        }
+        // every point of the original arc is within this distance of the
+        // merged circle
+        let (r, c) = seg_arc_radius_and_center(u1, u2);
+        (c - center).length() + (r - radius).abs() <= tolerance
+    })
+}
+
+/// Merge runs of consecutive arc segments lying on a common circle (within
+/// `tolerance`) into single arcs. The start vertex of closed polylines is kept.
+fn merge_arcs(pline: &Polyline<f64>, tolerance: f64) -> Polyline<f64> {
+    let n = pline.vertex_count();
+    let seg_count = pline.segment_count();
+    let mut result = Polyline::with_capacity(n, pline.is_closed());
+    let mut i = 0;
+    while i < n {
+        let v = pline.at(i);
+        if i >= seg_count || v.bulge_is_zero() {
+            result.add_vertex(v);
+            i += 1;
+            continue;
+        }
+        let mut bulge = v.bulge;
+        let mut sweep = seg_sweep(v);
+        let mut j = i + 1;
+        while j < seg_count {
+            let next = pline.at(j);
+            if next.bulge_is_zero() || next.bulge_is_neg() != v.bulge_is_neg() {
+                break;
+            }
+            let merged_sweep = sweep + seg_sweep(next);
+            // a full circle cannot be a single segment
+            if merged_sweep.abs() >= 0.99 * TAU {
+                break;
+            }
+            let merged_bulge = (merged_sweep / 4.0).tan();
+            if !merged_arc_fits(pline, i, j + 1, merged_bulge, tolerance) {
+                break;
+            }
+            sweep = merged_sweep;
+            bulge = merged_bulge;
+            j += 1;
+        }
+        result.add_vertex(v.with_bulge(bulge));
+        i = j;
+    }
+    result
+}
+
+/// Distance from `p` to the line segment `a`-`b`.
+fn dist_to_line_seg(p: Vector2<f64>, a: Vector2<f64>, b: Vector2<f64>) -> f64 {
+    let ab = b - a;
+    let len_sq = ab.dot(ab);
+    if len_sq == 0.0 {
+        return (p - a).length();
+    }
+    let t = ((p - a).dot(ab) / len_sq).clamp(0.0, 1.0);
+    (p - (a + ab.scale(t))).length()
+}
+
+/// Douglas-Peucker over `points`, marking retained points in `keep`. The end
+/// points are always retained.
+fn douglas_peucker(points: &[Vector2<f64>], tolerance: f64, keep: &mut [bool]) {
+    let last = points.len() - 1;
+    keep[0] = true;
+    keep[last] = true;
+    // explicit stack since traced input easily reaches recursion depths that
+    // would overflow the stack
+    let mut stack = vec![(0, last)];
+    while let Some((first, last)) = stack.pop() {
+        let mut max_dist = 0.0;
+        let mut max_index = first;
+        for (i, &p) in points.iter().enumerate().take(last).skip(first + 1) {
+            let dist = dist_to_line_seg(p, points[first], points[last]);
+            if dist > max_dist {
+                max_dist = dist;
+                max_index = i;
+            }
+        }
+        if max_dist > tolerance {
+            keep[max_index] = true;
+            stack.push((first, max_index));
+            stack.push((max_index, last));
+        }
+    }
+}
+
+/// Arc aware Douglas-Peucker simplification.
+///
+/// Consecutive arcs on a common circle are merged first. Arcs deviating at
+/// most half the tolerance from their chord are then simplified along with the
+/// lines, while the end vertexes of all other arcs are retained with their
+/// bulges and only the runs in between are simplified.
+pub(crate) fn simplify(pline: &Polyline<f64>, tolerance: f64) -> Polyline<f64> {
+    if pline.vertex_count() < 3 {
+        return pline.clone();
+    }
+    let pline = &merge_arcs(pline, tolerance);
+    let n = pline.vertex_count();
+    if n < 3 {
+        return pline.clone();
+    }
+
+    let is_closed = pline.is_closed();
+    let seg_sagitta: Vec<f64> = (0..n)
+        .map(|i| {
+            if is_closed || i + 1 < n {
+                sagitta(pline.at(i), pline.at((i + 1) % n))
+            } else {
+                0.0
+            }
+        })
+        .collect();
+    let is_fixed_arc = |i: usize| seg_sagitta[i] > 0.5 * tolerance;
+    let mut keep: Vec<bool> = (0..n)
+        .map(|i| {
+            let incoming = (is_closed || i > 0) && is_fixed_arc(pline.prev_wrapping_index(i));
+            is_fixed_arc(i) || incoming
+        })
+        .collect();
+
+    if !is_closed {
+        keep[0] = true;
+        keep[n - 1] = true;
+    } else if !keep.iter().any(|&k| k) {
+        // closed polyline of lines only, anchor at the first vertex and the
+        // vertex farthest from it
+        let start = pline.at(0).pos();
+        let farthest = (1..n)
+            .max_by(|&a, &b| {
+                let da = (pline.at(a).pos() - start).length();
+                let db = (pline.at(b).pos() - start).length();
+                da.total_cmp(&db)
+            })
+            .unwrap();
+        keep[0] = true;
+        keep[farthest] = true;
+    }
+
+    let anchors: Vec<usize> = (0..n).filter(|&i| keep[i]).collect();
+    let mut runs: Vec<(usize, usize)> = anchors.windows(2).map(|w| (w[0], w[1])).collect();
+    if is_closed {
+        // run wrapping around from the last anchor back to the first
+        runs.push((*anchors.last().unwrap(), anchors[0] + n));
+    }
+
+    let mut points = Vec::new();
+    let mut run_keep = Vec::new();
+    for (start, end) in runs {
+        if end - start < 2 {
+            continue;
+        }
+        // flat arcs in the run may deviate from their chords by up to their
+        // sagitta on top of the chord deviation
+        let run_tolerance =
+            tolerance - (start..end).map(|i| seg_sagitta[i % n]).fold(0.0, f64::max);
+        points.clear();
+        points.extend((start..=end).map(|i| pline.at(i % n).pos()));
+        run_keep.clear();
+        run_keep.resize(points.len(), false);
+        douglas_peucker(&points, run_tolerance, &mut run_keep);
+        for (offset, &k) in run_keep.iter().enumerate() {
+            if k {
+                keep[(start + offset) % n] = true;
+            }
+        }
+    }
+
+    let kept: Vec<usize> = (0..n).filter(|&i| keep[i]).collect();
+    let mut result = Polyline::with_capacity(kept.len(), is_closed);
+    for (k, &i) in kept.iter().enumerate() {
+        let v = pline.at(i);
+        let next = kept.get(k + 1).copied().unwrap_or(kept[0] + n);
+        // segments replacing dropped vertexes are lines
+        let bulge = if next == i + 1 { v.bulge } else { 0.0 };
+        result.add_vertex(v.with_bulge(bulge));
+    }
+    result
+}
+
+/// Simplify a polyline using Douglas-Peucker, keeping the result within
+/// `tolerance` of the original.
+///
+/// Arc segments are preserved where they fit: consecutive arcs lying on the
+/// same circle (within `tolerance`) are merged into a single arc, arcs that
+/// are nearly straight (deviating at most `tolerance / 2` from their chord)
+/// may be replaced by lines, and the end vertexes of all other arcs are
+/// retained with their bulges while the runs of segments between them are
+/// simplified.
+///
+/// `result` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `result` is null.
+/// * 2 = `tolerance` is negative or NaN.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_simplify(
+    pline: *const cavc_pline,
+    tolerance: f64,
+    result: *mut *mut cavc_pline,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || result.is_null() {
+            return 1;
+        }
+        if tolerance.is_nan() || tolerance < 0.0 {
+            return 2;
+        }
+        *result = pline_into_raw(simplify(&(*pline).0, tolerance));
+        0
+    })
+}
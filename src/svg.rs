@@ -0,0 +1,653 @@
+//! Conversion between polylines and SVG path data.
+//!
+//! Coordinates are used as they are, no axis is flipped. Since an SVG arc
+//! with sweep flag 1 runs in the direction of increasing angle, a positive
+//! bulge maps to sweep flag 1.
+use std::f64::consts::{PI, TAU};
+use std::ffi::CStr;
+use std::os::raw::c_char;
+
+use cavalier_contours::core::math::Vector2;
+use cavalier_contours::polyline::{
+    seg_arc_radius_and_center, PlineCreation, PlineSource, PlineVertex, Polyline,
+};
+use cavalier_contours_ffi::{cavc_pline, cavc_plinelist};
+
+use crate::error::set_last_error;
+use crate::json::write_number;
+use crate::util::{plinelist_into_raw, write_c_string};
+
+/// Append space separated numbers to `out`, returns false if any is not
+/// finite.
+fn write_numbers(out: &mut String, values: &[f64]) -> bool {
+    for &value in values {
+        out.push(' ');
+        if !write_number(out, value) {
+            return false;
+        }
+    }
+    true
+}
+
+/// Append the SVG path data of `pline` to `out`.
+///
+/// Returns false if any vertex holds a non-finite value.
+pub(crate) fn write_svg_path(out: &mut String, pline: &Polyline<f64>) -> bool {
+    let first = match pline.get(0) {
+        Some(v) => v,
+        None => return true,
+    };
+    out.push('M');
+    if !write_numbers(out, &[first.x, first.y]) {
+        return false;
+    }
+    for (v1, v2) in pline.iter_segments() {
+        let ok = if v1.bulge_is_zero() || v1.pos() == v2.pos() {
+            out.push_str(" L");
+            write_numbers(out, &[v2.x, v2.y])
+        } else {
+            let (radius, _) = seg_arc_radius_and_center(v1, v2);
+            let large_arc = if v1.bulge.abs() > 1.0 { 1.0 } else { 0.0 };
+            let sweep = if v1.bulge_is_pos() { 1.0 } else { 0.0 };
+            out.push_str(" A");
+            write_numbers(out, &[radius, radius, 0.0, large_arc, sweep, v2.x, v2.y])
+        };
+        if !ok {
+            return false;
+        }
+    }
+    if pline.is_closed() {
+        out.push_str(" Z");
+    }
+    true
+}
+
+/// Convert a polyline to SVG path data using `M`, `L`, `A` and `Z` commands.
+///
+/// Arc segments become exact circular `A` commands. The nul terminated string
+/// is written to `out` which has room for `out_cap` bytes. `out_len` receives
+/// the length of the string (not counting the nul terminator) even when `out`
+/// is too small, so the caller can allocate a large enough buffer and call
+/// again. `out` may be null if `out_cap` is 0. A polyline without vertexes
+/// gives an empty string.
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `out_len` is null.
+/// * 2 = `out_cap` is too small to hold the string and its nul terminator.
+/// * 3 = `pline` contains a NaN or infinite value.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_to_svg_path(
+    pline: *const cavc_pline,
+    out: *mut c_char,
+    out_cap: u32,
+    out_len: *mut u32,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || out_len.is_null() {
+            return 1;
+        }
+        let mut path = String::new();
+        if !write_svg_path(&mut path, &(*pline).0) {
+            return 3;
+        }
+        *out_len = path.len() as u32;
+        if !write_c_string(&path, out, out_cap as usize) {
+            return 2;
+        }
+        0
+    })
+}
+
+/// Error returned by [parse_svg_path] for malformed path data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct SvgPathError;
+
+/// Tokenizer for the SVG path data grammar.
+struct PathLexer<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl PathLexer<'_> {
+    fn skip_separators(&mut self) {
+        while self.pos < self.bytes.len()
+            && (self.bytes[self.pos].is_ascii_whitespace() || self.bytes[self.pos] == b',')
+        {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_separators();
+        self.bytes.get(self.pos).copied()
+    }
+
+    /// True if the next token is a number (continuing the current command).
+    fn at_number(&mut self) -> bool {
+        matches!(self.peek(), Some(b'0'..=b'9' | b'.' | b'+' | b'-'))
+    }
+
+    fn number(&mut self) -> Result<f64, SvgPathError> {
+        self.skip_separators();
+        let start = self.pos;
+        let digits = |lexer: &mut Self| {
+            let from = lexer.pos;
+            while lexer.pos < lexer.bytes.len() && lexer.bytes[lexer.pos].is_ascii_digit() {
+                lexer.pos += 1;
+            }
+            lexer.pos - from
+        };
+        if matches!(self.bytes.get(self.pos), Some(b'+' | b'-')) {
+            self.pos += 1;
+        }
+        let mut mantissa_digits = digits(self);
+        if self.bytes.get(self.pos) == Some(&b'.') {
+            self.pos += 1;
+            mantissa_digits += digits(self);
+        }
+        if mantissa_digits == 0 {
+            return Err(SvgPathError);
+        }
+        if matches!(self.bytes.get(self.pos), Some(b'e' | b'E')) {
+            let before_exponent = self.pos;
+            self.pos += 1;
+            if matches!(self.bytes.get(self.pos), Some(b'+' | b'-')) {
+                self.pos += 1;
+            }
+            if digits(self) == 0 {
+                self.pos = before_exponent;
+            }
+        }
+        std::str::from_utf8(&self.bytes[start..self.pos])
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .ok_or(SvgPathError)
+    }
+
+    fn point(&mut self) -> Result<Vector2<f64>, SvgPathError> {
+        Ok(Vector2::new(self.number()?, self.number()?))
+    }
+
+    /// Arc flags are single digits which need not be separated.
+    fn flag(&mut self) -> Result<bool, SvgPathError> {
+        let flag = match self.peek() {
+            Some(b'0') => false,
+            Some(b'1') => true,
+            _ => return Err(SvgPathError),
+        };
+        self.pos += 1;
+        Ok(flag)
+    }
+}
+
+/// Distance from `p` to the line through `a` and `b`.
+fn dist_to_line(p: Vector2<f64>, a: Vector2<f64>, b: Vector2<f64>) -> f64 {
+    let ab = b - a;
+    let len = ab.length();
+    if len == 0.0 {
+        (p - a).length()
+    } else {
+        ab.perp_dot(p - a).abs() / len
+    }
+}
+
+/// Maximum depth of Bézier subdivision, bounding the points per curve.
+const MAX_BEZIER_DEPTH: u32 = 16;
+
+/// Maximum number of lines a flattened elliptical arc is divided into.
+const MAX_ARC_LINES: f64 = 65536.0;
+
+/// Accumulates polylines while the path data is interpreted.
+struct PathBuilder {
+    plines: Vec<Polyline<f64>>,
+    vertexes: Vec<PlineVertex<f64>>,
+    current: Vector2<f64>,
+    subpath_start: Vector2<f64>,
+    arc_error: f64,
+}
+
+impl PathBuilder {
+    fn finish_subpath(&mut self, is_closed: bool) {
+        let mut vertexes = std::mem::take(&mut self.vertexes);
+        if is_closed && vertexes.len() > 1 {
+            let first = vertexes[0].pos();
+            let last = vertexes[vertexes.len() - 1].pos();
+            if last.fuzzy_eq_eps(first, 1e-9 * (1.0 + first.length())) {
+                vertexes.pop();
+            }
+        }
+        if vertexes.len() >= 2 {
+            self.plines
+                .push(Polyline::from_iter(vertexes.into_iter(), is_closed));
+        }
+    }
+
+    fn move_to(&mut self, p: Vector2<f64>) {
+        self.finish_subpath(false);
+        self.vertexes.push(PlineVertex::from_vector2(p, 0.0));
+        self.current = p;
+        self.subpath_start = p;
+    }
+
+    /// Start of the segment about to be added, opening a subpath at the current
+    /// point if needed (drawing right after `Z`).
+    fn segment_start(&mut self) -> &mut PlineVertex<f64> {
+        if self.vertexes.is_empty() {
+            self.vertexes
+                .push(PlineVertex::from_vector2(self.current, 0.0));
+        }
+        self.vertexes.last_mut().unwrap()
+    }
+
+    fn add_segment(&mut self, bulge: f64, end: Vector2<f64>) {
+        if end != self.current {
+            self.segment_start().bulge = bulge;
+            self.vertexes.push(PlineVertex::from_vector2(end, 0.0));
+        }
+        self.current = end;
+    }
+
+    fn line_to(&mut self, p: Vector2<f64>) {
+        self.add_segment(0.0, p);
+    }
+
+    fn cubic_to(&mut self, c1: Vector2<f64>, c2: Vector2<f64>, end: Vector2<f64>) {
+        let mut stack = vec![(self.current, c1, c2, end, 0)];
+        // depth first with the second half pushed first so points come out in
+        // order
+        while let Some((p0, p1, p2, p3, depth)) = stack.pop() {
+            let flat = dist_to_line(p1, p0, p3).max(dist_to_line(p2, p0, p3)) <= self.arc_error;
+            if flat || depth >= MAX_BEZIER_DEPTH {
+                self.line_to(p3);
+                continue;
+            }
+            let mid = |a: Vector2<f64>, b: Vector2<f64>| (a + b).scale(0.5);
+            let (p01, p12, p23) = (mid(p0, p1), mid(p1, p2), mid(p2, p3));
+            let (p012, p123) = (mid(p01, p12), mid(p12, p23));
+            let split = mid(p012, p123);
+            stack.push((split, p123, p23, p3, depth + 1));
+            stack.push((p0, p01, p012, split, depth + 1));
+        }
+    }
+
+    fn quad_to(&mut self, control: Vector2<f64>, end: Vector2<f64>) {
+        let p0 = self.current;
+        let c1 = p0 + (control - p0).scale(2.0 / 3.0);
+        let c2 = end + (control - end).scale(2.0 / 3.0);
+        self.cubic_to(c1, c2, end);
+    }
+
+    /// SVG elliptical arc, a circular arc becomes a single bulge segment,
+    /// otherwise it is flattened.
+    ///
+    /// Fails if the arc is not finite or would be flattened into more than
+    /// [MAX_ARC_LINES] lines (`arc_error` too small for its radii).
+    fn arc_to(
+        &mut self,
+        rx: f64,
+        ry: f64,
+        x_axis_rotation: f64,
+        large_arc: bool,
+        sweep: bool,
+        end: Vector2<f64>,
+    ) -> Result<(), SvgPathError> {
+        let start = self.current;
+        if start == end {
+            return Ok(());
+        }
+        let (mut rx, mut ry) = (rx.abs(), ry.abs());
+        if rx == 0.0 || ry == 0.0 {
+            self.line_to(end);
+            return Ok(());
+        }
+        // endpoint to center parameterization, SVG 1.1 appendix F.6.5
+        let (sin_phi, cos_phi) = x_axis_rotation.to_radians().sin_cos();
+        let half = (start - end).scale(0.5);
+        let x1p = cos_phi * half.x + sin_phi * half.y;
+        let y1p = -sin_phi * half.x + cos_phi * half.y;
+        let lambda = (x1p * x1p) / (rx * rx) + (y1p * y1p) / (ry * ry);
+        if lambda > 1.0 {
+            rx *= lambda.sqrt();
+            ry *= lambda.sqrt();
+        }
+        if (rx - ry).abs() <= 1e-9 * rx.max(ry) {
+            let half_chord = 0.5 * (end - start).length();
+            let small_sweep = 2.0 * (half_chord / rx).min(1.0).asin();
+            let sweep_angle = if large_arc {
+                TAU - small_sweep
+            } else {
+                small_sweep
+            };
+            let signed = if sweep { sweep_angle } else { -sweep_angle };
+            self.add_segment((signed / 4.0).tan(), end);
+            return Ok(());
+        }
+        let (rx2, ry2) = (rx * rx, ry * ry);
+        let num = rx2 * ry2 - rx2 * y1p * y1p - ry2 * x1p * x1p;
+        let den = rx2 * y1p * y1p + ry2 * x1p * x1p;
+        let sign = if large_arc == sweep { -1.0 } else { 1.0 };
+        let coef = sign * (num / den).max(0.0).sqrt();
+        let cxp = coef * rx * y1p / ry;
+        let cyp = -coef * ry * x1p / rx;
+        let mid = (start + end).scale(0.5);
+        let cx = cos_phi * cxp - sin_phi * cyp + mid.x;
+        let cy = sin_phi * cxp + cos_phi * cyp + mid.y;
+        let theta1 = ((y1p - cyp) / ry).atan2((x1p - cxp) / rx);
+        let theta2 = ((-y1p - cyp) / ry).atan2((-x1p - cxp) / rx);
+        let mut dtheta = theta2 - theta1;
+        if sweep && dtheta < 0.0 {
+            dtheta += TAU;
+        } else if !sweep && dtheta > 0.0 {
+            dtheta -= TAU;
+        }
+        // chord deviation of the larger circle bounds the deviation
+        let r_max = rx.max(ry);
+        let step = if self.arc_error < r_max {
+            2.0 * (1.0 - self.arc_error / r_max).acos()
+        } else {
+            PI / 2.0
+        };
+        // the step is 0 once arc_error / r_max is below precision
+        let count = (dtheta.abs() / step).ceil().max(1.0);
+        if count.is_nan() || count > MAX_ARC_LINES {
+            return Err(SvgPathError);
+        }
+        let count = count as usize;
+        for k in 1..count {
+            let t = theta1 + dtheta * k as f64 / count as f64;
+            let (sin_t, cos_t) = t.sin_cos();
+            self.line_to(Vector2::new(
+                cx + rx * cos_t * cos_phi - ry * sin_t * sin_phi,
+                cy + rx * cos_t * sin_phi + ry * sin_t * cos_phi,
+            ));
+        }
+        self.line_to(end);
+        Ok(())
+    }
+}
+
+/// Parse SVG path data into polylines, one per subpath.
+///
+/// Circular arcs become bulge segments while elliptical arcs and Bézier
+/// curves are flattened to lines deviating at most `arc_error`. Subpaths
+/// with less than 2 distinct vertexes are dropped.
+pub(crate) fn parse_svg_path(
+    data: &str,
+    arc_error: f64,
+) -> Result<Vec<Polyline<f64>>, SvgPathError> {
+    let mut lexer = PathLexer {
+        bytes: data.as_bytes(),
+        pos: 0,
+    };
+    let mut builder = PathBuilder {
+        plines: Vec::new(),
+        vertexes: Vec::new(),
+        current: Vector2::zero(),
+        subpath_start: Vector2::zero(),
+        arc_error,
+    };
+    // second control point of the previous curve for the S and T shorthands
+    let mut prev_cubic_ctrl: Option<Vector2<f64>> = None;
+    let mut prev_quad_ctrl: Option<Vector2<f64>> = None;
+    let mut started = false;
+    while let Some(cmd) = lexer.peek() {
+        lexer.pos += 1;
+        let upper = cmd.to_ascii_uppercase();
+        if !started && upper != b'M' {
+            return Err(SvgPathError);
+        }
+        started = true;
+        let relative = cmd.is_ascii_lowercase();
+        if upper == b'Z' {
+            builder.finish_subpath(true);
+            builder.current = builder.subpath_start;
+            prev_cubic_ctrl = None;
+            prev_quad_ctrl = None;
+            continue;
+        }
+        let mut first = true;
+        loop {
+            if !first && !lexer.at_number() {
+                break;
+            }
+            let origin = if relative {
+                builder.current
+            } else {
+                Vector2::zero()
+            };
+            let (mut cubic_ctrl, mut quad_ctrl) = (None, None);
+            match upper {
+                b'M' => {
+                    let p = origin + lexer.point()?;
+                    // pairs after the first are implicit line commands
+                    if first {
+                        builder.move_to(p);
+                    } else {
+                        builder.line_to(p);
+                    }
+                }
+                b'L' => {
+                    let p = origin + lexer.point()?;
+                    builder.line_to(p);
+                }
+                b'H' => {
+                    let x = lexer.number()? + origin.x;
+                    builder.line_to(Vector2::new(x, builder.current.y));
+                }
+                b'V' => {
+                    let y = lexer.number()? + origin.y;
+                    builder.line_to(Vector2::new(builder.current.x, y));
+                }
+                b'C' | b'S' => {
+                    let c1 = if upper == b'C' {
+                        origin + lexer.point()?
+                    } else {
+                        let current = builder.current;
+                        prev_cubic_ctrl.map_or(current, |c| current + (current - c))
+                    };
+                    let c2 = origin + lexer.point()?;
+                    let end = origin + lexer.point()?;
+                    builder.cubic_to(c1, c2, end);
+                    cubic_ctrl = Some(c2);
+                }
+                b'Q' | b'T' => {
+                    let control = if upper == b'Q' {
+                        origin + lexer.point()?
+                    } else {
+                        let current = builder.current;
+                        prev_quad_ctrl.map_or(current, |c| current + (current - c))
+                    };
+                    let end = origin + lexer.point()?;
+                    builder.quad_to(control, end);
+                    quad_ctrl = Some(control);
+                }
+                b'A' => {
+                    let rx = lexer.number()?;
+                    let ry = lexer.number()?;
+                    let rotation = lexer.number()?;
+                    let large_arc = lexer.flag()?;
+                    let sweep = lexer.flag()?;
+                    let end = origin + lexer.point()?;
+                    builder.arc_to(rx, ry, rotation, large_arc, sweep, end)?;
+                }
+                _ => return Err(SvgPathError),
+            }
+            prev_cubic_ctrl = cubic_ctrl;
+            prev_quad_ctrl = quad_ctrl;
+            first = false;
+        }
+    }
+    builder.finish_subpath(false);
+    Ok(builder.plines)
+}
+
+/// Create polylines from SVG path data (the `d` attribute of a `path`
+/// element), one polyline per subpath.
+///
+/// All commands are supported in absolute and relative form. Subpaths ended
+/// with `Z` become closed polylines. Circular arcs (`A` with equal radii after
+/// the out of range radii correction of the SVG specification) become exact
+/// bulge segments, while elliptical arcs and quadratic and cubic Bézier curves
+/// are flattened to lines deviating at most `arc_error` from the curve.
+/// Coordinates are used as they are, a positive bulge corresponds to sweep
+/// flag 1. Subpaths with less than 2 distinct vertexes are dropped.
+///
+/// `data` must be a nul terminated UTF-8 string. `result_plinelist` is only
+/// written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `data` or `result_plinelist` is null.
+/// * 2 = `data` is not valid UTF-8 or not valid path data, or an elliptical
+///   arc would be flattened into more than 65536 lines.
+/// * 3 = `arc_error` is not greater than 0.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_from_svg_path(
+    data: *const c_char,
+    arc_error: f64,
+    result_plinelist: *mut *mut cavc_plinelist,
+) -> i32 {
+    ffi_catch_unwind!({
+        if data.is_null() || result_plinelist.is_null() {
+            return 1;
+        }
+        if arc_error.is_nan() || arc_error <= 0.0 {
+            return 3;
+        }
+        let plines = match CStr::from_ptr(data)
+            .to_str()
+            .map(|d| parse_svg_path(d, arc_error))
+        {
+            Ok(Ok(plines)) => plines,
+            Ok(Err(_)) => {
+                set_last_error("invalid SVG path data");
+                return 2;
+            }
+            Err(_) => {
+                set_last_error("SVG path data is not valid UTF-8");
+                return 2;
+            }
+        };
+        *result_plinelist = plinelist_into_raw(plines);
+        0
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use cavalier_contours::polyline::PlineSourceMut;
+
+    use super::*;
+
+    fn positions(pline: &Polyline<f64>) -> Vec<(f64, f64)> {
+        pline.iter_vertexes().map(|v| (v.x, v.y)).collect()
+    }
+
+    #[test]
+    fn parses_lines_and_closes_subpaths() {
+        let plines = parse_svg_path("M0,0 L10 0 10 10 Z m1 1 h2 v2 h-2", 0.01).unwrap();
+        assert_eq!(plines.len(), 2);
+        assert!(plines[0].is_closed());
+        assert_eq!(
+            positions(&plines[0]),
+            vec![(0.0, 0.0), (10.0, 0.0), (10.0, 10.0)]
+        );
+        // relative commands after Z start from the closed subpath start
+        assert!(!plines[1].is_closed());
+        assert_eq!(
+            positions(&plines[1]),
+            vec![(1.0, 1.0), (3.0, 1.0), (3.0, 3.0), (1.0, 3.0)]
+        );
+    }
+
+    #[test]
+    fn parses_compact_numbers() {
+        let plines = parse_svg_path("M-.5.5L1e1-2E-1", 0.01).unwrap();
+        assert_eq!(positions(&plines[0]), vec![(-0.5, 0.5), (10.0, -0.2)]);
+    }
+
+    #[test]
+    fn circular_arcs_become_bulges() {
+        let plines = parse_svg_path("M0 0 A5 5 0 0 1 10 0", 0.01).unwrap();
+        assert_eq!(plines[0].vertex_count(), 2);
+        assert!((plines[0].at(0).bulge - 1.0).abs() < 1e-12);
+        let plines = parse_svg_path("M0 0 A5 5 0 1 0 5 5", 0.01).unwrap();
+        assert!(plines[0].at(0).bulge < -1.0);
+        // radii too small are scaled up to reach the end point
+        let plines = parse_svg_path("M0 0 A1 1 0 0 1 10 0", 0.01).unwrap();
+        assert!((plines[0].at(0).bulge - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn flattens_curves_within_arc_error() {
+        let plines = parse_svg_path("M0 0 C0 10 10 10 10 0", 0.01).unwrap();
+        let pline = &plines[0];
+        assert!(pline.vertex_count() > 8);
+        assert_eq!(
+            pline.at(pline.vertex_count() - 1).pos(),
+            Vector2::new(10.0, 0.0)
+        );
+        assert!(pline.iter_vertexes().all(|v| v.bulge == 0.0));
+        let plines = parse_svg_path("M0 0 A10 5 0 0 1 20 0", 0.01).unwrap();
+        for v in plines[0].iter_vertexes() {
+            let on_ellipse = ((v.x - 10.0) / 10.0).powi(2) + (v.y / 5.0).powi(2);
+            assert!((on_ellipse - 1.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn degenerate_input() {
+        assert_eq!(parse_svg_path("", 0.01).unwrap().len(), 0);
+        // zero length segments and single points are dropped
+        assert_eq!(parse_svg_path("M1 1", 0.01).unwrap().len(), 0);
+        let plines = parse_svg_path("M0 0 L0 0 L1 0 L1 0", 0.01).unwrap();
+        assert_eq!(positions(&plines[0]), vec![(0.0, 0.0), (1.0, 0.0)]);
+        // zero radius arcs are lines
+        let plines = parse_svg_path("M0 0 A0 5 0 0 1 10 0", 0.01).unwrap();
+        assert_eq!(plines[0].at(0).bulge, 0.0);
+    }
+
+    #[test]
+    fn rejects_malformed_data() {
+        for data in [
+            "L1 1",
+            "M0",
+            "M0 0 L1",
+            "M0 0 X1 1",
+            "M0 0 A5 5 0 2 1 10 0",
+            "M.",
+        ] {
+            assert!(parse_svg_path(data, 0.01).is_err(), "{:?}", data);
+        }
+    }
+
+    #[test]
+    fn rejects_elliptical_arcs_with_tiny_arc_error() {
+        assert!(parse_svg_path("M0 0 A10 5 0 0 1 20 0", 1e-300).is_err());
+        // circular arcs do not need flattening
+        assert!(parse_svg_path("M0 0 A5 5 0 0 1 10 0", 1e-300).is_ok());
+    }
+
+    #[test]
+    fn round_trips_written_paths() {
+        let mut pline = Polyline::new_closed();
+        pline.add(0.0, 0.0, 0.5);
+        pline.add(10.0, 0.0, 0.0);
+        pline.add(10.0, 10.0, -1.5);
+        pline.add(0.0, 10.0, 0.0);
+        let mut data = String::new();
+        assert!(write_svg_path(&mut data, &pline));
+        let parsed = parse_svg_path(&data, 0.01).unwrap();
+        assert_eq!(parsed.len(), 1);
+        assert!(parsed[0].is_closed());
+        assert_eq!(parsed[0].vertex_count(), pline.vertex_count());
+        for (a, b) in parsed[0].iter_vertexes().zip(pline.iter_vertexes()) {
+            assert!(a.fuzzy_eq_eps(b, 1e-9), "{:?} != {:?}", a, b);
+        }
+
+        let mut data = String::new();
+        assert!(write_svg_path(&mut data, &Polyline::new()));
+        assert_eq!(data, "");
+        pline.set_vertex(1, PlineVertex::new(f64::NAN, 0.0, 0.0));
+        assert!(!write_svg_path(&mut String::new(), &pline));
+    }
+}
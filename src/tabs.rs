@@ -0,0 +1,355 @@
+//! Holding tabs on closed contours.
+//!
+//! A tab is a gap of the cut path that leaves the part attached to the
+//! surrounding material. Tabs are spread evenly along the path and moved away
+//! from corners, where a gap would spoil the corner of the part.
+use cavalier_contours::polyline::{PlineSource, Polyline};
+use cavalier_contours_ffi::{cavc_pline, cavc_plinelist};
+
+use crate::corners::turn_angles;
+use crate::path::{locate_sorted, seg_ends, seg_lengths, sub_path_from};
+use crate::util::plinelist_into_raw;
+
+/// Turning angles up to this (in radians) are smooth joins, not corners.
+const CORNER_ANGLE_EPS: f64 = 1e-6;
+
+/// Distance between path lengths `a` and `b` around a loop of length `total`.
+fn loop_distance(a: f64, b: f64, total: f64) -> f64 {
+    let d = (a - b).rem_euclid(total);
+    d.min(total - d)
+}
+
+/// Path lengths of the vertexes of the closed `pline` where the direction of
+/// the path changes abruptly, zero length segments are skipped (see
+/// [turn_angles]).
+fn corner_lengths(pline: &Polyline<f64>, lengths: &[f64], ends: &[f64]) -> Vec<f64> {
+    turn_angles(pline, lengths)
+        .into_iter()
+        .filter(|(_, angle)| angle.abs() > CORNER_ANGLE_EPS)
+        .map(|(i, _)| if i == 0 { 0.0 } else { ends[i - 1] })
+        .collect()
+}
+
+/// Centers of the tabs, each as close as possible to its evenly spaced
+/// position while staying at least `clearance` away from every corner and not
+/// overlapping a previous tab. Tabs that can not be placed are dropped.
+fn tab_centers(
+    total: f64,
+    corners: &[f64],
+    tab_count: u32,
+    tab_width: f64,
+    clearance: f64,
+) -> Vec<f64> {
+    let tol = 1e-9 * total;
+    let allowed = |s: f64| {
+        corners
+            .iter()
+            .all(|&c| loop_distance(s, c, total) >= clearance - tol)
+    };
+    // nearest allowed position is the ideal one or next to a corner
+    let candidates: Vec<f64> = corners
+        .iter()
+        .flat_map(|&c| [c - clearance, c + clearance])
+        .map(|s| s.rem_euclid(total))
+        .filter(|&s| allowed(s))
+        .collect();
+    let step = total / tab_count as f64;
+    let mut centers: Vec<f64> = (0..tab_count)
+        .filter_map(|k| {
+            let ideal = (k as f64 + 0.5) * step;
+            if allowed(ideal) {
+                return Some(ideal);
+            }
+            candidates.iter().copied().min_by(|a, b| {
+                loop_distance(*a, ideal, total).total_cmp(&loop_distance(*b, ideal, total))
+            })
+        })
+        .collect();
+    centers.sort_by(|a, b| a.total_cmp(b));
+    let mut placed: Vec<f64> = Vec::with_capacity(centers.len());
+    for s in centers {
+        if placed.last().map_or(true, |&last| s - last >= tab_width) {
+            placed.push(s);
+        }
+    }
+    if placed.len() > 1 && placed[0] + total - placed[placed.len() - 1] < tab_width {
+        placed.pop();
+    }
+    placed
+}
+
+/// Open pieces of the closed `pline` left between `tab_count` tabs, Err with
+/// the error code of [cavc_pline_insert_tabs] if the tabs do not fit.
+fn insert_tabs(
+    pline: &Polyline<f64>,
+    tab_width: f64,
+    tab_count: u32,
+    min_edge_margin: f64,
+) -> Result<Vec<Polyline<f64>>, i32> {
+    let lengths = seg_lengths(pline);
+    let ends = seg_ends(&lengths);
+    let total = ends[ends.len() - 1];
+    if tab_count as f64 * tab_width >= total {
+        return Err(5);
+    }
+    let corners = corner_lengths(pline, &lengths, &ends);
+    let clearance = min_edge_margin + 0.5 * tab_width;
+    let centers = tab_centers(total, &corners, tab_count, tab_width, clearance);
+    let pos_at = |s: f64| locate_sorted(&lengths, &ends, s.rem_euclid(total));
+    if centers.is_empty() {
+        return Ok(vec![pline.clone()]);
+    }
+    let first = centers[0];
+    Ok(centers
+        .iter()
+        .enumerate()
+        .filter_map(|(i, &center)| {
+            let next = centers.get(i + 1).copied().unwrap_or(first + total);
+            let start = center + 0.5 * tab_width;
+            let length = next - center - tab_width;
+            (length > 0.0).then(|| sub_path_from(pline, &lengths, pos_at(start), length))
+        })
+        .collect())
+}
+
+/// Cut `tab_count` evenly spaced gaps of path length `tab_width` into a closed
+/// polyline, leaving the open pieces between them.
+///
+/// The tabs start half a spacing after the first vertex and are spaced evenly
+/// by path length. A tab whose gap would come closer than `min_edge_margin` to
+/// a corner (a vertex where the direction of the path changes, smooth joins
+/// such as tangent arcs and zero length segments are not corners) is moved
+/// along the path to the nearest position that keeps the margin, and a tab
+/// that would then overlap another one is dropped. `result_plinelist`
+/// receives the open pieces between the tabs in path order starting after the
+/// first tab, arc segments are cut exactly. If no tab can be placed it holds a
+/// copy of `pline`. `pline` is left unchanged. See
+/// [cavc_pline_insert_tabs_spaced] to give the spacing of the tabs instead of
+/// their count.
+///
+/// `result_plinelist` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `result_plinelist` is null.
+/// * 2 = `pline` is not closed or has less than 2 vertexes.
+/// * 3 = `tab_count` is 0 or `tab_width` is not greater than 0 or not finite.
+/// * 4 = `min_edge_margin` is negative or not finite.
+/// * 5 = the tabs take up the whole path length of `pline`.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_insert_tabs(
+    pline: *const cavc_pline,
+    tab_width: f64,
+    tab_count: u32,
+    min_edge_margin: f64,
+    result_plinelist: *mut *mut cavc_plinelist,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || result_plinelist.is_null() {
+            return 1;
+        }
+        let pline = &(*pline).0;
+        if !pline.is_closed() || pline.vertex_count() < 2 {
+            return 2;
+        }
+        if tab_count == 0 || !tab_width.is_finite() || tab_width <= 0.0 {
+            return 3;
+        }
+        if !min_edge_margin.is_finite() || min_edge_margin < 0.0 {
+            return 4;
+        }
+        match insert_tabs(pline, tab_width, tab_count, min_edge_margin) {
+            Ok(plines) => {
+                *result_plinelist = plinelist_into_raw(plines);
+                0
+            }
+            Err(code) => code,
+        }
+    })
+}
+
+/// Same as [cavc_pline_insert_tabs] but with tabs every `tab_spacing` of path
+/// length (from the center of a tab to the center of the next one) instead of
+/// a tab count.
+///
+/// The spacing is adjusted to the nearest value that divides the path length
+/// into a whole number of tabs, at least one tab is inserted.
+///
+/// `result_plinelist` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `result_plinelist` is null.
+/// * 2 = `pline` is not closed or has less than 2 vertexes.
+/// * 3 = `tab_width` is not greater than 0 or not finite, or `tab_spacing` is
+///   not greater than `tab_width` or not finite.
+/// * 4 = `min_edge_margin` is negative or not finite.
+/// * 5 = the tabs take up the whole path length of `pline`.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_insert_tabs_spaced(
+    pline: *const cavc_pline,
+    tab_width: f64,
+    tab_spacing: f64,
+    min_edge_margin: f64,
+    result_plinelist: *mut *mut cavc_plinelist,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || result_plinelist.is_null() {
+            return 1;
+        }
+        let pline = &(*pline).0;
+        if !pline.is_closed() || pline.vertex_count() < 2 {
+            return 2;
+        }
+        if !tab_width.is_finite()
+            || tab_width <= 0.0
+            || !tab_spacing.is_finite()
+            || tab_spacing <= tab_width
+        {
+            return 3;
+        }
+        if !min_edge_margin.is_finite() || min_edge_margin < 0.0 {
+            return 4;
+        }
+        let count = (pline.path_length() / tab_spacing).round().max(1.0);
+        if count > u32::MAX as f64 {
+            return 5;
+        }
+        match insert_tabs(pline, tab_width, count as u32, min_edge_margin) {
+            Ok(plines) => {
+                *result_plinelist = plinelist_into_raw(plines);
+                0
+            }
+            Err(code) => code,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+    use std::ptr::{null, null_mut};
+
+    use cavalier_contours::core::math::Vector2;
+    use cavalier_contours::polyline::PlineSourceMut;
+
+    use super::*;
+    use crate::handle::cavc_plinelist_release;
+    use crate::test_util::{pline, square};
+
+    #[test]
+    fn tab_centers_are_spread_evenly() {
+        let corners = [0.0, 10.0, 20.0, 30.0];
+        assert_eq!(
+            tab_centers(40.0, &corners, 4, 1.0, 1.5),
+            [5.0, 15.0, 25.0, 35.0]
+        );
+        // moved to the nearest position clear of the corner
+        assert_eq!(tab_centers(40.0, &[19.0], 1, 2.0, 3.0), [22.0]);
+        // tabs moved onto their neighbors are dropped
+        assert_eq!(
+            tab_centers(40.0, &[10.0], 8, 2.0, 6.0),
+            [2.5, 16.0, 22.5, 27.5, 32.5, 37.5]
+        );
+        // nowhere clear of the corners
+        assert!(tab_centers(4.0, &[0.0, 1.0, 2.0, 3.0], 2, 0.1, 1.05).is_empty());
+    }
+
+    #[test]
+    fn tabs_split_the_path() {
+        let pieces = insert_tabs(&square(10.0), 1.0, 4, 0.0).unwrap();
+        assert_eq!(pieces.len(), 4);
+        for piece in &pieces {
+            assert!(!piece.is_closed());
+            assert!((piece.path_length() - 9.0).abs() < 1e-9);
+        }
+        assert_eq!(pieces[0].at(0).pos(), Vector2::new(5.5, 0.0));
+        assert_eq!(pieces[0].at(2).pos(), Vector2::new(10.0, 4.5));
+        // the last piece runs across the closing segment
+        let last = &pieces[3];
+        assert_eq!(
+            last.at(last.vertex_count() - 1).pos(),
+            Vector2::new(4.5, 0.0)
+        );
+    }
+
+    #[test]
+    fn tangent_arcs_are_not_corners() {
+        let circle = pline(&[(0.0, 0.0, 1.0), (2.0, 0.0, 1.0)], true);
+        let lengths = seg_lengths(&circle);
+        let ends = seg_ends(&lengths);
+        assert!(corner_lengths(&circle, &lengths, &ends).is_empty());
+        let pieces = insert_tabs(&circle, 0.1, 2, 10.0).unwrap();
+        assert_eq!(pieces.len(), 2);
+        assert!(pieces
+            .iter()
+            .all(|p| (p.path_length() - (PI - 0.1)).abs() < 1e-9));
+    }
+
+    #[test]
+    fn tabs_degenerate_input() {
+        // zero length segments neither add corners nor change the pieces
+        let repeated = pline(
+            &[
+                (0.0, 0.0, 0.0),
+                (10.0, 0.0, 0.0),
+                (10.0, 0.0, 0.0),
+                (10.0, 10.0, 0.0),
+                (0.0, 10.0, 0.0),
+            ],
+            true,
+        );
+        let lengths = seg_lengths(&repeated);
+        let ends = seg_ends(&lengths);
+        assert_eq!(corner_lengths(&repeated, &lengths, &ends).len(), 4);
+        let pieces = insert_tabs(&repeated, 1.0, 4, 0.0).unwrap();
+        assert_eq!(pieces.len(), 4);
+        assert!(pieces.iter().all(|p| (p.path_length() - 9.0).abs() < 1e-9));
+        // a copy of the polyline if no tab fits between the corners
+        let pieces = insert_tabs(&square(1.0), 0.1, 2, 1.0).unwrap();
+        assert_eq!(pieces.len(), 1);
+        assert!(pieces[0].is_closed());
+        assert_eq!(pieces[0].vertex_count(), 4);
+        // tabs taking up the whole path
+        assert_eq!(insert_tabs(&square(10.0), 1.0, 40, 0.0).err(), Some(5));
+        assert_eq!(insert_tabs(&square(1e-9), 1e-6, 1, 0.0).err(), Some(5));
+    }
+
+    #[test]
+    fn tabs_reject_invalid_parameters() {
+        let call = |pline: Polyline<f64>, width: f64, count: u32, margin: f64| unsafe {
+            let input = cavc_pline(pline);
+            let mut result = null_mut();
+            let code = cavc_pline_insert_tabs(&input, width, count, margin, &mut result);
+            cavc_plinelist_release(result);
+            code
+        };
+        assert_eq!(call(square(10.0), 1.0, 4, 0.0), 0);
+        let mut open = square(10.0);
+        open.set_is_closed(false);
+        assert_eq!(call(open, 1.0, 4, 0.0), 2);
+        assert_eq!(call(pline(&[(0.0, 0.0, 0.0)], true), 1.0, 4, 0.0), 2);
+        assert_eq!(call(square(10.0), 1.0, 0, 0.0), 3);
+        for width in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert_eq!(call(square(10.0), width, 4, 0.0), 3);
+        }
+        for margin in [-1.0, f64::NAN, f64::INFINITY] {
+            assert_eq!(call(square(10.0), 1.0, 4, margin), 4);
+        }
+        let mut result = null_mut();
+        let code = unsafe { cavc_pline_insert_tabs(null(), 1.0, 4, 0.0, &mut result) };
+        assert_eq!(code, 1);
+
+        let input = cavc_pline(square(10.0));
+        unsafe {
+            for spacing in [1.0, 0.5, f64::NAN] {
+                let code = cavc_pline_insert_tabs_spaced(&input, 1.0, spacing, 0.0, &mut result);
+                assert_eq!(code, 3);
+            }
+            assert_eq!(
+                cavc_pline_insert_tabs_spaced(&input, 1.0, 10.0, 0.0, &mut result),
+                0
+            );
+            assert_eq!((*result).0.len(), 4);
+            cavc_plinelist_release(result);
+        }
+    }
+}
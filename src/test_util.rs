@@ -0,0 +1,31 @@
+//! Polylines shared by the unit tests of the modules.
+use cavalier_contours::polyline::{PlineCreation, PlineSourceMut, Polyline};
+
+/// Polyline with the given `x, y, bulge` vertexes.
+pub(crate) fn pline(vertexes: &[(f64, f64, f64)], is_closed: bool) -> Polyline<f64> {
+    let mut pline = Polyline::with_capacity(vertexes.len(), is_closed);
+    for &(x, y, bulge) in vertexes {
+        pline.add(x, y, bulge);
+    }
+    pline
+}
+
+/// Counter clockwise axis aligned rectangle with corners `(x0, y0)` and
+/// `(x1, y1)`.
+pub(crate) fn rect(x0: f64, y0: f64, x1: f64, y1: f64) -> Polyline<f64> {
+    pline(
+        &[(x0, y0, 0.0), (x1, y0, 0.0), (x1, y1, 0.0), (x0, y1, 0.0)],
+        true,
+    )
+}
+
+/// Counter clockwise square with its lower left corner at the origin.
+pub(crate) fn square(side: f64) -> Polyline<f64> {
+    rect(0.0, 0.0, side, side)
+}
+
+/// `pline` with its direction reversed.
+pub(crate) fn cw(mut pline: Polyline<f64>) -> Polyline<f64> {
+    pline.invert_direction_mut();
+    pline
+}
@@ -0,0 +1,505 @@
+//! Stroking polylines into closed outlines.
+//!
+//! With round joins the outline is built from the offsets of the polyline to
+//! both sides joined by the end caps. Otherwise (or if the stroke overlaps
+//! itself from opposite sides) it is the union of the areas swept by every
+//! segment, a piece at every join between segments and the end caps, so
+//! overlaps of the stroke with itself are resolved like any other union.
+use cavalier_contours::core::math::Vector2;
+use cavalier_contours::polyline::{
+    seg_arc_radius_and_center, FindIntersectsOptions, PlineCreation, PlineOffsetOptions,
+    PlineSource, PlineSourceMut, PlineVertex, Polyline,
+};
+use cavalier_contours_ffi::{cavc_pline, cavc_plinelist};
+
+use crate::boolean::union_all;
+use crate::chain::append;
+use crate::geom::seg_tangent_at;
+use crate::hierarchy::parent_indexes;
+use crate::util::plinelist_into_raw;
+
+/// Shape of the stroke at the ends of open polylines.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum CapStyle {
+    /// Flat at the end point.
+    Butt,
+    /// Half circle around the end point.
+    Round,
+    /// Flat, extended half the width past the end point.
+    Square,
+}
+
+impl CapStyle {
+    pub(crate) fn from_i32(cap_style: i32) -> Option<CapStyle> {
+        match cap_style {
+            0 => Some(CapStyle::Butt),
+            1 => Some(CapStyle::Round),
+            2 => Some(CapStyle::Square),
+            _ => None,
+        }
+    }
+}
+
+/// Shape of the stroke at joins between segments.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) enum JoinStyle {
+    /// Circle around the join.
+    Round,
+    /// Outer edges extended until they meet, beveled instead if the miter
+    /// length would exceed the limit times the width.
+    Miter(f64),
+    /// Outer edges connected by a line.
+    Bevel,
+}
+
+/// Closed polyline through `points` made of lines.
+fn polygon(points: &[Vector2<f64>]) -> Polyline<f64> {
+    let mut pline = Polyline::with_capacity(points.len(), true);
+    for p in points {
+        pline.add(p.x, p.y, 0.0);
+    }
+    pline
+}
+
+/// Circle of `radius` around `center` as two half arcs.
+fn disc(center: Vector2<f64>, radius: f64) -> Polyline<f64> {
+    let mut pline = Polyline::with_capacity(2, true);
+    pline.add(center.x - radius, center.y, 1.0);
+    pline.add(center.x + radius, center.y, 1.0);
+    pline
+}
+
+/// Left normal of the unit direction `dir`.
+fn left(dir: Vector2<f64>) -> Vector2<f64> {
+    Vector2::new(-dir.y, dir.x)
+}
+
+/// Area swept by the segment stroked `half_width` to both sides, nothing for
+/// zero length segments.
+fn seg_body(v1: PlineVertex<f64>, v2: PlineVertex<f64>, half_width: f64) -> Vec<Polyline<f64>> {
+    let (p1, p2) = (v1.pos(), v2.pos());
+    if (p2 - p1).length() == 0.0 {
+        return Vec::new();
+    }
+    if v1.bulge_is_zero() {
+        let n = left(seg_tangent_at(v1, v2, 0.0)).scale(half_width);
+        return vec![polygon(&[p1 - n, p2 - n, p2 + n, p1 + n])];
+    }
+    let (radius, center) = seg_arc_radius_and_center(v1, v2);
+    let at_radius = |p: Vector2<f64>, r: f64| center + (p - center).scale(r / radius);
+    let outer = radius + half_width;
+    let mut body = Polyline::with_capacity(4, true);
+    let (o1, o2) = (at_radius(p1, outer), at_radius(p2, outer));
+    body.add_vertex(PlineVertex::from_vector2(o1, v1.bulge));
+    body.add_vertex(PlineVertex::from_vector2(o2, 0.0));
+    let inner = radius - half_width;
+    if inner > 0.0 {
+        let (i1, i2) = (at_radius(p1, inner), at_radius(p2, inner));
+        body.add_vertex(PlineVertex::from_vector2(i2, -v1.bulge));
+        body.add_vertex(PlineVertex::from_vector2(i1, 0.0));
+        return vec![body];
+    }
+    // the stroke covers the center, the sector is closed there and the disc
+    // of points closer to the center than to the arc from anywhere is added
+    body.add_vertex(PlineVertex::from_vector2(center, 0.0));
+    let mut pieces = vec![body];
+    if inner < 0.0 {
+        pieces.push(disc(center, -inner));
+    }
+    pieces
+}
+
+/// Piece filling the gap at the end of a stroke leaving `point` along the unit
+/// `dir`, None for butt caps.
+fn cap(
+    point: Vector2<f64>,
+    dir: Vector2<f64>,
+    half_width: f64,
+    style: CapStyle,
+) -> Option<Polyline<f64>> {
+    match style {
+        CapStyle::Butt => None,
+        CapStyle::Round => Some(disc(point, half_width)),
+        CapStyle::Square => {
+            let n = left(dir).scale(half_width);
+            let ahead = point + dir.scale(half_width);
+            Some(polygon(&[point - n, ahead - n, ahead + n, point + n]))
+        }
+    }
+}
+
+/// Piece filling the gap at the outer side of a join at `point` between
+/// segments arriving along the unit `dir_in` and leaving along the unit
+/// `dir_out`, None if there is no gap.
+fn join(
+    point: Vector2<f64>,
+    dir_in: Vector2<f64>,
+    dir_out: Vector2<f64>,
+    half_width: f64,
+    style: JoinStyle,
+) -> Option<Polyline<f64>> {
+    if style == JoinStyle::Round || dir_in.length() == 0.0 || dir_out.length() == 0.0 {
+        // directions are unknown next to zero length segments
+        return Some(disc(point, half_width));
+    }
+    let turn = dir_in.perp_dot(dir_out);
+    let angle = turn.atan2(dir_in.dot(dir_out)).abs();
+    if angle <= 1e-9 || angle >= std::f64::consts::PI - 1e-9 {
+        // straight or folding back, no outer side to fill
+        return None;
+    }
+    // the outer side is to the right of a left turn
+    let side = -turn.signum();
+    let a_dir = left(dir_in).scale(side);
+    let b_dir = left(dir_out).scale(side);
+    let a = point + a_dir.scale(half_width);
+    let b = point + b_dir.scale(half_width);
+    let miter_ratio = 1.0 / (0.5 * angle).cos();
+    match style {
+        JoinStyle::Miter(limit) if miter_ratio <= limit => {
+            let bisector = (a_dir + b_dir).normalize();
+            let tip = point + bisector.scale(half_width * miter_ratio);
+            Some(polygon(&[point, a, tip, b]))
+        }
+        _ => Some(polygon(&[point, a, b])),
+    }
+}
+
+/// Pieces of the stroke outline of `pline` with half the width `half_width`.
+pub(crate) fn stroke_pieces(
+    pline: &Polyline<f64>,
+    half_width: f64,
+    cap_style: CapStyle,
+    join_style: JoinStyle,
+) -> Vec<Polyline<f64>> {
+    let mut pieces = Vec::new();
+    let n = pline.vertex_count();
+    if n == 0 {
+        return pieces;
+    }
+    if n == 1 || pline.path_length() == 0.0 {
+        // a dot, round caps are the only cap with a direction independent
+        // shape
+        if cap_style == CapStyle::Round {
+            pieces.push(disc(pline.at(0).pos(), half_width));
+        }
+        return pieces;
+    }
+    for (v1, v2) in pline.iter_segments() {
+        pieces.extend(seg_body(v1, v2, half_width));
+    }
+    let joins = if pline.is_closed() { 0..n } else { 1..n - 1 };
+    for i in joins {
+        let prev = pline.at(pline.prev_wrapping_index(i));
+        let v = pline.at(i);
+        let next = pline.at(pline.next_wrapping_index(i));
+        let dir_in = seg_tangent_at(prev, v, 1.0);
+        let dir_out = seg_tangent_at(v, next, 0.0);
+        pieces.extend(join(v.pos(), dir_in, dir_out, half_width, join_style));
+    }
+    if !pline.is_closed() {
+        let first = pline.at(0);
+        let last = pline.at(n - 1);
+        let start_dir = seg_tangent_at(first, pline.at(1), 0.0);
+        let end_dir = seg_tangent_at(pline.at(n - 2), last, 1.0);
+        pieces.extend(cap(first.pos(), -start_dir, half_width, cap_style));
+        pieces.extend(cap(last.pos(), end_dir, half_width, cap_style));
+    }
+    pieces
+}
+
+/// Stroke the polyline at `width` into the closed outline of the area it
+/// covers.
+///
+/// `cap_style` selects the shape at the ends of open polylines: 0 = butt
+/// (flat at the end points), 1 = round (half circles), 2 = square (flat, half
+/// the width past the end points). Closed polylines have no ends and become
+/// rings. Joins between segments are round and arcs are stroked exactly.
+/// `result_plinelist` receives the disjoint regions of the outline, each as
+/// its outer boundary (counter clockwise) followed by its holes (clockwise),
+/// e.g. a closed polyline gives its outer outline followed by the inner one.
+///
+/// `result_plinelist` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `result_plinelist` is null.
+/// * 2 = `width` is not greater than 0 or infinite.
+/// * 3 = `cap_style` is not a valid cap style.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_thicken(
+    pline: *const cavc_pline,
+    width: f64,
+    cap_style: i32,
+    result_plinelist: *mut *mut cavc_plinelist,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || result_plinelist.is_null() {
+            return 1;
+        }
+        if !width.is_finite() || width <= 0.0 {
+            return 2;
+        }
+        let cap_style = match CapStyle::from_i32(cap_style) {
+            Some(style) => style,
+            None => return 3,
+        };
+        let plines = stroke_outline(&(*pline).0, 0.5 * width, cap_style, JoinStyle::Round);
+        *result_plinelist = plinelist_into_raw(plines);
+        0
+    })
+}
+
+/// Union of the stroke pieces as outer boundaries each followed by its holes.
+fn outline(pieces: Vec<Polyline<f64>>) -> Vec<Polyline<f64>> {
+    let pos_equal_eps = FindIntersectsOptions::<f64>::new().pos_equal_eps;
+    let mut plines = Vec::new();
+    for region in union_all(pieces, pos_equal_eps) {
+        plines.push(region.outer);
+        plines.extend(region.holes);
+    }
+    plines
+}
+
+/// Edge of the cap at the end `point` of a stroke leaving along the unit
+/// `dir`, from the left side of the stroke around the end to the right side.
+fn cap_edge(
+    point: Vector2<f64>,
+    dir: Vector2<f64>,
+    half_width: f64,
+    style: CapStyle,
+) -> Polyline<f64> {
+    let n = left(dir).scale(half_width);
+    let (a, b) = (point + n, point - n);
+    let mut edge = Polyline::new();
+    match style {
+        CapStyle::Butt => edge.add(a.x, a.y, 0.0),
+        // clockwise half circle around the end
+        CapStyle::Round => edge.add(a.x, a.y, -1.0),
+        CapStyle::Square => {
+            let ahead = dir.scale(half_width);
+            edge.add(a.x, a.y, 0.0);
+            edge.add(a.x + ahead.x, a.y + ahead.y, 0.0);
+            edge.add(b.x + ahead.x, b.y + ahead.y, 0.0);
+        }
+    }
+    edge.add(b.x, b.y, 0.0);
+    edge
+}
+
+/// Join the directed `pieces` end to start into closed loops, closed pieces
+/// are loops already. None if a piece does not continue into another one.
+fn stitch(pieces: Vec<Polyline<f64>>, join_eps: f64) -> Option<Vec<Polyline<f64>>> {
+    let mut used = vec![false; pieces.len()];
+    let mut loops = Vec::new();
+    for first in 0..pieces.len() {
+        if used[first] {
+            continue;
+        }
+        used[first] = true;
+        let mut chain = pieces[first].clone();
+        if chain.is_closed() {
+            loops.push(chain);
+            continue;
+        }
+        loop {
+            let end = chain.last()?.pos();
+            if chain.vertex_count() > 1 && end.fuzzy_eq_eps(chain.at(0).pos(), join_eps) {
+                chain.remove_last();
+                chain.set_is_closed(true);
+                loops.push(chain);
+                break;
+            }
+            let next = (0..pieces.len()).find(|&j| {
+                !used[j]
+                    && !pieces[j].is_closed()
+                    && pieces[j]
+                        .get(0)
+                        .map_or(false, |v| v.pos().fuzzy_eq_eps(end, join_eps))
+            })?;
+            used[next] = true;
+            append(&mut chain, &pieces[next]);
+        }
+    }
+    Some(loops)
+}
+
+/// Stroke outline with round joins built directly from the offsets of `pline`
+/// to both sides joined by the end caps, as outer boundaries each followed by
+/// its holes.
+///
+/// Every offset keeps only what is at least `half_width` away from the whole
+/// polyline, so the offsets are the boundary of the stroke. Where the stroke
+/// overlaps itself from opposite sides (e.g. a polyline crossing itself) the
+/// offsets of both sides cut each other without being split there and do not
+/// close up, None is returned then and the stroke has to be united from its
+/// pieces.
+fn offset_outline(
+    pline: &Polyline<f64>,
+    half_width: f64,
+    cap_style: CapStyle,
+) -> Option<Vec<Polyline<f64>>> {
+    let pos_equal_eps = PlineOffsetOptions::<f64>::new().pos_equal_eps;
+    // every piece of the boundary runs with the stroke on its right
+    let mut pieces = pline.parallel_offset(half_width);
+    for mut piece in pline.parallel_offset(-half_width) {
+        piece.invert_direction_mut();
+        pieces.push(piece);
+    }
+    if !pline.is_closed() {
+        let n = pline.vertex_count();
+        let start_dir = seg_tangent_at(pline.at(0), pline.at(1), 0.0);
+        let end_dir = seg_tangent_at(pline.at(n - 2), pline.at(n - 1), 1.0);
+        if start_dir.length() == 0.0 || end_dir.length() == 0.0 {
+            return None;
+        }
+        pieces.push(cap_edge(
+            pline.at(n - 1).pos(),
+            end_dir,
+            half_width,
+            cap_style,
+        ));
+        pieces.push(cap_edge(
+            pline.at(0).pos(),
+            -start_dir,
+            half_width,
+            cap_style,
+        ));
+    }
+    let mut loops = stitch(pieces, pos_equal_eps)?;
+    // counter clockwise outer boundaries and clockwise holes
+    for l in loops.iter_mut() {
+        l.invert_direction_mut();
+    }
+    let parents = parent_indexes(&loops.iter().collect::<Vec<_>>());
+    let is_outer: Vec<bool> = loops.iter().map(|l| l.area() > 0.0).collect();
+    let mut groups: Vec<(usize, Vec<usize>)> = (0..loops.len())
+        .filter(|&i| is_outer[i])
+        .map(|i| (i, Vec::new()))
+        .collect();
+    for hole in (0..loops.len()).filter(|&i| !is_outer[i]) {
+        let mut parent = parents[hole];
+        while parent >= 0 && !is_outer[parent as usize] {
+            parent = parents[parent as usize];
+        }
+        let group = groups
+            .iter_mut()
+            .find(|(outer, _)| *outer as i32 == parent)?;
+        group.1.push(hole);
+    }
+    if groups.is_empty() {
+        return None;
+    }
+    let mut loops: Vec<Option<Polyline<f64>>> = loops.into_iter().map(Some).collect();
+    let mut plines = Vec::new();
+    for (outer, holes) in groups {
+        plines.extend(loops[outer].take());
+        plines.extend(holes.into_iter().filter_map(|h| loops[h].take()));
+    }
+    Some(plines)
+}
+
+/// Stroke outline of `pline` as outer boundaries each followed by its holes.
+///
+/// Round joins are built from the offsets of the polyline if possible, other
+/// joins (and strokes overlapping themselves) by uniting the pieces of the
+/// stroke.
+pub(crate) fn stroke_outline(
+    pline: &Polyline<f64>,
+    half_width: f64,
+    cap_style: CapStyle,
+    join_style: JoinStyle,
+) -> Vec<Polyline<f64>> {
+    if join_style == JoinStyle::Round && pline.vertex_count() > 1 && pline.path_length() > 0.0 {
+        if let Some(plines) = offset_outline(pline, half_width, cap_style) {
+            return plines;
+        }
+    }
+    outline(stroke_pieces(pline, half_width, cap_style, join_style))
+}
+
+/// Options for [cavc_pline_thicken_opt].
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct cavc_pline_thicken_o {
+    /// Shape at the ends of open polylines, see [cavc_pline_thicken].
+    pub cap_style: i32,
+    /// Shape at joins between segments: 0 = round, 1 = miter, 2 = bevel.
+    pub join_style: i32,
+    /// Miter joins whose miter length (from the inner to the outer corner)
+    /// exceeds this times the width are beveled instead, as in SVG. Must be at
+    /// least 1.
+    pub miter_limit: f64,
+}
+
+impl Default for cavc_pline_thicken_o {
+    fn default() -> Self {
+        Self {
+            cap_style: 1,
+            join_style: 0,
+            miter_limit: 4.0,
+        }
+    }
+}
+
+/// Write the default [cavc_pline_thicken_o] values to `options`: round caps,
+/// round joins and a miter limit of 4.
+///
+/// ## Specific Error Codes
+/// * 1 = `options` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_thicken_o_init(options: *mut cavc_pline_thicken_o) -> i32 {
+    ffi_catch_unwind!({
+        if options.is_null() {
+            return 1;
+        }
+        *options = cavc_pline_thicken_o::default();
+        0
+    })
+}
+
+/// Same as [cavc_pline_thicken] with control over the joins between segments.
+///
+/// Miter and bevel joins fill the outer side of every corner with the outer
+/// edges extended until they meet or connected by a line, the inner side is
+/// unaffected. Joins next to zero length segments are always round. Arcs are
+/// joined along their tangents at the join. `options` may be null in which case
+/// defaults are used (see [cavc_pline_thicken_o_init]).
+///
+/// `result_plinelist` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `result_plinelist` is null.
+/// * 2 = `width` is not greater than 0 or infinite.
+/// * 3 = `options` has an invalid `cap_style` or `join_style`, or a
+///   `miter_limit` less than 1 for miter joins.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_thicken_opt(
+    pline: *const cavc_pline,
+    width: f64,
+    options: *const cavc_pline_thicken_o,
+    result_plinelist: *mut *mut cavc_plinelist,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || result_plinelist.is_null() {
+            return 1;
+        }
+        if !width.is_finite() || width <= 0.0 {
+            return 2;
+        }
+        let options = options.as_ref().copied().unwrap_or_default();
+        let cap_style = match CapStyle::from_i32(options.cap_style) {
+            Some(style) => style,
+            None => return 3,
+        };
+        let join_style = match options.join_style {
+            0 => JoinStyle::Round,
+            // a limit below 1 would bevel every join, NaN none
+            1 if options.miter_limit >= 1.0 => JoinStyle::Miter(options.miter_limit),
+            2 => JoinStyle::Bevel,
+            _ => return 3,
+        };
+        let plines = stroke_outline(&(*pline).0, 0.5 * width, cap_style, join_style);
+        *result_plinelist = plinelist_into_raw(plines);
+        0
+    })
+}
@@ -0,0 +1,266 @@
+//! Geometric transformations of polylines.
+use cavalier_contours::polyline::{PlineSource, PlineSourceMut, PlineVertex, Polyline};
+use cavalier_contours_ffi::{cavc_pline, cavc_plinelist};
+
+use crate::cache::evict;
+use crate::util::pline_into_raw;
+
+/// 2D affine transformation `[m00 m01 m02; m10 m11 m12]` applied to column
+/// vectors `[x y 1]`.
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct Affine {
+    pub m00: f64,
+    pub m01: f64,
+    pub m02: f64,
+    pub m10: f64,
+    pub m11: f64,
+    pub m12: f64,
+}
+
+impl Affine {
+    pub fn apply(&self, x: f64, y: f64) -> (f64, f64) {
+        (
+            self.m00 * x + self.m01 * y + self.m02,
+            self.m10 * x + self.m11 * y + self.m12,
+        )
+    }
+
+    /// True if the transformation is a combination of rotation, reflection,
+    /// uniform scale and translation, i.e. it maps circles to circles.
+    pub fn is_similarity(&self) -> bool {
+        let len_a = self.m00.hypot(self.m10);
+        let len_b = self.m01.hypot(self.m11);
+        let dot = self.m00 * self.m01 + self.m10 * self.m11;
+        let tol = 1e-9 * len_a.max(len_b);
+        len_a > 0.0 && (len_a - len_b).abs() <= tol && dot.abs() <= tol * len_a.max(len_b)
+    }
+
+    /// True if the transformation mirrors, which flips the arc directions.
+    pub fn is_reflection(&self) -> bool {
+        self.m00 * self.m11 - self.m01 * self.m10 < 0.0
+    }
+}
+
+/// Apply `transform` to `pline` in place.
+///
+/// Returns false and leaves `pline` unchanged if it contains arcs and the
+/// transformation is not a similarity, since the arcs would become elliptical.
+pub(crate) fn transform_pline(pline: &mut Polyline<f64>, transform: &Affine) -> bool {
+    let has_arcs = pline.iter_vertexes().any(|v| !v.bulge_is_zero());
+    if has_arcs && !transform.is_similarity() {
+        return false;
+    }
+    let bulge_sign = if transform.is_reflection() { -1.0 } else { 1.0 };
+    for i in 0..pline.vertex_count() {
+        let v = pline.at(i);
+        let (x, y) = transform.apply(v.x, v.y);
+        pline.set_vertex(i, PlineVertex::new(x, y, bulge_sign * v.bulge));
+    }
+    true
+}
+
+/// Apply the affine transformation `[m00 m01 m02; m10 m11 m12]` to the
+/// polyline in place, each vertex `(x, y)` becomes
+/// `(m00 * x + m01 * y + m02, m10 * x + m11 * y + m12)`.
+///
+/// Arc segments stay exact under rotation, reflection, uniform scale and
+/// translation (bulges are negated for reflections). A transformation with
+/// shear or non-uniform scale would turn arcs into elliptical arcs, so it is
+/// rejected for polylines containing arcs, polylines made of lines only accept
+/// any affine transformation.
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` is null.
+/// * 2 = the transformation has shear or non-uniform scale and `pline` contains
+///   arc segments, `pline` is left unchanged.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_transform(
+    pline: *mut cavc_pline,
+    m00: f64,
+    m01: f64,
+    m02: f64,
+    m10: f64,
+    m11: f64,
+    m12: f64,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() {
+            return 1;
+        }
+        let transform = Affine {
+            m00,
+            m01,
+            m02,
+            m10,
+            m11,
+            m12,
+        };
+        evict(pline);
+        if !transform_pline(&mut (*pline).0, &transform) {
+            return 2;
+        }
+        0
+    })
+}
+
+/// Scale the polyline by `sx` along the x axis and `sy` along the y axis
+/// around the origin, writing the result to a new polyline.
+///
+/// Non-uniform scale turns arcs into elliptical arcs which cannot be
+/// represented exactly, so arc segments are approximated by lines deviating
+/// at most `error_distance` from the true scaled geometry. When `|sx|` equals
+/// `|sy|` arcs are kept exact. `pline` is left unchanged.
+///
+/// `result` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `result` is null.
+/// * 2 = `error_distance` is not greater than 0.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_scale_non_uniform(
+    pline: *const cavc_pline,
+    sx: f64,
+    sy: f64,
+    error_distance: f64,
+    result: *mut *mut cavc_pline,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || result.is_null() {
+            return 1;
+        }
+        if error_distance.is_nan() || error_distance <= 0.0 {
+            return 2;
+        }
+        let pline = &(*pline).0;
+        let transform = Affine {
+            m00: sx,
+            m01: 0.0,
+            m02: 0.0,
+            m10: 0.0,
+            m11: sy,
+            m12: 0.0,
+        };
+        let mut scaled = if transform.is_similarity() {
+            pline.clone()
+        } else {
+            // deviation from the arc scales by at most the larger factor
+            let max_scale = sx.abs().max(sy.abs());
+            let linearize_error = if max_scale > 0.0 {
+                error_distance / max_scale
+            } else {
+                error_distance
+            };
+            pline
+                .arcs_to_approx_lines(linearize_error)
+                .unwrap_or_else(|| pline.clone())
+        };
+        let transformed = transform_pline(&mut scaled, &transform);
+        debug_assert!(transformed);
+        *result = pline_into_raw(scaled);
+        0
+    })
+}
+
+/// Mirror the polyline across the line through `(px, py)` with direction
+/// `(dx, dy)`, writing the result to a new polyline.
+///
+/// Arc bulges are negated so the arcs are mirrored exactly, and as with any
+/// reflection the orientation of a closed polyline flips. `pline` is left
+/// unchanged.
+///
+/// `result` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `result` is null.
+/// * 2 = `(dx, dy)` has zero length.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_mirror(
+    pline: *const cavc_pline,
+    px: f64,
+    py: f64,
+    dx: f64,
+    dy: f64,
+    result: *mut *mut cavc_pline,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || result.is_null() {
+            return 1;
+        }
+        let len = dx.hypot(dy);
+        if len == 0.0 || !len.is_finite() {
+            return 2;
+        }
+        let (ux, uy) = (dx / len, dy / len);
+        let (r00, r01, r11) = (ux * ux - uy * uy, 2.0 * ux * uy, uy * uy - ux * ux);
+        let transform = Affine {
+            m00: r00,
+            m01: r01,
+            m02: px - (r00 * px + r01 * py),
+            m10: r01,
+            m11: r11,
+            m12: py - (r01 * px + r11 * py),
+        };
+        let mut mirrored = (*pline).0.clone();
+        transform_pline(&mut mirrored, &transform);
+        *result = pline_into_raw(mirrored);
+        0
+    })
+}
+
+/// Apply a separate affine transformation to every polyline of the list in
+/// place, e.g. to place parts when nesting.
+///
+/// `matrices` holds `count` transformations of 6 values each, the matrix
+/// `[m00 m01 m02; m10 m11 m12]` row by row, the first one for the first
+/// polyline of the list. Every transformation is applied as by
+/// [cavc_pline_transform]. All transformations are checked before any
+/// polyline is modified, so nothing is modified if an error is returned.
+///
+/// ## Specific Error Codes
+/// * 1 = `plinelist` is null or `matrices` is null while `count` is not 0.
+/// * 2 = `count` is not the number of polylines of the list.
+/// * 3 = a transformation has shear or non-uniform scale and its polyline
+///   contains arc segments.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_plinelist_transform_batch(
+    plinelist: *mut cavc_plinelist,
+    matrices: *const f64,
+    count: u32,
+) -> i32 {
+    ffi_catch_unwind!({
+        if plinelist.is_null() || (matrices.is_null() && count != 0) {
+            return 1;
+        }
+        let plines = &(*plinelist).0;
+        if count as usize != plines.len() {
+            return 2;
+        }
+        if count == 0 {
+            return 0;
+        }
+        let values = std::slice::from_raw_parts(matrices, 6 * count as usize);
+        let transforms: Vec<Affine> = values
+            .chunks_exact(6)
+            .map(|m| Affine {
+                m00: m[0],
+                m01: m[1],
+                m02: m[2],
+                m10: m[3],
+                m11: m[4],
+                m12: m[5],
+            })
+            .collect();
+        let rejected = plines.iter().zip(&transforms).any(|(&pline, transform)| {
+            let has_arcs = (*pline).0.iter_vertexes().any(|v| !v.bulge_is_zero());
+            has_arcs && !transform.is_similarity()
+        });
+        if rejected {
+            return 3;
+        }
+        for (&pline, transform) in plines.iter().zip(&transforms) {
+            evict(pline);
+            transform_pline(&mut (*pline).0, transform);
+        }
+        0
+    })
+}
@@ -0,0 +1,558 @@
+//! Triangulation of regions bounded by closed polylines.
+//!
+//! Regions are triangulated by ear clipping after joining every hole to its
+//! outer boundary with a bridge, the triangles only use the polyline
+//! vertexes (no points are added). The best shaped ear is clipped first and
+//! ears are only tested against the nearby reflex vertexes.
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::os::raw::c_void;
+
+use cavalier_contours::core::math::Vector2;
+use cavalier_contours::polyline::{PlineSource, Polyline};
+use cavalier_contours_ffi::cavc_plinelist;
+
+use crate::hierarchy::parent_indexes;
+use crate::progress::{cavc_progress_fn, Cancelled, Progress, CAVC_CANCELLED};
+use crate::util::{free_raw, into_raw};
+
+/// Opaque type holding the result of [cavc_plinelist_triangulate].
+pub struct cavc_triangulation {
+    /// Vertex positions interleaved as x, y.
+    vertexes: Vec<f64>,
+    /// Vertex indexes of the triangles, three per triangle.
+    indices: Vec<u32>,
+}
+
+/// Twice the signed area of the triangle, positive if counter clockwise.
+fn cross(a: Vector2<f64>, b: Vector2<f64>, c: Vector2<f64>) -> f64 {
+    (b - a).perp_dot(c - a)
+}
+
+/// Whether `p` lies inside or on the counter clockwise triangle `a`, `b`, `c`.
+fn in_triangle(a: Vector2<f64>, b: Vector2<f64>, c: Vector2<f64>, p: Vector2<f64>) -> bool {
+    cross(a, b, p) >= 0.0 && cross(b, c, p) >= 0.0 && cross(c, a, p) >= 0.0
+}
+
+/// Join `hole` (clockwise vertex indexes) into the counter clockwise `ring`
+/// with a bridge from the rightmost hole vertex to a visible ring vertex.
+fn bridge_hole(points: &[Vector2<f64>], ring: &mut Vec<usize>, hole: &[usize]) {
+    let (m_at, &m) = hole
+        .iter()
+        .enumerate()
+        .max_by(|a, b| points[*a.1].x.total_cmp(&points[*b.1].x))
+        .unwrap();
+    let mp = points[m];
+    // closest ring edge hit by the ray from m toward positive x
+    let mut best: Option<(f64, usize)> = None;
+    for i in 0..ring.len() {
+        let a = points[ring[i]];
+        let b = points[ring[(i + 1) % ring.len()]];
+        if (a.y > mp.y) == (b.y > mp.y) {
+            continue;
+        }
+        let x = a.x + (mp.y - a.y) * (b.x - a.x) / (b.y - a.y);
+        if x >= mp.x && !matches!(best, Some((bx, _)) if bx <= x) {
+            // the edge end point further along the ray is the candidate
+            let candidate = if a.x > b.x { i } else { (i + 1) % ring.len() };
+            best = Some((x, candidate));
+        }
+    }
+    let (x, mut p_at) = match best {
+        Some(best) => best,
+        // hole outside of the ring, cannot be bridged
+        None => return,
+    };
+    let hit = Vector2::new(x, mp.y);
+    let p = points[ring[p_at]];
+    // a reflex vertex inside the triangle m, hit, p would block the bridge,
+    // the one with the smallest angle to the ray is visible instead
+    let (a, b, c) = if cross(mp, hit, p) >= 0.0 {
+        (mp, hit, p)
+    } else {
+        (mp, p, hit)
+    };
+    let mut best_angle = f64::INFINITY;
+    for i in 0..ring.len() {
+        let r = points[ring[i]];
+        if i == p_at || r == p || !in_triangle(a, b, c, r) {
+            continue;
+        }
+        let prev = points[ring[(i + ring.len() - 1) % ring.len()]];
+        let next = points[ring[(i + 1) % ring.len()]];
+        if cross(prev, r, next) >= 0.0 {
+            continue;
+        }
+        let d = r - mp;
+        let angle = d.y.abs().atan2(d.x);
+        if angle < best_angle {
+            best_angle = angle;
+            p_at = i;
+        }
+    }
+    let mut spliced = Vec::with_capacity(ring.len() + hole.len() + 2);
+    spliced.extend_from_slice(&ring[..=p_at]);
+    spliced.extend(hole[m_at..].iter().chain(&hole[..=m_at]));
+    spliced.extend_from_slice(&ring[p_at..]);
+    *ring = spliced;
+}
+
+/// Shape quality of the counter clockwise triangle, `4 sqrt(3) area / (sum of
+/// squared edge lengths)`: 1 for equilateral triangles, toward 0 for slivers.
+fn triangle_quality(a: Vector2<f64>, b: Vector2<f64>, c: Vector2<f64>) -> f64 {
+    let squares = (b - a).dot(b - a) + (c - b).dot(c - b) + (a - c).dot(a - c);
+    if squares > 0.0 {
+        2.0 * 3f64.sqrt() * cross(a, b, c) / squares
+    } else {
+        0.0
+    }
+}
+
+/// Uniform grid bucketing the ring nodes which are reflex (or collinear), the
+/// only ones that can lie inside an ear.
+///
+/// Nodes are removed once they turn convex and never come back, since
+/// clipping ears only makes the corners of the remaining nodes sharper.
+struct ReflexGrid {
+    min: Vector2<f64>,
+    cell_size: f64,
+    cells_per_axis: usize,
+    cells: Vec<Vec<usize>>,
+    /// Cell of every node, None if it is not in the grid.
+    node_cells: Vec<Option<usize>>,
+    /// Number of nodes in the grid.
+    len: usize,
+}
+
+impl ReflexGrid {
+    fn new(positions: &[Vector2<f64>], reflex: &[bool]) -> Self {
+        let (mut min, mut max) = (positions[0], positions[0]);
+        for p in positions {
+            min = Vector2::new(min.x.min(p.x), min.y.min(p.y));
+            max = Vector2::new(max.x.max(p.x), max.y.max(p.y));
+        }
+        let mut grid = Self {
+            min,
+            cell_size: (max.x - min.x).max(max.y - min.y),
+            cells_per_axis: 0,
+            cells: Vec::new(),
+            node_cells: vec![None; positions.len()],
+            len: 0,
+        };
+        let nodes: Vec<usize> = (0..positions.len()).filter(|&k| reflex[k]).collect();
+        grid.fill(positions, &nodes);
+        grid
+    }
+
+    /// Bucket `nodes` in cells sized for their count.
+    fn fill(&mut self, positions: &[Vector2<f64>], nodes: &[usize]) {
+        let size = self.cell_size * self.cells_per_axis.max(1) as f64;
+        self.len = nodes.len();
+        self.cells_per_axis = ((self.len as f64).sqrt().ceil() as usize).max(1);
+        self.cell_size = if size > 0.0 {
+            size / self.cells_per_axis as f64
+        } else {
+            1.0
+        };
+        self.cells = vec![Vec::new(); self.cells_per_axis * self.cells_per_axis];
+        for &k in nodes {
+            let (x, y) = self.cell(positions[k]);
+            let cell = y * self.cells_per_axis + x;
+            self.cells[cell].push(k);
+            self.node_cells[k] = Some(cell);
+        }
+    }
+
+    fn cell(&self, p: Vector2<f64>) -> (usize, usize) {
+        let last = self.cells_per_axis - 1;
+        let to_cell = |v: f64| (((v / self.cell_size).max(0.0)) as usize).min(last);
+        (to_cell(p.x - self.min.x), to_cell(p.y - self.min.y))
+    }
+
+    fn remove(&mut self, positions: &[Vector2<f64>], k: usize) {
+        if let Some(cell) = self.node_cells[k].take() {
+            let nodes = &mut self.cells[cell];
+            if let Some(at) = nodes.iter().position(|&n| n == k) {
+                nodes.swap_remove(at);
+            }
+            self.len -= 1;
+            // shrink the grid as it empties, so queries over large boxes
+            // do not walk mostly empty cells
+            if self.len * 4 < self.cells.len() {
+                let nodes: Vec<usize> = self.cells.iter().flatten().copied().collect();
+                self.fill(positions, &nodes);
+            }
+        }
+    }
+
+    /// Whether `f` is true for any node in the cells overlapping the box from
+    /// `min` to `max`.
+    fn any(&self, min: Vector2<f64>, max: Vector2<f64>, mut f: impl FnMut(usize) -> bool) -> bool {
+        let (x0, y0) = self.cell(min);
+        let (x1, y1) = self.cell(max);
+        (y0..=y1).any(|y| {
+            (x0..=x1).any(|x| {
+                self.cells[y * self.cells_per_axis + x]
+                    .iter()
+                    .any(|&k| f(k))
+            })
+        })
+    }
+}
+
+/// Triangulate the counter clockwise `ring` of vertex indexes by ear clipping,
+/// appending the triangles to `indices`.
+///
+/// The ring is a linked list of nodes, ears are tested only against the
+/// reflex nodes near them (see [ReflexGrid]) and the best shaped ear (see
+/// [triangle_quality]) is clipped first, which avoids slivers. `step` is
+/// called after every ear clipped, an error aborts the triangulation.
+fn clip_ears(
+    points: &[Vector2<f64>],
+    ring: Vec<usize>,
+    indices: &mut Vec<u32>,
+    step: &mut dyn FnMut() -> Result<(), Cancelled>,
+) -> Result<(), Cancelled> {
+    let n = ring.len();
+    if n < 3 {
+        return Ok(());
+    }
+    let pos: Vec<Vector2<f64>> = ring.iter().map(|&j| points[j]).collect();
+    let mut prev: Vec<usize> = (0..n).map(|k| (k + n - 1) % n).collect();
+    let mut next: Vec<usize> = (0..n).map(|k| (k + 1) % n).collect();
+    let mut alive = vec![true; n];
+    let corner =
+        |prev: &[usize], next: &[usize], k: usize| cross(pos[prev[k]], pos[k], pos[next[k]]);
+    let reflex: Vec<bool> = (0..n).map(|k| corner(&prev, &next, k) <= 0.0).collect();
+    let mut grid = ReflexGrid::new(&pos, &reflex);
+    let ear_quality = |prev: &[usize], next: &[usize], grid: &ReflexGrid, k: usize| {
+        let (ia, ic) = (prev[k], next[k]);
+        let (a, b, c) = (pos[ia], pos[k], pos[ic]);
+        if cross(a, b, c) <= 0.0 {
+            return None;
+        }
+        let min = Vector2::new(a.x.min(b.x).min(c.x), a.y.min(b.y).min(c.y));
+        let max = Vector2::new(a.x.max(b.x).max(c.x), a.y.max(b.y).max(c.y));
+        let blocked = grid.any(min, max, |r| {
+            let p = pos[r];
+            // bridge duplicates share positions with the ear corners
+            r != ia && r != ic && p != a && p != b && p != c && in_triangle(a, b, c, p)
+        });
+        (!blocked).then(|| triangle_quality(a, b, c))
+    };
+
+    // best ears first, entries of nodes whose neighbors changed since are
+    // skipped by their version
+    let mut version = vec![0u32; n];
+    let mut ears = BinaryHeap::new();
+    for k in 0..n {
+        if let Some(q) = ear_quality(&prev, &next, &grid, k) {
+            ears.push((q.to_bits(), Reverse(k), 0));
+        }
+    }
+    let mut head = 0;
+    let mut remaining = n;
+    while remaining > 3 {
+        let k = match ears.pop() {
+            Some((_, Reverse(k), v)) if alive[k] && v == version[k] => k,
+            Some(_) => continue,
+            None => {
+                // ears unblocked by reflex nodes becoming convex are only
+                // found by a new scan
+                let mut k = head;
+                for _ in 0..remaining {
+                    if let Some(q) = ear_quality(&prev, &next, &grid, k) {
+                        ears.push((q.to_bits(), Reverse(k), version[k]));
+                    }
+                    k = next[k];
+                }
+                if !ears.is_empty() {
+                    continue;
+                }
+                // no clean ear left (degenerate input), clip any convex node
+                let mut k = head;
+                let mut found = None;
+                for _ in 0..remaining {
+                    if corner(&prev, &next, k) > 0.0 {
+                        found = Some(k);
+                        break;
+                    }
+                    k = next[k];
+                }
+                match found {
+                    Some(k) => k,
+                    None => return Ok(()),
+                }
+            }
+        };
+        let (p, q) = (prev[k], next[k]);
+        indices.extend([ring[p] as u32, ring[k] as u32, ring[q] as u32]);
+        alive[k] = false;
+        grid.remove(&pos, k);
+        next[p] = q;
+        prev[q] = p;
+        if head == k {
+            head = q;
+        }
+        remaining -= 1;
+        for v in [p, q] {
+            version[v] += 1;
+            if corner(&prev, &next, v) > 0.0 {
+                grid.remove(&pos, v);
+            }
+            if let Some(quality) = ear_quality(&prev, &next, &grid, v) {
+                ears.push((quality.to_bits(), Reverse(v), version[v]));
+            }
+        }
+        step()?;
+    }
+    let (a, b) = (prev[head], next[head]);
+    if cross(pos[a], pos[head], pos[b]) > 0.0 {
+        indices.extend([ring[a] as u32, ring[head] as u32, ring[b] as u32]);
+    }
+    Ok(())
+}
+
+/// Triangulate the regions formed by the closed polylines `plines` (which
+/// must only contain lines), returning the vertex positions and triangle
+/// indexes.
+///
+/// `step` is called with the fraction of the triangles done after every ear
+/// clipped, an error aborts the triangulation.
+pub(crate) fn triangulate(
+    plines: &[Polyline<f64>],
+    step: &mut dyn FnMut(f64) -> Result<(), Cancelled>,
+) -> Result<(Vec<Vector2<f64>>, Vec<u32>), Cancelled> {
+    let refs: Vec<&Polyline<f64>> = plines.iter().collect();
+    let parents = parent_indexes(&refs);
+    let depth = |mut i: usize| {
+        let mut d = 0;
+        while parents[i] >= 0 {
+            i = parents[i] as usize;
+            d += 1;
+        }
+        d
+    };
+    let mut points = Vec::new();
+    // counter clockwise (or clockwise for holes) ring of vertex indexes of
+    // every polyline
+    let rings: Vec<Vec<usize>> = plines
+        .iter()
+        .enumerate()
+        .map(|(i, p)| {
+            if !p.is_closed() || p.vertex_count() < 3 {
+                return Vec::new();
+            }
+            let start = points.len();
+            points.extend(p.iter_vertexes().map(|v| v.pos()));
+            let mut ring: Vec<usize> = (start..points.len()).collect();
+            let is_hole = depth(i) % 2 == 1;
+            if (p.area() < 0.0) != is_hole {
+                ring.reverse();
+            }
+            ring
+        })
+        .collect();
+
+    // every clipped ear removes one vertex, bridges add two per hole
+    let total = points.len().max(1) as f64;
+    let mut clipped = 0;
+    let mut indices = Vec::new();
+    for (i, outer) in rings.iter().enumerate() {
+        if outer.is_empty() || depth(i) % 2 == 1 {
+            continue;
+        }
+        let mut holes: Vec<&Vec<usize>> = (0..rings.len())
+            .filter(|&h| parents[h] == i as i32 && !rings[h].is_empty())
+            .map(|h| &rings[h])
+            .collect();
+        // bridge the holes from right to left so bridges do not cross
+        let max_x = |ring: &[usize]| {
+            ring.iter()
+                .map(|&j| points[j].x)
+                .fold(f64::NEG_INFINITY, f64::max)
+        };
+        holes.sort_by(|a, b| max_x(b).total_cmp(&max_x(a)));
+        let mut ring = outer.clone();
+        for hole in holes {
+            bridge_hole(&points, &mut ring, hole);
+        }
+        clip_ears(&points, ring, &mut indices, &mut || {
+            clipped += 1;
+            step(clipped as f64 / total)
+        })?;
+    }
+    Ok((points, indices))
+}
+
+/// Polylines of the list with arcs approximated by lines deviating at most
+/// `arc_error` from them.
+unsafe fn approx_plines(plinelist: *const cavc_plinelist, arc_error: f64) -> Vec<Polyline<f64>> {
+    (*plinelist)
+        .0
+        .iter()
+        .map(|&p| {
+            let p = &(*p).0;
+            p.arcs_to_approx_lines(arc_error)
+                .unwrap_or_else(|| p.clone())
+        })
+        .collect()
+}
+
+/// Move the triangulation into a newly allocated [cavc_triangulation].
+fn triangulation_into_raw(points: Vec<Vector2<f64>>, indices: Vec<u32>) -> *mut cavc_triangulation {
+    let vertexes = points.iter().flat_map(|p| [p.x, p.y]).collect();
+    into_raw(cavc_triangulation { vertexes, indices })
+}
+
+/// Triangulate the regions bounded by the closed polylines of the list.
+///
+/// Polylines nested at an even depth (see `cavc_plinelist_build_hierarchy`)
+/// are outer boundaries and their children are holes, orientation does not
+/// matter. Open polylines are ignored. Arc segments are approximated by lines
+/// deviating at most `arc_error` from the arcs first. Triangles are counter
+/// clockwise and only use the (approximated) polyline vertexes, the best
+/// shaped ears are clipped first to avoid slivers where possible. The
+/// polylines are expected not to intersect each other or themselves.
+///
+/// `result` is only written to if the function returns 0 (success) and must be
+/// freed with [cavc_triangulation_f].
+///
+/// ## Specific Error Codes
+/// * 1 = `plinelist` or `result` is null.
+/// * 2 = `arc_error` is not greater than 0.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_plinelist_triangulate(
+    plinelist: *const cavc_plinelist,
+    arc_error: f64,
+    result: *mut *mut cavc_triangulation,
+) -> i32 {
+    ffi_catch_unwind!({
+        if plinelist.is_null() || result.is_null() {
+            return 1;
+        }
+        if arc_error.is_nan() || arc_error <= 0.0 {
+            return 2;
+        }
+        let plines = approx_plines(plinelist, arc_error);
+        let (points, indices) = triangulate(&plines, &mut |_| Ok(())).unwrap_or_default();
+        *result = triangulation_into_raw(points, indices);
+        0
+    })
+}
+
+/// Same as [cavc_plinelist_triangulate] but reports its progress to
+/// `progress` and can be cancelled by it.
+///
+/// `progress` is invoked with `user_data` on the calling thread while ears are
+/// clipped (at most every few milliseconds) with the estimated fraction of
+/// the triangles done, and with 1 once the triangulation is done. Once it
+/// returns non-zero the triangulation stops and the function returns
+/// [CAVC_CANCELLED]. If `progress` is null this behaves exactly like
+/// [cavc_plinelist_triangulate].
+///
+/// `result` is only written to if the function returns 0 (success) and must be
+/// freed with [cavc_triangulation_f].
+///
+/// ## Specific Error Codes
+/// * 1 = `plinelist` or `result` is null.
+/// * 2 = `arc_error` is not greater than 0.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_plinelist_triangulate_cancellable(
+    plinelist: *const cavc_plinelist,
+    arc_error: f64,
+    progress: cavc_progress_fn,
+    user_data: *mut c_void,
+    result: *mut *mut cavc_triangulation,
+) -> i32 {
+    ffi_catch_unwind!({
+        if plinelist.is_null() || result.is_null() {
+            return 1;
+        }
+        if arc_error.is_nan() || arc_error <= 0.0 {
+            return 2;
+        }
+        let mut progress = Progress::new(progress, user_data);
+        if progress.report(0.0).is_err() {
+            return CAVC_CANCELLED;
+        }
+        let plines = approx_plines(plinelist, arc_error);
+        match triangulate(&plines, &mut |fraction| progress.report(fraction)) {
+            Ok((points, indices)) => {
+                progress.finish();
+                *result = triangulation_into_raw(points, indices);
+                0
+            }
+            Err(Cancelled) => CAVC_CANCELLED,
+        }
+    })
+}
+
+/// Free an existing [cavc_triangulation] object. Nothing happens if
+/// `triangulation` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_triangulation_f(triangulation: *mut cavc_triangulation) {
+    free_raw(triangulation);
+}
+
+/// Get the number of vertexes and triangles of the triangulation.
+///
+/// ## Specific Error Codes
+/// * 1 = `triangulation`, `vertex_count` or `triangle_count` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_triangulation_get_counts(
+    triangulation: *const cavc_triangulation,
+    vertex_count: *mut u32,
+    triangle_count: *mut u32,
+) -> i32 {
+    ffi_catch_unwind!({
+        if triangulation.is_null() || vertex_count.is_null() || triangle_count.is_null() {
+            return 1;
+        }
+        *vertex_count = ((*triangulation).vertexes.len() / 2) as u32;
+        *triangle_count = ((*triangulation).indices.len() / 3) as u32;
+        0
+    })
+}
+
+/// Copy the vertex positions interleaved as `[x0, y0, x1, y1, ...]` to
+/// `vertexes`, which must have room for twice the vertex count given by
+/// [cavc_triangulation_get_counts].
+///
+/// ## Specific Error Codes
+/// * 1 = `triangulation` or `vertexes` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_triangulation_get_vertexes(
+    triangulation: *const cavc_triangulation,
+    vertexes: *mut f64,
+) -> i32 {
+    ffi_catch_unwind!({
+        if triangulation.is_null() || vertexes.is_null() {
+            return 1;
+        }
+        let data = &(*triangulation).vertexes;
+        std::ptr::copy_nonoverlapping(data.as_ptr(), vertexes, data.len());
+        0
+    })
+}
+
+/// Copy the vertex indexes of the triangles (three per triangle) to
+/// `indices`, which must have room for three times the triangle count given
+/// by [cavc_triangulation_get_counts].
+///
+/// ## Specific Error Codes
+/// * 1 = `triangulation` or `indices` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_triangulation_get_indices(
+    triangulation: *const cavc_triangulation,
+    indices: *mut u32,
+) -> i32 {
+    ffi_catch_unwind!({
+        if triangulation.is_null() || indices.is_null() {
+            return 1;
+        }
+        let data = &(*triangulation).indices;
+        std::ptr::copy_nonoverlapping(data.as_ptr(), indices, data.len());
+        0
+    })
+}
@@ -0,0 +1,85 @@
+//! User data tags attached to polyline handles.
+//!
+//! A tag is an arbitrary `u64` (e.g. a part id) carried along by the
+//! operations of this library: results of offsets, boolean operations and
+//! list filters take the tag of the polyline they were derived from. Tags are
+//! kept in a side table keyed by the handle address since `cavc_pline` has no
+//! room for them. Handles created by this library start untagged and
+//! [crate::handle::cavc_pline_release] removes the tag when freeing a handle,
+//! see [crate::handle]. Functions of `cavalier_contours_ffi` (e.g.
+//! `cavc_pline_clone`) do not carry tags.
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use cavalier_contours_ffi::{cavc_pline, cavc_plinelist};
+
+static TAGS: Mutex<BTreeMap<usize, u64>> = Mutex::new(BTreeMap::new());
+
+fn tags() -> std::sync::MutexGuard<'static, BTreeMap<usize, u64>> {
+    // every operation leaves the table consistent so poisoning can be ignored
+    TAGS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Tag of `pline`, 0 if it has none.
+pub(crate) fn get_tag(pline: *const cavc_pline) -> u64 {
+    tags().get(&(pline as usize)).copied().unwrap_or(0)
+}
+
+/// Set the tag of `pline`, 0 removes it.
+pub(crate) fn set_tag(pline: *const cavc_pline, tag: u64) {
+    let mut tags = tags();
+    if tag == 0 {
+        tags.remove(&(pline as usize));
+    } else {
+        tags.insert(pline as usize, tag);
+    }
+}
+
+/// Set the tag of every polyline of `plinelist`, nothing happens for 0.
+pub(crate) unsafe fn tag_plinelist(plinelist: *const cavc_plinelist, tag: u64) {
+    if tag == 0 {
+        return;
+    }
+    let mut tags = tags();
+    for &pline in (*plinelist).0.iter() {
+        tags.insert(pline as usize, tag);
+    }
+}
+
+/// Set the tag of a polyline, 0 removes it.
+///
+/// The tag stays with the handle when the polyline is modified until it is
+/// freed with [crate::handle::cavc_pline_release].
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_set_userdata(pline: *mut cavc_pline, userdata: u64) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() {
+            return 1;
+        }
+        set_tag(pline, userdata);
+        0
+    })
+}
+
+/// Get the tag of a polyline, 0 if it has none.
+///
+/// `userdata` is only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `userdata` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_get_userdata(
+    pline: *const cavc_pline,
+    userdata: *mut u64,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || userdata.is_null() {
+            return 1;
+        }
+        *userdata = get_tag(pline);
+        0
+    })
+}
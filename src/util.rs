@@ -0,0 +1,179 @@
+//! Helpers shared by the FFI modules.
+use std::os::raw::c_char;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use cavalier_contours::core::math::Vector2;
+use cavalier_contours::polyline::{PlineVertex, Polyline};
+use cavalier_contours_ffi::{cavc_pline, cavc_plinelist, cavc_point, cavc_vertex};
+
+use crate::debug::{track_created, track_freed, KIND_PLINELIST, KIND_RESULT};
+use crate::handle::pline_created;
+
+/// Move `value` into a newly allocated handle owned by the caller, registered
+/// in the debug registry (see [crate::debug]) and freed with [free_raw].
+pub(crate) fn into_raw<T>(value: T) -> *mut T {
+    let handle = Box::into_raw(Box::new(value));
+    track_created(KIND_RESULT, handle);
+    handle
+}
+
+/// Free a handle created by [into_raw], nothing happens if `handle` is null.
+pub(crate) unsafe fn free_raw<T>(handle: *mut T) {
+    if !handle.is_null() {
+        track_freed(handle);
+        drop(Box::from_raw(handle));
+    }
+}
+
+/// Move `pline` into a newly allocated [cavc_pline] handle owned by the caller.
+///
+/// The handle starts without user data or other per-handle state, even if a
+/// freed handle had the same address (see [crate::handle]).
+pub(crate) fn pline_into_raw(pline: Polyline<f64>) -> *mut cavc_pline {
+    let pline = Box::into_raw(Box::new(cavc_pline(pline)));
+    pline_created(pline);
+    pline
+}
+
+/// Move `plines` into a newly allocated [cavc_plinelist] handle owned by the
+/// caller.
+pub(crate) fn plinelist_into_raw(plines: Vec<Polyline<f64>>) -> *mut cavc_plinelist {
+    let list = plines.into_iter().map(pline_into_raw).collect();
+    let plinelist = Box::into_raw(Box::new(cavc_plinelist(list)));
+    track_created(KIND_PLINELIST, plinelist);
+    plinelist
+}
+
+/// Copy `s` followed by a nul terminator into `out` which has room for
+/// `out_cap` bytes.
+///
+/// Returns false and leaves `out` untouched if it is null or too small.
+pub(crate) unsafe fn write_c_string(s: &str, out: *mut c_char, out_cap: usize) -> bool {
+    if out.is_null() || s.len() >= out_cap {
+        return false;
+    }
+    std::ptr::copy_nonoverlapping(s.as_ptr() as *const c_char, out, s.len());
+    *out.add(s.len()) = 0;
+    true
+}
+
+/// Whether `eps` can be used as a fuzzy comparison epsilon such as
+/// `pos_equal_eps`: finite and not negative.
+///
+/// Functions taking an epsilon reject other values with an error code instead
+/// of passing them to `cavalier_contours`, where a NaN or negative epsilon
+/// silently makes every comparison fail.
+pub(crate) fn valid_eps(eps: f64) -> bool {
+    eps.is_finite() && eps >= 0.0
+}
+
+/// Convert an internal vertex into its FFI representation.
+pub(crate) fn to_cavc_vertex(v: PlineVertex<f64>) -> cavc_vertex {
+    cavc_vertex {
+        x: v.x,
+        y: v.y,
+        bulge: v.bulge,
+    }
+}
+
+/// Convert an internal point into its FFI representation.
+pub(crate) fn to_cavc_point(p: Vector2<f64>) -> cavc_point {
+    cavc_point { x: p.x, y: p.y }
+}
+
+/// Convert an FFI vertex into its internal representation.
+pub(crate) fn from_cavc_vertex(v: cavc_vertex) -> PlineVertex<f64> {
+    PlineVertex::new(v.x, v.y, v.bulge)
+}
+
+/// Map every index in `0..count` with `f` on all available cores, the results
+/// are returned in index order.
+///
+/// Indexes are handed out one at a time since the cost of geometry operations
+/// varies a lot between inputs. A panic in `f` is propagated to the caller.
+pub(crate) fn parallel_map<R: Send>(count: usize, f: impl Fn(usize) -> R + Sync) -> Vec<R> {
+    let threads = std::thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .min(count);
+    if threads <= 1 {
+        return (0..count).map(f).collect();
+    }
+    let next = AtomicUsize::new(0);
+    let mut results: Vec<(usize, R)> = std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        if i >= count {
+                            break done;
+                        }
+                        done.push((i, f(i)));
+                    }
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| match worker.join() {
+                Ok(done) => done,
+                Err(panic) => std::panic::resume_unwind(panic),
+            })
+            .collect()
+    });
+    results.sort_unstable_by_key(|(i, _)| *i);
+    results.into_iter().map(|(_, r)| r).collect()
+}
+
+/// Small deterministic pseudo random generator (SplitMix64), so results
+/// depending on it are reproducible across platforms and versions.
+pub(crate) struct SplitMix64(pub(crate) u64);
+
+impl SplitMix64 {
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform value in `[0, 1)`.
+    pub(crate) fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Resolution (bits per axis) of the grid used by [hilbert_key].
+const HILBERT_BITS: u32 = 16;
+
+/// Position of `p` along a Hilbert curve through the square of `size` with
+/// its minimum corner at `min`, points close in this order are close in the
+/// plane. Used to sort geometry so consecutive items are spatially coherent.
+pub(crate) fn hilbert_key(p: Vector2<f64>, min: Vector2<f64>, size: f64) -> u64 {
+    let n = 1u32 << HILBERT_BITS;
+    let scale = if size > 0.0 {
+        (n - 1) as f64 / size
+    } else {
+        0.0
+    };
+    let cell = |v: f64| ((v * scale) as u32).min(n - 1);
+    let (mut x, mut y) = (cell(p.x - min.x), cell(p.y - min.y));
+    let mut d = 0;
+    let mut s = n / 2;
+    while s > 0 {
+        let rx = ((x & s) > 0) as u32;
+        let ry = ((y & s) > 0) as u32;
+        d += s as u64 * s as u64 * ((3 * rx) ^ ry) as u64;
+        if ry == 0 {
+            if rx == 1 {
+                x = n - 1 - x;
+                y = n - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        s /= 2;
+    }
+    d
+}
@@ -0,0 +1,228 @@
+//! Checking polylines for degenerate input and repairing it.
+//!
+//! Offsetting and boolean operations assume finite coordinates and no
+//! degenerate segments, [cavc_pline_validate] reports what would break them
+//! and [cavc_pline_repair] applies the fixes that do not change the shape.
+use cavalier_contours::polyline::{
+    PlineCreation, PlineSource, PlineSourceMut, PlineVertex, Polyline,
+};
+use cavalier_contours_ffi::cavc_pline;
+
+use crate::cache::evict;
+use crate::intersect::visit_self_intersects;
+use crate::util::valid_eps;
+
+/// Check for non-finite coordinates or bulges.
+const CHECK_NON_FINITE: u32 = 1;
+/// Check for duplicate consecutive vertexes and zero length segments.
+const CHECK_ZERO_LENGTH: u32 = 2;
+/// Check for near zero and extreme bulges.
+const CHECK_BULGES: u32 = 4;
+/// Check for self intersections.
+const CHECK_SELF_INTERSECTS: u32 = 8;
+/// All checks.
+const CHECK_ALL: u32 = 15;
+
+/// Bulges with a greater magnitude are reported as extreme, the arc then sweeps
+/// within 0.004 radians of a full circle.
+const EXTREME_BULGE: f64 = 1e3;
+
+/// Problems found by [cavc_pline_validate], counts of checks not run are 0.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default)]
+pub struct cavc_pline_validate_report {
+    /// Vertexes with a coordinate or bulge that is NaN or infinite.
+    pub non_finite_count: u32,
+    /// Segments whose end points are exactly equal.
+    pub duplicate_vertex_count: u32,
+    /// Segments whose end points are distinct but within `pos_equal_eps`.
+    pub zero_length_count: u32,
+    /// Arcs deviating from their chord by no more than `pos_equal_eps`.
+    pub near_zero_bulge_count: u32,
+    /// Arcs with a bulge magnitude above 1000 (sweeping nearly a full circle).
+    pub extreme_bulge_count: u32,
+    /// Self intersections, as counted by `cavc_pline_scan_for_self_intersect`.
+    pub self_intersect_count: u32,
+    /// Index of the first vertex (or segment start vertex) with a problem, -1
+    /// if there is none.
+    pub first_index: i32,
+}
+
+fn is_finite(v: &PlineVertex<f64>) -> bool {
+    v.x.is_finite() && v.y.is_finite() && v.bulge.is_finite()
+}
+
+fn validate(pline: &Polyline<f64>, flags: u32, pos_equal_eps: f64) -> cavc_pline_validate_report {
+    let mut report = cavc_pline_validate_report::default();
+    let mut first = usize::MAX;
+    let n = pline.vertex_count();
+    let all_finite = pline.iter_vertexes().all(|v| is_finite(&v));
+
+    if flags & CHECK_NON_FINITE != 0 {
+        for (i, v) in pline.iter_vertexes().enumerate() {
+            if !is_finite(&v) {
+                report.non_finite_count += 1;
+                first = first.min(i);
+            }
+        }
+    }
+
+    let seg_count = if pline.is_closed() && n > 1 {
+        n
+    } else {
+        n.saturating_sub(1)
+    };
+    for i in 0..seg_count {
+        let (v1, v2) = (pline.at(i), pline.at((i + 1) % n));
+        if !is_finite(&v1) || !is_finite(&v2) {
+            continue;
+        }
+        let chord = (v2.pos() - v1.pos()).length();
+        if flags & CHECK_ZERO_LENGTH != 0 {
+            if v1.pos() == v2.pos() {
+                report.duplicate_vertex_count += 1;
+                first = first.min(i);
+            } else if chord <= pos_equal_eps {
+                report.zero_length_count += 1;
+                first = first.min(i);
+            }
+        }
+        if flags & CHECK_BULGES != 0 && v1.bulge != 0.0 {
+            if v1.bulge.abs() > EXTREME_BULGE {
+                report.extreme_bulge_count += 1;
+                first = first.min(i);
+            } else if v1.bulge.abs() * chord / 2.0 <= pos_equal_eps {
+                // the bulge is twice the sagitta over the chord length
+                report.near_zero_bulge_count += 1;
+                first = first.min(i);
+            }
+        }
+    }
+
+    // segment intersection is meaningless (and may panic) with non-finite input
+    if flags & CHECK_SELF_INTERSECTS != 0 && all_finite {
+        visit_self_intersects(pline, pos_equal_eps, |i, _, _| {
+            report.self_intersect_count += 1;
+            first = first.min(i);
+            true
+        });
+    }
+
+    report.first_index = if first == usize::MAX {
+        -1
+    } else {
+        first as i32
+    };
+    report
+}
+
+/// Check the polyline for problems that break offsetting and boolean
+/// operations.
+///
+/// `flags` is a bitwise or of the checks to run:
+/// * 1 = NaN or infinite coordinates and bulges.
+/// * 2 = duplicate consecutive vertexes and segments shorter than
+///   `pos_equal_eps`.
+/// * 4 = arcs deviating from their chord by no more than `pos_equal_eps` and
+///   arcs with a bulge magnitude above 1000.
+/// * 8 = self intersections, skipped if any value is not finite.
+///
+/// A polyline with no problems found gives a report with all counts 0 and
+/// `first_index` -1. `report` is only written to if the function returns 0
+/// (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `report` is null.
+/// * 2 = `pos_equal_eps` is negative or not finite.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_validate(
+    pline: *const cavc_pline,
+    flags: u32,
+    pos_equal_eps: f64,
+    report: *mut cavc_pline_validate_report,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || report.is_null() {
+            return 1;
+        }
+        if !valid_eps(pos_equal_eps) {
+            return 2;
+        }
+        *report = validate(&(*pline).0, flags, pos_equal_eps);
+        0
+    })
+}
+
+/// Apply the fixes for problems found by [cavc_pline_validate] that keep the
+/// shape of the polyline.
+///
+/// Vertexes with a non-finite position are removed (the arc leading to them
+/// becomes a line), non-finite bulges and bulges of arcs deviating from their
+/// chord by no more than `pos_equal_eps` are set to 0 and vertexes within
+/// `pos_equal_eps` of the previous one are removed. Extreme bulges and self
+/// intersections are left as they are since fixing them changes the shape.
+///
+/// `report` may be null, otherwise it receives the result of validating the
+/// repaired polyline with all checks, showing what is left to fix. `report` is
+/// only written to if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` is null.
+/// * 2 = `pos_equal_eps` is negative or not finite.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_repair(
+    pline: *mut cavc_pline,
+    pos_equal_eps: f64,
+    report: *mut cavc_pline_validate_report,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() {
+            return 1;
+        }
+        if !valid_eps(pos_equal_eps) {
+            return 2;
+        }
+        evict(pline);
+        let pline = &mut (*pline).0;
+        let mut repaired = Polyline::with_capacity(pline.vertex_count(), pline.is_closed());
+        for v in pline.iter_vertexes() {
+            if !v.x.is_finite() || !v.y.is_finite() {
+                let last = repaired.vertex_count();
+                if last > 0 {
+                    let prev = repaired.at(last - 1);
+                    repaired.set_vertex(last - 1, prev.with_bulge(0.0));
+                }
+                continue;
+            }
+            repaired.add_vertex(if v.bulge.is_finite() {
+                v
+            } else {
+                v.with_bulge(0.0)
+            });
+        }
+        let n = repaired.vertex_count();
+        let seg_count = if repaired.is_closed() && n > 1 {
+            n
+        } else {
+            n.saturating_sub(1)
+        };
+        for i in 0..seg_count {
+            let (v1, v2) = (repaired.at(i), repaired.at((i + 1) % n));
+            let chord = (v2.pos() - v1.pos()).length();
+            if v1.bulge != 0.0
+                && v1.bulge.abs() <= EXTREME_BULGE
+                && v1.bulge.abs() * chord / 2.0 <= pos_equal_eps
+            {
+                repaired.set_vertex(i, v1.with_bulge(0.0));
+            }
+        }
+        if let Some(deduped) = repaired.remove_repeat_pos(pos_equal_eps) {
+            repaired = deduped;
+        }
+        *pline = repaired;
+        if !report.is_null() {
+            *report = validate(pline, CHECK_ALL, pos_equal_eps);
+        }
+        0
+    })
+}
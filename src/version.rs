@@ -0,0 +1,254 @@
+//! Version and capability queries.
+//!
+//! Every module of extensions and every exported function is a feature which
+//! can be probed with [cavc_has_feature], so wrappers supporting several
+//! builds of the library can check what is available before calling into it.
+use std::ffi::CStr;
+use std::mem::{align_of, size_of};
+use std::os::raw::c_char;
+
+use cavalier_contours_ffi::{
+    cavc_basic_intersect, cavc_overlapping_intersect, cavc_pline_parallel_offset_o, cavc_point,
+    cavc_vertex,
+};
+
+use crate::boolean::{cavc_pline_boolean_opt_o, cavc_plinelist_boolean_o};
+use crate::debug::cavc_debug_handle_counts;
+use crate::f32::{cavc_basic_intersectf, cavc_overlapping_intersectf, cavc_pointf, cavc_vertexf};
+use crate::gcode::{cavc_gcode_import_o, cavc_gcode_o};
+use crate::intersect::{cavc_basic_intersect_ex, cavc_shape_intersect};
+use crate::measure::{cavc_closest_point, cavc_closest_point_ex};
+use crate::offset::cavc_raw_offset_seg;
+use crate::segment::cavc_segment;
+use crate::shape::cavc_shape_offset_o;
+use crate::thicken::cavc_pline_thicken_o;
+use crate::validate::cavc_pline_validate_report;
+
+/// Names of the available features, one per module of extensions.
+const FEATURES: &[&str] = &[
+    "binary",
+    "boolean",
+    "buffer",
+    "builder",
+    "cache",
+    "chain",
+    "cleanup",
+    "clip",
+    "compare",
+    "corners",
+    "debug",
+    "dxf",
+    "edit",
+    "error",
+    "f32",
+    "fit",
+    "gcode",
+    "geojson",
+    "handle",
+    "hatch",
+    "hierarchy",
+    "index",
+    "intersect",
+    "json",
+    "measure",
+    "medial",
+    "memory",
+    "minkowski",
+    "offset",
+    "options",
+    "path",
+    "plinelist",
+    "pocket",
+    "primitive",
+    "progress",
+    "raster",
+    "segment",
+    "shape",
+    "simplify",
+    "svg",
+    "tabs",
+    "thicken",
+    "transform",
+    "triangulate",
+    "userdata",
+    "validate",
+    "version",
+    "wkt",
+];
+
+/// Names of all exported functions (including the ones of
+/// `cavalier_contours_ffi`) sorted, generated from the header by the build
+/// script.
+const FUNCTIONS: &[&str] = include!(concat!(env!("OUT_DIR"), "/functions.rs"));
+
+/// Parse a version component set by cargo or the build script.
+fn component(value: &str) -> u32 {
+    value.parse().unwrap_or(0)
+}
+
+/// Get the version of this library.
+///
+/// ## Specific Error Codes
+/// * 1 = `major`, `minor` or `patch` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_version(major: *mut u32, minor: *mut u32, patch: *mut u32) -> i32 {
+    ffi_catch_unwind!({
+        if major.is_null() || minor.is_null() || patch.is_null() {
+            return 1;
+        }
+        *major = component(env!("CARGO_PKG_VERSION_MAJOR"));
+        *minor = component(env!("CARGO_PKG_VERSION_MINOR"));
+        *patch = component(env!("CARGO_PKG_VERSION_PATCH"));
+        0
+    })
+}
+
+/// Get the version of the cavalier_contours crate this library was built
+/// against.
+///
+/// ## Specific Error Codes
+/// * 1 = `major`, `minor` or `patch` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_upstream_version(
+    major: *mut u32,
+    minor: *mut u32,
+    patch: *mut u32,
+) -> i32 {
+    ffi_catch_unwind!({
+        if major.is_null() || minor.is_null() || patch.is_null() {
+            return 1;
+        }
+        *major = component(env!("CAVC_UPSTREAM_VERSION_MAJOR"));
+        *minor = component(env!("CAVC_UPSTREAM_VERSION_MINOR"));
+        *patch = component(env!("CAVC_UPSTREAM_VERSION_PATCH"));
+        0
+    })
+}
+
+/// Check whether the feature `name` (a nul terminated string) is available,
+/// returns 1 if it is and 0 otherwise (including when `name` is null).
+///
+/// Feature names are the module names of the extensions, e.g. `"json"` for
+/// the JSON serialization functions, and the names of the exported functions,
+/// e.g. `"cavc_pline_to_json"`. Modules gain functions over time, so probing a
+/// function name is the reliable way to check for a function added after its
+/// module.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_has_feature(name: *const c_char) -> u8 {
+    if name.is_null() {
+        return 0;
+    }
+    match CStr::from_ptr(name).to_str() {
+        Ok(name) => (FEATURES.contains(&name) || FUNCTIONS.binary_search(&name).is_ok()) as u8,
+        Err(_) => 0,
+    }
+}
+
+/// Version of the binary interface, increased whenever the signature of an
+/// exported function or the layout of a struct changes incompatibly.
+const ABI_VERSION: u32 = 1;
+
+/// Get the version of the binary interface of this library.
+///
+/// Unlike [cavc_version] this only changes when declarations written against
+/// an older build of the library would no longer match, so wrappers can
+/// refuse to load a library their declarations do not fit.
+///
+/// ## Specific Error Codes
+/// * 1 = `out_u32` is null.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_abi_version(out_u32: *mut u32) -> i32 {
+    ffi_catch_unwind!({
+        if out_u32.is_null() {
+            return 1;
+        }
+        *out_u32 = ABI_VERSION;
+        0
+    })
+}
+
+/// Size and alignment of a struct of the header, see [cavc_struct_sizes].
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct cavc_struct_size {
+    /// Name of the struct as a static nul terminated string.
+    pub name: *const c_char,
+    /// `sizeof` of the struct in bytes.
+    pub size: u32,
+    /// Alignment of the struct in bytes.
+    pub align: u32,
+}
+
+macro_rules! struct_size {
+    ($ty: ident) => {
+        cavc_struct_size {
+            name: concat!(stringify!($ty), "\0").as_ptr() as *const c_char,
+            size: size_of::<$ty>() as u32,
+            align: align_of::<$ty>() as u32,
+        }
+    };
+}
+
+/// Sizes of all structs with a layout defined by the header.
+fn struct_sizes() -> Vec<cavc_struct_size> {
+    vec![
+        struct_size!(cavc_basic_intersect),
+        struct_size!(cavc_basic_intersect_ex),
+        struct_size!(cavc_basic_intersectf),
+        struct_size!(cavc_closest_point),
+        struct_size!(cavc_closest_point_ex),
+        struct_size!(cavc_debug_handle_counts),
+        struct_size!(cavc_gcode_import_o),
+        struct_size!(cavc_gcode_o),
+        struct_size!(cavc_overlapping_intersect),
+        struct_size!(cavc_overlapping_intersectf),
+        struct_size!(cavc_pline_boolean_opt_o),
+        struct_size!(cavc_pline_parallel_offset_o),
+        struct_size!(cavc_pline_thicken_o),
+        struct_size!(cavc_pline_validate_report),
+        struct_size!(cavc_plinelist_boolean_o),
+        struct_size!(cavc_point),
+        struct_size!(cavc_pointf),
+        struct_size!(cavc_raw_offset_seg),
+        struct_size!(cavc_segment),
+        struct_size!(cavc_shape_intersect),
+        struct_size!(cavc_shape_offset_o),
+        struct_size!(cavc_struct_size),
+        struct_size!(cavc_vertex),
+        struct_size!(cavc_vertexf),
+    ]
+}
+
+/// Write the size and alignment of every struct with a layout defined by the
+/// header (including the structs of `cavalier_contours_ffi`) to `out_array`,
+/// sorted by name.
+///
+/// Wrappers can compare them with the sizes of their own declarations when
+/// loading the library and fail cleanly on a mismatch instead of corrupting
+/// memory. `out_array` must have room for `capacity` entries, `written`
+/// receives the number of structs even when the array is too small, so the
+/// caller can size the array and call again. The names are static and must
+/// not be freed. `out_array` may be null if `capacity` is 0.
+///
+/// ## Specific Error Codes
+/// * 1 = `written` is null.
+/// * 2 = `capacity` is less than the number of structs, nothing is copied.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_struct_sizes(
+    out_array: *mut cavc_struct_size,
+    capacity: u32,
+    written: *mut u32,
+) -> i32 {
+    ffi_catch_unwind!({
+        if written.is_null() {
+            return 1;
+        }
+        let sizes = struct_sizes();
+        *written = sizes.len() as u32;
+        if sizes.len() > capacity as usize || out_array.is_null() {
+            return 2;
+        }
+        std::ptr::copy_nonoverlapping(sizes.as_ptr(), out_array, sizes.len());
+        0
+    })
+}
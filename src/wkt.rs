@@ -0,0 +1,344 @@
+//! Well-known text (WKT) interchange of polylines.
+//!
+//! Open polylines map to `LINESTRING` and closed polylines to single ring
+//! `POLYGON` geometries. WKT has no arcs, so arc segments are approximated
+//! by lines on export.
+use std::ffi::CStr;
+use std::os::raw::c_char;
+
+use cavalier_contours::polyline::{PlineCreation, PlineSource, PlineSourceMut, Polyline};
+use cavalier_contours_ffi::cavc_pline;
+
+use crate::error::set_last_error;
+use crate::json::write_number;
+use crate::util::{pline_into_raw, write_c_string};
+
+/// Append the WKT of `pline` to `out`, which must only contain lines.
+///
+/// Returns false if any vertex holds a non-finite value.
+fn write_wkt(out: &mut String, pline: &Polyline<f64>) -> bool {
+    let (tag, is_ring) = if pline.is_closed() {
+        ("POLYGON", true)
+    } else {
+        ("LINESTRING", false)
+    };
+    out.push_str(tag);
+    if pline.vertex_count() == 0 {
+        out.push_str(" EMPTY");
+        return true;
+    }
+    out.push_str(if is_ring { " ((" } else { " (" });
+    // rings repeat the first point at the end
+    let ring_end = pline.get(0).filter(|_| is_ring);
+    for (i, v) in pline.iter_vertexes().chain(ring_end).enumerate() {
+        if i != 0 {
+            out.push_str(", ");
+        }
+        if !write_number(out, v.x) {
+            return false;
+        }
+        out.push(' ');
+        if !write_number(out, v.y) {
+            return false;
+        }
+    }
+    out.push_str(if is_ring { "))" } else { ")" });
+    true
+}
+
+/// Convert a polyline to WKT, a `LINESTRING` if it is open or a `POLYGON` if
+/// it is closed.
+///
+/// Arc segments are approximated by lines deviating at most `tolerance` from
+/// the arcs. The nul terminated string is written to `out` which has room for
+/// `out_cap` bytes. `out_len` receives the length of the string (not counting
+/// the nul terminator) even when `out` is too small, so the caller can
+/// allocate a large enough buffer and call again. `out` may be null if
+/// `out_cap` is 0.
+///
+/// ## Specific Error Codes
+/// * 1 = `pline` or `out_len` is null.
+/// * 2 = `out_cap` is too small to hold the string and its nul terminator.
+/// * 3 = `pline` contains a NaN or infinite value.
+/// * 4 = `tolerance` is not greater than 0.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_to_wkt(
+    pline: *const cavc_pline,
+    tolerance: f64,
+    out: *mut c_char,
+    out_cap: u32,
+    out_len: *mut u32,
+) -> i32 {
+    ffi_catch_unwind!({
+        if pline.is_null() || out_len.is_null() {
+            return 1;
+        }
+        if tolerance.is_nan() || tolerance <= 0.0 {
+            return 4;
+        }
+        let pline = &(*pline).0;
+        let lines = pline.arcs_to_approx_lines(tolerance);
+        let mut wkt = String::new();
+        if !write_wkt(&mut wkt, lines.as_ref().unwrap_or(pline)) {
+            return 3;
+        }
+        *out_len = wkt.len() as u32;
+        if !write_c_string(&wkt, out, out_cap as usize) {
+            return 2;
+        }
+        0
+    })
+}
+
+/// Error returned by [parse_wkt].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum WktError {
+    /// Not valid WKT.
+    Syntax,
+    /// Valid WKT of a geometry that is not a single line string or ring.
+    Unsupported,
+}
+
+struct WktLexer<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl WktLexer<'_> {
+    fn peek(&mut self) -> Option<u8> {
+        while self.pos < self.bytes.len() && self.bytes[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn expect(&mut self, c: u8) -> Result<(), WktError> {
+        if self.peek() != Some(c) {
+            return Err(WktError::Syntax);
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn word(&mut self) -> String {
+        self.peek();
+        let start = self.pos;
+        while self.pos < self.bytes.len() && self.bytes[self.pos].is_ascii_alphabetic() {
+            self.pos += 1;
+        }
+        String::from_utf8_lossy(&self.bytes[start..self.pos]).to_ascii_uppercase()
+    }
+
+    fn number(&mut self) -> Result<f64, WktError> {
+        self.peek();
+        let start = self.pos;
+        while self.pos < self.bytes.len()
+            && matches!(
+                self.bytes[self.pos],
+                b'0'..=b'9' | b'.' | b'+' | b'-' | b'e' | b'E'
+            )
+        {
+            self.pos += 1;
+        }
+        std::str::from_utf8(&self.bytes[start..self.pos])
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .ok_or(WktError::Syntax)
+    }
+
+    /// Parenthesized list of points, the coordinates past x and y (z and m
+    /// values) are skipped.
+    fn points(&mut self) -> Result<Vec<(f64, f64)>, WktError> {
+        self.expect(b'(')?;
+        let mut points = Vec::new();
+        loop {
+            let x = self.number()?;
+            let y = self.number()?;
+            while matches!(self.peek(), Some(b'0'..=b'9' | b'.' | b'+' | b'-')) {
+                self.number()?;
+            }
+            points.push((x, y));
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b')') => {
+                    self.pos += 1;
+                    return Ok(points);
+                }
+                _ => return Err(WktError::Syntax),
+            }
+        }
+    }
+}
+
+/// Parse a `LINESTRING` or single ring `POLYGON` into a polyline.
+pub(crate) fn parse_wkt(wkt: &str) -> Result<Polyline<f64>, WktError> {
+    let mut lexer = WktLexer {
+        bytes: wkt.as_bytes(),
+        pos: 0,
+    };
+    let tag = lexer.word();
+    let is_polygon = match tag.as_str() {
+        "LINESTRING" => false,
+        "POLYGON" => true,
+        "" => return Err(WktError::Syntax),
+        _ => return Err(WktError::Unsupported),
+    };
+    // optional dimension tag (Z, M or ZM)
+    let mut word = lexer.word();
+    if matches!(word.as_str(), "Z" | "M" | "ZM") {
+        word = lexer.word();
+    }
+    let points = match word.as_str() {
+        "EMPTY" => Vec::new(),
+        "" if is_polygon => {
+            lexer.expect(b'(')?;
+            let ring = lexer.points()?;
+            if lexer.peek() == Some(b',') {
+                // interior rings cannot be represented by a single polyline
+                return Err(WktError::Unsupported);
+            }
+            lexer.expect(b')')?;
+            ring
+        }
+        "" => lexer.points()?,
+        _ => return Err(WktError::Syntax),
+    };
+    if lexer.peek().is_some() {
+        return Err(WktError::Syntax);
+    }
+    let mut points = points.as_slice();
+    if is_polygon && points.len() > 1 && points.first() == points.last() {
+        points = &points[..points.len() - 1];
+    }
+    let mut pline = Polyline::with_capacity(points.len(), is_polygon);
+    for &(x, y) in points {
+        pline.add(x, y, 0.0);
+    }
+    Ok(pline)
+}
+
+/// Create a polyline from WKT.
+///
+/// A `LINESTRING` becomes an open polyline and a `POLYGON` with a single ring
+/// becomes a closed polyline (without repeating the first point), `EMPTY`
+/// geometries become empty polylines. Z and M coordinates are ignored.
+///
+/// `wkt` must be a nul terminated UTF-8 string. `result` is only written to
+/// if the function returns 0 (success).
+///
+/// ## Specific Error Codes
+/// * 1 = `wkt` or `result` is null.
+/// * 2 = `wkt` is not valid UTF-8 or not valid WKT.
+/// * 3 = `wkt` is a different geometry type or a polygon with holes.
+#[no_mangle]
+pub unsafe extern "C" fn cavc_pline_from_wkt(
+    wkt: *const c_char,
+    result: *mut *mut cavc_pline,
+) -> i32 {
+    ffi_catch_unwind!({
+        if wkt.is_null() || result.is_null() {
+            return 1;
+        }
+        let wkt = match CStr::from_ptr(wkt).to_str() {
+            Ok(wkt) => wkt,
+            Err(_) => {
+                set_last_error("WKT string is not valid UTF-8");
+                return 2;
+            }
+        };
+        match parse_wkt(wkt) {
+            Ok(pline) => {
+                *result = pline_into_raw(pline);
+                0
+            }
+            Err(WktError::Syntax) => {
+                set_last_error("invalid WKT");
+                2
+            }
+            Err(WktError::Unsupported) => {
+                set_last_error("WKT is not a LINESTRING or a POLYGON without holes");
+                3
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn positions(pline: &Polyline<f64>) -> Vec<(f64, f64)> {
+        pline.iter_vertexes().map(|v| (v.x, v.y)).collect()
+    }
+
+    #[test]
+    fn parses_line_strings_and_polygons() {
+        let pline = parse_wkt("LINESTRING (0 0, 1.5 -2, 3e2 4)").unwrap();
+        assert!(!pline.is_closed());
+        assert_eq!(
+            positions(&pline),
+            vec![(0.0, 0.0), (1.5, -2.0), (300.0, 4.0)]
+        );
+        // the repeated first point of the ring is dropped
+        let pline = parse_wkt(" polygon((0 0,1 0,1 1,0 0)) ").unwrap();
+        assert!(pline.is_closed());
+        assert_eq!(positions(&pline), vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0)]);
+        // z and m values are skipped
+        let pline = parse_wkt("LINESTRING ZM (0 0 5 6, 1 1 7 8)").unwrap();
+        assert_eq!(positions(&pline), vec![(0.0, 0.0), (1.0, 1.0)]);
+    }
+
+    #[test]
+    fn round_trips_written_wkt() {
+        let mut pline = Polyline::new_closed();
+        pline.add(0.1, 0.2, 0.0);
+        pline.add(-5.0, 1e-9, 0.0);
+        pline.add(3.0, 7.25, 0.0);
+        for is_closed in [true, false] {
+            pline.set_is_closed(is_closed);
+            let mut wkt = String::new();
+            assert!(write_wkt(&mut wkt, &pline));
+            let parsed = parse_wkt(&wkt).unwrap();
+            assert_eq!(parsed.is_closed(), is_closed);
+            assert_eq!(positions(&parsed), positions(&pline));
+        }
+    }
+
+    #[test]
+    fn empty_geometries() {
+        let mut wkt = String::new();
+        assert!(write_wkt(&mut wkt, &Polyline::new()));
+        assert_eq!(wkt, "LINESTRING EMPTY");
+        assert_eq!(parse_wkt("POLYGON EMPTY").unwrap().vertex_count(), 0);
+        assert_eq!(parse_wkt("LINESTRING Z EMPTY").unwrap().vertex_count(), 0);
+        // a single point ring keeps its point
+        assert_eq!(parse_wkt("POLYGON ((1 1))").unwrap().vertex_count(), 1);
+    }
+
+    #[test]
+    fn rejects_invalid_wkt() {
+        for wkt in [
+            "",
+            "LINESTRING",
+            "LINESTRING (0 0, 1",
+            "LINESTRING (0 0 1 1",
+            "LINESTRING (0 0) x",
+            "LINESTRING (a b)",
+            "POLYGON (0 0, 1 1)",
+        ] {
+            assert_eq!(parse_wkt(wkt).err(), Some(WktError::Syntax), "{:?}", wkt);
+        }
+        for wkt in [
+            "POINT (0 0)",
+            "MULTILINESTRING ((0 0, 1 1))",
+            "POLYGON ((0 0, 4 0, 4 4, 0 0), (1 1, 2 1, 2 2, 1 1))",
+        ] {
+            assert_eq!(
+                parse_wkt(wkt).err(),
+                Some(WktError::Unsupported),
+                "{:?}",
+                wkt
+            );
+        }
+    }
+}
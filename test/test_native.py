@@ -0,0 +1,42 @@
+import pytest
+
+native = pytest.importorskip("py_cavalier_contours._native")
+np = pytest.importorskip("numpy")
+
+
+def square(size=1.0):
+    vertexes = np.array([[0, 0, 0], [size, 0, 0], [size, size, 0], [0, size, 0]],
+                        dtype=float)
+    return native.Polyline(vertexes, True)
+
+
+def test_native_polyline_vertexes():
+    p = square()
+    assert len(p) == 4
+    assert p[-1] == (0, 1, 0)
+    p[0] = (0, 0, 0.5)
+    assert np.asarray(p)[0, 2] == 0.5
+    p.remove(0)
+    assert len(p) == 3
+    with pytest.raises(IndexError):
+        p[3]
+
+
+def test_native_polyline_offset_and_boolean():
+    p = square(2.0)
+    assert p.area() == pytest.approx(4.0)
+    offsets = p.parallel_offset(0.5)
+    assert len(offsets) == 1
+    assert abs(offsets[0].area()) == pytest.approx(1.0)
+    other = square(2.0)
+    other.translate(1.0, 0.0)
+    pos, neg = p.boolean(other, 0)
+    assert len(pos) == 1 and not neg
+    assert abs(pos[0].area()) == pytest.approx(6.0)
+
+
+def test_native_shape_offset():
+    shape = native.Shape([square(4.0)])
+    assert len(shape.parallel_offset(1.0).ccw_polylines) == 1
+    with pytest.raises(ValueError):
+        shape.parallel_offset(1.0, pos_equal_eps=-1.0)
@@ -0,0 +1,23 @@
+from py_cavalier_contours import Vertex, Polyline
+
+
+def test_polyline_remove_redundant():
+    p = Polyline([Vertex(0, 0), Vertex(1, 0), Vertex(2, 0), Vertex(2, 2)],
+                 closed=False)
+    p.remove_redundant()
+
+    assert len(p) == 3
+    assert p[0] == Vertex(0, 0)
+    assert p[1] == Vertex(2, 0)
+    assert p[2] == Vertex(2, 2)
+    assert not p.closed
+
+
+def test_polyline_reverse():
+    p = Polyline([Vertex(0, 0, 0.5), Vertex(1, 0), Vertex(1, 1)], closed=False)
+    p.reverse()
+
+    assert len(p) == 3
+    assert p[0] == Vertex(1, 1)
+    assert p[1] == Vertex(1, 0, -0.5)
+    assert p[2] == Vertex(0, 0)